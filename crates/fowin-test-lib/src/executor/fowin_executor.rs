@@ -1,15 +1,23 @@
-use std::{collections::HashMap, fmt::Debug};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    thread,
+    time::{Duration, Instant},
+};
 
-use fowin::Window;
+use fowin::{Window, WindowError};
 use log::{debug, info};
 
 use crate::{
-    executor::{encode_title, ExecutionError, Executor, WindowProps},
+    executor::{encode_title, ExecutionError, Executor, ValidationError, WindowProps},
     state::Mutation,
     timeline::{Action, ExecScope, Step},
     Position, Size, Timeline,
 };
 
+// How long a closed window may take to disappear.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(1);
+
 #[derive(Debug, Default)]
 pub struct FowinExecutor {
     windows: HashMap<u32, Window>,
@@ -44,8 +52,16 @@ impl FowinExecutor {
             // println!("VALIDATING");
 
             match step.action {
-                // If it's terminated, there's nothing to validate.
-                Action::Terminate => {}
+                Action::Terminate | Action::Kill => {
+                    states.remove(&step.id);
+                    // Only fowin keeps its windows around once they're terminated.
+                    if let ExecScope::Fowin = step.scope {
+                        debug!("FowinExecutor validating termination of window {}", step.id);
+                        self.validate_closed(step.id)?;
+                    } else {
+                        self.windows.remove(&step.id);
+                    }
+                }
                 Action::Spawn(mut state) => {
                     self.cache_window(step.id, &state.title)?;
 
@@ -75,6 +91,29 @@ impl FowinExecutor {
         Ok(())
     }
 
+    // Closing or killing a window is only a request, so wait for the window to disappear.
+    fn validate_closed(&mut self, id: u32) -> Result<(), ExecutionError> {
+        let window = self
+            .windows
+            .remove(&id)
+            .ok_or(ExecutionError::UnknownWindowId(id))?;
+
+        let start = Instant::now();
+        loop {
+            let open = fowin::iter_windows().flatten().any(|other| other == window);
+            if !open {
+                return Ok(());
+            }
+            if start.elapsed() >= CLOSE_TIMEOUT {
+                return Err(ValidationError::ClosedMismatch {
+                    expected: true,
+                    actually: false,
+                })?;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
     fn cache_window(&mut self, id: u32, title: &str) -> Result<(), ExecutionError> {
         if self.windows.contains_key(&id) {
             return Ok(());
@@ -109,11 +148,11 @@ impl Executor for FowinExecutor {
                             "fowin set title".to_owned(),
                         )),
                         Mutation::Size(size) => {
-                            window.resize((*size).into())?;
+                            window.resize(fowin::LogicalSize::from(*size))?;
                             Ok(())
                         }
                         Mutation::Position(position) => {
-                            window.reposition((*position).into())?;
+                            window.reposition(fowin::LogicalPosition::from(*position))?;
                             Ok(())
                         }
                         Mutation::Fullscreen(fullscreen) => {
@@ -150,10 +189,30 @@ impl Executor for FowinExecutor {
                 Action::Spawn(_) => Err(ExecutionError::UnsupportedOperation(
                     "fowin spawn window".to_owned(),
                 )),
-                // TODO: I believe this can be done on macos via kAXCloseButtonAttribute and kAXPressButton
-                Action::Terminate => Err(ExecutionError::UnsupportedOperation(
-                    "fowin terminate window".to_owned(),
-                )),
+                Action::Terminate => {
+                    let window = self
+                        .windows
+                        .get(&step.id)
+                        .ok_or(ExecutionError::UnknownWindowId(step.id))?;
+                    match window.close() {
+                        Err(WindowError::Unsupported) => Err(ExecutionError::UnsupportedOperation(
+                            "fowin terminate window".to_owned(),
+                        )),
+                        result => Ok(result?),
+                    }
+                }
+                Action::Kill => {
+                    let window = self
+                        .windows
+                        .get(&step.id)
+                        .ok_or(ExecutionError::UnknownWindowId(step.id))?;
+                    match window.kill() {
+                        Err(WindowError::Unsupported) => Err(ExecutionError::UnsupportedOperation(
+                            "fowin kill window".to_owned(),
+                        )),
+                        result => Ok(result?),
+                    }
+                }
             }
         } else {
             Ok(())
@@ -209,18 +268,18 @@ impl WindowProps for &Window {
     }
 }
 
-impl From<Size> for fowin::Size {
+impl From<Size> for fowin::LogicalSize {
     fn from(size: Size) -> Self {
-        fowin::Size {
+        fowin::LogicalSize {
             width: size.width,
             height: size.height,
         }
     }
 }
 
-impl From<Position> for fowin::Position {
+impl From<Position> for fowin::LogicalPosition {
     fn from(position: Position) -> Self {
-        fowin::Position {
+        fowin::LogicalPosition {
             x: position.x,
             y: position.y,
         }
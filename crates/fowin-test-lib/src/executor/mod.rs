@@ -1,6 +1,7 @@
 use std::{
     error::Error,
     fmt::{self, Debug},
+    io,
 };
 
 use fowin::WindowError;
@@ -9,12 +10,14 @@ use crate::{state::Mutation, timeline::Step, Position, Size};
 #[cfg(feature = "binary_executor")]
 pub use binary_executor::{BinaryExecutor, IpcError, Request, RequestProp, Response};
 pub use fowin_executor::FowinExecutor;
+pub use process_executor::{ProcessExecutor, HOST_WINDOW};
 #[cfg(feature = "winit_executor")]
 pub use winit_executor::WinitExecutor;
 
 #[cfg(feature = "binary_executor")]
 mod binary_executor;
 mod fowin_executor;
+mod process_executor;
 #[cfg(feature = "winit_executor")]
 mod winit_executor;
 
@@ -100,6 +103,7 @@ pub trait Executor {
 pub trait WindowProps {
     fn title(&self) -> Result<String, ExecutionError>;
 
+    // The outer size, see `Mutation::Size`.
     fn size(&self) -> Result<Size, ExecutionError>;
 
     fn position(&self) -> Result<Position, ExecutionError>;
@@ -131,6 +135,7 @@ pub enum ExecutionError {
     UnsupportedOperation(String),
     Validation(ValidationError),
     Fowin(fowin::WindowError),
+    Process(io::Error),
     #[cfg(feature = "binary_executor")]
     Ipc(IpcError),
 }
@@ -152,6 +157,7 @@ impl fmt::Display for ExecutionError {
             }
             ExecutionError::Validation(validation_error) => fmt::Display::fmt(validation_error, f),
             ExecutionError::Fowin(window_err) => fmt::Display::fmt(window_err, f),
+            ExecutionError::Process(err) => write!(f, "failed to run the host process: {}", err),
             #[cfg(feature = "binary_executor")]
             ExecutionError::Ipc(err) => write!(f, "{}", err),
         }
@@ -204,6 +210,10 @@ pub enum ValidationError {
         expected: bool,
         actually: bool,
     },
+    ClosedMismatch {
+        expected: bool,
+        actually: bool,
+    },
 }
 
 impl Error for ValidationError {}
@@ -267,6 +277,13 @@ impl fmt::Display for ValidationError {
                     expected, actually
                 )
             }
+            ValidationError::ClosedMismatch { expected, actually } => {
+                write!(
+                    f,
+                    "mismatched closed, expected `{}`, got `{}`",
+                    expected, actually
+                )
+            }
         }
     }
 }
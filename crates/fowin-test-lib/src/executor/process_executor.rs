@@ -0,0 +1,119 @@
+use std::{
+    collections::HashMap,
+    env,
+    process::{Child, Command},
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::debug;
+
+use crate::{
+    executor::{encode_title, ExecutionError, Executor, WindowProps},
+    timeline::{Action, ExecScope, Step},
+};
+
+/// The environment variable holding the title of the window that a child process of
+/// [`ProcessExecutor`] should host.
+pub const HOST_WINDOW: &str = "FOWIN_TEST_HOST_WINDOW";
+
+// How long the hosted window may take to open.
+const SPAWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Hosts every window in a child process that runs the current executable again, so that fowin can
+/// kill the window without killing the tests. The executable must spawn a window titled by
+/// [`HOST_WINDOW`] when it's set and keep it open.
+///
+/// The windows can't be mutated externally, and only the title of a spawned state is applied.
+#[derive(Debug, Default)]
+pub struct ProcessExecutor {
+    children: HashMap<u32, Child>,
+    windows: HashMap<u32, fowin::Window>,
+    namespace: Option<String>,
+}
+
+impl ProcessExecutor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_namespace<T: Into<String>>(namespace: T) -> Self {
+        Self {
+            children: HashMap::new(),
+            windows: HashMap::new(),
+            namespace: Some(namespace.into()),
+        }
+    }
+
+    // Waits for the hosted window to open, so that it can be found as soon as the step is done.
+    fn find_window(&self, title: &str) -> Option<fowin::Window> {
+        let start = Instant::now();
+        while start.elapsed() < SPAWN_TIMEOUT {
+            let window = fowin::iter_windows()
+                .flatten()
+                .find(|window| window.title().is_ok_and(|other| other == title));
+            if window.is_some() {
+                return window;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        None
+    }
+}
+
+impl Executor for ProcessExecutor {
+    // Steps executed by fowin are left to fowin, the child processes are only reaped once the
+    // executor is dropped so that a window fowin failed to kill is still noticed.
+    fn execute(&mut self, step: &Step) -> Result<(), ExecutionError> {
+        if let ExecScope::Fowin = step.scope {
+            return Ok(());
+        }
+
+        debug!("ProcessExecutor executing step: {:?}", step);
+        match &step.action {
+            Action::Spawn(state) => {
+                let title = encode_title(&self.namespace, step.id, &state.title);
+                let child = env::current_exe()
+                    .and_then(|program| Command::new(program).env(HOST_WINDOW, &title).spawn())
+                    .map_err(ExecutionError::Process)?;
+                self.children.insert(step.id, child);
+
+                let window = self
+                    .find_window(&title)
+                    .ok_or(ExecutionError::UnknownWindowId(step.id))?;
+                self.windows.insert(step.id, window);
+                Ok(())
+            }
+            Action::Terminate | Action::Kill => {
+                self.windows.remove(&step.id);
+                let mut child = self
+                    .children
+                    .remove(&step.id)
+                    .ok_or(ExecutionError::UnknownWindowId(step.id))?;
+                child.kill().map_err(ExecutionError::Process)?;
+                child.wait().map_err(ExecutionError::Process)?;
+                Ok(())
+            }
+            Action::Mutate(_) => Err(ExecutionError::UnsupportedOperation(
+                "process executor mutate window".to_owned(),
+            )),
+        }
+    }
+
+    // The hosted windows can only be read through fowin.
+    fn window_props(&self, id: u32) -> Result<impl WindowProps, ExecutionError> {
+        self.windows
+            .get(&id)
+            .ok_or(ExecutionError::UnknownWindowId(id))
+    }
+}
+
+impl Drop for ProcessExecutor {
+    fn drop(&mut self) {
+        for child in self.children.values_mut() {
+            // The child is already gone if fowin killed its window.
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
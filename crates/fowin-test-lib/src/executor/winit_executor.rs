@@ -58,6 +58,18 @@ impl WinitExecutor {
     }
 }
 
+impl WinitExecutor {
+    /// Keeps handling the events of the windows for the duration, such as while another process
+    /// operates on them.
+    pub fn pump(&mut self, duration: Duration) {
+        let start = Instant::now();
+        while start.elapsed() < duration {
+            self.event_loop
+                .pump_app_events(Some(Duration::from_millis(10)), &mut self.app);
+        }
+    }
+}
+
 impl Default for WinitExecutor {
     fn default() -> Self {
         Self::new()
@@ -71,6 +83,13 @@ impl Executor for WinitExecutor {
     // Note that we ignore the ExecScope here because we want to pump app events even if
     // fowin executes a window operation so that they apply immediately.
     fn execute(&mut self, step: &Step) -> Result<(), ExecutionError> {
+        // The windows belong to this process, so they can't be killed without killing the tests.
+        if let (ExecScope::External, Action::Kill) = (step.scope, &step.action) {
+            return Err(ExecutionError::UnsupportedOperation(
+                "winit kill window".to_owned(),
+            ));
+        }
+
         if let ExecScope::External = step.scope {
             // self.receiver.try_iter().for_each(drop);
             debug!("WinitExecutor executing step: {:?}", step);
@@ -213,24 +232,23 @@ impl ApplicationHandler<Step> for App {
 
                 self.windows.insert(step.id, window);
             }
-            Action::Terminate => {
+            // Kills are refused before they get here.
+            Action::Terminate | Action::Kill => {
                 self.windows.remove(&step.id);
             }
             Action::Mutate(mutation) => {
                 let window = self.windows.get_mut(&step.id).unwrap();
                 match mutation {
                     Mutation::Size(size) => {
-                        // The macOS accessibility API (used in fowin) does two interesting things:
-                        // 1. Includes the title bar size when getting and setting the window size.
-                        // 2. Sets and gets logical sizes rather than physical.
+                        // Sizes are logical outer sizes like in fowin, but winit can only request
+                        // inner sizes, so the decorations are subtracted.
                         let scale_factor = window.scale_factor();
-                        let title_bar_size =
-                            window.outer_size().to_logical::<f64>(scale_factor).height
-                                - window.inner_size().to_logical::<f64>(scale_factor).height;
+                        let outer = window.outer_size().to_logical::<f64>(scale_factor);
+                        let inner = window.inner_size().to_logical::<f64>(scale_factor);
 
                         let _ = window.request_inner_size(LogicalSize {
-                            width: size.width,
-                            height: size.height - title_bar_size,
+                            width: size.width - (outer.width - inner.width),
+                            height: size.height - (outer.height - inner.height),
                         });
                     }
                     Mutation::Position(position) => window.set_outer_position(LogicalPosition {
@@ -266,11 +284,16 @@ impl ApplicationHandler<Step> for App {
     fn window_event(
         &mut self,
         _event_loop: &ActiveEventLoop,
-        _window_id: WindowId,
+        window_id: WindowId,
         event: WindowEvent,
     ) {
         debug!("WinitExecutor received window event: {:?}", event);
 
+        // Honor close requests from fowin, winit leaves closing up to us.
+        if let WindowEvent::CloseRequested = event {
+            self.windows.retain(|_, window| window.id() != window_id);
+        }
+
         // if let WindowEvent::Resized(..) = event {
         // self.sender.send(()).unwrap();
         // }
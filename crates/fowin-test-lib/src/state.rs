@@ -133,6 +133,8 @@ impl Default for State {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Mutation {
     Title(String),
+    // The outer size, including decorations, the same as `Window::size` and `FrameKind::Outer` in
+    // fowin.
     Size(Size),
     Position(Position),
     Fullscreen(bool),
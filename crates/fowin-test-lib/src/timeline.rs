@@ -11,10 +11,10 @@ pub struct Step {
 }
 
 impl Step {
-    pub fn fowin(id: u32, mutation: Mutation) -> Self {
+    pub fn fowin<T: Into<Action>>(id: u32, action: T) -> Self {
         Self {
             id,
-            action: Action::Mutate(mutation),
+            action: action.into(),
             scope: ExecScope::Fowin,
         }
     }
@@ -41,6 +41,8 @@ pub enum Action {
     Mutate(Mutation),
     Spawn(State),
     Terminate,
+    // Kills the process that owns the window, see `ProcessExecutor`.
+    Kill,
 }
 
 impl From<Mutation> for Action {
@@ -1,6 +1,6 @@
 // TODO: use https://github.com/cross-rs/cross
 
-use fowin::WindowError;
+use fowin::{PropertyMask, WindowError};
 
 fn main() -> Result<(), WindowError> {
     if !fowin::request_trust()? {
@@ -8,7 +8,7 @@ fn main() -> Result<(), WindowError> {
     }
 
     // Skip windows that aren't valid.
-    for window in fowin::iter_windows().flatten() {
+    for (window, properties) in fowin::iter_windows_with(PropertyMask::ALL).flatten() {
         println!(
             "handle: {:?}
 title: {:?}
@@ -20,13 +20,13 @@ hidden: {:?}
 focused: {:?}
 ",
             window.handle(),
-            window.title(),
-            window.size(),
-            window.position(),
-            window.is_fullscreen(),
-            window.is_minimized(),
-            window.is_hidden(),
-            window.is_focused()
+            properties.title,
+            properties.size,
+            properties.position,
+            properties.is_fullscreen,
+            properties.is_minimized,
+            properties.is_hidden,
+            properties.is_focused
         );
     }
 
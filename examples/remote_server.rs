@@ -0,0 +1,21 @@
+use std::{env, net::TcpListener};
+
+use fowin::{remote::Server, Session, WindowError};
+
+// Usage: cargo run --example remote_server --features remote -- [ADDRESS]
+fn main() -> Result<(), WindowError> {
+    if !fowin::request_trust()? {
+        return Err(WindowError::NotTrusted);
+    }
+
+    let address = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:7878".to_owned());
+    let listener = TcpListener::bind(&address).map_err(WindowError::OsError)?;
+
+    let session = Session::new()?;
+    println!("Serving windows from {:?} on {address}", session.backend());
+    Server::new(session)
+        .serve_tcp(&listener)
+        .map_err(WindowError::OsError)
+}
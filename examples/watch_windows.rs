@@ -1,4 +1,6 @@
-use fowin::{Watcher, WindowError, WindowEvent};
+use std::fmt::Debug;
+
+use fowin::{Watcher, Window, WindowError, WindowEvent};
 
 fn main() -> Result<(), WindowError> {
     if !fowin::request_trust()? {
@@ -19,10 +21,36 @@ fn main() -> Result<(), WindowError> {
                     WindowEvent::Shown(window) => (window.title(), "shown"),
                     WindowEvent::Minimized(window) => (window.title(), "minimized"),
                     WindowEvent::Unminimized(window) => (window.title(), "unminimized"),
+                    WindowEvent::Maximized(window) => (window.title(), "maximized"),
+                    WindowEvent::Unmaximized(window) => (window.title(), "unmaximized"),
+                    WindowEvent::Fullscreened(window) => (window.title(), "fullscreened"),
+                    WindowEvent::Unfullscreened(window) => (window.title(), "unfullscreened"),
                     WindowEvent::Focused(window) => (window.title(), "focused"),
-                    WindowEvent::Moved(window) => (window.title(), "moved"),
-                    WindowEvent::Resized(window) => (window.title(), "resized"),
-                    WindowEvent::Renamed(window) => (window.title(), "renamed"),
+                    WindowEvent::Unfocused(window) => (window.title(), "unfocused"),
+                    WindowEvent::MoveResizeStarted(window) => (window.title(), "grabbed"),
+                    WindowEvent::MoveResizeEnded(window) => (window.title(), "released"),
+                    WindowEvent::Moved { window, old, new } => {
+                        print_change(&window, "moved", old, new);
+                        continue;
+                    }
+                    WindowEvent::Resized { window, old, new } => {
+                        print_change(&window, "resized", old, new);
+                        continue;
+                    }
+                    WindowEvent::Renamed {
+                        old_title,
+                        new_title,
+                        ..
+                    } => {
+                        let old_title = old_title.as_deref().unwrap_or("UNKNOWN");
+                        let new_title = new_title.as_deref().unwrap_or("UNKNOWN");
+                        println!("Window `{old_title}` has been renamed to `{new_title}`!");
+                        continue;
+                    }
+                    event => {
+                        print_application_event(event);
+                        continue;
+                    }
                 };
                 let name = name.as_deref().unwrap_or("UNKNOWN");
 
@@ -34,3 +62,28 @@ fn main() -> Result<(), WindowError> {
         }
     }
 }
+
+fn print_change<T: Debug>(window: &Window, kind: &str, old: Option<T>, new: Option<T>) {
+    let name = window.title();
+    let name = name.as_deref().unwrap_or("UNKNOWN");
+
+    println!("Window `{name}` has been {kind} from {old:?} to {new:?}!");
+}
+
+fn print_application_event(event: WindowEvent) {
+    let (application, kind) = match event {
+        WindowEvent::ApplicationLaunched(application) => (application, "launched"),
+        WindowEvent::ApplicationTerminated(pid) => {
+            println!("Application {pid} has been terminated!");
+            return;
+        }
+        WindowEvent::ApplicationActivated(application) => (application, "activated"),
+        WindowEvent::ApplicationHidden(application) => (application, "hidden"),
+        WindowEvent::ApplicationShown(application) => (application, "shown"),
+        _ => return,
+    };
+    let name = application.name();
+    let name = name.as_deref().unwrap_or("UNKNOWN");
+
+    println!("Application `{name}` has been {kind}!");
+}
@@ -1,12 +1,34 @@
-pub use protocol::{Position, Size, Window, WindowError, WindowEvent, WindowHandle};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
+use protocol::Coalescer;
+pub use protocol::{
+    available_backends, Application, ApplicationOps, Backend, Change, Coalesce, EventEnvelope,
+    EventFilter, EventMask, EventSource, FrameKind, Insets, LogicalPosition, LogicalSize,
+    PhysicalPosition, PhysicalSize, Position, PropertyMask, Rect, Session, Size, Snapshot,
+    Transaction, Window, WindowBackend, WindowError, WindowEvent, WindowHandle, WindowOps,
+    WindowProperties, WindowSnapshot,
+};
+
+#[cfg(feature = "mock")]
+pub mod mock;
 mod protocol;
+#[cfg(feature = "remote")]
+pub mod remote;
 mod sys;
 
 /// A handle that provides various methods for interacting with windows and window events.
 #[derive(Debug)]
 pub struct Watcher {
-    inner: sys::Watcher,
+    inner: Box<dyn EventSource>,
+    source: Backend,
+    // The sequence number of the next event.
+    sequence: u64,
+    // An error that ended a batch early, returned by the next call instead.
+    error: Option<WindowError>,
+    coalescer: Option<Coalescer>,
 }
 
 impl Watcher {
@@ -14,22 +36,304 @@ impl Watcher {
     ///
     /// To stop watching events, drop the returned [Watcher].
     ///
-    /// Note, this function will begin listening to new events. To access a list of
-    /// existing windows, call [`iter_windows`].
+    /// Note, this function will begin listening to new events. To also receive the windows that
+    /// already exist, call [`Watcher::with_initial_state`].
+    ///
+    /// Events are received from the backend detected by [`Session::new`], to choose a different
+    /// backend, call [`Session::watch`]. To only watch some windows or events, call
+    /// [`Watcher::builder`].
     #[inline]
     pub fn new() -> Result<Watcher, WindowError> {
-        Ok(Watcher {
-            inner: sys::Watcher::new()?,
-        })
+        Session::global()?.watch()
+    }
+
+    /// Watches for all window events, starting with a [`WindowEvent::Opened`] for every window
+    /// that already exists.
+    ///
+    /// Unlike calling [`iter_windows`] after [`Watcher::new`], windows opened in between are
+    /// neither missed nor reported twice, every window is reported as opened once. A window that
+    /// closes before the existing windows are listed isn't reported as closed either.
+    ///
+    /// To choose a different backend, call [`Session::watch_with_initial_state`].
+    #[inline]
+    pub fn with_initial_state() -> Result<Watcher, WindowError> {
+        Session::global()?.watch_with_initial_state()
+    }
+
+    /// Configures a watcher for only some window events, such as those of a single window or
+    /// application.
+    ///
+    /// ```no_run
+    /// # use fowin::{EventMask, Watcher};
+    /// let mut watcher = Watcher::builder()
+    ///     .events(EventMask::MOVED | EventMask::RESIZED)
+    ///     .title_matches(|title| title.ends_with("- Visual Studio Code"))
+    ///     .build()?;
+    /// # Ok::<(), fowin::WindowError>(())
+    /// ```
+    ///
+    /// To choose a different backend, call [`Session::watcher`].
+    #[inline]
+    pub fn builder() -> WatcherBuilder {
+        WatcherBuilder::new(None)
+    }
+
+    pub(crate) fn from_source(inner: Box<dyn EventSource>, source: Backend) -> Watcher {
+        Watcher {
+            inner,
+            source,
+            sequence: 0,
+            error: None,
+            coalescer: None,
+        }
+    }
+
+    /// Reduces bursts of events, such as while a window is dragged, according to the policy.
+    ///
+    /// Held events are released once they're ready while waiting for the next event. If the
+    /// backend can't wait with a timeout, see [`EventSource::next_request_timeout`], they're only
+    /// released once another event is received.
+    pub fn coalesce(mut self, policy: Coalesce) -> Watcher {
+        self.coalescer = Some(Coalescer::new(policy));
+        self
     }
 
     /// Returns the next window event.
     ///
-    /// Note, these events are not guaranteed to be precisely ordered. However, they do provide
-    /// a timestamp that can be used for ordering. Consider buffering events if order is important.
+    /// Note, events are returned in the order they're received from the backend, which isn't
+    /// necessarily the order they happened in.
     #[inline]
     pub fn next_request(&mut self) -> Result<WindowEvent, WindowError> {
-        self.inner.next_request()
+        self.next_envelope().map(|envelope| envelope.event)
+    }
+
+    /// Returns the next window event, along with when and from which backend it was received.
+    pub fn next_envelope(&mut self) -> Result<EventEnvelope, WindowError> {
+        loop {
+            if let Some(envelope) = self.receive(None)? {
+                return Ok(envelope);
+            }
+        }
+    }
+
+    /// Waits for the next window event, then collects every event received within `interval` of
+    /// it.
+    ///
+    /// The batch is ordered by timestamp, with the sequence number breaking ties, and every event
+    /// in it was received after every event of the previous batch. Events are received in the
+    /// order the backend reports them, which isn't necessarily the order they happened in, such as
+    /// when an application is slow to respond. Sorting by timestamp restores that order where the
+    /// backend reports when events happened, see [`EventEnvelope::timestamp`], and a longer
+    /// interval gives late events more time to arrive. Otherwise, the batch is in the order the
+    /// events were received.
+    ///
+    /// If the backend can't wait with a timeout, see [`EventSource::next_request_timeout`], every
+    /// batch holds a single event.
+    pub fn next_batch(&mut self, interval: Duration) -> Result<Vec<EventEnvelope>, WindowError> {
+        let first = self.next_envelope()?;
+        let deadline = Instant::now() + interval;
+        let mut batch = vec![first];
+
+        loop {
+            match self.receive(Some(deadline)) {
+                Ok(Some(envelope)) => batch.push(envelope),
+                Ok(None) | Err(WindowError::Unsupported) => break,
+                // The events received so far would be lost, so the error is returned next time.
+                Err(err) => {
+                    self.error = Some(err);
+                    break;
+                }
+            }
+        }
+
+        // Held events may have been received before the events that overtook them too.
+        batch.sort_by_key(|envelope| (envelope.timestamp, envelope.sequence));
+        Ok(batch)
+    }
+
+    // Returns the next event, or `None` if the deadline passed first.
+    fn receive(&mut self, deadline: Option<Instant>) -> Result<Option<EventEnvelope>, WindowError> {
+        if self.coalescer.is_none() {
+            if let Some(err) = self.error.take() {
+                return Err(err);
+            }
+            return Ok(self.wait(deadline)?.map(|event| self.stamp(event)));
+        }
+
+        loop {
+            let now = Instant::now();
+            // The coalescer was just checked.
+            let coalescer = self.coalescer.as_mut().unwrap();
+            if let Some(envelope) = coalescer.release(now) {
+                return Ok(Some(envelope));
+            }
+            // Errors are returned once the events received before them are released.
+            if let Some(err) = self.error.take() {
+                return Err(err);
+            }
+            if deadline.is_some_and(|deadline| deadline <= now) {
+                return Ok(None);
+            }
+
+            // Wait for the next event, but no longer than until a held event is ready.
+            let wake = match (deadline, coalescer.next_ready()) {
+                (Some(deadline), Some(ready)) => Some(deadline.min(ready)),
+                (deadline, ready) => deadline.or(ready),
+            };
+            let event = match self.wait(wake) {
+                Ok(Some(event)) => event,
+                Ok(None) => continue,
+                Err(WindowError::Unsupported) if deadline.is_none() => self.inner.next_request()?,
+                Err(err) => return Err(err),
+            };
+            self.hold(event);
+
+            // Events that already arrived are held too, so they can be merged before anything is
+            // released.
+            loop {
+                match self.inner.next_request_timeout(Duration::ZERO) {
+                    Ok(Some(event)) => self.hold(event),
+                    Ok(None) | Err(WindowError::Unsupported) => break,
+                    Err(err) => {
+                        self.error = Some(err);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    // Waits for the next event until the deadline, or indefinitely if there is none.
+    fn wait(&mut self, deadline: Option<Instant>) -> Result<Option<WindowEvent>, WindowError> {
+        match deadline {
+            Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                Some(timeout) => self.inner.next_request_timeout(timeout),
+                None => Ok(None),
+            },
+            None => self.inner.next_request().map(Some),
+        }
+    }
+
+    fn hold(&mut self, event: WindowEvent) {
+        let envelope = self.stamp(event);
+        // Only called while coalescing.
+        self.coalescer.as_mut().unwrap().push(envelope);
+    }
+
+    // Events are stamped with when they happened if the backend reports it, otherwise with when
+    // they're received.
+    fn stamp(&mut self, event: WindowEvent) -> EventEnvelope {
+        let sequence = self.sequence;
+        self.sequence += 1;
+        EventEnvelope {
+            event,
+            timestamp: self.inner.event_time().unwrap_or_else(Instant::now),
+            sequence,
+            source: self.source,
+        }
+    }
+}
+
+/// Configures which window events a [`Watcher`] reports, see [`Watcher::builder`].
+///
+/// An event is reported if it passes every filter that is set, and any of the values given to a
+/// filter that is called several times, such as [`WatcherBuilder::window`]. Filters on windows,
+/// their title or application drop the events of windows that don't match. Application events
+/// are dropped by filters on windows and titles.
+///
+/// A window's application and title are read from the backend when the watcher is built for the
+/// windows that are already open, and as they're received for the windows that open later. The
+/// events of a window that already closed by the time its opened event is received are dropped.
+/// The windows that are already open are listed once, and only the properties that the requested
+/// events are compared on are read, for the windows that could pass the filter.
+///
+/// Backends use the filter to only observe what is needed. On macOS, applications that are
+/// filtered out aren't observed at all, and only the notifications for the requested events are
+/// registered. On Windows, a single pid limits the hook to that process. Other backends receive
+/// every event regardless, the watcher drops those that don't pass.
+#[derive(Debug)]
+pub struct WatcherBuilder {
+    session: Option<Session>,
+    filter: EventFilter,
+    initial_state: bool,
+}
+
+impl WatcherBuilder {
+    pub(crate) fn new(session: Option<Session>) -> WatcherBuilder {
+        WatcherBuilder {
+            session,
+            filter: EventFilter::default(),
+            initial_state: false,
+        }
+    }
+
+    /// Only reports the kinds of events in the mask.
+    pub fn events(mut self, events: EventMask) -> WatcherBuilder {
+        self.filter.events = events;
+        self
+    }
+
+    /// Only reports the events of the window.
+    pub fn window(mut self, handle: WindowHandle) -> WatcherBuilder {
+        self.filter
+            .windows
+            .get_or_insert_with(Vec::new)
+            .push(handle);
+        self
+    }
+
+    /// Only reports the events of the application and its windows.
+    #[inline]
+    pub fn application(self, application: &Application) -> WatcherBuilder {
+        self.pid(application.pid())
+    }
+
+    /// Only reports the events of the application with the process id and its windows.
+    pub fn pid(mut self, pid: u32) -> WatcherBuilder {
+        self.filter.pids.get_or_insert_with(Vec::new).push(pid);
+        self
+    }
+
+    /// Only reports the events of windows whose title passes the predicate.
+    ///
+    /// The title is checked when the first event of a window is received, and again whenever
+    /// it's renamed, so a window is only reported while its title matches.
+    pub fn title_matches(
+        mut self,
+        predicate: impl Fn(&str) -> bool + Send + Sync + 'static,
+    ) -> WatcherBuilder {
+        self.filter.title = Some(Arc::new(predicate));
+        self
+    }
+
+    /// Only reports the events of applications that pass the predicate, and their windows.
+    ///
+    /// Each application is only checked once.
+    pub fn application_matches(
+        mut self,
+        predicate: impl Fn(&Application) -> bool + Send + Sync + 'static,
+    ) -> WatcherBuilder {
+        self.filter.application = Some(Arc::new(predicate));
+        self
+    }
+
+    /// Starts with a [`WindowEvent::Opened`] for every window that already exists and passes the
+    /// filter, see [`Watcher::with_initial_state`].
+    pub fn initial_state(mut self) -> WatcherBuilder {
+        self.initial_state = true;
+        self
+    }
+
+    /// Starts watching for the events that pass the filter.
+    ///
+    /// Unless a session was given with [`Session::watcher`], events are received from the
+    /// backend detected by [`Session::new`].
+    pub fn build(self) -> Result<Watcher, WindowError> {
+        let session = match self.session {
+            Some(session) => session,
+            None => Session::global()?,
+        };
+        session.watch_filtered(self.filter, self.initial_state)
     }
 }
 
@@ -50,11 +354,39 @@ pub fn request_trust() -> Result<bool, WindowError> {
 /// Returns an iterator over all existing windows.
 #[inline]
 pub fn iter_windows() -> impl Iterator<Item = Result<Window, WindowError>> {
-    sys::iter_windows().map(|result| result.map(Window))
+    let (windows, err) = match Session::global() {
+        Ok(session) => (Some(session.iter_windows()), None),
+        Err(err) => (None, Some(Err(err))),
+    };
+    windows.into_iter().flatten().chain(err)
+}
+
+/// Returns an iterator over all existing windows, along with the properties in the mask.
+///
+/// Read [`Window::properties`] for more information.
+#[inline]
+pub fn iter_windows_with(
+    mask: PropertyMask,
+) -> impl Iterator<Item = Result<(Window, WindowProperties), WindowError>> {
+    let (windows, err) = match Session::global() {
+        Ok(session) => (Some(session.iter_windows_with(mask)), None),
+        Err(err) => (None, Some(Err(err))),
+    };
+    windows.into_iter().flatten().chain(err)
+}
+
+/// Returns an iterator over all running applications that own windows.
+#[inline]
+pub fn iter_applications() -> impl Iterator<Item = Result<Application, WindowError>> {
+    let (applications, err) = match Session::global() {
+        Ok(session) => (Some(session.iter_applications()), None),
+        Err(err) => (None, Some(Err(err))),
+    };
+    applications.into_iter().flatten().chain(err)
 }
 
 /// Returns the globally focused window if one exists.
 #[inline]
 pub fn focused_window() -> Result<Option<Window>, WindowError> {
-    sys::focused_window().map(|option| option.map(Window))
+    Session::global()?.focused_window()
 }
@@ -0,0 +1,1754 @@
+//! An in-memory desktop for testing code built on fowin without a display server.
+//!
+//! A [`MockDesktop`] models a set of windows and the applications owning them, along with their
+//! stacking order and focus. Every
+//! [`Window`] operation is applied to that model and the matching [`WindowEvent`]s are delivered
+//! to watchers, the same as a real backend would. The desktop itself acts as the other
+//! applications, spawning, closing, renaming, and moving windows on its own.
+//!
+//! ```
+//! use fowin::{mock::MockDesktop, LogicalPosition, LogicalSize, WindowEvent};
+//!
+//! let desktop = MockDesktop::new();
+//! let session = desktop.session();
+//! let mut watcher = session.watch()?;
+//!
+//! let position = LogicalPosition { x: 0.0, y: 0.0 };
+//! let size = LogicalSize { width: 800.0, height: 600.0 };
+//! let window = desktop.spawn("editor", position, size);
+//! assert!(matches!(watcher.next_request()?, WindowEvent::Opened(_)));
+//!
+//! window.minimize()?;
+//! assert!(matches!(watcher.next_request()?, WindowEvent::Minimized(_)));
+//! # Ok::<(), fowin::WindowError>(())
+//! ```
+
+use std::{
+    fmt, io,
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex, Weak,
+    },
+    time::Duration,
+};
+
+use crate::{
+    protocol::HandleKind, Application, ApplicationOps, Backend, EventSource, Insets,
+    LogicalPosition, LogicalSize, PropertyMask, Rect, Session, Window, WindowBackend, WindowError,
+    WindowEvent, WindowHandle, WindowOps, WindowProperties,
+};
+
+type Event = Result<WindowEvent, WindowError>;
+
+/// An operation that can be made to fail with [`MockDesktop::fail`].
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Operation {
+    /// [`Window::title`]
+    Title,
+    /// [`Window::size`]
+    Size,
+    /// [`Window::position`]
+    Position,
+    /// [`Window::is_focused`]
+    IsFocused,
+    /// [`Window::is_fullscreen`]
+    IsFullscreen,
+    /// [`Window::is_minimized`]
+    IsMinimized,
+    /// [`Window::is_hidden`]
+    IsHidden,
+    /// [`Window::scale_factor`]
+    ScaleFactor,
+    /// [`Window::properties`]
+    Properties,
+    /// [`Window::resize`]
+    Resize,
+    /// [`Window::reposition`]
+    Reposition,
+    /// [`Window::outer_frame`]
+    Frame,
+    /// [`Window::set_frame`]
+    SetFrame,
+    /// [`Window::decoration_insets`]
+    DecorationInsets,
+    /// [`Window::focus`]
+    Focus,
+    /// [`Window::fullscreen`]
+    Fullscreen,
+    /// [`Window::unfullscreen`]
+    Unfullscreen,
+    /// [`Window::maximize`]
+    Maximize,
+    /// [`Window::minimize`]
+    Minimize,
+    /// [`Window::unminimize`]
+    Unminimize,
+    /// [`Window::show`]
+    Show,
+    /// [`Window::hide`]
+    Hide,
+    /// [`Window::bring_to_front`]
+    BringToFront,
+    /// [`Window::close`]
+    Close,
+    /// [`Window::kill`]
+    Kill,
+    /// [`Window::application`]
+    Application,
+    /// [`Session::iter_windows`]
+    IterWindows,
+    /// [`Session::iter_applications`]
+    IterApplications,
+    /// [`Session::focused_window`]
+    FocusedWindow,
+    /// [`Session::stacking_order`]
+    StackingOrder,
+    /// [`Session::watch`]
+    Watch,
+}
+
+/// A scripted virtual desktop, see the [module documentation](self).
+///
+/// Cloning a desktop returns another reference to the same desktop.
+#[derive(Debug, Clone)]
+pub struct MockDesktop {
+    inner: Arc<Mutex<Desktop>>,
+}
+
+impl MockDesktop {
+    /// Creates an empty desktop with a 1920x1080 screen.
+    pub fn new() -> MockDesktop {
+        MockDesktop::with_screen(LogicalSize {
+            width: 1920.0,
+            height: 1080.0,
+        })
+    }
+
+    /// Creates an empty desktop with a screen of the specified size.
+    ///
+    /// Maximized and fullscreened windows fill the screen.
+    pub fn with_screen(screen: LogicalSize) -> MockDesktop {
+        MockDesktop {
+            inner: Arc::new_cyclic(|this| {
+                Mutex::new(Desktop {
+                    this: this.clone(),
+                    screen,
+                    scale_factor: 1.0,
+                    decoration_insets: Insets::default(),
+                    next_id: 0,
+                    next_pid: 1,
+                    windows: Vec::new(),
+                    applications: Vec::new(),
+                    focused: None,
+                    active: None,
+                    failures: Vec::new(),
+                    subscribers: Vec::new(),
+                })
+            }),
+        }
+    }
+
+    /// A session whose windows and events come from this desktop.
+    ///
+    /// Pass it to [`Session::set_global`] to use the desktop from the free functions as well.
+    pub fn session(&self) -> Session {
+        Session::from_backend(MockBackend {
+            desktop: self.inner.clone(),
+        })
+    }
+
+    /// Starts a new application without any windows.
+    ///
+    /// Unlike the built-in backends on Windows and Linux, the desktop knows about applications
+    /// without windows, so it reports them as soon as they launch.
+    pub fn launch(
+        &self,
+        name: impl Into<String>,
+        executable_path: impl Into<PathBuf>,
+    ) -> Application {
+        let mut desktop = self.inner.lock().unwrap();
+        let pid = desktop.next_pid;
+        desktop.next_pid += 1;
+        desktop.applications.push(Process {
+            pid,
+            name: name.into(),
+            executable_path: executable_path.into(),
+            hidden: false,
+        });
+
+        desktop.emit(|desktop| WindowEvent::ApplicationLaunched(desktop.application(pid)));
+        desktop.application(pid)
+    }
+
+    /// Closes every window of the application, then terminates it.
+    pub fn quit(&self, application: &Application) -> Result<(), WindowError> {
+        self.inner.lock().unwrap().terminate(application.pid())
+    }
+
+    /// Hides the application along with all of its windows, like hiding an application on macOS.
+    pub fn hide_application(&self, application: &Application) -> Result<(), WindowError> {
+        self.inner
+            .lock()
+            .unwrap()
+            .set_application_hidden(application.pid(), true)
+    }
+
+    /// Shows the application along with all of its windows.
+    pub fn show_application(&self, application: &Application) -> Result<(), WindowError> {
+        self.inner
+            .lock()
+            .unwrap()
+            .set_application_hidden(application.pid(), false)
+    }
+
+    /// Opens a new window on top of every other window, without focusing it.
+    ///
+    /// The window doesn't belong to any application, so [`Window::application`] is unsupported.
+    /// Use [`MockDesktop::spawn_for`] to open a window for an application.
+    pub fn spawn(
+        &self,
+        title: impl Into<String>,
+        position: LogicalPosition,
+        size: LogicalSize,
+    ) -> Window {
+        self.inner
+            .lock()
+            .unwrap()
+            .spawn(None, title.into(), position, size)
+    }
+
+    /// Same as [`MockDesktop::spawn`], but the window belongs to an application started with
+    /// [`MockDesktop::launch`].
+    pub fn spawn_for(
+        &self,
+        application: &Application,
+        title: impl Into<String>,
+        position: LogicalPosition,
+        size: LogicalSize,
+    ) -> Result<Window, WindowError> {
+        let mut desktop = self.inner.lock().unwrap();
+        desktop.process(application.pid())?;
+        Ok(desktop.spawn(Some(application.pid()), title.into(), position, size))
+    }
+
+    /// Closes the window, as if its application closed it.
+    pub fn close(&self, handle: &WindowHandle) -> Result<(), WindowError> {
+        let mut desktop = self.inner.lock().unwrap();
+        let index = desktop.index(id(handle)?)?;
+        desktop.remove(index);
+        Ok(())
+    }
+
+    /// Changes the title of the window.
+    pub fn rename(
+        &self,
+        handle: &WindowHandle,
+        title: impl Into<String>,
+    ) -> Result<(), WindowError> {
+        let mut desktop = self.inner.lock().unwrap();
+        let index = desktop.index(id(handle)?)?;
+        let title = title.into();
+        if desktop.windows[index].title != title {
+            let old_title = std::mem::replace(&mut desktop.windows[index].title, title.clone());
+            let id = desktop.windows[index].id;
+            desktop.emit(|desktop| WindowEvent::Renamed {
+                window: desktop.window(id),
+                old_title: Some(old_title.clone()),
+                new_title: Some(title.clone()),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Moves the window, as if the user dragged it.
+    pub fn move_to(
+        &self,
+        handle: &WindowHandle,
+        position: LogicalPosition,
+    ) -> Result<(), WindowError> {
+        let mut desktop = self.inner.lock().unwrap();
+        let index = desktop.index(id(handle)?)?;
+        desktop.set_position(index, position);
+        Ok(())
+    }
+
+    /// Resizes the window, as if the user dragged its border.
+    pub fn resize(&self, handle: &WindowHandle, size: LogicalSize) -> Result<(), WindowError> {
+        let mut desktop = self.inner.lock().unwrap();
+        let index = desktop.index(id(handle)?)?;
+        desktop.set_size(index, size);
+        Ok(())
+    }
+
+    /// Moves and resizes the window in a single interaction, as if the user dragged its corner.
+    ///
+    /// The moved and resized events are surrounded by [`WindowEvent::MoveResizeStarted`] and
+    /// [`WindowEvent::MoveResizeEnded`].
+    pub fn drag(&self, handle: &WindowHandle, frame: Rect) -> Result<(), WindowError> {
+        let mut desktop = self.inner.lock().unwrap();
+        let index = desktop.index(id(handle)?)?;
+        let id = desktop.windows[index].id;
+        desktop.emit(|desktop| WindowEvent::MoveResizeStarted(desktop.window(id)));
+        desktop.set_frame(index, frame);
+        desktop.emit(|desktop| WindowEvent::MoveResizeEnded(desktop.window(id)));
+        Ok(())
+    }
+
+    /// Sets the scale factor of the screen, which is `1.0` by default.
+    ///
+    /// Window geometry is kept in logical pixels, so changing the scale factor doesn't move or
+    /// resize any window.
+    pub fn set_scale_factor(&self, scale_factor: f64) {
+        self.inner.lock().unwrap().scale_factor = scale_factor;
+    }
+
+    /// Sets the decorations around the content of every window, which are empty by default.
+    ///
+    /// Window geometry refers to the outer frame, so changing the decorations leaves it as it is
+    /// and shrinks the content instead.
+    pub fn set_decoration_insets(&self, insets: Insets) {
+        self.inner.lock().unwrap().decoration_insets = insets;
+    }
+
+    /// The handles of every window, from the front to the back.
+    pub fn stacking_order(&self) -> Vec<WindowHandle> {
+        self.inner.lock().unwrap().stacking_order()
+    }
+
+    /// Makes the operation fail with the error returned by `error` for every window, until
+    /// [`MockDesktop::clear_failures`] is called.
+    pub fn fail(&self, operation: Operation, error: impl Fn() -> WindowError + Send + 'static) {
+        self.inner.lock().unwrap().failures.push(Failure {
+            target: None,
+            operation,
+            error: Box::new(error),
+        });
+    }
+
+    /// Same as [`MockDesktop::fail`], but only for the specified window.
+    pub fn fail_window(
+        &self,
+        handle: &WindowHandle,
+        operation: Operation,
+        error: impl Fn() -> WindowError + Send + 'static,
+    ) -> Result<(), WindowError> {
+        let target = id(handle)?;
+        self.inner.lock().unwrap().failures.push(Failure {
+            target: Some(target),
+            operation,
+            error: Box::new(error),
+        });
+        Ok(())
+    }
+
+    /// Removes every failure added by [`MockDesktop::fail`] and [`MockDesktop::fail_window`].
+    pub fn clear_failures(&self) {
+        self.inner.lock().unwrap().failures.clear();
+    }
+}
+
+impl Default for MockDesktop {
+    fn default() -> Self {
+        MockDesktop::new()
+    }
+}
+
+// Mock windows use custom handles, anything else can't belong to the desktop.
+fn id(handle: &WindowHandle) -> Result<u64, WindowError> {
+    match handle.0 {
+        HandleKind::Custom(id) => Ok(id),
+        _ => Err(WindowError::InvalidHandle),
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    id: u64,
+    // The application owning the window, if any.
+    pid: Option<u32>,
+    title: String,
+    position: LogicalPosition,
+    size: LogicalSize,
+    minimized: bool,
+    maximized: bool,
+    fullscreen: bool,
+    hidden: bool,
+    // The frame to go back to after leaving fullscreen.
+    restore: Option<(LogicalPosition, LogicalSize)>,
+}
+
+#[derive(Debug)]
+struct Process {
+    pid: u32,
+    name: String,
+    executable_path: PathBuf,
+    hidden: bool,
+}
+
+struct Failure {
+    // Applies to every window if unset.
+    target: Option<u64>,
+    operation: Operation,
+    error: Box<dyn Fn() -> WindowError + Send>,
+}
+
+impl fmt::Debug for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Failure")
+            .field("target", &self.target)
+            .field("operation", &self.operation)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug)]
+struct Desktop {
+    // Lets the desktop hand out windows that refer back to it.
+    this: Weak<Mutex<Desktop>>,
+    screen: LogicalSize,
+    scale_factor: f64,
+    decoration_insets: Insets,
+    next_id: u64,
+    next_pid: u32,
+    // Ordered from the back to the front.
+    windows: Vec<State>,
+    applications: Vec<Process>,
+    focused: Option<u64>,
+    // The application that last had focus.
+    active: Option<u32>,
+    failures: Vec<Failure>,
+    subscribers: Vec<Sender<Event>>,
+}
+
+impl Desktop {
+    fn window(&self, id: u64) -> Window {
+        Window::new(MockWindow {
+            id,
+            // The desktop is locked through an `Arc`, so it must still be alive.
+            desktop: self.this.upgrade().unwrap(),
+        })
+    }
+
+    fn application(&self, pid: u32) -> Application {
+        Application::new(MockApplication {
+            pid,
+            desktop: self.this.upgrade().unwrap(),
+        })
+    }
+
+    // Windows are kept from the back to the front.
+    fn stacking_order(&self) -> Vec<WindowHandle> {
+        self.windows
+            .iter()
+            .rev()
+            .map(|state| WindowHandle::custom(state.id))
+            .collect()
+    }
+
+    fn process(&self, pid: u32) -> Result<&Process, WindowError> {
+        self.applications
+            .iter()
+            .find(|process| process.pid == pid)
+            .ok_or(WindowError::InvalidHandle)
+    }
+
+    fn spawn(
+        &mut self,
+        pid: Option<u32>,
+        title: String,
+        position: LogicalPosition,
+        size: LogicalSize,
+    ) -> Window {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.windows.push(State {
+            id,
+            pid,
+            title,
+            position,
+            size,
+            minimized: false,
+            maximized: false,
+            fullscreen: false,
+            hidden: false,
+            restore: None,
+        });
+
+        self.emit(|desktop| WindowEvent::Opened(desktop.window(id)));
+        self.window(id)
+    }
+
+    fn remove(&mut self, index: usize) {
+        let state = self.windows.remove(index);
+        if self.focused == Some(state.id) {
+            self.focused = None;
+        }
+
+        self.emit(|_| WindowEvent::Closed(WindowHandle::custom(state.id)));
+    }
+
+    // Closes every window of the application, then terminates it.
+    fn terminate(&mut self, pid: u32) -> Result<(), WindowError> {
+        self.process(pid)?;
+        while let Some(index) = self.windows.iter().position(|state| state.pid == Some(pid)) {
+            self.remove(index);
+        }
+
+        self.applications.retain(|process| process.pid != pid);
+        if self.active == Some(pid) {
+            self.active = None;
+        }
+        self.emit(|_| WindowEvent::ApplicationTerminated(pid));
+        Ok(())
+    }
+
+    fn set_application_hidden(&mut self, pid: u32, hidden: bool) -> Result<(), WindowError> {
+        let process = self
+            .applications
+            .iter_mut()
+            .find(|process| process.pid == pid)
+            .ok_or(WindowError::InvalidHandle)?;
+        if process.hidden == hidden {
+            return Ok(());
+        }
+
+        process.hidden = hidden;
+        self.emit(|desktop| {
+            let application = desktop.application(pid);
+            if hidden {
+                WindowEvent::ApplicationHidden(application)
+            } else {
+                WindowEvent::ApplicationShown(application)
+            }
+        });
+        // Like macOS, every window of the application follows.
+        for index in 0..self.windows.len() {
+            if self.windows[index].pid == Some(pid) && self.windows[index].hidden != hidden {
+                self.set_hidden(index, hidden);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn set_hidden(&mut self, index: usize, hidden: bool) {
+        let id = self.windows[index].id;
+        self.windows[index].hidden = hidden;
+        if hidden {
+            self.emit(|desktop| WindowEvent::Hidden(desktop.window(id)));
+            self.unfocus(index);
+        } else {
+            self.emit(|desktop| WindowEvent::Shown(desktop.window(id)));
+        }
+    }
+
+    fn index(&self, id: u64) -> Result<usize, WindowError> {
+        self.windows
+            .iter()
+            .position(|state| state.id == id)
+            .ok_or(WindowError::InvalidHandle)
+    }
+
+    fn check(&self, target: Option<u64>, operation: Operation) -> Result<(), WindowError> {
+        match self.failures.iter().find(|failure| {
+            failure.operation == operation && (failure.target.is_none() || failure.target == target)
+        }) {
+            Some(failure) => Err((failure.error)()),
+            None => Ok(()),
+        }
+    }
+
+    fn emit(&mut self, event: impl Fn(&Desktop) -> WindowEvent) {
+        let mut subscribers = std::mem::take(&mut self.subscribers);
+        // If the receiver is gone, then the watcher was dropped and we can remove it.
+        subscribers.retain(|subscriber| subscriber.send(Ok(event(self))).is_ok());
+        self.subscribers = subscribers;
+    }
+
+    fn set_position(&mut self, index: usize, position: LogicalPosition) {
+        let old = self.windows[index].position;
+        if old != position {
+            self.windows[index].position = position;
+            let id = self.windows[index].id;
+            self.emit(|desktop| WindowEvent::Moved {
+                window: desktop.window(id),
+                old: Some(old),
+                new: Some(position),
+            });
+            self.unmaximize(index);
+        }
+    }
+
+    fn set_size(&mut self, index: usize, size: LogicalSize) {
+        let old = self.windows[index].size;
+        if old != size {
+            self.windows[index].size = size;
+            let id = self.windows[index].id;
+            self.emit(|desktop| WindowEvent::Resized {
+                window: desktop.window(id),
+                old: Some(old),
+                new: Some(size),
+            });
+            self.unmaximize(index);
+        }
+    }
+
+    fn set_frame(&mut self, index: usize, frame: Rect) {
+        self.set_position(index, frame.position);
+        self.set_size(index, frame.size);
+    }
+
+    fn fill_screen(&mut self, index: usize) {
+        self.set_frame(
+            index,
+            Rect {
+                position: LogicalPosition { x: 0.0, y: 0.0 },
+                size: self.screen,
+            },
+        );
+    }
+
+    fn unfocus(&mut self, index: usize) {
+        let id = self.windows[index].id;
+        if self.focused == Some(id) {
+            self.focused = None;
+            self.emit(|desktop| WindowEvent::Unfocused(desktop.window(id)));
+        }
+    }
+
+    // Like most window managers, moving or resizing a maximized window unmaximizes it.
+    fn unmaximize(&mut self, index: usize) {
+        if self.windows[index].maximized {
+            self.windows[index].maximized = false;
+            let id = self.windows[index].id;
+            self.emit(|desktop| WindowEvent::Unmaximized(desktop.window(id)));
+        }
+    }
+
+    // Returns the new index of the window.
+    fn raise(&mut self, index: usize) -> usize {
+        let state = self.windows.remove(index);
+        self.windows.push(state);
+        self.windows.len() - 1
+    }
+}
+
+#[derive(Debug)]
+struct MockWindow {
+    id: u64,
+    desktop: Arc<Mutex<Desktop>>,
+}
+
+impl MockWindow {
+    fn with<T>(
+        &self,
+        operation: Operation,
+        f: impl FnOnce(&mut Desktop, usize) -> T,
+    ) -> Result<T, WindowError> {
+        let mut desktop = self.desktop.lock().unwrap();
+        // Injected failures take precedence, so they can be tested on closed windows too.
+        desktop.check(Some(self.id), operation)?;
+        let index = desktop.index(self.id)?;
+        Ok(f(&mut desktop, index))
+    }
+}
+
+impl WindowOps for MockWindow {
+    fn handle(&self) -> WindowHandle {
+        WindowHandle::custom(self.id)
+    }
+
+    fn title(&self) -> Result<String, WindowError> {
+        self.with(Operation::Title, |desktop, index| {
+            desktop.windows[index].title.clone()
+        })
+    }
+
+    fn size(&self) -> Result<LogicalSize, WindowError> {
+        self.with(Operation::Size, |desktop, index| {
+            desktop.windows[index].size
+        })
+    }
+
+    fn position(&self) -> Result<LogicalPosition, WindowError> {
+        self.with(Operation::Position, |desktop, index| {
+            desktop.windows[index].position
+        })
+    }
+
+    fn is_focused(&self) -> Result<bool, WindowError> {
+        self.with(Operation::IsFocused, |desktop, _| {
+            desktop.focused == Some(self.id)
+        })
+    }
+
+    fn is_fullscreen(&self) -> Result<bool, WindowError> {
+        self.with(Operation::IsFullscreen, |desktop, index| {
+            desktop.windows[index].fullscreen
+        })
+    }
+
+    fn is_minimized(&self) -> Result<bool, WindowError> {
+        self.with(Operation::IsMinimized, |desktop, index| {
+            desktop.windows[index].minimized
+        })
+    }
+
+    fn is_hidden(&self) -> Result<bool, WindowError> {
+        self.with(Operation::IsHidden, |desktop, index| {
+            desktop.windows[index].hidden
+        })
+    }
+
+    fn scale_factor(&self) -> Result<f64, WindowError> {
+        self.with(Operation::ScaleFactor, |desktop, _| desktop.scale_factor)
+    }
+
+    // Read under a single lock, so the properties are consistent with each other.
+    fn properties(&self, mask: PropertyMask) -> Result<WindowProperties, WindowError> {
+        self.with(Operation::Properties, |desktop, index| {
+            let window = &desktop.windows[index];
+            WindowProperties {
+                title: mask.select(PropertyMask::TITLE, || window.title.clone()),
+                size: mask.select(PropertyMask::SIZE, || window.size),
+                position: mask.select(PropertyMask::POSITION, || window.position),
+                is_focused: mask.select(PropertyMask::FOCUSED, || desktop.focused == Some(self.id)),
+                is_fullscreen: mask.select(PropertyMask::FULLSCREEN, || window.fullscreen),
+                is_minimized: mask.select(PropertyMask::MINIMIZED, || window.minimized),
+                is_hidden: mask.select(PropertyMask::HIDDEN, || window.hidden),
+            }
+        })
+    }
+
+    fn resize(&self, size: LogicalSize) -> Result<(), WindowError> {
+        self.with(Operation::Resize, |desktop, index| {
+            desktop.set_size(index, size)
+        })
+    }
+
+    fn reposition(&self, position: LogicalPosition) -> Result<(), WindowError> {
+        self.with(Operation::Reposition, |desktop, index| {
+            desktop.set_position(index, position)
+        })
+    }
+
+    fn frame(&self) -> Result<Rect, WindowError> {
+        self.with(Operation::Frame, |desktop, index| Rect {
+            position: desktop.windows[index].position,
+            size: desktop.windows[index].size,
+        })
+    }
+
+    fn set_frame(&self, frame: Rect) -> Result<Rect, WindowError> {
+        self.with(Operation::SetFrame, |desktop, index| {
+            desktop.set_frame(index, frame);
+            frame
+        })
+    }
+
+    fn decoration_insets(&self) -> Result<Insets, WindowError> {
+        self.with(Operation::DecorationInsets, |desktop, _| {
+            desktop.decoration_insets
+        })
+    }
+
+    // Like most window managers, focusing a window also raises it.
+    fn focus(&self) -> Result<(), WindowError> {
+        self.with(Operation::Focus, |desktop, index| {
+            let pid = desktop.windows[index].pid;
+            desktop.raise(index);
+            if desktop.focused != Some(self.id) {
+                if let Some(previous) = desktop.focused.replace(self.id) {
+                    desktop.emit(|desktop| WindowEvent::Unfocused(desktop.window(previous)));
+                }
+                desktop.emit(|desktop| WindowEvent::Focused(desktop.window(self.id)));
+            }
+
+            if let Some(pid) = pid.filter(|&pid| desktop.active != Some(pid)) {
+                desktop.active = Some(pid);
+                desktop.emit(|desktop| WindowEvent::ApplicationActivated(desktop.application(pid)));
+            }
+        })
+    }
+
+    fn fullscreen(&self) -> Result<(), WindowError> {
+        self.with(Operation::Fullscreen, |desktop, index| {
+            let state = &mut desktop.windows[index];
+            if !state.fullscreen {
+                state.fullscreen = true;
+                state.restore = Some((state.position, state.size));
+                desktop.fill_screen(index);
+                desktop.emit(|desktop| WindowEvent::Fullscreened(desktop.window(self.id)));
+            }
+        })
+    }
+
+    fn unfullscreen(&self) -> Result<(), WindowError> {
+        self.with(Operation::Unfullscreen, |desktop, index| {
+            let state = &mut desktop.windows[index];
+            if state.fullscreen {
+                state.fullscreen = false;
+                if let Some((position, size)) = state.restore.take() {
+                    desktop.set_position(index, position);
+                    desktop.set_size(index, size);
+                }
+                desktop.emit(|desktop| WindowEvent::Unfullscreened(desktop.window(self.id)));
+            }
+        })
+    }
+
+    fn maximize(&self) -> Result<(), WindowError> {
+        self.with(Operation::Maximize, |desktop, index| {
+            desktop.fill_screen(index);
+            if !desktop.windows[index].maximized {
+                desktop.windows[index].maximized = true;
+                desktop.emit(|desktop| WindowEvent::Maximized(desktop.window(self.id)));
+            }
+        })
+    }
+
+    fn minimize(&self) -> Result<(), WindowError> {
+        self.with(Operation::Minimize, |desktop, index| {
+            if !desktop.windows[index].minimized {
+                desktop.windows[index].minimized = true;
+                desktop.emit(|desktop| WindowEvent::Minimized(desktop.window(self.id)));
+                desktop.unfocus(index);
+            }
+        })
+    }
+
+    fn unminimize(&self) -> Result<(), WindowError> {
+        self.with(Operation::Unminimize, |desktop, index| {
+            if desktop.windows[index].minimized {
+                desktop.windows[index].minimized = false;
+                desktop.emit(|desktop| WindowEvent::Unminimized(desktop.window(self.id)));
+            }
+        })
+    }
+
+    fn show(&self) -> Result<(), WindowError> {
+        self.with(Operation::Show, |desktop, index| {
+            if desktop.windows[index].hidden {
+                desktop.set_hidden(index, false);
+            }
+        })
+    }
+
+    fn hide(&self) -> Result<(), WindowError> {
+        self.with(Operation::Hide, |desktop, index| {
+            if !desktop.windows[index].hidden {
+                desktop.set_hidden(index, true);
+            }
+        })
+    }
+
+    fn bring_to_front(&self) -> Result<(), WindowError> {
+        self.with(Operation::BringToFront, |desktop, index| {
+            desktop.raise(index);
+        })
+    }
+
+    // Mock windows never prompt the user, so they always close.
+    fn close(&self) -> Result<(), WindowError> {
+        self.with(Operation::Close, |desktop, index| desktop.remove(index))
+    }
+
+    fn kill(&self) -> Result<(), WindowError> {
+        self.with(Operation::Kill, |desktop, index| {
+            let pid = desktop.windows[index].pid.ok_or(WindowError::Unsupported)?;
+            desktop.terminate(pid)
+        })?
+    }
+
+    fn application(&self) -> Result<Application, WindowError> {
+        self.with(Operation::Application, |desktop, index| {
+            desktop.windows[index]
+                .pid
+                .map(|pid| desktop.application(pid))
+                .ok_or(WindowError::Unsupported)
+        })?
+    }
+}
+
+#[derive(Debug)]
+struct MockApplication {
+    pid: u32,
+    desktop: Arc<Mutex<Desktop>>,
+}
+
+impl MockApplication {
+    fn with<T>(&self, f: impl FnOnce(&Desktop, &Process) -> T) -> Result<T, WindowError> {
+        let desktop = self.desktop.lock().unwrap();
+        let process = desktop.process(self.pid)?;
+        Ok(f(&desktop, process))
+    }
+}
+
+impl ApplicationOps for MockApplication {
+    fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    fn name(&self) -> Result<String, WindowError> {
+        self.with(|_, process| process.name.clone())
+    }
+
+    fn executable_path(&self) -> Result<PathBuf, WindowError> {
+        self.with(|_, process| process.executable_path.clone())
+    }
+
+    fn iter_windows(&self) -> Box<dyn Iterator<Item = Result<Window, WindowError>>> {
+        let windows = match self.with(|desktop, _| {
+            desktop
+                .windows
+                .iter()
+                .filter(|state| state.pid == Some(self.pid))
+                .map(|state| Ok(desktop.window(state.id)))
+                .collect()
+        }) {
+            Ok(windows) => windows,
+            Err(err) => vec![Err(err)],
+        };
+        Box::new(windows.into_iter())
+    }
+
+    fn is_frontmost(&self) -> Result<bool, WindowError> {
+        self.with(|desktop, _| {
+            desktop.focused.is_some_and(|id| {
+                desktop
+                    .windows
+                    .iter()
+                    .any(|state| state.id == id && state.pid == Some(self.pid))
+            })
+        })
+    }
+
+    // Focuses the front-most window of the application.
+    fn activate(&self) -> Result<(), WindowError> {
+        let window = self.with(|desktop, _| {
+            desktop
+                .windows
+                .iter()
+                .rev()
+                .find(|state| state.pid == Some(self.pid))
+                .map(|state| desktop.window(state.id))
+        })?;
+        // Focusing locks the desktop again, so it has to be released first.
+        window.ok_or(WindowError::InvalidHandle)?.focus()
+    }
+}
+
+#[derive(Debug)]
+struct MockBackend {
+    desktop: Arc<Mutex<Desktop>>,
+}
+
+impl WindowBackend for MockBackend {
+    fn kind(&self) -> Backend {
+        Backend::Custom("mock")
+    }
+
+    fn watch(&self) -> Result<Box<dyn EventSource>, WindowError> {
+        let mut desktop = self.desktop.lock().unwrap();
+        desktop.check(None, Operation::Watch)?;
+
+        let (sender, receiver) = mpsc::channel();
+        desktop.subscribers.push(sender);
+        Ok(Box::new(MockEvents { receiver }))
+    }
+
+    fn iter_windows(&self) -> Box<dyn Iterator<Item = Result<Window, WindowError>>> {
+        let desktop = self.desktop.lock().unwrap();
+        let windows = match desktop.check(None, Operation::IterWindows) {
+            Ok(()) => desktop
+                .windows
+                .iter()
+                .map(|state| Ok(desktop.window(state.id)))
+                .collect(),
+            Err(err) => vec![Err(err)],
+        };
+        Box::new(windows.into_iter())
+    }
+
+    fn focused_window(&self) -> Result<Option<Window>, WindowError> {
+        let desktop = self.desktop.lock().unwrap();
+        desktop.check(None, Operation::FocusedWindow)?;
+        Ok(desktop.focused.map(|id| desktop.window(id)))
+    }
+
+    fn stacking_order(&self) -> Result<Vec<WindowHandle>, WindowError> {
+        let desktop = self.desktop.lock().unwrap();
+        desktop.check(None, Operation::StackingOrder)?;
+        Ok(desktop.stacking_order())
+    }
+
+    // Unlike the default, this includes applications without any windows.
+    fn iter_applications(&self) -> Box<dyn Iterator<Item = Result<Application, WindowError>>> {
+        let desktop = self.desktop.lock().unwrap();
+        let applications = match desktop.check(None, Operation::IterApplications) {
+            Ok(()) => desktop
+                .applications
+                .iter()
+                .map(|process| Ok(desktop.application(process.pid)))
+                .collect(),
+            Err(err) => vec![Err(err)],
+        };
+        Box::new(applications.into_iter())
+    }
+}
+
+#[derive(Debug)]
+struct MockEvents {
+    receiver: Receiver<Event>,
+}
+
+impl EventSource for MockEvents {
+    fn next_request(&mut self) -> Result<WindowEvent, WindowError> {
+        self.receiver.recv().unwrap_or_else(|_| Err(dropped()))
+    }
+
+    fn next_request_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<WindowEvent>, WindowError> {
+        match self.receiver.recv_timeout(timeout) {
+            Ok(event) => event.map(Some),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err(dropped()),
+        }
+    }
+}
+
+fn dropped() -> WindowError {
+    WindowError::OsError(io::Error::new(
+        io::ErrorKind::NotConnected,
+        "the mock desktop was dropped",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Coalesce, EventMask, FrameKind, PhysicalPosition, PhysicalSize};
+
+    use super::*;
+
+    const ORIGIN: LogicalPosition = LogicalPosition { x: 0.0, y: 0.0 };
+    const SIZE: LogicalSize = LogicalSize {
+        width: 800.0,
+        height: 600.0,
+    };
+
+    #[test]
+    fn window_operations() {
+        let desktop = MockDesktop::new();
+        let mut watcher = desktop.session().watch().unwrap();
+        let window = desktop.spawn("editor", ORIGIN, SIZE);
+        assert!(
+            matches!(watcher.next_request(), Ok(WindowEvent::Opened(opened)) if opened == window)
+        );
+
+        window.focus().unwrap();
+        assert!(window.is_focused().unwrap());
+        assert!(matches!(
+            watcher.next_request(),
+            Ok(WindowEvent::Focused(_))
+        ));
+
+        window.minimize().unwrap();
+        assert!(window.is_minimized().unwrap());
+        assert!(!window.is_focused().unwrap());
+        assert!(matches!(
+            watcher.next_request(),
+            Ok(WindowEvent::Minimized(_))
+        ));
+        assert!(matches!(
+            watcher.next_request(),
+            Ok(WindowEvent::Unfocused(_))
+        ));
+
+        window.fullscreen().unwrap();
+        assert!(matches!(
+            watcher.next_request(),
+            Ok(WindowEvent::Resized { old: Some(old), .. }) if old == SIZE
+        ));
+        assert!(matches!(
+            watcher.next_request(),
+            Ok(WindowEvent::Fullscreened(_))
+        ));
+        assert_eq!(
+            window.size().unwrap(),
+            LogicalSize {
+                width: 1920.0,
+                height: 1080.0
+            }
+        );
+
+        window.unfullscreen().unwrap();
+        assert!(matches!(
+            watcher.next_request(),
+            Ok(WindowEvent::Resized { new: Some(new), .. }) if new == SIZE
+        ));
+        assert!(matches!(
+            watcher.next_request(),
+            Ok(WindowEvent::Unfullscreened(_))
+        ));
+        assert_eq!(window.size().unwrap(), SIZE);
+
+        window.maximize().unwrap();
+        assert!(matches!(
+            watcher.next_request(),
+            Ok(WindowEvent::Resized { .. })
+        ));
+        assert!(matches!(
+            watcher.next_request(),
+            Ok(WindowEvent::Maximized(_))
+        ));
+        // Resizing a maximized window unmaximizes it.
+        window.resize(SIZE).unwrap();
+        assert!(matches!(
+            watcher.next_request(),
+            Ok(WindowEvent::Resized { .. })
+        ));
+        assert!(matches!(
+            watcher.next_request(),
+            Ok(WindowEvent::Unmaximized(_))
+        ));
+    }
+
+    #[test]
+    fn drag() {
+        let desktop = MockDesktop::new();
+        let mut watcher = desktop.session().watch().unwrap();
+        let window = desktop.spawn("editor", ORIGIN, SIZE);
+        assert!(matches!(watcher.next_request(), Ok(WindowEvent::Opened(_))));
+
+        let position = LogicalPosition { x: 100.0, y: 50.0 };
+        desktop
+            .drag(
+                &window.handle(),
+                Rect {
+                    position,
+                    size: SIZE,
+                },
+            )
+            .unwrap();
+        assert!(matches!(
+            watcher.next_request(),
+            Ok(WindowEvent::MoveResizeStarted(_))
+        ));
+        assert!(matches!(
+            watcher.next_request(),
+            Ok(WindowEvent::Moved { new: Some(new), .. }) if new == position
+        ));
+        assert!(matches!(
+            watcher.next_request(),
+            Ok(WindowEvent::MoveResizeEnded(_))
+        ));
+        // The size didn't change and the move was already reported, so nothing follows.
+        desktop.close(&window.handle()).unwrap();
+        assert!(matches!(watcher.next_request(), Ok(WindowEvent::Closed(_))));
+    }
+
+    #[test]
+    fn frame() {
+        let desktop = MockDesktop::new();
+        let mut watcher = desktop.session().watch().unwrap();
+        let window = desktop.spawn("editor", ORIGIN, SIZE);
+        assert!(matches!(watcher.next_request(), Ok(WindowEvent::Opened(_))));
+        assert_eq!(
+            window.outer_frame().unwrap(),
+            Rect {
+                position: ORIGIN,
+                size: SIZE
+            }
+        );
+
+        let frame = Rect {
+            position: LogicalPosition { x: 100.0, y: 50.0 },
+            size: LogicalSize {
+                width: 400.0,
+                height: 300.0,
+            },
+        };
+        assert_eq!(window.set_frame(frame, FrameKind::Outer).unwrap(), frame);
+        assert_eq!(window.outer_frame().unwrap(), frame);
+        assert!(matches!(
+            watcher.next_request(),
+            Ok(WindowEvent::Moved { old: Some(old), new: Some(new), .. })
+                if old == ORIGIN && new == frame.position
+        ));
+        assert!(matches!(
+            watcher.next_request(),
+            Ok(WindowEvent::Resized { old: Some(old), new: Some(new), .. })
+                if old == SIZE && new == frame.size
+        ));
+    }
+
+    #[test]
+    fn content_frame() {
+        let desktop = MockDesktop::new();
+        let insets = Insets {
+            top: 28.0,
+            right: 1.0,
+            bottom: 1.0,
+            left: 1.0,
+        };
+        desktop.set_decoration_insets(insets);
+        let window = desktop.spawn("editor", ORIGIN, SIZE);
+        assert_eq!(window.decoration_insets().unwrap(), insets);
+
+        // Writing back the frame that was read doesn't grow the window.
+        let content = window.content_frame().unwrap();
+        assert_eq!(
+            window.set_frame(content, FrameKind::Content).unwrap(),
+            content
+        );
+        assert_eq!(window.size().unwrap(), SIZE);
+
+        let frame = Rect {
+            position: LogicalPosition { x: 100.0, y: 100.0 },
+            size: LogicalSize {
+                width: 400.0,
+                height: 300.0,
+            },
+        };
+        window.set_frame(frame, FrameKind::Content).unwrap();
+        assert_eq!(window.content_frame().unwrap(), frame);
+        assert_eq!(
+            window.outer_frame().unwrap(),
+            Rect {
+                position: LogicalPosition { x: 99.0, y: 72.0 },
+                size: LogicalSize {
+                    width: 402.0,
+                    height: 329.0
+                },
+            }
+        );
+
+        desktop.fail(Operation::DecorationInsets, || WindowError::Unsupported);
+        assert!(matches!(
+            window.set_frame(frame, FrameKind::Content),
+            Err(WindowError::Unsupported)
+        ));
+    }
+
+    #[test]
+    fn scale_factor() {
+        let desktop = MockDesktop::new();
+        desktop.set_scale_factor(2.0);
+        let window = desktop.spawn("editor", ORIGIN, SIZE);
+        assert_eq!(window.scale_factor().unwrap(), 2.0);
+        assert_eq!(
+            window.physical_size().unwrap(),
+            PhysicalSize {
+                width: 1600.0,
+                height: 1200.0
+            }
+        );
+
+        window
+            .resize(PhysicalSize {
+                width: 400.0,
+                height: 300.0,
+            })
+            .unwrap();
+        window
+            .reposition(PhysicalPosition { x: 100.0, y: 50.0 })
+            .unwrap();
+        assert_eq!(
+            window.outer_frame().unwrap(),
+            Rect {
+                position: LogicalPosition { x: 50.0, y: 25.0 },
+                size: LogicalSize {
+                    width: 200.0,
+                    height: 150.0
+                }
+            }
+        );
+
+        // Logical geometry is passed through as it is.
+        window.resize(SIZE).unwrap();
+        assert_eq!(window.size().unwrap(), SIZE);
+
+        desktop.fail(Operation::ScaleFactor, || WindowError::Unsupported);
+        assert!(matches!(
+            window.resize(PhysicalSize::default()),
+            Err(WindowError::Unsupported)
+        ));
+    }
+
+    #[test]
+    fn properties() {
+        let desktop = MockDesktop::new();
+        let session = desktop.session();
+        let first = desktop.spawn("first", ORIGIN, SIZE);
+        let second = desktop.spawn("second", ORIGIN, SIZE);
+        second.focus().unwrap();
+        first.minimize().unwrap();
+
+        let windows = session
+            .iter_windows_with(PropertyMask::TITLE | PropertyMask::FOCUSED)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(windows.len(), 2);
+        assert_eq!(windows[0].0, first);
+        assert_eq!(
+            windows[0].1,
+            WindowProperties {
+                title: Some("first".to_owned()),
+                is_focused: Some(false),
+                ..WindowProperties::default()
+            }
+        );
+        assert_eq!(windows[1].1.is_focused, Some(true));
+
+        let properties = first.properties(PropertyMask::ALL).unwrap();
+        assert_eq!(properties.size, Some(SIZE));
+        assert_eq!(properties.position, Some(ORIGIN));
+        assert_eq!(properties.is_minimized, Some(true));
+        assert_eq!(properties.is_fullscreen, Some(false));
+
+        desktop.fail(Operation::Properties, || WindowError::Unsupported);
+        assert!(matches!(
+            first.properties(PropertyMask::TITLE),
+            Err(WindowError::Unsupported)
+        ));
+    }
+
+    #[test]
+    fn transaction() {
+        let desktop = MockDesktop::new();
+        let first = desktop.spawn("first", ORIGIN, SIZE);
+        let second = desktop.spawn("second", ORIGIN, SIZE);
+        let closed = desktop.spawn("closed", ORIGIN, SIZE);
+        closed.close().unwrap();
+
+        let frame = Rect {
+            position: LogicalPosition { x: 100.0, y: 50.0 },
+            size: LogicalSize {
+                width: 400.0,
+                height: 300.0,
+            },
+        };
+        let mut transaction = crate::Transaction::new();
+        transaction
+            .focus(&first)
+            .set_frame(&closed, frame)
+            .set_frame(&second, frame)
+            .minimize(&second);
+        assert_eq!(transaction.len(), 4);
+
+        let results = transaction.commit();
+        assert_eq!(
+            results
+                .iter()
+                .map(|(window, _)| window.clone())
+                .collect::<Vec<_>>(),
+            [first.clone(), closed, second.clone(), second.clone()]
+        );
+        assert!(results[0].1.is_ok());
+        assert!(matches!(results[1].1, Err(WindowError::InvalidHandle)));
+        assert!(results[2].1.is_ok());
+        assert!(results[3].1.is_ok());
+
+        assert!(first.is_focused().unwrap());
+        assert_eq!(second.outer_frame().unwrap(), frame);
+        assert!(second.is_minimized().unwrap());
+    }
+
+    #[test]
+    fn snapshot() {
+        let desktop = MockDesktop::new();
+        let session = desktop.session();
+        let editor = desktop.launch("editor", "/usr/bin/editor");
+        let first = desktop.spawn_for(&editor, "first", ORIGIN, SIZE).unwrap();
+        let second = desktop.spawn("second", ORIGIN, SIZE);
+        first.focus().unwrap();
+        let (first_id, second_id) = (id(&first.handle()).unwrap(), id(&second.handle()).unwrap());
+
+        let before = session.snapshot().unwrap();
+        assert_eq!(before.focused, Some(first_id));
+        let snapshot = before.window(first_id).unwrap();
+        assert_eq!(snapshot.window.as_ref(), Some(&first));
+        assert_eq!(snapshot.pid, Some(editor.pid()));
+        assert_eq!(snapshot.application.as_deref(), Some("editor"));
+        assert_eq!(snapshot.properties.title.as_deref(), Some("first"));
+        assert_eq!(snapshot.z_index, Some(0));
+        assert_eq!(before.window(second_id).unwrap().pid, None);
+        assert_eq!(before.window(second_id).unwrap().z_index, Some(1));
+
+        let position = LogicalPosition { x: 10.0, y: 20.0 };
+        desktop.move_to(&second.handle(), position).unwrap();
+        desktop.rename(&second.handle(), "renamed").unwrap();
+        second.minimize().unwrap();
+        desktop.close(&first.handle()).unwrap();
+        let third = desktop.spawn("third", ORIGIN, SIZE);
+
+        let after = session.snapshot().unwrap();
+        assert_eq!(after.focused, None);
+        assert_eq!(
+            before.diff(&after),
+            [
+                crate::Change::Closed(first_id),
+                crate::Change::Moved {
+                    id: second_id,
+                    from: ORIGIN,
+                    to: position,
+                },
+                crate::Change::Renamed {
+                    id: second_id,
+                    from: "second".to_owned(),
+                    to: "renamed".to_owned(),
+                },
+                crate::Change::StateChanged {
+                    id: second_id,
+                    state: PropertyMask::MINIMIZED,
+                    value: true,
+                },
+                crate::Change::Opened(id(&third.handle()).unwrap()),
+            ]
+        );
+
+        // Windows that close while the snapshot is captured are left out.
+        desktop
+            .fail_window(&third.handle(), Operation::Properties, || {
+                WindowError::InvalidHandle
+            })
+            .unwrap();
+        assert_eq!(session.snapshot().unwrap().windows.len(), 1);
+        desktop.fail(Operation::StackingOrder, || WindowError::Unsupported);
+        assert_eq!(session.snapshot().unwrap().windows[0].z_index, None);
+    }
+
+    #[test]
+    fn controller() {
+        let desktop = MockDesktop::new();
+        let session = desktop.session();
+        let first = desktop.spawn("first", ORIGIN, SIZE);
+        let second = desktop.spawn("second", ORIGIN, SIZE);
+        assert_eq!(desktop.stacking_order(), [second.handle(), first.handle()]);
+
+        first.focus().unwrap();
+        assert_eq!(desktop.stacking_order(), [first.handle(), second.handle()]);
+        assert_eq!(session.focused_window().unwrap(), Some(first.clone()));
+
+        let mut watcher = session.watch().unwrap();
+        desktop.rename(&first.handle(), "renamed").unwrap();
+        assert_eq!(first.title().unwrap(), "renamed");
+        desktop.close(&first.handle()).unwrap();
+        // Both titles are known even though the window closed before the event was received.
+        assert!(matches!(
+            watcher.next_request(),
+            Ok(WindowEvent::Renamed { old_title, new_title, .. })
+                if old_title.as_deref() == Some("first") && new_title.as_deref() == Some("renamed")
+        ));
+        assert!(
+            matches!(watcher.next_request(), Ok(WindowEvent::Closed(handle)) if handle == first.handle())
+        );
+        assert!(matches!(first.title(), Err(WindowError::InvalidHandle)));
+        assert_eq!(session.focused_window().unwrap(), None);
+        assert_eq!(session.iter_windows().count(), 1);
+    }
+
+    #[test]
+    fn applications() {
+        let desktop = MockDesktop::new();
+        let session = desktop.session();
+        let editor = desktop.launch("editor", "/usr/bin/editor");
+        let terminal = desktop.launch("terminal", "/usr/bin/terminal");
+        let first = desktop.spawn_for(&editor, "first", ORIGIN, SIZE).unwrap();
+        let second = desktop.spawn_for(&editor, "second", ORIGIN, SIZE).unwrap();
+        let orphan = desktop.spawn("orphan", ORIGIN, SIZE);
+
+        assert_eq!(first.application().unwrap(), editor);
+        assert!(matches!(
+            orphan.application(),
+            Err(WindowError::Unsupported)
+        ));
+        assert_eq!(editor.name().unwrap(), "editor");
+        assert_eq!(
+            terminal.executable_path().unwrap(),
+            PathBuf::from("/usr/bin/terminal")
+        );
+        assert_eq!(
+            editor
+                .iter_windows()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap(),
+            [first, second.clone()]
+        );
+        assert_eq!(terminal.iter_windows().count(), 0);
+        assert_eq!(
+            session
+                .iter_applications()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap(),
+            [editor.clone(), terminal.clone()]
+        );
+
+        editor.activate().unwrap();
+        assert!(second.is_focused().unwrap());
+        assert!(editor.is_frontmost().unwrap());
+        assert!(!terminal.is_frontmost().unwrap());
+        assert!(matches!(
+            terminal.activate(),
+            Err(WindowError::InvalidHandle)
+        ));
+    }
+
+    #[test]
+    fn application_events() {
+        let desktop = MockDesktop::new();
+        let mut watcher = desktop.session().watch().unwrap();
+        let editor = desktop.launch("editor", "/usr/bin/editor");
+        assert!(matches!(
+            watcher.next_request(),
+            Ok(WindowEvent::ApplicationLaunched(launched)) if launched == editor
+        ));
+
+        let window = desktop.spawn_for(&editor, "file", ORIGIN, SIZE).unwrap();
+        window.focus().unwrap();
+        assert!(matches!(watcher.next_request(), Ok(WindowEvent::Opened(_))));
+        assert!(matches!(
+            watcher.next_request(),
+            Ok(WindowEvent::Focused(_))
+        ));
+        assert!(matches!(
+            watcher.next_request(),
+            Ok(WindowEvent::ApplicationActivated(activated)) if activated == editor
+        ));
+
+        desktop.hide_application(&editor).unwrap();
+        assert!(matches!(
+            watcher.next_request(),
+            Ok(WindowEvent::ApplicationHidden(_))
+        ));
+        assert!(matches!(watcher.next_request(), Ok(WindowEvent::Hidden(_))));
+        assert!(matches!(
+            watcher.next_request(),
+            Ok(WindowEvent::Unfocused(_))
+        ));
+        assert!(window.is_hidden().unwrap());
+
+        desktop.quit(&editor).unwrap();
+        assert!(matches!(watcher.next_request(), Ok(WindowEvent::Closed(_))));
+        assert!(matches!(
+            watcher.next_request(),
+            Ok(WindowEvent::ApplicationTerminated(pid)) if pid == editor.pid()
+        ));
+        assert!(matches!(editor.name(), Err(WindowError::InvalidHandle)));
+    }
+
+    #[test]
+    fn event_batches() {
+        let desktop = MockDesktop::new();
+        let mut watcher = desktop.session().watch().unwrap();
+        let window = desktop.spawn("editor", ORIGIN, SIZE);
+        window.focus().unwrap();
+
+        let batch = watcher.next_batch(Duration::from_millis(50)).unwrap();
+        assert_eq!(batch.len(), 2);
+        assert!(matches!(batch[0].event, WindowEvent::Opened(_)));
+        assert!(matches!(batch[1].event, WindowEvent::Focused(_)));
+        assert_eq!(batch[1].source, Backend::Custom("mock"));
+
+        window.close().unwrap();
+        let closed = watcher.next_envelope().unwrap();
+        assert!(matches!(closed.event, WindowEvent::Closed(_)));
+        assert_eq!(
+            [batch[0].sequence, batch[1].sequence, closed.sequence],
+            [0, 1, 2]
+        );
+        assert!(batch[1].timestamp <= closed.timestamp);
+    }
+
+    #[test]
+    fn coalesced_events() {
+        let desktop = MockDesktop::new();
+        let window = desktop.spawn("editor", ORIGIN, SIZE);
+        let mut watcher = desktop.session().watch().unwrap().coalesce(
+            Coalesce::new()
+                .collapse()
+                .drop_closed()
+                .debounce(Duration::from_millis(20)),
+        );
+
+        for x in 1..=100 {
+            let position = LogicalPosition {
+                x: x as f64,
+                y: 0.0,
+            };
+            desktop.move_to(&window.handle(), position).unwrap();
+        }
+        let popup = desktop.spawn("popup", ORIGIN, SIZE);
+        desktop.close(&popup.handle()).unwrap();
+
+        let moved = watcher.next_envelope().unwrap();
+        assert!(matches!(
+            moved.event,
+            WindowEvent::Moved { old: Some(old), new: Some(new), .. }
+                if old == ORIGIN && new.x == 100.0
+        ));
+        assert_eq!(moved.sequence, 99);
+
+        window.close().unwrap();
+        assert!(matches!(watcher.next_request(), Ok(WindowEvent::Closed(_))));
+    }
+
+    #[test]
+    fn filtered_events() {
+        let desktop = MockDesktop::new();
+        let session = desktop.session();
+        let editor = desktop.launch("editor", "/usr/bin/editor");
+        let browser = desktop.launch("browser", "/usr/bin/browser");
+        let main = desktop.spawn_for(&editor, "main.rs", ORIGIN, SIZE).unwrap();
+        let docs = desktop.spawn_for(&browser, "docs", ORIGIN, SIZE).unwrap();
+        let mut by_application = session
+            .watcher()
+            .events(EventMask::MOVED | EventMask::CLOSED | EventMask::APPLICATION_TERMINATED)
+            .application(&editor)
+            .build()
+            .unwrap();
+        let mut by_title = session
+            .watcher()
+            .title_matches(|title| title.starts_with("docs"))
+            .build()
+            .unwrap();
+
+        let position = LogicalPosition { x: 10.0, y: 10.0 };
+        desktop.move_to(&docs.handle(), position).unwrap();
+        desktop.rename(&main.handle(), "lib.rs").unwrap();
+        desktop.move_to(&main.handle(), position).unwrap();
+        assert!(matches!(
+            by_application.next_request(),
+            Ok(WindowEvent::Moved { window, .. }) if window == main
+        ));
+        assert!(matches!(
+            by_title.next_request(),
+            Ok(WindowEvent::Moved { window, .. }) if window == docs
+        ));
+
+        // Not reported by title until it matches again.
+        desktop.rename(&docs.handle(), "search").unwrap();
+        desktop.move_to(&docs.handle(), ORIGIN).unwrap();
+        desktop.rename(&docs.handle(), "docs - search").unwrap();
+        assert!(matches!(
+            by_title.next_request(),
+            Ok(WindowEvent::Renamed { old_title: Some(old), new_title: Some(new), .. })
+                if old == "search" && new == "docs - search"
+        ));
+
+        docs.close().unwrap();
+        desktop.quit(&editor).unwrap();
+        assert!(matches!(
+            by_title.next_request(),
+            Ok(WindowEvent::Closed(handle)) if handle == docs.handle()
+        ));
+        assert!(matches!(
+            by_application.next_request(),
+            Ok(WindowEvent::Closed(handle)) if handle == main.handle()
+        ));
+        assert!(matches!(
+            by_application.next_request(),
+            Ok(WindowEvent::ApplicationTerminated(pid)) if pid == editor.pid()
+        ));
+    }
+
+    #[test]
+    fn filtered_closed_events() {
+        let desktop = MockDesktop::new();
+        let session = desktop.session();
+        let editor = desktop.launch("editor", "/usr/bin/editor");
+        let main = desktop.spawn_for(&editor, "main.rs", ORIGIN, SIZE).unwrap();
+        let other = desktop.spawn("other", ORIGIN, SIZE);
+        let mut by_window = session.watcher().window(main.handle()).build().unwrap();
+        let mut by_application = session.watcher().application(&editor).build().unwrap();
+        let mut by_kind = session.watcher().events(EventMask::CLOSED).build().unwrap();
+
+        // Neither window was reported before they closed.
+        other.close().unwrap();
+        main.close().unwrap();
+        for watcher in [&mut by_window, &mut by_application] {
+            assert!(matches!(
+                watcher.next_request(),
+                Ok(WindowEvent::Closed(handle)) if handle == main.handle()
+            ));
+        }
+        assert!(matches!(
+            by_kind.next_request(),
+            Ok(WindowEvent::Closed(handle)) if handle == other.handle()
+        ));
+        assert!(matches!(
+            by_kind.next_request(),
+            Ok(WindowEvent::Closed(handle)) if handle == main.handle()
+        ));
+
+        // Moved before it closes, which isn't reported.
+        let late = desktop.spawn_for(&editor, "lib.rs", ORIGIN, SIZE).unwrap();
+        let mut closed_by_application = session
+            .watcher()
+            .application(&editor)
+            .events(EventMask::CLOSED)
+            .build()
+            .unwrap();
+        let position = LogicalPosition { x: 10.0, y: 10.0 };
+        desktop.move_to(&late.handle(), position).unwrap();
+        late.close().unwrap();
+        assert!(matches!(
+            closed_by_application.next_request(),
+            Ok(WindowEvent::Closed(handle)) if handle == late.handle()
+        ));
+    }
+
+    #[test]
+    fn close_and_kill() {
+        let desktop = MockDesktop::new();
+        let session = desktop.session();
+        let editor = desktop.launch("editor", "/usr/bin/editor");
+        let first = desktop.spawn_for(&editor, "first", ORIGIN, SIZE).unwrap();
+        let second = desktop.spawn_for(&editor, "second", ORIGIN, SIZE).unwrap();
+        let orphan = desktop.spawn("orphan", ORIGIN, SIZE);
+
+        first.close().unwrap();
+        assert!(matches!(first.title(), Err(WindowError::InvalidHandle)));
+        assert_eq!(editor.iter_windows().count(), 1);
+
+        assert!(matches!(orphan.kill(), Err(WindowError::Unsupported)));
+        second.kill().unwrap();
+        assert!(matches!(editor.name(), Err(WindowError::InvalidHandle)));
+        assert_eq!(
+            session
+                .iter_windows()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap(),
+            [orphan]
+        );
+    }
+
+    #[test]
+    fn injected_failures() {
+        let desktop = MockDesktop::new();
+        let first = desktop.spawn("first", ORIGIN, SIZE);
+        let second = desktop.spawn("second", ORIGIN, SIZE);
+
+        desktop
+            .fail_window(&first.handle(), Operation::Focus, || {
+                WindowError::NotTrusted
+            })
+            .unwrap();
+        assert!(matches!(first.focus(), Err(WindowError::NotTrusted)));
+        assert!(second.focus().is_ok());
+
+        desktop.fail(Operation::IterWindows, || WindowError::Unsupported);
+        assert!(matches!(
+            desktop.session().iter_windows().next(),
+            Some(Err(WindowError::Unsupported))
+        ));
+
+        desktop.clear_failures();
+        assert!(first.focus().is_ok());
+    }
+}
@@ -0,0 +1,98 @@
+use std::{path::PathBuf, sync::Arc};
+
+use crate::{
+    protocol::{backend::BuiltinApplication, ApplicationOps, Window, WindowError},
+    Backend,
+};
+
+/// Representation of a running application that owns windows.
+#[derive(Debug, Clone)]
+pub struct Application(pub(crate) Arc<dyn ApplicationOps>);
+
+impl Application {
+    /// Wraps an application from a custom [`WindowBackend`](crate::WindowBackend).
+    #[inline]
+    pub fn new(application: impl ApplicationOps + 'static) -> Application {
+        Application(Arc::new(application))
+    }
+
+    pub(crate) fn from_sys(backend: Backend, pid: u32) -> Application {
+        Application::new(BuiltinApplication { backend, pid })
+    }
+
+    /// The process identifier of the application.
+    ///
+    /// Like window handles, process identifiers may be recycled once the application exits.
+    #[inline]
+    pub fn pid(&self) -> u32 {
+        self.0.pid()
+    }
+
+    /// The name of the application.
+    ///
+    /// On macOS, this is the localized name shown in the menu bar.
+    ///
+    /// On Windows, this is the file name of the executable, without its extension.
+    ///
+    /// On Linux, this is the process name from `/proc`, which the kernel truncates to 15 bytes.
+    #[inline]
+    pub fn name(&self) -> Result<String, WindowError> {
+        self.0.name()
+    }
+
+    /// The path to the executable of the application.
+    #[inline]
+    pub fn executable_path(&self) -> Result<PathBuf, WindowError> {
+        self.0.executable_path()
+    }
+
+    /// Returns an iterator over the windows of the application.
+    #[inline]
+    pub fn iter_windows(&self) -> impl Iterator<Item = Result<Window, WindowError>> {
+        self.0.iter_windows()
+    }
+
+    /// Whether or not the application owns the focused window.
+    #[inline]
+    pub fn is_frontmost(&self) -> Result<bool, WindowError> {
+        self.0.is_frontmost()
+    }
+
+    /// Bring the application to the front and focus it.
+    ///
+    /// On macOS, this function activates the application, which brings all of its windows forward.
+    ///
+    /// On Windows and Linux, there is no such concept, so this function focuses one of the
+    /// application's windows instead.
+    #[inline]
+    pub fn activate(&self) -> Result<(), WindowError> {
+        self.0.activate()
+    }
+}
+
+// The pid uniquely identifies an application whilst it's running.
+impl PartialEq for Application {
+    fn eq(&self, other: &Self) -> bool {
+        self.pid() == other.pid()
+    }
+}
+
+impl Eq for Application {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn needs_send<T: Send>() {}
+    fn needs_sync<T: Sync>() {}
+
+    #[test]
+    fn application_send() {
+        needs_send::<Application>();
+    }
+
+    #[test]
+    fn application_sync() {
+        needs_sync::<Application>();
+    }
+}
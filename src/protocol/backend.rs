@@ -0,0 +1,521 @@
+use std::{
+    any::Any,
+    collections::HashSet,
+    fmt::Debug,
+    iter,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    protocol::{
+        lifecycle::Lifecycle, Application, EventFilter, Insets, LogicalPosition, LogicalSize,
+        PropertyMask, Rect, Window, WindowError, WindowEvent, WindowHandle, WindowProperties,
+    },
+    sys, Backend,
+};
+
+/// A source of windows, such as a platform API or a remote desktop.
+///
+/// Implement this trait to plug a custom backend into fowin, then pass it to
+/// [`Session::from_backend`](crate::Session::from_backend). The built-in backends are implemented
+/// the same way.
+pub trait WindowBackend: Debug + Send + Sync {
+    /// The kind of backend, custom backends should return [`Backend::Custom`].
+    fn kind(&self) -> Backend;
+
+    /// Watches for all window events from this backend.
+    fn watch(&self) -> Result<Box<dyn EventSource>, WindowError>;
+
+    /// Watches for the window events that pass the filter from this backend.
+    ///
+    /// Backends may report events that don't pass the filter, the watcher drops them. By default,
+    /// every event is watched with [`WindowBackend::watch`].
+    fn watch_filtered(&self, _filter: &EventFilter) -> Result<Box<dyn EventSource>, WindowError> {
+        self.watch()
+    }
+
+    /// Returns an iterator over all existing windows from this backend.
+    fn iter_windows(&self) -> Box<dyn Iterator<Item = Result<Window, WindowError>>>;
+
+    /// Returns the globally focused window from this backend if one exists.
+    fn focused_window(&self) -> Result<Option<Window>, WindowError>;
+
+    /// Returns an iterator over all existing windows from this backend, along with the properties
+    /// in the mask.
+    ///
+    /// By default, the properties of each window from [`WindowBackend::iter_windows`] are fetched
+    /// with [`WindowOps::properties`].
+    fn iter_windows_with(
+        &self,
+        mask: PropertyMask,
+    ) -> Box<dyn Iterator<Item = Result<(Window, WindowProperties), WindowError>>> {
+        Box::new(self.iter_windows().map(move |window| {
+            let window = window?;
+            let properties = window.properties(mask)?;
+            Ok((window, properties))
+        }))
+    }
+
+    /// Returns the handles of every window from this backend, from the front to the back.
+    fn stacking_order(&self) -> Result<Vec<WindowHandle>, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    /// Returns an iterator over the applications from this backend.
+    ///
+    /// By default, these are the applications owning the windows from [`WindowBackend::iter_windows`].
+    fn iter_applications(&self) -> Box<dyn Iterator<Item = Result<Application, WindowError>>> {
+        let mut pids = HashSet::new();
+        Box::new(self.iter_windows().filter_map(move |window| {
+            match window.and_then(|window| window.application()) {
+                Ok(application) => pids.insert(application.pid()).then_some(Ok(application)),
+                Err(err) => Some(Err(err)),
+            }
+        }))
+    }
+}
+
+/// The operations that can be performed on a single window, see [`Window`] for their semantics.
+///
+/// Geometry is always in logical pixels and refers to the outer frame of the window, [`Window`]
+/// converts physical pixels with [`WindowOps::scale_factor`] and content frames with
+/// [`WindowOps::decoration_insets`].
+///
+/// Every operation except [`WindowOps::handle`] returns [`WindowError::Unsupported`] by default,
+/// so backends only need to implement what they support. The exceptions are [`WindowOps::frame`]
+/// and [`WindowOps::set_frame`], which fall back to their separate position and size operations,
+/// and [`WindowOps::properties`], which falls back to fetching each property on its own.
+pub trait WindowOps: Any + Debug + Send + Sync {
+    /// A handle associated with the window.
+    fn handle(&self) -> WindowHandle;
+
+    /// The title of the window.
+    fn title(&self) -> Result<String, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    /// The size of the window in logical pixels.
+    fn size(&self) -> Result<LogicalSize, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    /// The position of the window in logical pixels relative to the current display.
+    fn position(&self) -> Result<LogicalPosition, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    /// The ratio of physical pixels to logical pixels for the window.
+    fn scale_factor(&self) -> Result<f64, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    /// Whether or not the window is focused.
+    fn is_focused(&self) -> Result<bool, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    /// Whether or not the window is fullscreened.
+    fn is_fullscreen(&self) -> Result<bool, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    /// Whether or not the window is minimized.
+    fn is_minimized(&self) -> Result<bool, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    /// Whether or not the window is hidden.
+    fn is_hidden(&self) -> Result<bool, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    /// The properties in the mask, fetched together.
+    fn properties(&self, mask: PropertyMask) -> Result<WindowProperties, WindowError> {
+        WindowProperties::fetch(self, mask)
+    }
+
+    /// Change the size of the window in logical pixels.
+    fn resize(&self, _size: LogicalSize) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    /// Change the position of the window in logical pixels.
+    fn reposition(&self, _position: LogicalPosition) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    /// The outer frame of the window in logical pixels.
+    fn frame(&self) -> Result<Rect, WindowError> {
+        Ok(Rect {
+            position: self.position()?,
+            size: self.size()?,
+        })
+    }
+
+    /// Change the outer frame of the window, returning the frame it ended up with.
+    fn set_frame(&self, frame: Rect) -> Result<Rect, WindowError> {
+        self.reposition(frame.position)?;
+        self.resize(frame.size)?;
+        self.frame()
+    }
+
+    /// The space between the outer frame and the content of the window in logical pixels.
+    fn decoration_insets(&self) -> Result<Insets, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    /// Focus the window.
+    fn focus(&self) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    /// Fullscreen the window.
+    fn fullscreen(&self) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    /// Unfullscreen the window.
+    fn unfullscreen(&self) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    /// Maximize the window.
+    fn maximize(&self) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    /// Minimizes the window.
+    fn minimize(&self) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    /// Unminimizes the window.
+    fn unminimize(&self) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    /// Show the window.
+    fn show(&self) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    /// Hide the window.
+    fn hide(&self) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    /// Bring the window to the front.
+    fn bring_to_front(&self) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    /// Ask the window to close.
+    fn close(&self) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    /// Forcefully terminate the process that owns the window.
+    fn kill(&self) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    /// The application that owns the window.
+    fn application(&self) -> Result<Application, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+}
+
+/// The operations that can be performed on a single application, see [`Application`] for their
+/// semantics.
+///
+/// Every operation except [`ApplicationOps::pid`] returns [`WindowError::Unsupported`] by default.
+pub trait ApplicationOps: Debug + Send + Sync {
+    /// The process identifier of the application.
+    fn pid(&self) -> u32;
+
+    /// The name of the application.
+    fn name(&self) -> Result<String, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    /// The path to the executable of the application.
+    fn executable_path(&self) -> Result<PathBuf, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    /// Returns an iterator over the windows of the application.
+    fn iter_windows(&self) -> Box<dyn Iterator<Item = Result<Window, WindowError>>> {
+        Box::new(iter::once(Err(WindowError::Unsupported)))
+    }
+
+    /// Whether or not the application owns the focused window.
+    fn is_frontmost(&self) -> Result<bool, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    /// Bring the application to the front and focus it.
+    fn activate(&self) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+}
+
+/// A stream of window events, backing a [`Watcher`](crate::Watcher).
+///
+/// Event sources aren't required to be [`Send`], since some platforms can only receive events on
+/// the thread that started watching.
+pub trait EventSource: Debug {
+    /// Blocks until the next window event is available.
+    fn next_request(&mut self) -> Result<WindowEvent, WindowError>;
+
+    /// Blocks until the next window event is available, or returns `None` once the timeout
+    /// elapses.
+    ///
+    /// This is used to collect batches of events, see [`Watcher::next_batch`](crate::Watcher::next_batch).
+    fn next_request_timeout(
+        &mut self,
+        _timeout: Duration,
+    ) -> Result<Option<WindowEvent>, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    /// When the event that was returned last happened, if the backend reports it.
+    ///
+    /// The watcher stamps events with it, or with when they were received if there is none, see
+    /// [`EventEnvelope::timestamp`](crate::EventEnvelope::timestamp). By default, `None`.
+    fn event_time(&self) -> Option<Instant> {
+        None
+    }
+}
+
+/// One of the backends built into fowin, implemented by the `sys` module.
+#[derive(Debug)]
+pub(crate) struct Builtin(pub Backend);
+
+impl WindowBackend for Builtin {
+    fn kind(&self) -> Backend {
+        self.0
+    }
+
+    fn watch(&self) -> Result<Box<dyn EventSource>, WindowError> {
+        self.watch_filtered(&EventFilter::default())
+    }
+
+    fn watch_filtered(&self, filter: &EventFilter) -> Result<Box<dyn EventSource>, WindowError> {
+        let watcher = sys::watch(self.0, filter)?;
+        if sys::APPLICATION_EVENTS {
+            Ok(Box::new(watcher))
+        } else {
+            Ok(Box::new(Lifecycle::new(watcher, self)))
+        }
+    }
+
+    fn iter_windows(&self) -> Box<dyn Iterator<Item = Result<Window, WindowError>>> {
+        Box::new(sys::iter_windows(self.0).map(|result| result.map(Window::from_sys)))
+    }
+
+    fn focused_window(&self) -> Result<Option<Window>, WindowError> {
+        sys::focused_window(self.0).map(|option| option.map(Window::from_sys))
+    }
+
+    fn stacking_order(&self) -> Result<Vec<WindowHandle>, WindowError> {
+        Ok(sys::stacking_order(self.0)?
+            .iter()
+            .map(|window| WindowHandle::from_sys(window.handle()))
+            .collect())
+    }
+
+    // The properties of every window are fetched together, rather than one window at a time.
+    fn iter_windows_with(
+        &self,
+        mask: PropertyMask,
+    ) -> Box<dyn Iterator<Item = Result<(Window, WindowProperties), WindowError>>> {
+        let windows: Vec<_> = sys::iter_windows(self.0).collect();
+        let valid: Vec<_> = windows.iter().flatten().collect();
+        let mut properties = sys::properties(&valid, mask).into_iter();
+
+        let results: Vec<_> = windows
+            .into_iter()
+            .map(|window| {
+                let window = window?;
+                // Every valid window has a result, in the same order.
+                let properties = properties.next().expect("missing window properties")?;
+                Ok((Window::from_sys(window), properties))
+            })
+            .collect();
+        Box::new(results.into_iter())
+    }
+}
+
+impl WindowOps for sys::Window {
+    fn handle(&self) -> WindowHandle {
+        WindowHandle::from_sys(self.handle())
+    }
+
+    fn title(&self) -> Result<String, WindowError> {
+        self.title()
+    }
+
+    fn size(&self) -> Result<LogicalSize, WindowError> {
+        self.size()
+    }
+
+    fn position(&self) -> Result<LogicalPosition, WindowError> {
+        self.position()
+    }
+
+    fn scale_factor(&self) -> Result<f64, WindowError> {
+        self.scale_factor()
+    }
+
+    fn is_focused(&self) -> Result<bool, WindowError> {
+        self.is_focused()
+    }
+
+    fn is_fullscreen(&self) -> Result<bool, WindowError> {
+        self.is_fullscreen()
+    }
+
+    fn is_minimized(&self) -> Result<bool, WindowError> {
+        self.is_minimized()
+    }
+
+    fn is_hidden(&self) -> Result<bool, WindowError> {
+        self.is_hidden()
+    }
+
+    fn properties(&self, mask: PropertyMask) -> Result<WindowProperties, WindowError> {
+        self.properties(mask)
+    }
+
+    fn resize(&self, size: LogicalSize) -> Result<(), WindowError> {
+        self.resize(size)
+    }
+
+    fn reposition(&self, position: LogicalPosition) -> Result<(), WindowError> {
+        self.reposition(position)
+    }
+
+    fn frame(&self) -> Result<Rect, WindowError> {
+        self.frame()
+    }
+
+    fn set_frame(&self, frame: Rect) -> Result<Rect, WindowError> {
+        self.set_frame(frame)
+    }
+
+    fn decoration_insets(&self) -> Result<Insets, WindowError> {
+        self.decoration_insets()
+    }
+
+    fn focus(&self) -> Result<(), WindowError> {
+        self.focus()
+    }
+
+    fn fullscreen(&self) -> Result<(), WindowError> {
+        self.fullscreen()
+    }
+
+    fn unfullscreen(&self) -> Result<(), WindowError> {
+        self.unfullscreen()
+    }
+
+    fn maximize(&self) -> Result<(), WindowError> {
+        self.maximize()
+    }
+
+    fn minimize(&self) -> Result<(), WindowError> {
+        self.minimize()
+    }
+
+    fn unminimize(&self) -> Result<(), WindowError> {
+        self.unminimize()
+    }
+
+    fn show(&self) -> Result<(), WindowError> {
+        self.show()
+    }
+
+    fn hide(&self) -> Result<(), WindowError> {
+        self.hide()
+    }
+
+    fn bring_to_front(&self) -> Result<(), WindowError> {
+        self.bring_to_front()
+    }
+
+    fn close(&self) -> Result<(), WindowError> {
+        self.close()
+    }
+
+    fn kill(&self) -> Result<(), WindowError> {
+        sys::kill_process(self.pid()?)
+    }
+
+    fn application(&self) -> Result<Application, WindowError> {
+        Ok(Application::from_sys(self.backend(), self.pid()?))
+    }
+}
+
+/// A process that owns windows from one of the built-in backends.
+#[derive(Debug)]
+pub(crate) struct BuiltinApplication {
+    pub backend: Backend,
+    pub pid: u32,
+}
+
+impl ApplicationOps for BuiltinApplication {
+    fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    fn name(&self) -> Result<String, WindowError> {
+        sys::application_name(self.pid)
+    }
+
+    fn executable_path(&self) -> Result<PathBuf, WindowError> {
+        sys::executable_path(self.pid)
+    }
+
+    fn iter_windows(&self) -> Box<dyn Iterator<Item = Result<Window, WindowError>>> {
+        let pid = self.pid;
+        Box::new(
+            sys::iter_windows(self.backend)
+                // Windows that can't report their process can't belong to this application.
+                .filter(move |window| match window {
+                    Ok(window) => window.pid().ok() == Some(pid),
+                    Err(_) => true,
+                })
+                .map(|result| result.map(Window::from_sys)),
+        )
+    }
+
+    fn is_frontmost(&self) -> Result<bool, WindowError> {
+        match sys::focused_window(self.backend)? {
+            Some(window) => Ok(window.pid()? == self.pid),
+            None => Ok(false),
+        }
+    }
+
+    fn activate(&self) -> Result<(), WindowError> {
+        sys::activate_application(self.backend, self.pid)
+    }
+}
+
+impl EventSource for sys::Watcher {
+    fn next_request(&mut self) -> Result<WindowEvent, WindowError> {
+        // Not every platform's watcher takes `&mut self`, which would resolve to this method instead.
+        sys::Watcher::next_request(self)
+    }
+
+    fn next_request_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<WindowEvent>, WindowError> {
+        sys::Watcher::next_request_timeout(self, timeout)
+    }
+
+    fn event_time(&self) -> Option<Instant> {
+        sys::Watcher::event_time(self)
+    }
+}
@@ -0,0 +1,338 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    mem,
+    time::{Duration, Instant},
+};
+
+use crate::protocol::{EventEnvelope, WindowEvent, WindowHandle};
+
+/// How a [`Watcher`](crate::Watcher) reduces bursts of events, see
+/// [`Watcher::coalesce`](crate::Watcher::coalesce).
+///
+/// Interactively dragging a window reports hundreds of moved or resized events per second. The
+/// policies below hold events back and merge them, so that only the final position, or one
+/// update per frame, is delivered:
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use fowin::{Coalesce, Watcher};
+/// let mut watcher = Watcher::new()?.coalesce(
+///     Coalesce::new()
+///         .collapse()
+///         .debounce(Duration::from_millis(100)),
+/// );
+/// # Ok::<(), fowin::WindowError>(())
+/// ```
+///
+/// Policies apply to each window separately, and to each application for application events.
+/// Events of the same window are always delivered in order, but a window whose events are held
+/// back may be overtaken by other windows. Held events keep their timestamp and sequence number.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Coalesce {
+    collapse: bool,
+    debounce: Option<Duration>,
+    throttle: Option<Duration>,
+    drop_closed: bool,
+}
+
+impl Coalesce {
+    /// A policy that delivers every event as soon as it's received.
+    pub fn new() -> Coalesce {
+        Coalesce::default()
+    }
+
+    /// Merges consecutive events of the same kind for the same window into the latest one.
+    ///
+    /// Only events that arrived before the first of them is delivered can be merged, so this is
+    /// usually combined with [`Coalesce::debounce`] or [`Coalesce::throttle`] to hold it back.
+    /// Merged moved, resized and renamed events carry the previous value of the first event and
+    /// the new value of the last.
+    pub fn collapse(mut self) -> Coalesce {
+        self.collapse = true;
+        self
+    }
+
+    /// Holds the events of a window until none were received for the `quiet` period.
+    pub fn debounce(mut self, quiet: Duration) -> Coalesce {
+        self.debounce = Some(quiet);
+        self
+    }
+
+    /// Delivers at most `rate` events per second for each window, holding back the rest.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rate` is zero.
+    pub fn throttle(mut self, rate: u32) -> Coalesce {
+        self.throttle = Some(Duration::from_secs(1) / rate);
+        self
+    }
+
+    /// Drops the held events of a window once it closes.
+    ///
+    /// If the window was opened and closed before either was delivered, neither is.
+    pub fn drop_closed(mut self) -> Coalesce {
+        self.drop_closed = true;
+        self
+    }
+}
+
+/// What events are coalesced by.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Window(WindowHandle),
+    Application(u32),
+}
+
+impl Key {
+    fn of(event: &WindowEvent) -> Key {
+        match event {
+            WindowEvent::Opened(window)
+            | WindowEvent::Hidden(window)
+            | WindowEvent::Shown(window)
+            | WindowEvent::Minimized(window)
+            | WindowEvent::Unminimized(window)
+            | WindowEvent::Maximized(window)
+            | WindowEvent::Unmaximized(window)
+            | WindowEvent::Fullscreened(window)
+            | WindowEvent::Unfullscreened(window)
+            | WindowEvent::Focused(window)
+            | WindowEvent::Unfocused(window)
+            | WindowEvent::Moved { window, .. }
+            | WindowEvent::Resized { window, .. }
+            | WindowEvent::MoveResizeStarted(window)
+            | WindowEvent::MoveResizeEnded(window)
+            | WindowEvent::Renamed { window, .. } => Key::Window(window.handle()),
+            WindowEvent::Closed(handle) => Key::Window(handle.clone()),
+            WindowEvent::ApplicationTerminated(pid) => Key::Application(*pid),
+            WindowEvent::ApplicationLaunched(application)
+            | WindowEvent::ApplicationActivated(application)
+            | WindowEvent::ApplicationHidden(application)
+            | WindowEvent::ApplicationShown(application) => Key::Application(application.pid()),
+        }
+    }
+}
+
+/// Holds and merges events according to a [`Coalesce`] policy until they're ready.
+#[derive(Debug)]
+pub(crate) struct Coalescer {
+    policy: Coalesce,
+    // Events that are yet to be delivered, in the order they were received.
+    pending: VecDeque<(Key, EventEnvelope)>,
+    // When the last event of each window was delivered, only tracked when throttling.
+    delivered: HashMap<Key, Instant>,
+}
+
+// Wayland handles are proxies with interior mutability, but they hash by their id.
+#[allow(clippy::mutable_key_type)]
+impl Coalescer {
+    pub fn new(policy: Coalesce) -> Coalescer {
+        Coalescer {
+            policy,
+            pending: VecDeque::new(),
+            delivered: HashMap::new(),
+        }
+    }
+
+    pub fn push(&mut self, mut envelope: EventEnvelope) {
+        let key = Key::of(&envelope.event);
+        if let WindowEvent::Closed(_) = envelope.event {
+            self.delivered.remove(&key);
+            if self.policy.drop_closed {
+                let mut opened = false;
+                self.pending.retain(|(other, envelope)| {
+                    opened |= *other == key && matches!(envelope.event, WindowEvent::Opened(_));
+                    *other != key
+                });
+                // The window was never reported as opened, so it isn't reported as closed either.
+                if opened {
+                    return;
+                }
+            }
+        }
+
+        if self.policy.collapse {
+            let previous = self.pending.iter().rposition(|(other, _)| *other == key);
+            if let Some(index) = previous.filter(|&index| {
+                mem::discriminant(&self.pending[index].1.event)
+                    == mem::discriminant(&envelope.event)
+            }) {
+                // The index was just found.
+                let (_, previous) = self.pending.remove(index).unwrap();
+                merge(previous.event, &mut envelope.event);
+            }
+        }
+
+        self.pending.push_back((key, envelope));
+    }
+
+    // Removes the first event that's ready to be delivered.
+    pub fn release(&mut self, now: Instant) -> Option<EventEnvelope> {
+        let latest = self.latest();
+        let index = self
+            .pending
+            .iter()
+            .position(|(key, envelope)| self.ready_at(key, envelope, &latest) <= now)?;
+
+        // The index was just found.
+        let (key, envelope) = self.pending.remove(index).unwrap();
+        if self.policy.throttle.is_some() {
+            self.delivered.insert(key, now);
+        }
+        Some(envelope)
+    }
+
+    // When the next held event will be ready, if any.
+    pub fn next_ready(&self) -> Option<Instant> {
+        let latest = self.latest();
+        self.pending
+            .iter()
+            .map(|(key, envelope)| self.ready_at(key, envelope, &latest))
+            .min()
+    }
+
+    fn ready_at(
+        &self,
+        key: &Key,
+        envelope: &EventEnvelope,
+        latest: &HashMap<&Key, Instant>,
+    ) -> Instant {
+        let mut ready = envelope.timestamp;
+        if let Some(quiet) = self.policy.debounce {
+            ready = ready.max(latest[&key] + quiet);
+        }
+        if let (Some(interval), Some(delivered)) = (self.policy.throttle, self.delivered.get(key)) {
+            ready = ready.max(*delivered + interval);
+        }
+        ready
+    }
+
+    // When the last held event of each window was received.
+    fn latest(&self) -> HashMap<&Key, Instant> {
+        self.pending
+            .iter()
+            .map(|(key, envelope)| (key, envelope.timestamp))
+            .collect()
+    }
+}
+
+// Carries the previous value of the earlier event over to the later one.
+fn merge(earlier: WindowEvent, later: &mut WindowEvent) {
+    match (earlier, later) {
+        (WindowEvent::Moved { old, .. }, WindowEvent::Moved { old: later, .. }) => *later = old,
+        (WindowEvent::Resized { old, .. }, WindowEvent::Resized { old: later, .. }) => *later = old,
+        (
+            WindowEvent::Renamed { old_title, .. },
+            WindowEvent::Renamed {
+                old_title: later, ..
+            },
+        ) => *later = old_title,
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Backend, LogicalPosition, Window, WindowOps};
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct FakeWindow(u64);
+
+    impl WindowOps for FakeWindow {
+        fn handle(&self) -> WindowHandle {
+            WindowHandle::custom(self.0)
+        }
+    }
+
+    fn envelope(event: WindowEvent, timestamp: Instant) -> EventEnvelope {
+        EventEnvelope {
+            event,
+            timestamp,
+            sequence: 0,
+            source: Backend::Custom("fake"),
+        }
+    }
+
+    fn window(id: u64) -> Window {
+        Window::new(FakeWindow(id))
+    }
+
+    fn moved(id: u64, old: f64, new: f64) -> WindowEvent {
+        WindowEvent::Moved {
+            window: window(id),
+            old: Some(LogicalPosition { x: old, y: 0.0 }),
+            new: Some(LogicalPosition { x: new, y: 0.0 }),
+        }
+    }
+
+    #[test]
+    fn collapse() {
+        let now = Instant::now();
+        let mut coalescer = Coalescer::new(Coalesce::new().collapse().drop_closed());
+        coalescer.push(envelope(moved(1, 0.0, 10.0), now));
+        coalescer.push(envelope(moved(2, 0.0, 10.0), now));
+        coalescer.push(envelope(moved(1, 10.0, 20.0), now));
+        // Opened and closed before either was delivered.
+        coalescer.push(envelope(WindowEvent::Opened(window(3)), now));
+        coalescer.push(envelope(moved(3, 0.0, 10.0), now));
+        coalescer.push(envelope(WindowEvent::Closed(WindowHandle::custom(3)), now));
+
+        let mut release = || coalescer.release(now).map(|envelope| envelope.event);
+        assert!(
+            matches!(release(), Some(WindowEvent::Moved { window: moved, .. }) if moved == window(2))
+        );
+        assert!(matches!(
+            release(),
+            Some(WindowEvent::Moved { window: moved, old: Some(old), new: Some(new) })
+                if moved == window(1) && old.x == 0.0 && new.x == 20.0
+        ));
+        assert!(release().is_none());
+    }
+
+    #[test]
+    fn debounce() {
+        let quiet = Duration::from_millis(100);
+        let start = Instant::now();
+        let mut coalescer = Coalescer::new(Coalesce::new().debounce(quiet));
+        coalescer.push(envelope(moved(1, 0.0, 10.0), start));
+        coalescer.push(envelope(moved(2, 0.0, 10.0), start));
+        let later = start + Duration::from_millis(80);
+        coalescer.push(envelope(moved(1, 10.0, 20.0), later));
+
+        // The first window is still moving, so the second window overtakes it.
+        assert_eq!(coalescer.next_ready(), Some(start + quiet));
+        assert!(coalescer.release(later).is_none());
+        assert!(matches!(
+            coalescer.release(start + quiet).map(|envelope| envelope.event),
+            Some(WindowEvent::Moved { window: moved, .. }) if moved == window(2)
+        ));
+        assert_eq!(coalescer.next_ready(), Some(later + quiet));
+        assert!(coalescer.release(later + quiet).is_some());
+        assert!(coalescer.release(later + quiet).is_some());
+        assert_eq!(coalescer.next_ready(), None);
+    }
+
+    #[test]
+    fn throttle() {
+        let start = Instant::now();
+        let mut coalescer = Coalescer::new(Coalesce::new().collapse().throttle(10));
+        coalescer.push(envelope(moved(1, 0.0, 10.0), start));
+        assert!(coalescer.release(start).is_some());
+
+        // Held until 100ms after the last delivery, merging everything received in between.
+        for x in 1..5 {
+            let x = x as f64 * 10.0;
+            coalescer.push(envelope(moved(1, x, x + 10.0), start));
+        }
+        assert!(coalescer.release(start).is_none());
+        let next = start + Duration::from_millis(100);
+        assert_eq!(coalescer.next_ready(), Some(next));
+        assert!(matches!(
+            coalescer.release(next).map(|envelope| envelope.event),
+            Some(WindowEvent::Moved { old: Some(old), new: Some(new), .. })
+                if old.x == 10.0 && new.x == 50.0
+        ));
+    }
+}
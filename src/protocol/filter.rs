@@ -0,0 +1,431 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    ops::{BitOr, BitOrAssign},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::protocol::{Application, EventSource, Window, WindowError, WindowEvent, WindowHandle};
+
+/// A set of window event kinds, see [`WatcherBuilder::events`](crate::WatcherBuilder::events).
+///
+/// Kinds are combined with `|`, for example `EventMask::MOVED | EventMask::RESIZED`.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct EventMask(u32);
+
+impl EventMask {
+    /// [`WindowEvent::Opened`].
+    pub const OPENED: EventMask = EventMask(1 << 0);
+    /// [`WindowEvent::Closed`].
+    pub const CLOSED: EventMask = EventMask(1 << 1);
+    /// [`WindowEvent::Hidden`].
+    pub const HIDDEN: EventMask = EventMask(1 << 2);
+    /// [`WindowEvent::Shown`].
+    pub const SHOWN: EventMask = EventMask(1 << 3);
+    /// [`WindowEvent::Minimized`].
+    pub const MINIMIZED: EventMask = EventMask(1 << 4);
+    /// [`WindowEvent::Unminimized`].
+    pub const UNMINIMIZED: EventMask = EventMask(1 << 5);
+    /// [`WindowEvent::Maximized`].
+    pub const MAXIMIZED: EventMask = EventMask(1 << 6);
+    /// [`WindowEvent::Unmaximized`].
+    pub const UNMAXIMIZED: EventMask = EventMask(1 << 7);
+    /// [`WindowEvent::Fullscreened`].
+    pub const FULLSCREENED: EventMask = EventMask(1 << 8);
+    /// [`WindowEvent::Unfullscreened`].
+    pub const UNFULLSCREENED: EventMask = EventMask(1 << 9);
+    /// [`WindowEvent::Focused`].
+    pub const FOCUSED: EventMask = EventMask(1 << 10);
+    /// [`WindowEvent::Unfocused`].
+    pub const UNFOCUSED: EventMask = EventMask(1 << 11);
+    /// [`WindowEvent::Moved`].
+    pub const MOVED: EventMask = EventMask(1 << 12);
+    /// [`WindowEvent::Resized`].
+    pub const RESIZED: EventMask = EventMask(1 << 13);
+    /// [`WindowEvent::MoveResizeStarted`].
+    pub const MOVE_RESIZE_STARTED: EventMask = EventMask(1 << 14);
+    /// [`WindowEvent::MoveResizeEnded`].
+    pub const MOVE_RESIZE_ENDED: EventMask = EventMask(1 << 15);
+    /// [`WindowEvent::Renamed`].
+    pub const RENAMED: EventMask = EventMask(1 << 16);
+    /// [`WindowEvent::ApplicationLaunched`].
+    pub const APPLICATION_LAUNCHED: EventMask = EventMask(1 << 17);
+    /// [`WindowEvent::ApplicationTerminated`].
+    pub const APPLICATION_TERMINATED: EventMask = EventMask(1 << 18);
+    /// [`WindowEvent::ApplicationActivated`].
+    pub const APPLICATION_ACTIVATED: EventMask = EventMask(1 << 19);
+    /// [`WindowEvent::ApplicationHidden`].
+    pub const APPLICATION_HIDDEN: EventMask = EventMask(1 << 20);
+    /// [`WindowEvent::ApplicationShown`].
+    pub const APPLICATION_SHOWN: EventMask = EventMask(1 << 21);
+    /// Every application event.
+    pub const APPLICATION: EventMask = EventMask(
+        Self::APPLICATION_LAUNCHED.0
+            | Self::APPLICATION_TERMINATED.0
+            | Self::APPLICATION_ACTIVATED.0
+            | Self::APPLICATION_HIDDEN.0
+            | Self::APPLICATION_SHOWN.0,
+    );
+    /// Every event.
+    pub const ALL: EventMask = EventMask((1 << 22) - 1);
+
+    /// A mask without any events.
+    #[inline]
+    pub const fn empty() -> EventMask {
+        EventMask(0)
+    }
+
+    /// The kind of the event.
+    pub fn of(event: &WindowEvent) -> EventMask {
+        match event {
+            WindowEvent::Opened(_) => EventMask::OPENED,
+            WindowEvent::Closed(_) => EventMask::CLOSED,
+            WindowEvent::Hidden(_) => EventMask::HIDDEN,
+            WindowEvent::Shown(_) => EventMask::SHOWN,
+            WindowEvent::Minimized(_) => EventMask::MINIMIZED,
+            WindowEvent::Unminimized(_) => EventMask::UNMINIMIZED,
+            WindowEvent::Maximized(_) => EventMask::MAXIMIZED,
+            WindowEvent::Unmaximized(_) => EventMask::UNMAXIMIZED,
+            WindowEvent::Fullscreened(_) => EventMask::FULLSCREENED,
+            WindowEvent::Unfullscreened(_) => EventMask::UNFULLSCREENED,
+            WindowEvent::Focused(_) => EventMask::FOCUSED,
+            WindowEvent::Unfocused(_) => EventMask::UNFOCUSED,
+            WindowEvent::Moved { .. } => EventMask::MOVED,
+            WindowEvent::Resized { .. } => EventMask::RESIZED,
+            WindowEvent::MoveResizeStarted(_) => EventMask::MOVE_RESIZE_STARTED,
+            WindowEvent::MoveResizeEnded(_) => EventMask::MOVE_RESIZE_ENDED,
+            WindowEvent::Renamed { .. } => EventMask::RENAMED,
+            WindowEvent::ApplicationLaunched(_) => EventMask::APPLICATION_LAUNCHED,
+            WindowEvent::ApplicationTerminated(_) => EventMask::APPLICATION_TERMINATED,
+            WindowEvent::ApplicationActivated(_) => EventMask::APPLICATION_ACTIVATED,
+            WindowEvent::ApplicationHidden(_) => EventMask::APPLICATION_HIDDEN,
+            WindowEvent::ApplicationShown(_) => EventMask::APPLICATION_SHOWN,
+        }
+    }
+
+    /// Whether or not every event in `other` is in the mask.
+    #[inline]
+    pub const fn contains(self, other: EventMask) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Whether or not any event in `other` is in the mask.
+    #[inline]
+    pub const fn intersects(self, other: EventMask) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl Default for EventMask {
+    fn default() -> EventMask {
+        EventMask::ALL
+    }
+}
+
+impl BitOr for EventMask {
+    type Output = EventMask;
+
+    fn bitor(self, rhs: EventMask) -> EventMask {
+        EventMask(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for EventMask {
+    fn bitor_assign(&mut self, rhs: EventMask) {
+        self.0 |= rhs.0;
+    }
+}
+
+type Predicate<T> = Arc<dyn Fn(&T) -> bool + Send + Sync>;
+
+/// Which events a [`Watcher`](crate::Watcher) is interested in, built with
+/// [`WatcherBuilder`](crate::WatcherBuilder).
+///
+/// Backends receive the filter in [`WindowBackend::watch_filtered`](crate::WindowBackend::watch_filtered)
+/// so that they can avoid observing what isn't needed. Events that get through anyways are
+/// filtered out by the watcher.
+#[derive(Clone, Default)]
+pub struct EventFilter {
+    pub(crate) events: EventMask,
+    pub(crate) windows: Option<Vec<WindowHandle>>,
+    pub(crate) pids: Option<Vec<u32>>,
+    pub(crate) title: Option<Predicate<str>>,
+    pub(crate) application: Option<Predicate<Application>>,
+}
+
+impl EventFilter {
+    /// The kinds of events to report.
+    #[inline]
+    pub fn events(&self) -> EventMask {
+        self.events
+    }
+
+    /// The windows to report events for, or `None` for every window.
+    #[inline]
+    pub fn windows(&self) -> Option<&[WindowHandle]> {
+        self.windows.as_deref()
+    }
+
+    /// The process ids of the applications to report events for, or `None` for every
+    /// application.
+    #[inline]
+    pub fn pids(&self) -> Option<&[u32]> {
+        self.pids.as_deref()
+    }
+
+    /// Whether or not events from the application could be reported, according to its pid and
+    /// the application predicate.
+    ///
+    /// Window handles and the title predicate aren't taken into account, since they apply to
+    /// each window separately.
+    pub fn includes_application(&self, application: &Application) -> bool {
+        self.pids
+            .as_ref()
+            .is_none_or(|pids| pids.contains(&application.pid()))
+            && self
+                .application
+                .as_ref()
+                .is_none_or(|predicate| predicate(application))
+    }
+
+    // Whether or not only some windows are reported.
+    fn by_window(&self) -> bool {
+        self.windows.is_some()
+            || self.pids.is_some()
+            || self.title.is_some()
+            || self.application.is_some()
+    }
+
+    // Whether or not windows have to be read from the backend to check them.
+    pub(crate) fn reads_windows(&self) -> bool {
+        self.pids.is_some() || self.title.is_some() || self.application.is_some()
+    }
+
+    // Application events are dropped by filters on windows and titles.
+    fn by_application_only(&self) -> bool {
+        self.windows.is_none() && self.title.is_none()
+    }
+}
+
+impl fmt::Debug for EventFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventFilter")
+            .field("events", &self.events)
+            .field("windows", &self.windows)
+            .field("pids", &self.pids)
+            .field("title", &self.title.is_some())
+            .field("application", &self.application.is_some())
+            .finish()
+    }
+}
+
+/// Drops the events that don't pass an [`EventFilter`].
+#[derive(Debug)]
+pub(crate) struct Filtered {
+    inner: Box<dyn EventSource>,
+    matcher: Matcher,
+}
+
+/// Decides which events pass an [`EventFilter`].
+///
+/// Whether a window matches is decided once it opens, or when watching begins for windows that
+/// were already open, and its title is checked again once it's renamed.
+#[derive(Debug)]
+pub(crate) struct Matcher {
+    filter: EventFilter,
+    // Whether each window matched the last time it was checked.
+    windows: HashMap<WindowHandle, Matched>,
+    // Whether each application matched, only tracked with an application predicate.
+    applications: HashMap<u32, bool>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Matched {
+    // The handle and application, which never change.
+    window: bool,
+    title: bool,
+}
+
+impl Filtered {
+    pub fn new(inner: Box<dyn EventSource>, matcher: Matcher) -> Filtered {
+        Filtered { inner, matcher }
+    }
+}
+
+impl Matcher {
+    pub fn new(filter: EventFilter) -> Matcher {
+        Matcher {
+            filter,
+            windows: HashMap::new(),
+            applications: HashMap::new(),
+        }
+    }
+
+    pub fn by_window(&self) -> bool {
+        self.filter.by_window()
+    }
+
+    pub fn reads_windows(&self) -> bool {
+        self.filter.reads_windows()
+    }
+
+    // Checks a window that was already open, since it can't be read anymore once it closes.
+    // Returns whether or not its events could pass, regardless of its title, which can change.
+    pub fn seed(&mut self, window: &Window) -> bool {
+        if !self.filter.by_window() {
+            return true;
+        }
+
+        self.window_matches(window, false, None);
+        self.windows[&window.handle()].window
+    }
+
+    // Wayland handles are proxies with interior mutability, but they hash by their id.
+    #[allow(clippy::mutable_key_type)]
+    fn matches(&mut self, event: &WindowEvent) -> bool {
+        let kind = self.filter.events.contains(EventMask::of(event));
+        if !self.filter.by_window() {
+            return kind;
+        }
+
+        match event {
+            // The window can't be read anymore, so it's reported if it matched before.
+            WindowEvent::Closed(handle) => {
+                let matched = match self.windows.remove(handle) {
+                    Some(matched) => matched.window && matched.title,
+                    None => {
+                        !self.filter.reads_windows()
+                            && self
+                                .filter
+                                .windows
+                                .as_ref()
+                                .is_none_or(|windows| windows.contains(handle))
+                    }
+                };
+                kind && matched
+            }
+            WindowEvent::Opened(window)
+            | WindowEvent::Hidden(window)
+            | WindowEvent::Shown(window)
+            | WindowEvent::Minimized(window)
+            | WindowEvent::Unminimized(window)
+            | WindowEvent::Maximized(window)
+            | WindowEvent::Unmaximized(window)
+            | WindowEvent::Fullscreened(window)
+            | WindowEvent::Unfullscreened(window)
+            | WindowEvent::Focused(window)
+            | WindowEvent::Unfocused(window)
+            | WindowEvent::Moved { window, .. }
+            | WindowEvent::Resized { window, .. }
+            | WindowEvent::MoveResizeStarted(window)
+            // Checked even if the kind isn't reported, so that the window's close can be.
+            | WindowEvent::MoveResizeEnded(window) => {
+                self.window_matches(window, false, None) && kind
+            }
+            WindowEvent::Renamed {
+                window, new_title, ..
+            } => self.window_matches(window, true, new_title.as_deref()) && kind,
+            WindowEvent::ApplicationTerminated(pid) => {
+                let matched = match self.filter.application {
+                    // The application can't be checked anymore either.
+                    Some(_) => self.applications.remove(pid).unwrap_or(false),
+                    None => self
+                        .filter
+                        .pids
+                        .as_ref()
+                        .is_none_or(|pids| pids.contains(pid)),
+                };
+                kind && matched && self.filter.by_application_only()
+            }
+            WindowEvent::ApplicationLaunched(application)
+            | WindowEvent::ApplicationActivated(application)
+            | WindowEvent::ApplicationHidden(application)
+            | WindowEvent::ApplicationShown(application) => {
+                kind && self.filter.by_application_only() && self.application_matches(application)
+            }
+        }
+    }
+
+    #[allow(clippy::mutable_key_type)]
+    fn window_matches(&mut self, window: &Window, renamed: bool, new_title: Option<&str>) -> bool {
+        let handle = window.handle();
+        let cached = self.windows.get(&handle).copied();
+        let matched = match cached {
+            Some(matched) => matched.window,
+            None => {
+                self.filter
+                    .windows
+                    .as_ref()
+                    .is_none_or(|windows| windows.contains(&handle))
+                    && (self.filter.pids.is_none() && self.filter.application.is_none()
+                        || window
+                            .application()
+                            .is_ok_and(|application| self.application_matches(&application)))
+            }
+        };
+
+        // Only fetched for windows that otherwise match, and again once they're renamed.
+        let title = match (&self.filter.title, cached) {
+            (None, _) => true,
+            _ if !matched => false,
+            (Some(_), Some(cached)) if !renamed => cached.title,
+            (Some(predicate), _) => match new_title {
+                Some(title) => predicate(title),
+                None => window.title().is_ok_and(|title| predicate(&title)),
+            },
+        };
+
+        self.windows.insert(
+            handle,
+            Matched {
+                window: matched,
+                title,
+            },
+        );
+        matched && title
+    }
+
+    fn application_matches(&mut self, application: &Application) -> bool {
+        if self.filter.application.is_none() {
+            return self.filter.includes_application(application);
+        }
+
+        let filter = &self.filter;
+        *self
+            .applications
+            .entry(application.pid())
+            .or_insert_with(|| filter.includes_application(application))
+    }
+}
+
+impl EventSource for Filtered {
+    fn next_request(&mut self) -> Result<WindowEvent, WindowError> {
+        loop {
+            let event = self.inner.next_request()?;
+            if self.matcher.matches(&event) {
+                return Ok(event);
+            }
+        }
+    }
+
+    fn next_request_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<WindowEvent>, WindowError> {
+        // Filtered events don't count, so keep waiting for the rest of the timeout.
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self.inner.next_request_timeout(remaining)? {
+                Some(event) if self.matcher.matches(&event) => return Ok(Some(event)),
+                Some(_) => {}
+                None => return Ok(None),
+            }
+        }
+    }
+
+    fn event_time(&self) -> Option<Instant> {
+        self.inner.event_time()
+    }
+}
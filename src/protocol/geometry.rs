@@ -0,0 +1,294 @@
+/// A position in logical pixels, which are physical pixels divided by the scale factor.
+///
+/// Logical pixels stay the same size when moving a window between displays with different scale
+/// factors, see [`Window::scale_factor`](crate::Window::scale_factor).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LogicalPosition {
+    /// The x position.
+    pub x: f64,
+    /// The y position.
+    pub y: f64,
+}
+
+impl LogicalPosition {
+    /// Converts the position to physical pixels.
+    #[inline]
+    pub fn to_physical(self, scale_factor: f64) -> PhysicalPosition {
+        PhysicalPosition {
+            x: self.x * scale_factor,
+            y: self.y * scale_factor,
+        }
+    }
+}
+
+/// A position in physical pixels, the pixels of the display itself.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PhysicalPosition {
+    /// The x position.
+    pub x: f64,
+    /// The y position.
+    pub y: f64,
+}
+
+impl PhysicalPosition {
+    /// Converts the position to logical pixels.
+    #[inline]
+    pub fn to_logical(self, scale_factor: f64) -> LogicalPosition {
+        LogicalPosition {
+            x: self.x / scale_factor,
+            y: self.y / scale_factor,
+        }
+    }
+}
+
+/// A size in logical pixels, see [`LogicalPosition`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LogicalSize {
+    /// The width of the size.
+    pub width: f64,
+    /// The height of the size.
+    pub height: f64,
+}
+
+impl LogicalSize {
+    /// Converts the size to physical pixels.
+    #[inline]
+    pub fn to_physical(self, scale_factor: f64) -> PhysicalSize {
+        PhysicalSize {
+            width: self.width * scale_factor,
+            height: self.height * scale_factor,
+        }
+    }
+}
+
+/// A size in physical pixels, see [`PhysicalPosition`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PhysicalSize {
+    /// The width of the size.
+    pub width: f64,
+    /// The height of the size.
+    pub height: f64,
+}
+
+impl PhysicalSize {
+    /// Converts the size to logical pixels.
+    #[inline]
+    pub fn to_logical(self, scale_factor: f64) -> LogicalSize {
+        LogicalSize {
+            width: self.width / scale_factor,
+            height: self.height / scale_factor,
+        }
+    }
+}
+
+/// A position in either logical or physical pixels, such as for [`Window::reposition`](crate::Window::reposition).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Position {
+    /// A position in logical pixels.
+    Logical(LogicalPosition),
+    /// A position in physical pixels.
+    Physical(PhysicalPosition),
+}
+
+impl Position {
+    /// The position in logical pixels.
+    #[inline]
+    pub fn to_logical(self, scale_factor: f64) -> LogicalPosition {
+        match self {
+            Position::Logical(position) => position,
+            Position::Physical(position) => position.to_logical(scale_factor),
+        }
+    }
+
+    /// The position in physical pixels.
+    #[inline]
+    pub fn to_physical(self, scale_factor: f64) -> PhysicalPosition {
+        match self {
+            Position::Logical(position) => position.to_physical(scale_factor),
+            Position::Physical(position) => position,
+        }
+    }
+}
+
+impl From<LogicalPosition> for Position {
+    fn from(position: LogicalPosition) -> Position {
+        Position::Logical(position)
+    }
+}
+
+impl From<PhysicalPosition> for Position {
+    fn from(position: PhysicalPosition) -> Position {
+        Position::Physical(position)
+    }
+}
+
+/// A size in either logical or physical pixels, such as for [`Window::resize`](crate::Window::resize).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Size {
+    /// A size in logical pixels.
+    Logical(LogicalSize),
+    /// A size in physical pixels.
+    Physical(PhysicalSize),
+}
+
+impl Size {
+    /// The size in logical pixels.
+    #[inline]
+    pub fn to_logical(self, scale_factor: f64) -> LogicalSize {
+        match self {
+            Size::Logical(size) => size,
+            Size::Physical(size) => size.to_logical(scale_factor),
+        }
+    }
+
+    /// The size in physical pixels.
+    #[inline]
+    pub fn to_physical(self, scale_factor: f64) -> PhysicalSize {
+        match self {
+            Size::Logical(size) => size.to_physical(scale_factor),
+            Size::Physical(size) => size,
+        }
+    }
+}
+
+impl From<LogicalSize> for Size {
+    fn from(size: LogicalSize) -> Size {
+        Size::Logical(size)
+    }
+}
+
+impl From<PhysicalSize> for Size {
+    fn from(size: PhysicalSize) -> Size {
+        Size::Physical(size)
+    }
+}
+
+/// A rectangle in logical pixels with a position and size, such as the frame of a window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rect {
+    /// The position of the top-left corner.
+    pub position: LogicalPosition,
+    /// The size of the rectangle.
+    pub size: LogicalSize,
+}
+
+impl Rect {
+    /// The rectangle shrunk by the insets on each side, such as the content frame of a window
+    /// from its outer frame.
+    pub fn inset(self, insets: Insets) -> Rect {
+        Rect {
+            position: LogicalPosition {
+                x: self.position.x + insets.left,
+                y: self.position.y + insets.top,
+            },
+            size: LogicalSize {
+                width: self.size.width - insets.left - insets.right,
+                height: self.size.height - insets.top - insets.bottom,
+            },
+        }
+    }
+
+    /// The rectangle grown by the insets on each side, the opposite of [`Rect::inset`].
+    pub fn outset(self, insets: Insets) -> Rect {
+        Rect {
+            position: LogicalPosition {
+                x: self.position.x - insets.left,
+                y: self.position.y - insets.top,
+            },
+            size: LogicalSize {
+                width: self.size.width + insets.left + insets.right,
+                height: self.size.height + insets.top + insets.bottom,
+            },
+        }
+    }
+}
+
+/// The space taken by the decorations on each side of a window's content, such as its title bar
+/// and borders, in logical pixels.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Insets {
+    /// The space above the content, usually the title bar.
+    pub top: f64,
+    /// The space to the right of the content.
+    pub right: f64,
+    /// The space below the content.
+    pub bottom: f64,
+    /// The space to the left of the content.
+    pub left: f64,
+}
+
+/// Which of a window's rectangles a frame refers to, see [`Window::set_frame`](crate::Window::set_frame).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FrameKind {
+    /// The whole window, including its decorations.
+    #[default]
+    Outer,
+    /// Only the content of the window, excluding its decorations.
+    Content,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        let position = LogicalPosition { x: 100.0, y: 50.5 };
+        assert_eq!(
+            position.to_physical(2.0),
+            PhysicalPosition { x: 200.0, y: 101.0 }
+        );
+        assert_eq!(position.to_physical(1.5).to_logical(1.5), position);
+
+        let size = PhysicalSize {
+            width: 1920.0,
+            height: 1080.0,
+        };
+        assert_eq!(
+            Size::from(size).to_logical(2.0),
+            LogicalSize {
+                width: 960.0,
+                height: 540.0
+            }
+        );
+        // Sizes already in the requested unit are left as they are.
+        assert_eq!(Size::from(size).to_physical(2.0), size);
+        assert_eq!(Position::from(position).to_logical(2.0), position);
+    }
+
+    #[test]
+    fn insets() {
+        let outer = Rect {
+            position: LogicalPosition { x: 100.0, y: 100.0 },
+            size: LogicalSize {
+                width: 800.0,
+                height: 600.0,
+            },
+        };
+        let insets = Insets {
+            top: 28.0,
+            right: 1.0,
+            bottom: 1.0,
+            left: 1.0,
+        };
+
+        let content = outer.inset(insets);
+        assert_eq!(
+            content,
+            Rect {
+                position: LogicalPosition { x: 101.0, y: 128.0 },
+                size: LogicalSize {
+                    width: 798.0,
+                    height: 571.0
+                },
+            }
+        );
+        assert_eq!(content.outset(insets), outer);
+    }
+}
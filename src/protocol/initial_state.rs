@@ -0,0 +1,153 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    time::{Duration, Instant},
+};
+
+use crate::protocol::{EventSource, Window, WindowError, WindowEvent, WindowHandle};
+
+/// Reports every window that was already open as [`WindowEvent::Opened`] before any live event.
+///
+/// The windows are listed after the backend started watching, so a window opened in between is
+/// both listed and reported by the backend. Those live events are dropped, so that every window
+/// is reported as opened once. Likewise, the close of a window that was never reported as opened,
+/// because it closed before the windows were listed, is dropped.
+#[derive(Debug)]
+pub(crate) struct InitialState {
+    inner: Box<dyn EventSource>,
+    // Windows that were already open and are yet to be reported, in order.
+    pending: VecDeque<Window>,
+    // Every window that was reported as opened and hasn't closed since.
+    open: HashSet<WindowHandle>,
+    // Whether or not the last event was a live event, rather than a window that was listed.
+    live: bool,
+}
+
+impl InitialState {
+    // The windows are listed by the session, which shares them with the other layers.
+    // Wayland handles are proxies with interior mutability, but they hash by their id.
+    #[allow(clippy::mutable_key_type)]
+    pub fn new(inner: Box<dyn EventSource>, pending: VecDeque<Window>) -> InitialState {
+        let open = pending.iter().map(Window::handle).collect();
+        InitialState {
+            inner,
+            pending,
+            open,
+            live: false,
+        }
+    }
+}
+
+impl InitialState {
+    // Drops live events for windows that were already reported as opened.
+    fn filter(&mut self, event: WindowEvent) -> Option<WindowEvent> {
+        match &event {
+            // The window opened after watching began, but before it was listed.
+            WindowEvent::Opened(window) if !self.open.insert(window.handle()) => return None,
+            // The window closed before it was listed, so it was never reported as opened.
+            WindowEvent::Closed(handle) if !self.open.remove(handle) => return None,
+            _ => {}
+        }
+
+        Some(event)
+    }
+}
+
+impl EventSource for InitialState {
+    fn next_request(&mut self) -> Result<WindowEvent, WindowError> {
+        if let Some(window) = self.pending.pop_front() {
+            return Ok(WindowEvent::Opened(window));
+        }
+
+        self.live = true;
+        loop {
+            let event = self.inner.next_request()?;
+            if let Some(event) = self.filter(event) {
+                return Ok(event);
+            }
+        }
+    }
+
+    fn next_request_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<WindowEvent>, WindowError> {
+        if let Some(window) = self.pending.pop_front() {
+            return Ok(Some(WindowEvent::Opened(window)));
+        }
+
+        self.live = true;
+        let deadline = Instant::now() + timeout;
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let Some(event) = self.inner.next_request_timeout(timeout)? else {
+                return Ok(None);
+            };
+            if let Some(event) = self.filter(event) {
+                return Ok(Some(event));
+            }
+        }
+    }
+
+    // The windows that were listed were opened before watching began, at an unknown time.
+    fn event_time(&self) -> Option<Instant> {
+        self.live.then(|| self.inner.event_time()).flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::WindowOps;
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct FakeWindow(u64);
+
+    #[derive(Debug)]
+    struct FakeEvents(Vec<WindowEvent>);
+
+    impl WindowOps for FakeWindow {
+        fn handle(&self) -> WindowHandle {
+            WindowHandle::custom(self.0)
+        }
+    }
+
+    impl EventSource for FakeEvents {
+        fn next_request(&mut self) -> Result<WindowEvent, WindowError> {
+            if self.0.is_empty() {
+                return Err(WindowError::InvalidHandle);
+            }
+            Ok(self.0.remove(0))
+        }
+    }
+
+    fn window(id: u64) -> Window {
+        Window::new(FakeWindow(id))
+    }
+
+    #[test]
+    fn initial_windows() {
+        let events = FakeEvents(vec![
+            // Opened after watching began, but before the windows were listed.
+            WindowEvent::Opened(window(1)),
+            WindowEvent::Opened(window(2)),
+            // Already open when watching began, but closed before the windows were listed.
+            WindowEvent::Closed(WindowHandle::custom(3)),
+            WindowEvent::Closed(WindowHandle::custom(1)),
+            // The handle was reused by a new window.
+            WindowEvent::Opened(window(1)),
+        ]);
+        let mut watcher = InitialState::new(Box::new(events), (0..2).map(window).collect());
+
+        let mut next = || watcher.next_request().unwrap();
+        assert!(matches!(next(), WindowEvent::Opened(opened) if opened == window(0)));
+        assert!(matches!(next(), WindowEvent::Opened(opened) if opened == window(1)));
+        assert!(matches!(next(), WindowEvent::Opened(opened) if opened == window(2)));
+        assert!(matches!(next(), WindowEvent::Closed(handle) if handle == WindowHandle::custom(1)));
+        assert!(matches!(next(), WindowEvent::Opened(opened) if opened == window(1)));
+        assert!(matches!(
+            watcher.next_request(),
+            Err(WindowError::InvalidHandle)
+        ));
+    }
+}
@@ -0,0 +1,233 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use crate::protocol::{EventSource, WindowBackend, WindowError, WindowEvent, WindowHandle};
+
+/// Derives application events from the window events of a backend that can't observe
+/// applications directly.
+///
+/// An application is launched when its first window opens, terminated when its last window
+/// closes, and activated when one of its windows is focused after a window of another application.
+#[derive(Debug)]
+pub(crate) struct Lifecycle<S> {
+    inner: S,
+    // The pid of every window that can report one.
+    windows: HashMap<WindowHandle, u32>,
+    // The pid of the application that owns the focused window.
+    active: Option<u32>,
+    // Events that are yet to be returned in order, along with when the event they're derived from
+    // happened.
+    queue: VecDeque<(WindowEvent, Option<Instant>)>,
+    // When the event that was returned last happened.
+    time: Option<Instant>,
+}
+
+impl<S: EventSource> Lifecycle<S> {
+    // Wayland handles are proxies with interior mutability, but they hash by their id.
+    #[allow(clippy::mutable_key_type)]
+    pub fn new(inner: S, backend: &dyn WindowBackend) -> Lifecycle<S> {
+        // Applications that are already running weren't launched whilst watching.
+        let windows = backend
+            .iter_windows()
+            .flatten()
+            .filter_map(|window| Some((window.handle(), window.application().ok()?.pid())))
+            .collect();
+        let active = backend
+            .focused_window()
+            .ok()
+            .flatten()
+            .and_then(|window| window.application().ok())
+            .map(|application| application.pid());
+
+        Lifecycle {
+            inner,
+            windows,
+            active,
+            queue: VecDeque::new(),
+            time: None,
+        }
+    }
+
+    fn running(&self, pid: u32) -> bool {
+        self.windows.values().any(|&other| other == pid)
+    }
+
+    // Queues the event along with the application events it implies.
+    fn track(&mut self, event: WindowEvent) {
+        let mut launched = None;
+        let mut derived = None;
+        match &event {
+            WindowEvent::Opened(window) => {
+                if let Ok(application) = window.application() {
+                    let pid = application.pid();
+                    if !self.running(pid) {
+                        launched = Some(WindowEvent::ApplicationLaunched(application));
+                    }
+                    self.windows.insert(window.handle(), pid);
+                }
+            }
+            WindowEvent::Closed(handle) => {
+                if let Some(pid) = self.windows.remove(handle) {
+                    if !self.running(pid) {
+                        if self.active == Some(pid) {
+                            self.active = None;
+                        }
+                        derived = Some(WindowEvent::ApplicationTerminated(pid));
+                    }
+                }
+            }
+            WindowEvent::Focused(window) => {
+                if let Ok(application) = window.application() {
+                    if self.active != Some(application.pid()) {
+                        self.active = Some(application.pid());
+                        derived = Some(WindowEvent::ApplicationActivated(application));
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        // An application is launched before its window opens, the rest follow the window event.
+        let time = self.inner.event_time();
+        self.queue.extend(launched.map(|event| (event, time)));
+        self.queue.push_back((event, time));
+        self.queue.extend(derived.map(|event| (event, time)));
+    }
+
+    // Returns the next queued event, remembering when it happened.
+    fn pop(&mut self) -> Option<WindowEvent> {
+        let (event, time) = self.queue.pop_front()?;
+        self.time = time;
+        Some(event)
+    }
+}
+
+impl<S: EventSource> EventSource for Lifecycle<S> {
+    fn next_request(&mut self) -> Result<WindowEvent, WindowError> {
+        while self.queue.is_empty() {
+            let event = self.inner.next_request()?;
+            self.track(event);
+        }
+
+        // The queue was just filled.
+        Ok(self.pop().unwrap())
+    }
+
+    fn next_request_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<WindowEvent>, WindowError> {
+        if self.queue.is_empty() {
+            match self.inner.next_request_timeout(timeout)? {
+                Some(event) => self.track(event),
+                None => return Ok(None),
+            }
+        }
+
+        Ok(self.pop())
+    }
+
+    fn event_time(&self) -> Option<Instant> {
+        self.time
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Application, ApplicationOps, Backend, Window, WindowOps};
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct FakeBackend;
+
+    #[derive(Debug)]
+    struct FakeWindow {
+        id: u64,
+        pid: u32,
+    }
+
+    #[derive(Debug)]
+    struct FakeApplication(u32);
+
+    #[derive(Debug)]
+    struct FakeEvents(Vec<WindowEvent>);
+
+    impl WindowBackend for FakeBackend {
+        fn kind(&self) -> Backend {
+            Backend::Custom("fake")
+        }
+
+        fn watch(&self) -> Result<Box<dyn EventSource>, WindowError> {
+            Err(WindowError::Unsupported)
+        }
+
+        // The first window is already open.
+        fn iter_windows(&self) -> Box<dyn Iterator<Item = Result<Window, WindowError>>> {
+            Box::new([Ok(window(0, 1))].into_iter())
+        }
+
+        fn focused_window(&self) -> Result<Option<Window>, WindowError> {
+            Ok(Some(window(0, 1)))
+        }
+    }
+
+    impl WindowOps for FakeWindow {
+        fn handle(&self) -> WindowHandle {
+            WindowHandle::custom(self.id)
+        }
+
+        fn application(&self) -> Result<Application, WindowError> {
+            Ok(Application::new(FakeApplication(self.pid)))
+        }
+    }
+
+    impl ApplicationOps for FakeApplication {
+        fn pid(&self) -> u32 {
+            self.0
+        }
+    }
+
+    impl EventSource for FakeEvents {
+        fn next_request(&mut self) -> Result<WindowEvent, WindowError> {
+            if self.0.is_empty() {
+                return Err(WindowError::InvalidHandle);
+            }
+            Ok(self.0.remove(0))
+        }
+    }
+
+    fn window(id: u64, pid: u32) -> Window {
+        Window::new(FakeWindow { id, pid })
+    }
+
+    #[test]
+    fn derived_events() {
+        let events = FakeEvents(vec![
+            WindowEvent::Opened(window(1, 1)),
+            WindowEvent::Opened(window(2, 2)),
+            WindowEvent::Focused(window(1, 1)),
+            WindowEvent::Focused(window(2, 2)),
+            WindowEvent::Closed(WindowHandle::custom(2)),
+            WindowEvent::Closed(WindowHandle::custom(0)),
+            WindowEvent::Closed(WindowHandle::custom(1)),
+        ]);
+        let mut lifecycle = Lifecycle::new(events, &FakeBackend);
+
+        let mut next = || lifecycle.next_request().unwrap();
+        assert!(matches!(next(), WindowEvent::Opened(_)));
+        assert!(matches!(next(), WindowEvent::ApplicationLaunched(app) if app.pid() == 2));
+        assert!(matches!(next(), WindowEvent::Opened(_)));
+        // The application already had focus before watching.
+        assert!(matches!(next(), WindowEvent::Focused(_)));
+        assert!(matches!(next(), WindowEvent::Focused(_)));
+        assert!(matches!(next(), WindowEvent::ApplicationActivated(app) if app.pid() == 2));
+        assert!(matches!(next(), WindowEvent::Closed(_)));
+        assert!(matches!(next(), WindowEvent::ApplicationTerminated(2)));
+        assert!(matches!(next(), WindowEvent::Closed(_)));
+        assert!(matches!(next(), WindowEvent::Closed(_)));
+        assert!(matches!(next(), WindowEvent::ApplicationTerminated(1)));
+    }
+}
@@ -1,33 +1,36 @@
-use std::{error::Error, fmt, io};
+use std::{error::Error, fmt, io, time::Instant};
 
+pub use application::Application;
+pub use backend::{ApplicationOps, EventSource, WindowBackend, WindowOps};
+pub use coalesce::Coalesce;
+pub(crate) use coalesce::Coalescer;
+pub use filter::{EventFilter, EventMask};
+pub use geometry::{
+    FrameKind, Insets, LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize, Position,
+    Rect, Size,
+};
+pub use properties::{PropertyMask, WindowProperties};
+pub use session::{available_backends, Backend, Session};
+pub use snapshot::{Change, Snapshot, WindowSnapshot};
+pub use transaction::Transaction;
 pub use window::Window;
 
 use crate::sys;
 
+mod application;
+mod backend;
+mod coalesce;
+mod filter;
+mod geometry;
+mod initial_state;
+mod lifecycle;
+mod properties;
+mod property_cache;
+mod session;
+mod snapshot;
+pub(crate) mod transaction;
 mod window;
 
-// TODO: differentiate physical and logical pixels
-
-/// A position with an x and y axis.
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub struct Position {
-    /// The x position.
-    pub x: f64,
-    /// The y position.
-    pub y: f64,
-}
-
-/// A size with width and height.
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub struct Size {
-    /// The width of the size.
-    pub width: f64,
-    /// The height of the size.
-    pub height: f64,
-}
-
-// TODO: consider writing the protocol as traits so that they can be used w/ third party crates
-
 // Keybinds:
 // https://github.com/Narsil/rdev
 // https://github.com/obv-mikhail/inputbot
@@ -74,9 +77,40 @@ pub struct Size {
 /// * If a window is destroyed, consider the handle disposed and remove it from the cache
 /// * If a window is created, check equality on all recorded handles, if there is a match, then the handle was reused and the old handle should be disposed
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
-pub struct WindowHandle(pub(crate) sys::WindowHandle);
+pub struct WindowHandle(pub(crate) HandleKind);
+
+impl WindowHandle {
+    /// Creates a handle for a window from a custom [`WindowBackend`].
+    ///
+    /// The id only needs to be unique within the backend, handles from a custom backend never equal
+    /// handles from a built-in backend.
+    #[inline]
+    pub fn custom(id: u64) -> WindowHandle {
+        WindowHandle(HandleKind::Custom(id))
+    }
+
+    pub(crate) fn from_sys(handle: sys::WindowHandle) -> WindowHandle {
+        WindowHandle(HandleKind::Sys(handle))
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub(crate) enum HandleKind {
+    Sys(sys::WindowHandle),
+    Custom(u64),
+}
 
-/// An event signifying a change in window properties.
+/// An event signifying a change in window or application properties.
+///
+/// On Windows and Linux, applications are only known through their windows. An application is
+/// launched when its first window opens and terminated when its last window closes, and it is
+/// never hidden or shown.
+///
+/// Moved, resized and renamed events carry the previous and new value. The [`Watcher`](crate::Watcher)
+/// remembers the title, position and size of every window, so that the previous value is known
+/// even if the backend doesn't report it. Values the backend doesn't report are read as soon as
+/// the event is received, rather than when it's handled, so they're exact unless the window
+/// changed again in between.
 #[derive(Debug)]
 pub enum WindowEvent {
     /// The window was first opened.
@@ -91,14 +125,108 @@ pub enum WindowEvent {
     Minimized(Window),
     /// The window was unminimized.
     Unminimized(Window),
+    /// The window was maximized.
+    ///
+    /// Only reported on X11, wlroots and Plasma, the other platforms don't notify when a window is
+    /// maximized.
+    Maximized(Window),
+    /// The window was unmaximized, see [`WindowEvent::Maximized`].
+    Unmaximized(Window),
+    /// The window entered fullscreen.
+    ///
+    /// Platforms that don't notify when a window enters fullscreen report it when the window is
+    /// resized into fullscreen.
+    Fullscreened(Window),
+    /// The window left fullscreen, see [`WindowEvent::Fullscreened`].
+    Unfullscreened(Window),
     /// The window was focused.
     Focused(Window),
+    /// The window lost focus.
+    ///
+    /// Platforms that only notify when a window is focused report the previously focused window
+    /// as unfocused first. A window that closes while focused isn't reported.
+    Unfocused(Window),
     /// The window was moved.
-    Moved(Window),
+    ///
+    /// A window that is moved and resized at once, such as with
+    /// [`Window::set_frame`](crate::Window::set_frame), is reported as moved and then resized
+    /// straight after, from the same frame.
+    Moved {
+        /// The window that was moved.
+        window: Window,
+        /// The previous position, or `None` if it wasn't known.
+        old: Option<LogicalPosition>,
+        /// The new position, or `None` if it couldn't be read, such as when the window closed
+        /// before the event was received.
+        new: Option<LogicalPosition>,
+    },
     /// The window was resized.
-    Resized(Window),
+    Resized {
+        /// The window that was resized.
+        window: Window,
+        /// The previous size, or `None` if it wasn't known.
+        old: Option<LogicalSize>,
+        /// The new size, or `None` if it couldn't be read.
+        new: Option<LogicalSize>,
+    },
+    /// The user started moving or resizing the window, such as by dragging its title bar or
+    /// border.
+    ///
+    /// Only reported on Windows.
+    MoveResizeStarted(Window),
+    /// The user stopped moving or resizing the window, see [`WindowEvent::MoveResizeStarted`].
+    MoveResizeEnded(Window),
     /// The window title was renamed.
-    Renamed(Window),
+    Renamed {
+        /// The window that was renamed.
+        window: Window,
+        /// The previous title, or `None` if it wasn't known.
+        old_title: Option<String>,
+        /// The new title, or `None` if it couldn't be read.
+        new_title: Option<String>,
+    },
+    /// The application was launched.
+    ApplicationLaunched(Application),
+    /// The application with this pid was terminated.
+    ApplicationTerminated(u32),
+    /// The application was brought to the front.
+    ApplicationActivated(Application),
+    /// The application, along with all of its windows, was hidden.
+    ApplicationHidden(Application),
+    /// The application was shown.
+    ApplicationShown(Application),
+}
+
+/// A [`WindowEvent`] along with when and where it was received, see [`Watcher::next_envelope`](crate::Watcher::next_envelope).
+#[derive(Debug)]
+pub struct EventEnvelope {
+    /// The event itself.
+    pub event: WindowEvent,
+    /// When the event happened, or when the [`Watcher`](crate::Watcher) received it if the
+    /// backend doesn't report that.
+    ///
+    /// On Windows, every event reports when it happened.
+    ///
+    /// On Linux (X11), only events that come from a property change report when they happened,
+    /// such as a window opening, being focused, renamed or fullscreened. Events from a window
+    /// being configured, mapped or destroyed don't, which is usually the case for
+    /// [`WindowEvent::Moved`], [`WindowEvent::Resized`], [`WindowEvent::Shown`] and
+    /// [`WindowEvent::Hidden`]. Servers on another machine are on another clock, so none of their
+    /// events do.
+    ///
+    /// On macOS and Linux (Wayland), as well as i3 (sway) and Hyprland, no events report when
+    /// they happened.
+    ///
+    /// Events received later may have happened earlier, so timestamps don't necessarily increase
+    /// along with [`EventEnvelope::sequence`]. Events derived by the watcher have the timestamp of
+    /// the event they're derived from, and windows reported by
+    /// [`Watcher::with_initial_state`](crate::Watcher::with_initial_state) are stamped when
+    /// they're received.
+    pub timestamp: Instant,
+    /// The number of events the watcher received before this one.
+    pub sequence: u64,
+    /// The backend that reported the event.
+    pub source: Backend,
 }
 
 // TODO: add context to errors
@@ -0,0 +1,209 @@
+use std::ops::{BitOr, BitOrAssign};
+
+use crate::protocol::{LogicalPosition, LogicalSize, WindowError, WindowOps};
+
+/// A set of window properties to fetch together, see [`Window::properties`](crate::Window::properties).
+///
+/// Properties are combined with `|`, for example `PropertyMask::TITLE | PropertyMask::FRAME`.
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PropertyMask(u8);
+
+impl PropertyMask {
+    /// The title of the window.
+    pub const TITLE: PropertyMask = PropertyMask(1 << 0);
+    /// The size of the window.
+    pub const SIZE: PropertyMask = PropertyMask(1 << 1);
+    /// The position of the window.
+    pub const POSITION: PropertyMask = PropertyMask(1 << 2);
+    /// Whether or not the window is focused.
+    pub const FOCUSED: PropertyMask = PropertyMask(1 << 3);
+    /// Whether or not the window is fullscreened.
+    pub const FULLSCREEN: PropertyMask = PropertyMask(1 << 4);
+    /// Whether or not the window is minimized.
+    pub const MINIMIZED: PropertyMask = PropertyMask(1 << 5);
+    /// Whether or not the window is hidden.
+    pub const HIDDEN: PropertyMask = PropertyMask(1 << 6);
+    /// The size and position of the window.
+    pub const FRAME: PropertyMask = PropertyMask(Self::SIZE.0 | Self::POSITION.0);
+    /// Every property.
+    pub const ALL: PropertyMask = PropertyMask((1 << 7) - 1);
+
+    /// A mask without any properties.
+    #[inline]
+    pub const fn empty() -> PropertyMask {
+        PropertyMask(0)
+    }
+
+    /// The raw bits of the mask, such as for sending it over the wire.
+    #[inline]
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Creates a mask from raw bits, ignoring any that don't correspond to a property.
+    #[inline]
+    pub const fn from_bits_truncate(bits: u8) -> PropertyMask {
+        PropertyMask(bits & Self::ALL.0)
+    }
+
+    /// Whether or not every property in `other` is in the mask.
+    #[inline]
+    pub const fn contains(self, other: PropertyMask) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Whether or not any property in `other` is in the mask.
+    #[inline]
+    pub const fn intersects(self, other: PropertyMask) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    // For backends that read properties on request. The Wayland backends cache every property as
+    // the compositor sends it, so they have nothing to skip.
+    #[cfg(any(
+        target_os = "macos",
+        target_os = "windows",
+        feature = "mock",
+        all(
+            target_os = "linux",
+            any(feature = "x11", feature = "i3", feature = "hyprland")
+        )
+    ))]
+    pub(crate) fn select<T>(self, property: PropertyMask, value: impl FnOnce() -> T) -> Option<T> {
+        self.contains(property).then(value)
+    }
+}
+
+impl BitOr for PropertyMask {
+    type Output = PropertyMask;
+
+    fn bitor(self, rhs: PropertyMask) -> PropertyMask {
+        PropertyMask(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for PropertyMask {
+    fn bitor_assign(&mut self, rhs: PropertyMask) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// The properties of a window, fetched together with [`Window::properties`](crate::Window::properties).
+///
+/// A property is `None` if it wasn't in the [`PropertyMask`], or if the window doesn't support it.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WindowProperties {
+    /// The title of the window.
+    pub title: Option<String>,
+    /// The logical size of the window.
+    pub size: Option<LogicalSize>,
+    /// The logical position of the window relative to the current display.
+    pub position: Option<LogicalPosition>,
+    /// Whether or not the window is focused.
+    pub is_focused: Option<bool>,
+    /// Whether or not the window is fullscreened.
+    pub is_fullscreen: Option<bool>,
+    /// Whether or not the window is minimized.
+    pub is_minimized: Option<bool>,
+    /// Whether or not the window is hidden.
+    pub is_hidden: Option<bool>,
+}
+
+impl WindowProperties {
+    /// Fetches each property in the mask with its own operation, for backends that can't fetch
+    /// them together.
+    pub(crate) fn fetch<W: WindowOps + ?Sized>(
+        window: &W,
+        mask: PropertyMask,
+    ) -> Result<WindowProperties, WindowError> {
+        Ok(WindowProperties {
+            title: fetch(mask, PropertyMask::TITLE, || window.title())?,
+            size: fetch(mask, PropertyMask::SIZE, || window.size())?,
+            position: fetch(mask, PropertyMask::POSITION, || window.position())?,
+            is_focused: fetch(mask, PropertyMask::FOCUSED, || window.is_focused())?,
+            is_fullscreen: fetch(mask, PropertyMask::FULLSCREEN, || window.is_fullscreen())?,
+            is_minimized: fetch(mask, PropertyMask::MINIMIZED, || window.is_minimized())?,
+            is_hidden: fetch(mask, PropertyMask::HIDDEN, || window.is_hidden())?,
+        })
+    }
+}
+
+// An unsupported property is left empty, any other error (e.g. the window closed) fails the whole
+// fetch.
+fn fetch<T>(
+    mask: PropertyMask,
+    property: PropertyMask,
+    get: impl FnOnce() -> Result<T, WindowError>,
+) -> Result<Option<T>, WindowError> {
+    if !mask.contains(property) {
+        return Ok(None);
+    }
+
+    match get() {
+        Ok(value) => Ok(Some(value)),
+        Err(WindowError::Unsupported) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Window, WindowHandle};
+
+    use super::*;
+
+    // Only knows its title and whether it's focused.
+    #[derive(Debug)]
+    struct TitledWindow;
+
+    impl WindowOps for TitledWindow {
+        fn handle(&self) -> WindowHandle {
+            WindowHandle::custom(0)
+        }
+
+        fn title(&self) -> Result<String, WindowError> {
+            Ok("notes".to_owned())
+        }
+
+        fn is_focused(&self) -> Result<bool, WindowError> {
+            Err(WindowError::InvalidHandle)
+        }
+    }
+
+    #[test]
+    fn unsupported_properties() {
+        let window = Window::new(TitledWindow);
+        let properties = window
+            .properties(PropertyMask::TITLE | PropertyMask::FRAME)
+            .unwrap();
+        assert_eq!(
+            properties,
+            WindowProperties {
+                title: Some("notes".to_owned()),
+                ..WindowProperties::default()
+            }
+        );
+
+        // Errors other than unsupported properties still fail the whole call.
+        assert!(matches!(
+            window.properties(PropertyMask::ALL),
+            Err(WindowError::InvalidHandle)
+        ));
+    }
+
+    #[test]
+    fn mask() {
+        let mut mask = PropertyMask::TITLE | PropertyMask::SIZE;
+        assert!(mask.contains(PropertyMask::TITLE));
+        assert!(!mask.contains(PropertyMask::FRAME));
+        assert!(mask.intersects(PropertyMask::FRAME));
+
+        mask |= PropertyMask::POSITION;
+        assert!(mask.contains(PropertyMask::FRAME));
+        assert!(PropertyMask::ALL.contains(mask));
+        assert_eq!(PropertyMask::from_bits_truncate(u8::MAX), PropertyMask::ALL);
+        assert_eq!(PropertyMask::from_bits_truncate(mask.bits()), mask);
+    }
+}
@@ -0,0 +1,507 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    mem,
+    time::{Duration, Instant},
+};
+
+use crate::protocol::{
+    EventMask, EventSource, PropertyMask, Window, WindowBackend, WindowError, WindowEvent,
+    WindowHandle, WindowProperties,
+};
+
+/// Fills in the previous and new values of moved, resized and renamed events that the backend
+/// didn't report, and derives the events that some backends don't report at all.
+///
+/// The properties that the requested events are compared on are cached for every window from when
+/// it opened, or from when watching began for windows that were already open, and updated with
+/// every event.
+#[derive(Debug)]
+pub(crate) struct PropertyCache {
+    inner: Box<dyn EventSource>,
+    mask: PropertyMask,
+    windows: HashMap<WindowHandle, WindowProperties>,
+    // The window to report as unfocused once another window is focused.
+    focused: Option<Window>,
+    // Events that are yet to be returned in order, along with when the event they're derived from
+    // happened.
+    queue: VecDeque<(WindowEvent, Option<Instant>)>,
+    // When the event that was returned last happened.
+    time: Option<Instant>,
+}
+
+impl PropertyCache {
+    // The windows that were already open are read by the session, see `PropertyCache::mask`.
+    // Windows that couldn't be read aren't cached, their events are filled in from the window alone.
+    // Wayland handles are proxies with interior mutability, but they hash by their id.
+    #[allow(clippy::mutable_key_type)]
+    pub fn new(
+        inner: Box<dyn EventSource>,
+        backend: &dyn WindowBackend,
+        events: EventMask,
+        windows: HashMap<WindowHandle, WindowProperties>,
+    ) -> PropertyCache {
+        let focused = if events.contains(EventMask::UNFOCUSED) {
+            backend.focused_window().ok().flatten()
+        } else {
+            None
+        };
+
+        PropertyCache {
+            inner,
+            mask: PropertyCache::mask(events),
+            windows,
+            focused,
+            queue: VecDeque::new(),
+            time: None,
+        }
+    }
+
+    // The properties that are compared to fill in or derive the events.
+    pub fn mask(events: EventMask) -> PropertyMask {
+        let mut mask = PropertyMask::empty();
+        if events.contains(EventMask::RENAMED) {
+            mask |= PropertyMask::TITLE;
+        }
+        if events.contains(EventMask::MOVED) {
+            mask |= PropertyMask::POSITION;
+        }
+        if events.contains(EventMask::RESIZED) {
+            mask |= PropertyMask::SIZE;
+        }
+        // Fullscreen changes are noticed when the window is resized.
+        if events.intersects(EventMask::FULLSCREENED | EventMask::UNFULLSCREENED) {
+            mask |= PropertyMask::SIZE | PropertyMask::FULLSCREEN;
+        }
+        mask
+    }
+
+    // The events the backend has to report for the cache to derive the requested events, along
+    // with closed events to forget windows.
+    pub fn required(events: EventMask) -> EventMask {
+        let mut required = events | EventMask::CLOSED;
+        if events.intersects(EventMask::FULLSCREENED | EventMask::UNFULLSCREENED) {
+            required |= EventMask::RESIZED;
+        }
+        if events.contains(EventMask::UNFOCUSED) {
+            required |= EventMask::FOCUSED;
+        }
+        required
+    }
+
+    // Queues the event along with the events derived from it, unless it was already derived or
+    // didn't change anything.
+    fn track(&mut self, mut event: WindowEvent) {
+        let mut unfocused = None;
+        let mut derived = Vec::new();
+        match &mut event {
+            // Windows that were already open are reported as opened with the initial state.
+            WindowEvent::Opened(window) if !self.windows.contains_key(&window.handle()) => {
+                let properties = window.properties(self.mask).unwrap_or_default();
+                self.windows.insert(window.handle(), properties);
+            }
+            WindowEvent::Closed(handle) => {
+                self.windows.remove(handle);
+                if self
+                    .focused
+                    .as_ref()
+                    .is_some_and(|focused| focused.handle() == *handle)
+                {
+                    self.focused = None;
+                }
+            }
+            WindowEvent::Focused(window) if self.focused.as_ref() != Some(window) => {
+                unfocused = self
+                    .focused
+                    .replace(window.clone())
+                    .map(WindowEvent::Unfocused);
+            }
+            WindowEvent::Unfocused(window) => match &self.focused {
+                // It was already reported when the other window was focused.
+                Some(focused) if focused != window => return,
+                _ => self.focused = None,
+            },
+            // Skipped if they were already derived from the window being resized.
+            WindowEvent::Fullscreened(window) if !self.fullscreen_changed(window, true) => return,
+            WindowEvent::Unfullscreened(window) if !self.fullscreen_changed(window, false) => {
+                return
+            }
+            // Windows reports every location change as both, without values.
+            WindowEvent::Moved { window, old, new } => {
+                let mut uncached = WindowProperties::default();
+                let cached = &mut self.cached(window, &mut uncached).position;
+                delta(cached, old, new, || window.position());
+                if old.is_some() && old == new {
+                    return;
+                }
+            }
+            WindowEvent::Resized { window, old, new } => {
+                let mut uncached = WindowProperties::default();
+                let properties = self.cached(window, &mut uncached);
+                delta(&mut properties.size, old, new, || window.size());
+                if old.is_some() && old == new {
+                    return;
+                }
+                if self.mask.contains(PropertyMask::FULLSCREEN) {
+                    derived.extend(fullscreen_toggled(
+                        window,
+                        self.cached(window, &mut uncached),
+                    ));
+                }
+            }
+            WindowEvent::Renamed {
+                window,
+                old_title,
+                new_title,
+            } => {
+                let mut uncached = WindowProperties::default();
+                let cached = &mut self.cached(window, &mut uncached).title;
+                delta(cached, old_title, new_title, || window.title());
+            }
+            _ => {}
+        }
+
+        let time = self.inner.event_time();
+        self.queue.extend(unfocused.map(|event| (event, time)));
+        self.queue.push_back((event, time));
+        self.queue
+            .extend(derived.into_iter().map(|event| (event, time)));
+    }
+
+    // Returns the next queued event, remembering when it happened.
+    fn pop(&mut self) -> Option<WindowEvent> {
+        let (event, time) = self.queue.pop_front()?;
+        self.time = time;
+        Some(event)
+    }
+
+    // Caches the fullscreen state of the window, returning whether or not it changed.
+    fn fullscreen_changed(&mut self, window: &Window, is_fullscreen: bool) -> bool {
+        let mut uncached = WindowProperties::default();
+        let cached = &mut self.cached(window, &mut uncached).is_fullscreen;
+        cached.replace(is_fullscreen) != Some(is_fullscreen)
+    }
+
+    // The cached properties of the window, or `uncached` if it isn't cached. Only opened windows
+    // are cached, so that events received after a window closed don't cache it again.
+    fn cached<'a>(
+        &'a mut self,
+        window: &Window,
+        uncached: &'a mut WindowProperties,
+    ) -> &'a mut WindowProperties {
+        self.windows.get_mut(&window.handle()).unwrap_or(uncached)
+    }
+}
+
+impl EventSource for PropertyCache {
+    fn next_request(&mut self) -> Result<WindowEvent, WindowError> {
+        while self.queue.is_empty() {
+            let event = self.inner.next_request()?;
+            self.track(event);
+        }
+
+        // The queue was just filled.
+        Ok(self.pop().unwrap())
+    }
+
+    fn next_request_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<WindowEvent>, WindowError> {
+        // Events that were already derived are skipped, so keep waiting until the deadline.
+        let deadline = Instant::now() + timeout;
+        while self.queue.is_empty() {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            match self.inner.next_request_timeout(timeout)? {
+                Some(event) => self.track(event),
+                None => return Ok(None),
+            }
+        }
+
+        Ok(self.pop())
+    }
+
+    fn event_time(&self) -> Option<Instant> {
+        self.time
+    }
+}
+
+// Entering or leaving fullscreen resizes the window, which is how it's noticed on platforms that
+// don't report it.
+fn fullscreen_toggled(window: &Window, properties: &mut WindowProperties) -> Option<WindowEvent> {
+    let is_fullscreen = window.is_fullscreen().ok()?;
+    match properties.is_fullscreen.replace(is_fullscreen)? {
+        false if is_fullscreen => Some(WindowEvent::Fullscreened(window.clone())),
+        true if !is_fullscreen => Some(WindowEvent::Unfullscreened(window.clone())),
+        _ => None,
+    }
+}
+
+// Reads the new value if the backend didn't report it, then replaces the cached value with it.
+fn delta<T: Clone>(
+    cached: &mut Option<T>,
+    old: &mut Option<T>,
+    new: &mut Option<T>,
+    read: impl FnOnce() -> Result<T, WindowError>,
+) {
+    if new.is_none() {
+        *new = read().ok();
+    }
+
+    let previous = mem::replace(cached, new.clone());
+    if old.is_none() {
+        *old = previous;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::{Backend, LogicalPosition, LogicalSize, WindowOps};
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct FakeBackend;
+
+    #[derive(Debug)]
+    struct FakeWindow(u64);
+
+    // A window whose size and fullscreen state can be changed without an event.
+    #[derive(Debug)]
+    struct ResizableWindow(Arc<Mutex<(LogicalSize, bool)>>);
+
+    #[derive(Debug)]
+    struct FakeEvents(Vec<WindowEvent>);
+
+    impl WindowBackend for FakeBackend {
+        fn kind(&self) -> Backend {
+            Backend::Custom("fake")
+        }
+
+        fn watch(&self) -> Result<Box<dyn EventSource>, WindowError> {
+            Err(WindowError::Unsupported)
+        }
+
+        // The first window is already open.
+        fn iter_windows(&self) -> Box<dyn Iterator<Item = Result<Window, WindowError>>> {
+            Box::new([Ok(Window::new(FakeWindow(0)))].into_iter())
+        }
+
+        fn focused_window(&self) -> Result<Option<Window>, WindowError> {
+            Ok(Some(window(0)))
+        }
+    }
+
+    // Every window is at the same position and titled by its id, except for window 2 which
+    // already closed.
+    impl WindowOps for FakeWindow {
+        fn handle(&self) -> WindowHandle {
+            WindowHandle::custom(self.0)
+        }
+
+        fn title(&self) -> Result<String, WindowError> {
+            match self.0 {
+                2 => Err(WindowError::InvalidHandle),
+                id => Ok(id.to_string()),
+            }
+        }
+
+        fn position(&self) -> Result<LogicalPosition, WindowError> {
+            Ok(POSITION)
+        }
+    }
+
+    impl WindowOps for ResizableWindow {
+        fn handle(&self) -> WindowHandle {
+            WindowHandle::custom(10)
+        }
+
+        fn size(&self) -> Result<LogicalSize, WindowError> {
+            Ok(self.0.lock().unwrap().0)
+        }
+
+        fn position(&self) -> Result<LogicalPosition, WindowError> {
+            Ok(POSITION)
+        }
+
+        fn is_fullscreen(&self) -> Result<bool, WindowError> {
+            Ok(self.0.lock().unwrap().1)
+        }
+    }
+
+    impl EventSource for FakeEvents {
+        fn next_request(&mut self) -> Result<WindowEvent, WindowError> {
+            if self.0.is_empty() {
+                return Err(WindowError::InvalidHandle);
+            }
+            Ok(self.0.remove(0))
+        }
+    }
+
+    const POSITION: LogicalPosition = LogicalPosition { x: 10.0, y: 20.0 };
+    const SIZE: LogicalSize = LogicalSize {
+        width: 800.0,
+        height: 600.0,
+    };
+    const SCREEN: LogicalSize = LogicalSize {
+        width: 1920.0,
+        height: 1080.0,
+    };
+
+    fn window(id: u64) -> Window {
+        Window::new(FakeWindow(id))
+    }
+
+    // Caches every property of the windows that were already open, as for a watcher of every event.
+    #[allow(clippy::mutable_key_type)]
+    fn cache(events: FakeEvents) -> PropertyCache {
+        let windows = FakeBackend
+            .iter_windows_with(PropertyCache::mask(EventMask::ALL))
+            .flatten()
+            .map(|(window, properties)| (window.handle(), properties))
+            .collect();
+        PropertyCache::new(Box::new(events), &FakeBackend, EventMask::ALL, windows)
+    }
+
+    fn renamed(id: u64, new_title: Option<&str>) -> WindowEvent {
+        WindowEvent::Renamed {
+            window: window(id),
+            old_title: None,
+            new_title: new_title.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    fn deltas() {
+        let events = FakeEvents(vec![
+            // Reported by a backend that knows the new title.
+            renamed(0, Some("editor")),
+            // Read from the window instead.
+            renamed(0, None),
+            // Reported by a backend that doesn't know whether the window moved, which it didn't.
+            WindowEvent::Moved {
+                window: window(0),
+                old: None,
+                new: None,
+            },
+            WindowEvent::Moved {
+                window: window(0),
+                old: None,
+                new: Some(LogicalPosition { x: 0.0, y: 0.0 }),
+            },
+            // Closed before the event was received.
+            WindowEvent::Opened(window(2)),
+            renamed(2, None),
+        ]);
+        let mut cache = cache(events);
+
+        let mut next = || cache.next_request().unwrap();
+        assert!(matches!(
+            next(),
+            WindowEvent::Renamed { old_title, new_title, .. }
+                if old_title.as_deref() == Some("0") && new_title.as_deref() == Some("editor")
+        ));
+        assert!(matches!(
+            next(),
+            WindowEvent::Renamed { old_title, new_title, .. }
+                if old_title.as_deref() == Some("editor") && new_title.as_deref() == Some("0")
+        ));
+        assert!(matches!(
+            next(),
+            WindowEvent::Moved { old: Some(old), new: Some(new), .. } if old == POSITION && new.x == 0.0
+        ));
+        assert!(matches!(next(), WindowEvent::Opened(_)));
+        assert!(matches!(
+            next(),
+            WindowEvent::Renamed {
+                old_title: None,
+                new_title: None,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn derived_events() {
+        let geometry = Arc::new(Mutex::new((SIZE, false)));
+        let resizable = Window::new(ResizableWindow(geometry.clone()));
+        let events = FakeEvents(vec![
+            WindowEvent::Opened(resizable.clone()),
+            WindowEvent::Focused(window(1)),
+            // Already derived from the focused event.
+            WindowEvent::Unfocused(window(0)),
+            WindowEvent::Resized {
+                window: resizable.clone(),
+                old: None,
+                new: None,
+            },
+            // Already derived from the resized event.
+            WindowEvent::Fullscreened(resizable.clone()),
+            // Windows reports every location change as moved and resized, without values.
+            WindowEvent::Moved {
+                window: resizable.clone(),
+                old: None,
+                new: None,
+            },
+            WindowEvent::Resized {
+                window: resizable.clone(),
+                old: None,
+                new: None,
+            },
+            // Already derived from the resized event.
+            WindowEvent::Unfullscreened(resizable.clone()),
+            WindowEvent::Closed(WindowHandle::custom(1)),
+            WindowEvent::Focused(window(0)),
+            WindowEvent::Closed(resizable.handle()),
+            // Received after the window closed.
+            WindowEvent::Resized {
+                window: resizable.clone(),
+                old: None,
+                new: Some(SCREEN),
+            },
+        ]);
+        let mut cache = cache(events);
+
+        assert!(matches!(cache.next_request(), Ok(WindowEvent::Opened(_))));
+        assert!(
+            matches!(cache.next_request(), Ok(WindowEvent::Unfocused(unfocused)) if unfocused == window(0))
+        );
+        assert!(
+            matches!(cache.next_request(), Ok(WindowEvent::Focused(focused)) if focused == window(1))
+        );
+
+        *geometry.lock().unwrap() = (SCREEN, true);
+        assert!(matches!(
+            cache.next_request(),
+            Ok(WindowEvent::Resized { old: Some(old), new: Some(new), .. }) if old == SIZE && new == SCREEN
+        ));
+        assert!(matches!(
+            cache.next_request(),
+            Ok(WindowEvent::Fullscreened(_))
+        ));
+
+        // The window didn't move, so only the resize is reported.
+        *geometry.lock().unwrap() = (SIZE, false);
+        assert!(matches!(
+            cache.next_request(),
+            Ok(WindowEvent::Resized { old: Some(old), new: Some(new), .. }) if old == SCREEN && new == SIZE
+        ));
+        assert!(matches!(
+            cache.next_request(),
+            Ok(WindowEvent::Unfullscreened(_))
+        ));
+
+        // The focused window closed, so there's nothing to unfocus.
+        assert!(matches!(cache.next_request(), Ok(WindowEvent::Closed(_))));
+        assert!(matches!(cache.next_request(), Ok(WindowEvent::Focused(_))));
+
+        // Closed windows are forgotten and not cached again.
+        assert!(matches!(cache.next_request(), Ok(WindowEvent::Closed(_))));
+        assert!(matches!(
+            cache.next_request(),
+            Ok(WindowEvent::Resized { old: None, .. })
+        ));
+        assert!(!cache.windows.contains_key(&resizable.handle()));
+    }
+}
@@ -0,0 +1,575 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, RwLock},
+};
+
+use crate::{
+    protocol::{
+        backend::{Builtin, WindowBackend},
+        filter::{Filtered, Matcher},
+        initial_state::InitialState,
+        property_cache::PropertyCache,
+    },
+    sys, Application, EventFilter, EventMask, PropertyMask, Snapshot, Watcher, WatcherBuilder,
+    Window, WindowError, WindowHandle, WindowProperties,
+};
+
+/// An underlying API used to access windows.
+///
+/// Only the backends for the current platform whose feature is enabled are available, see
+/// [`available_backends`](crate::available_backends).
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Backend {
+    /// The accessibility API on macOS.
+    MacOS,
+    /// The Win32 API on Windows.
+    Windows,
+    /// An [EWMH](https://specifications.freedesktop.org/wm-spec/latest/)-compliant X11 window manager.
+    ///
+    /// Requires the `x11` feature, which is enabled by default.
+    X11,
+    /// A Wayland compositor implementing the [wlr-foreign-toplevel-management](https://wayland.app/protocols/wlr-foreign-toplevel-management-unstable-v1)
+    /// protocol.
+    ///
+    /// Requires the `wlr` feature.
+    Wlr,
+    /// A Wayland compositor implementing the read-only [ext-foreign-toplevel-list](https://wayland.app/protocols/ext-foreign-toplevel-list-v1)
+    /// protocol.
+    ///
+    /// Requires the `ext-toplevel` feature.
+    ExtToplevel,
+    /// The IPC socket of the [i3](https://i3wm.org/docs/ipc.html) or sway window managers.
+    ///
+    /// Requires the `i3` feature.
+    I3,
+    /// The IPC sockets of the [Hyprland](https://wiki.hyprland.org/IPC/) compositor.
+    ///
+    /// Requires the `hyprland` feature.
+    Hyprland,
+    /// KWin, the Wayland compositor of KDE Plasma, through the [plasma-window-management](https://wayland.app/protocols/kde-plasma-window-management)
+    /// protocol.
+    ///
+    /// Requires the `plasma` feature.
+    Plasma,
+    /// A [`WindowBackend`] implemented outside of fowin, identified by its name.
+    Custom(&'static str),
+}
+
+static GLOBAL: RwLock<Option<Session>> = RwLock::new(None);
+
+// Tests run in parallel, so they route the free functions to a session of their own thread rather
+// than replacing the global one.
+#[cfg(test)]
+thread_local! {
+    static INJECTED: std::cell::RefCell<Option<Session>> = const { std::cell::RefCell::new(None) };
+}
+
+/// A connection to a [`Backend`].
+///
+/// The free functions, such as [`iter_windows`](crate::iter_windows), use a session that is
+/// detected on first use. Create a session explicitly to choose the backend yourself.
+#[derive(Debug, Clone)]
+pub struct Session {
+    backend: Arc<dyn WindowBackend>,
+}
+
+impl Session {
+    /// Connects to the first backend that matches the current desktop session.
+    ///
+    /// On Linux, the session is detected from the `WAYLAND_DISPLAY`, `DISPLAY`, and
+    /// `XDG_SESSION_TYPE` environment variables. Wayland backends are preferred over X11, since
+    /// under XWayland, X11 can only see windows from other X11 clients.
+    pub fn new() -> Result<Session, WindowError> {
+        let mut error = WindowError::Unsupported;
+        for backend in sys::detect() {
+            match Session::with_backend(backend) {
+                Ok(session) => return Ok(session),
+                Err(err) => error = err,
+            }
+        }
+
+        Err(error)
+    }
+
+    /// Connects to the specified backend.
+    ///
+    /// Returns [`WindowError::Unsupported`] if the backend isn't available on this platform or
+    /// its feature isn't enabled.
+    pub fn with_backend(backend: Backend) -> Result<Session, WindowError> {
+        sys::connect(backend)?;
+        Ok(Session::from_backend(Builtin(backend)))
+    }
+
+    /// Uses a custom backend, such as one provided by a third-party crate.
+    ///
+    /// Call [`Session::set_global`] to use it from the free functions as well.
+    #[inline]
+    pub fn from_backend(backend: impl WindowBackend + 'static) -> Session {
+        Session {
+            backend: Arc::new(backend),
+        }
+    }
+
+    /// The backend this session is connected to.
+    #[inline]
+    pub fn backend(&self) -> Backend {
+        self.backend.kind()
+    }
+
+    /// Watches for all window events from this backend.
+    ///
+    /// Read [`Watcher::new`](Watcher::new) for more information.
+    #[inline]
+    pub fn watch(&self) -> Result<Watcher, WindowError> {
+        self.watcher().build()
+    }
+
+    /// Watches for all window events from this backend, starting with the windows that are
+    /// already open.
+    ///
+    /// Read [`Watcher::with_initial_state`](Watcher::with_initial_state) for more information.
+    #[inline]
+    pub fn watch_with_initial_state(&self) -> Result<Watcher, WindowError> {
+        self.watcher().initial_state().build()
+    }
+
+    /// Configures a watcher for only some of the events from this backend.
+    ///
+    /// Read [`Watcher::builder`](Watcher::builder) for more information.
+    #[inline]
+    pub fn watcher(&self) -> WatcherBuilder {
+        WatcherBuilder::new(Some(self.clone()))
+    }
+
+    pub(crate) fn watch_filtered(
+        &self,
+        filter: EventFilter,
+        initial_state: bool,
+    ) -> Result<Watcher, WindowError> {
+        let backend = self.backend.as_ref();
+        let mut required = filter.clone();
+        required.events = PropertyCache::required(filter.events());
+        // Windows are checked as they open, and their title again once they're renamed.
+        if filter.reads_windows() {
+            required.events |= EventMask::OPENED;
+        }
+        if filter.title.is_some() {
+            required.events |= EventMask::RENAMED;
+        }
+
+        let events = filter.events();
+        let mut matcher = Matcher::new(filter);
+        let mut inner = backend.watch_filtered(&required)?;
+        let (pending, properties) = open_windows(backend, &mut matcher, events, initial_state)?;
+        if initial_state {
+            inner = Box::new(InitialState::new(inner, pending));
+        }
+        // Outside of the initial state, so that the windows it reports are cached as they open.
+        let inner = Box::new(PropertyCache::new(inner, backend, events, properties));
+        // Outside of the cache, so that the events it derives are filtered too.
+        let inner = Filtered::new(inner, matcher);
+        Ok(Watcher::from_source(Box::new(inner), self.backend()))
+    }
+
+    /// Returns an iterator over all existing windows from this backend.
+    #[inline]
+    pub fn iter_windows(&self) -> impl Iterator<Item = Result<Window, WindowError>> {
+        self.backend.iter_windows()
+    }
+
+    /// Returns an iterator over all existing windows from this backend, along with the properties
+    /// in the mask.
+    ///
+    /// Read [`Window::properties`] for more information.
+    #[inline]
+    pub fn iter_windows_with(
+        &self,
+        mask: PropertyMask,
+    ) -> impl Iterator<Item = Result<(Window, WindowProperties), WindowError>> {
+        self.backend.iter_windows_with(mask)
+    }
+
+    /// Returns the globally focused window from this backend if one exists.
+    #[inline]
+    pub fn focused_window(&self) -> Result<Option<Window>, WindowError> {
+        self.backend.focused_window()
+    }
+
+    /// Returns the handles of every window from this backend, from the front to the back.
+    ///
+    /// On Windows and Linux (X11), this is the stacking order reported by the window manager.
+    ///
+    /// On macOS and Linux (Wayland), the stacking order is unsupported.
+    #[inline]
+    pub fn stacking_order(&self) -> Result<Vec<WindowHandle>, WindowError> {
+        self.backend.stacking_order()
+    }
+
+    /// Captures the state of every window from this backend.
+    ///
+    /// Read [`Snapshot::capture`] for more information.
+    #[inline]
+    pub fn snapshot(&self) -> Result<Snapshot, WindowError> {
+        Snapshot::capture_from(self)
+    }
+
+    /// Returns an iterator over all running applications from this backend.
+    #[inline]
+    pub fn iter_applications(&self) -> impl Iterator<Item = Result<Application, WindowError>> {
+        self.backend.iter_applications()
+    }
+
+    /// Replaces the session used by the free functions, such as [`iter_windows`](crate::iter_windows)
+    /// and [`Watcher::new`](Watcher::new).
+    ///
+    /// Existing watchers keep receiving events from the session that created them.
+    pub fn set_global(session: Session) {
+        *GLOBAL.write().unwrap() = Some(session);
+    }
+
+    // The session used by the free functions.
+    pub(crate) fn global() -> Result<Session, WindowError> {
+        #[cfg(test)]
+        if let Some(session) = INJECTED.with(|injected| injected.borrow().clone()) {
+            return Ok(session);
+        }
+
+        if let Some(session) = &*GLOBAL.read().unwrap() {
+            return Ok(session.clone());
+        }
+
+        let mut global = GLOBAL.write().unwrap();
+        match &*global {
+            // Another thread may have detected it in the meantime.
+            Some(session) => Ok(session.clone()),
+            None => {
+                // If detection fails, try again next time in case the display server was started since.
+                let session = Session::new()?;
+                *global = Some(session.clone());
+                Ok(session)
+            }
+        }
+    }
+}
+
+// Lists the windows that are already open once for every layer of the watcher. Windows that can't
+// pass the filter are left out, and only the properties the cache compares are read.
+#[allow(clippy::mutable_key_type)]
+fn open_windows(
+    backend: &dyn WindowBackend,
+    matcher: &mut Matcher,
+    events: EventMask,
+    initial_state: bool,
+) -> Result<(VecDeque<Window>, HashMap<WindowHandle, WindowProperties>), WindowError> {
+    let mask = PropertyCache::mask(events);
+    let mut windows = VecDeque::new();
+    let mut properties = HashMap::new();
+    if !initial_state && mask == PropertyMask::empty() && !matcher.reads_windows() {
+        return Ok((windows, properties));
+    }
+
+    // The properties of every window are read together, unless only some windows are needed.
+    let listed: Vec<_> = if matcher.by_window() {
+        backend
+            .iter_windows()
+            .filter_map(|window| match window {
+                Ok(window) if !matcher.seed(&window) => None,
+                Ok(window) => Some(window.properties(mask).map(|read| (window, read))),
+                Err(err) => Some(Err(err)),
+            })
+            .collect()
+    } else {
+        backend.iter_windows_with(mask).collect()
+    };
+
+    for result in listed {
+        match result {
+            Ok((window, read)) => {
+                properties.insert(window.handle(), read);
+                windows.push_back(window);
+            }
+            // The window closed while it was being read.
+            Err(WindowError::InvalidHandle) => {}
+            // Missing windows would never be reported as opened.
+            Err(err) if initial_state => return Err(err),
+            Err(_) => {}
+        }
+    }
+    Ok((windows, properties))
+}
+
+/// Returns the backends that are compiled in and can currently be connected to.
+pub fn available_backends() -> Vec<Backend> {
+    sys::BACKENDS
+        .iter()
+        .copied()
+        .filter(|&backend| sys::connect(backend).is_ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        iter,
+        sync::Mutex,
+        time::{Duration, Instant},
+    };
+
+    use crate::{EventSource, LogicalSize, WindowEvent, WindowHandle, WindowOps};
+
+    use super::*;
+
+    // A backend that can never be compiled in on the current platform.
+    #[cfg(target_os = "macos")]
+    const FOREIGN: Backend = Backend::Windows;
+    #[cfg(not(target_os = "macos"))]
+    const FOREIGN: Backend = Backend::MacOS;
+
+    #[test]
+    fn foreign_backend_unsupported() {
+        assert!(matches!(
+            Session::with_backend(FOREIGN),
+            Err(WindowError::Unsupported)
+        ));
+        assert!(!available_backends().contains(&FOREIGN));
+    }
+
+    #[derive(Debug)]
+    struct FakeBackend;
+
+    #[derive(Debug)]
+    struct FakeWindow(u64);
+
+    #[derive(Debug)]
+    struct FakeEvents(Vec<WindowEvent>);
+
+    impl WindowBackend for FakeBackend {
+        fn kind(&self) -> Backend {
+            Backend::Custom("fake")
+        }
+
+        fn watch(&self) -> Result<Box<dyn EventSource>, WindowError> {
+            Ok(Box::new(FakeEvents(vec![WindowEvent::Closed(
+                WindowHandle::custom(1),
+            )])))
+        }
+
+        fn iter_windows(&self) -> Box<dyn Iterator<Item = Result<Window, WindowError>>> {
+            Box::new((1..=2).map(|id| Ok(Window::new(FakeWindow(id)))))
+        }
+
+        fn focused_window(&self) -> Result<Option<Window>, WindowError> {
+            Ok(Some(Window::new(FakeWindow(2))))
+        }
+    }
+
+    impl WindowOps for FakeWindow {
+        fn handle(&self) -> WindowHandle {
+            WindowHandle::custom(self.0)
+        }
+
+        fn title(&self) -> Result<String, WindowError> {
+            Ok(format!("window {}", self.0))
+        }
+    }
+
+    impl EventSource for FakeEvents {
+        fn next_request(&mut self) -> Result<WindowEvent, WindowError> {
+            self.0.pop().ok_or(WindowError::InvalidHandle)
+        }
+    }
+
+    #[test]
+    fn custom_backend() {
+        let session = Session::from_backend(FakeBackend);
+        assert_eq!(session.backend(), Backend::Custom("fake"));
+
+        let titles = session
+            .iter_windows()
+            .map(|window| window.unwrap().title().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(titles, ["window 1", "window 2"]);
+
+        let focused = session.focused_window().unwrap().unwrap();
+        assert_eq!(focused, Window::new(FakeWindow(2)));
+        // Operations the backend didn't implement fall back to being unsupported.
+        assert!(matches!(
+            focused.resize(LogicalSize {
+                width: 1.0,
+                height: 1.0
+            }),
+            Err(WindowError::Unsupported)
+        ));
+
+        let mut watcher = session.watch().unwrap();
+        assert!(matches!(
+            watcher.next_request(),
+            Ok(WindowEvent::Closed(handle)) if handle == WindowHandle::custom(1)
+        ));
+    }
+
+    #[derive(Debug, Clone, Default)]
+    struct ReadsBackend(Arc<Mutex<Reads>>);
+
+    // How many times the windows were listed, and every window whose properties were read.
+    #[derive(Debug, Default)]
+    struct Reads {
+        listed: usize,
+        properties: Vec<(u64, PropertyMask)>,
+    }
+
+    #[derive(Debug)]
+    struct ReadWindow(u64, ReadsBackend);
+
+    impl WindowBackend for ReadsBackend {
+        fn kind(&self) -> Backend {
+            Backend::Custom("reads")
+        }
+
+        fn watch(&self) -> Result<Box<dyn EventSource>, WindowError> {
+            Ok(Box::new(FakeEvents(Vec::new())))
+        }
+
+        fn iter_windows(&self) -> Box<dyn Iterator<Item = Result<Window, WindowError>>> {
+            self.0.lock().unwrap().listed += 1;
+            let backend = self.clone();
+            Box::new((1..=2).map(move |id| Ok(Window::new(ReadWindow(id, backend.clone())))))
+        }
+
+        fn focused_window(&self) -> Result<Option<Window>, WindowError> {
+            Ok(None)
+        }
+    }
+
+    impl WindowOps for ReadWindow {
+        fn handle(&self) -> WindowHandle {
+            WindowHandle::custom(self.0)
+        }
+
+        fn properties(&self, mask: PropertyMask) -> Result<WindowProperties, WindowError> {
+            (self.1).0.lock().unwrap().properties.push((self.0, mask));
+            Ok(WindowProperties::default())
+        }
+    }
+
+    #[test]
+    fn open_windows_read_once() {
+        let backend = ReadsBackend::default();
+        let reads = backend.0.clone();
+        let session = Session::from_backend(backend);
+
+        // Nothing is compared to fill in focus events.
+        session
+            .watcher()
+            .events(EventMask::FOCUSED)
+            .build()
+            .unwrap();
+        assert_eq!(reads.lock().unwrap().listed, 0);
+
+        let mut watcher = session
+            .watcher()
+            .window(WindowHandle::custom(1))
+            .events(EventMask::OPENED | EventMask::MOVED)
+            .initial_state()
+            .build()
+            .unwrap();
+        assert!(matches!(
+            watcher.next_request(),
+            Ok(WindowEvent::Opened(window)) if window.handle() == WindowHandle::custom(1)
+        ));
+        assert!(watcher.next_request().is_err());
+        let reads = reads.lock().unwrap();
+        assert_eq!(reads.listed, 1);
+        // Only the filtered window is read, and not again once it's reported as opened.
+        assert_eq!(reads.properties, [(1, PropertyMask::POSITION)]);
+    }
+
+    // Reports renames in the reverse of the order they happened in.
+    #[derive(Debug)]
+    struct TimedBackend;
+
+    #[derive(Debug)]
+    struct TimedEvents {
+        events: VecDeque<(WindowEvent, Instant)>,
+        time: Option<Instant>,
+    }
+
+    impl WindowBackend for TimedBackend {
+        fn kind(&self) -> Backend {
+            Backend::Custom("timed")
+        }
+
+        fn watch(&self) -> Result<Box<dyn EventSource>, WindowError> {
+            let now = Instant::now();
+            let renamed = |id| WindowEvent::Renamed {
+                window: Window::new(FakeWindow(id)),
+                old_title: None,
+                new_title: Some(format!("window {id}")),
+            };
+            Ok(Box::new(TimedEvents {
+                events: VecDeque::from([
+                    (renamed(2), now - Duration::from_millis(5)),
+                    (renamed(1), now - Duration::from_millis(10)),
+                ]),
+                time: None,
+            }))
+        }
+
+        fn iter_windows(&self) -> Box<dyn Iterator<Item = Result<Window, WindowError>>> {
+            Box::new(iter::empty())
+        }
+
+        fn focused_window(&self) -> Result<Option<Window>, WindowError> {
+            Ok(None)
+        }
+    }
+
+    impl EventSource for TimedEvents {
+        fn next_request(&mut self) -> Result<WindowEvent, WindowError> {
+            self.next_request_timeout(Duration::ZERO)?
+                .ok_or(WindowError::InvalidHandle)
+        }
+
+        fn next_request_timeout(
+            &mut self,
+            _timeout: Duration,
+        ) -> Result<Option<WindowEvent>, WindowError> {
+            Ok(self.events.pop_front().map(|(event, time)| {
+                self.time = Some(time);
+                event
+            }))
+        }
+
+        fn event_time(&self) -> Option<Instant> {
+            self.time
+        }
+    }
+
+    #[test]
+    fn batches_ordered_by_event_time() {
+        let mut watcher = Session::from_backend(TimedBackend).watch().unwrap();
+        let batch = watcher.next_batch(Duration::from_millis(50)).unwrap();
+
+        let windows = batch
+            .iter()
+            .map(|envelope| match &envelope.event {
+                WindowEvent::Renamed { window, .. } => window.handle(),
+                event => panic!("unexpected event {event:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(windows, [WindowHandle::custom(1), WindowHandle::custom(2)]);
+        assert_eq!([batch[0].sequence, batch[1].sequence], [1, 0]);
+        assert!(batch[0].timestamp < batch[1].timestamp);
+    }
+
+    #[test]
+    fn custom_global_session() {
+        INJECTED.with(|injected| *injected.borrow_mut() = Some(Session::from_backend(FakeBackend)));
+        assert_eq!(crate::iter_windows().count(), 2);
+        assert_eq!(
+            crate::focused_window().unwrap().unwrap().handle(),
+            WindowHandle::custom(2)
+        );
+    }
+}
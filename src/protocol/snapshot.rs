@@ -0,0 +1,326 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
+
+use crate::protocol::{
+    HandleKind, LogicalPosition, LogicalSize, PropertyMask, Session, Window, WindowError,
+    WindowHandle, WindowProperties,
+};
+
+/// The state of every window on the desktop at one point in time.
+///
+/// Compare two snapshots with [`Snapshot::diff`] to find out what changed in between, such as
+/// when polling instead of watching for events.
+///
+/// ```no_run
+/// # use fowin::Snapshot;
+/// let before = Snapshot::capture()?;
+/// // ...
+/// let after = Snapshot::capture()?;
+/// for change in before.diff(&after) {
+///     println!("{change:?}");
+/// }
+/// # Ok::<(), fowin::WindowError>(())
+/// ```
+///
+/// With the `serde` feature, snapshots can be serialized, except for the live
+/// [`WindowSnapshot::window`].
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Snapshot {
+    /// Every window, in the order they were returned by [`iter_windows`](crate::iter_windows).
+    pub windows: Vec<WindowSnapshot>,
+    /// The id of the focused window, if any.
+    pub focused: Option<u64>,
+}
+
+/// The state of a single window in a [`Snapshot`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WindowSnapshot {
+    /// Identifies the window across snapshots taken from the same session.
+    ///
+    /// The id is derived from the [`WindowHandle`], so it may be reused once the window closes
+    /// the same way handles are.
+    pub id: u64,
+    /// The window itself, or `None` if the snapshot was deserialized.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub window: Option<Window>,
+    /// The pid of the application that owns the window, if known.
+    pub pid: Option<u32>,
+    /// The name of the application that owns the window, if known.
+    pub application: Option<String>,
+    /// Every property of the window that the backend supports.
+    pub properties: WindowProperties,
+    /// The position of the window in the stacking order, where `0` is the frontmost window.
+    ///
+    /// This is `None` if the backend doesn't report the stacking order, see
+    /// [`Session::stacking_order`].
+    pub z_index: Option<usize>,
+}
+
+/// A difference between two snapshots, see [`Snapshot::diff`].
+///
+/// Windows are identified by [`WindowSnapshot::id`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Change {
+    /// The window was opened.
+    Opened(u64),
+    /// The window was closed.
+    Closed(u64),
+    /// The window was moved.
+    Moved {
+        /// The id of the window.
+        id: u64,
+        /// The previous position.
+        from: LogicalPosition,
+        /// The new position.
+        to: LogicalPosition,
+    },
+    /// The window was resized.
+    Resized {
+        /// The id of the window.
+        id: u64,
+        /// The previous size.
+        from: LogicalSize,
+        /// The new size.
+        to: LogicalSize,
+    },
+    /// The window title was renamed.
+    Renamed {
+        /// The id of the window.
+        id: u64,
+        /// The previous title.
+        from: String,
+        /// The new title.
+        to: String,
+    },
+    /// The window was focused, fullscreened, minimized or hidden, or stopped being so.
+    StateChanged {
+        /// The id of the window.
+        id: u64,
+        /// The state that changed, one of [`PropertyMask::FOCUSED`], [`PropertyMask::FULLSCREEN`],
+        /// [`PropertyMask::MINIMIZED`] or [`PropertyMask::HIDDEN`].
+        state: PropertyMask,
+        /// Whether or not the window is now in that state.
+        value: bool,
+    },
+}
+
+impl Snapshot {
+    /// Captures the state of every window.
+    ///
+    /// Windows are captured from the backend detected by [`Session::new`], to choose a different
+    /// backend, call [`Session::snapshot`].
+    #[inline]
+    pub fn capture() -> Result<Snapshot, WindowError> {
+        Session::global()?.snapshot()
+    }
+
+    pub(crate) fn capture_from(session: &Session) -> Result<Snapshot, WindowError> {
+        let stacking: HashMap<u64, usize> = match session.stacking_order() {
+            Ok(handles) => handles.iter().map(id).zip(0..).collect(),
+            Err(WindowError::Unsupported) => HashMap::new(),
+            Err(err) => return Err(err),
+        };
+
+        let mut names = HashMap::new();
+        let mut windows = Vec::new();
+        for result in session.iter_windows_with(PropertyMask::ALL) {
+            let (window, properties) = match result {
+                Ok(result) => result,
+                // The window closed while the snapshot was being captured.
+                Err(WindowError::InvalidHandle) => continue,
+                Err(err) => return Err(err),
+            };
+
+            // The application is only informational, so it's left empty rather than failing.
+            let application = window.application().ok();
+            let pid = application.as_ref().map(|application| application.pid());
+            let name = application.and_then(|application| {
+                names
+                    .entry(application.pid())
+                    .or_insert_with(|| application.name().ok())
+                    .clone()
+            });
+
+            let id = id(&window.handle());
+            windows.push(WindowSnapshot {
+                id,
+                pid,
+                application: name,
+                properties,
+                z_index: stacking.get(&id).copied(),
+                window: Some(window),
+            });
+        }
+
+        let focused = match session.focused_window() {
+            Ok(window) => window.map(|window| id(&window.handle())),
+            Err(WindowError::Unsupported) => windows
+                .iter()
+                .find(|window| window.properties.is_focused == Some(true))
+                .map(|window| window.id),
+            Err(err) => return Err(err),
+        };
+
+        Ok(Snapshot { windows, focused })
+    }
+
+    /// The window with the specified id, if it's in the snapshot.
+    pub fn window(&self, id: u64) -> Option<&WindowSnapshot> {
+        self.windows.iter().find(|window| window.id == id)
+    }
+
+    /// Returns what changed from this snapshot to `other`.
+    ///
+    /// Closed windows come first, followed by the changes to each window of `other` in order. A
+    /// property only counts as changed if both snapshots know it.
+    pub fn diff(&self, other: &Snapshot) -> Vec<Change> {
+        let before: HashMap<u64, &WindowSnapshot> = self
+            .windows
+            .iter()
+            .map(|window| (window.id, window))
+            .collect();
+        let after: HashMap<u64, &WindowSnapshot> = other
+            .windows
+            .iter()
+            .map(|window| (window.id, window))
+            .collect();
+
+        let mut changes: Vec<Change> = self
+            .windows
+            .iter()
+            .filter(|window| !after.contains_key(&window.id))
+            .map(|window| Change::Closed(window.id))
+            .collect();
+        for window in &other.windows {
+            match before.get(&window.id) {
+                Some(previous) => previous.diff(window, &mut changes),
+                None => changes.push(Change::Opened(window.id)),
+            }
+        }
+
+        changes
+    }
+}
+
+impl WindowSnapshot {
+    fn diff(&self, other: &WindowSnapshot, changes: &mut Vec<Change>) {
+        let id = self.id;
+        let (before, after) = (&self.properties, &other.properties);
+        if let Some((from, to)) = changed(&before.position, &after.position) {
+            changes.push(Change::Moved { id, from, to });
+        }
+        if let Some((from, to)) = changed(&before.size, &after.size) {
+            changes.push(Change::Resized { id, from, to });
+        }
+        if let Some((from, to)) = changed(&before.title, &after.title) {
+            changes.push(Change::Renamed { id, from, to });
+        }
+
+        let states = [
+            (PropertyMask::FOCUSED, before.is_focused, after.is_focused),
+            (
+                PropertyMask::FULLSCREEN,
+                before.is_fullscreen,
+                after.is_fullscreen,
+            ),
+            (
+                PropertyMask::MINIMIZED,
+                before.is_minimized,
+                after.is_minimized,
+            ),
+            (PropertyMask::HIDDEN, before.is_hidden, after.is_hidden),
+        ];
+        for (state, before, after) in states {
+            if let Some((_, value)) = changed(&before, &after) {
+                changes.push(Change::StateChanged { id, state, value });
+            }
+        }
+    }
+}
+
+fn changed<T: Clone + PartialEq>(before: &Option<T>, after: &Option<T>) -> Option<(T, T)> {
+    match (before, after) {
+        (Some(before), Some(after)) if before != after => Some((before.clone(), after.clone())),
+        _ => None,
+    }
+}
+
+// Custom backends already pick their own ids, handles from the built-in backends aren't numbers
+// on every platform so they're hashed instead.
+fn id(handle: &WindowHandle) -> u64 {
+    match handle.0 {
+        HandleKind::Custom(id) => id,
+        HandleKind::Sys(ref handle) => {
+            let mut hasher = DefaultHasher::new();
+            handle.hash(&mut hasher);
+            hasher.finish()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(id: u64, title: &str, x: f64) -> WindowSnapshot {
+        WindowSnapshot {
+            id,
+            window: None,
+            pid: None,
+            application: None,
+            properties: WindowProperties {
+                title: Some(title.to_owned()),
+                position: Some(LogicalPosition { x, y: 0.0 }),
+                is_focused: Some(false),
+                ..WindowProperties::default()
+            },
+            z_index: None,
+        }
+    }
+
+    #[test]
+    fn diff() {
+        let before = Snapshot {
+            windows: vec![window(1, "editor", 0.0), window(2, "terminal", 0.0)],
+            focused: None,
+        };
+
+        let mut moved = window(2, "shell", 100.0);
+        moved.properties.is_focused = Some(true);
+        // Properties that one of the snapshots doesn't know aren't compared.
+        moved.properties.is_minimized = Some(true);
+        let after = Snapshot {
+            windows: vec![moved, window(3, "browser", 0.0)],
+            focused: Some(2),
+        };
+
+        assert_eq!(
+            before.diff(&after),
+            [
+                Change::Closed(1),
+                Change::Moved {
+                    id: 2,
+                    from: LogicalPosition { x: 0.0, y: 0.0 },
+                    to: LogicalPosition { x: 100.0, y: 0.0 },
+                },
+                Change::Renamed {
+                    id: 2,
+                    from: "terminal".to_owned(),
+                    to: "shell".to_owned(),
+                },
+                Change::StateChanged {
+                    id: 2,
+                    state: PropertyMask::FOCUSED,
+                    value: true,
+                },
+                Change::Opened(3),
+            ]
+        );
+        assert!(after.diff(&after).is_empty());
+    }
+}
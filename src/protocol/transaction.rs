@@ -0,0 +1,162 @@
+use crate::{
+    protocol::{Rect, Window, WindowError, WindowOps},
+    sys,
+};
+
+/// A batch of changes to many windows, committed together.
+///
+/// Arranging windows one at a time makes every intermediate layout visible. A transaction instead
+/// hands every change to the backend at once, so that it can use its batch primitive where one
+/// exists.
+///
+/// ```no_run
+/// # use fowin::{LogicalPosition, Rect, LogicalSize, Transaction};
+/// let mut transaction = Transaction::new();
+/// for (index, window) in fowin::iter_windows().flatten().enumerate() {
+///     transaction.set_frame(
+///         &window,
+///         Rect {
+///             position: LogicalPosition {
+///                 x: index as f64 * 400.0,
+///                 y: 0.0,
+///             },
+///             size: LogicalSize {
+///                 width: 400.0,
+///                 height: 600.0,
+///             },
+///         },
+///     );
+/// }
+///
+/// for (window, result) in transaction.commit() {
+///     if let Err(err) = result {
+///         println!("failed to arrange {:?}: {err}", window.handle());
+///     }
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct Transaction {
+    changes: Vec<(Window, Change)>,
+}
+
+impl Transaction {
+    /// Creates an empty transaction.
+    pub fn new() -> Transaction {
+        Transaction::default()
+    }
+
+    /// Change the outer frame of the window, see [`Window::set_frame`].
+    pub fn set_frame(&mut self, window: &Window, frame: Rect) -> &mut Transaction {
+        self.push(window, Change::SetFrame(frame))
+    }
+
+    /// Minimize the window, see [`Window::minimize`].
+    pub fn minimize(&mut self, window: &Window) -> &mut Transaction {
+        self.push(window, Change::Minimize)
+    }
+
+    /// Focus the window, see [`Window::focus`].
+    pub fn focus(&mut self, window: &Window) -> &mut Transaction {
+        self.push(window, Change::Focus)
+    }
+
+    /// Bring the window to the front, see [`Window::bring_to_front`].
+    pub fn raise(&mut self, window: &Window) -> &mut Transaction {
+        self.push(window, Change::Raise)
+    }
+
+    /// The number of changes in the transaction.
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    /// Whether or not the transaction has any changes.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Applies every change, returning the result of each in the order they were made.
+    ///
+    /// A change that fails doesn't prevent the others from being applied. Frames and stacking
+    /// are applied before any window is minimized or focused, otherwise changes are applied in
+    /// order.
+    ///
+    /// On Windows, frames and stacking are applied at once with `DeferWindowPos`.
+    ///
+    /// On Linux (X11), every request is sent before waiting on any of them.
+    ///
+    /// On Linux (Wayland), the i3 (sway) and Hyprland backends send every change in a single
+    /// message. The remaining backends apply them one at a time.
+    pub fn commit(self) -> Vec<(Window, Result<(), WindowError>)> {
+        let mut order: Vec<usize> = (0..self.changes.len()).collect();
+        // The sort is stable, so changes keep their order otherwise.
+        order.sort_by_key(|&index| !self.changes[index].1.is_geometry());
+
+        // Windows from the built-in backends are handed to the platform together, the rest are
+        // applied one at a time.
+        let mut results: Vec<Option<Result<(), WindowError>>> =
+            self.changes.iter().map(|_| None).collect();
+        let mut builtin = Vec::new();
+        let mut batch = Vec::new();
+        for index in order {
+            let (window, change) = &self.changes[index];
+            match window.as_sys() {
+                Some(window) => {
+                    builtin.push(index);
+                    batch.push((window, *change));
+                }
+                None => results[index] = Some(change.apply(window.0.as_ref())),
+            }
+        }
+        for (index, result) in builtin.into_iter().zip(sys::commit(&batch)) {
+            results[index] = Some(result);
+        }
+
+        self.changes
+            .into_iter()
+            .zip(results)
+            .map(|((window, _), result)| {
+                // Every change is either batched or applied above.
+                (window, result.expect("change was never applied"))
+            })
+            .collect()
+    }
+
+    fn push(&mut self, window: &Window, change: Change) -> &mut Transaction {
+        self.changes.push((window.clone(), change));
+        self
+    }
+}
+
+/// A single change queued in a [`Transaction`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Change {
+    SetFrame(Rect),
+    Minimize,
+    Focus,
+    Raise,
+}
+
+impl Change {
+    fn is_geometry(self) -> bool {
+        matches!(self, Change::SetFrame(_) | Change::Raise)
+    }
+
+    pub(crate) fn apply(self, window: &dyn WindowOps) -> Result<(), WindowError> {
+        match self {
+            Change::SetFrame(frame) => window.set_frame(frame).map(|_| ()),
+            Change::Minimize => window.minimize(),
+            Change::Focus => window.focus(),
+            Change::Raise => window.bring_to_front(),
+        }
+    }
+}
+
+/// Applies each change one at a time, for backends without a batch primitive.
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+pub(crate) fn apply_all<W: WindowOps>(changes: &[(&W, Change)]) -> Vec<Result<(), WindowError>> {
+    changes
+        .iter()
+        .map(|(window, change)| change.apply(*window))
+        .collect()
+}
@@ -1,18 +1,38 @@
+use std::{any::Any, sync::Arc};
+
 use crate::{
-    protocol::{Position, Size, WindowError, WindowHandle},
+    protocol::{
+        Application, FrameKind, Insets, LogicalPosition, LogicalSize, PhysicalPosition,
+        PhysicalSize, Position, PropertyMask, Rect, Size, WindowError, WindowHandle, WindowOps,
+        WindowProperties,
+    },
     sys,
 };
 
 /// Representation of a single window that can be queried and operated on.
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Window(pub(crate) sys::Window);
+#[derive(Debug, Clone)]
+pub struct Window(pub(crate) Arc<dyn WindowOps>);
 
 impl Window {
+    /// Wraps a window from a custom [`WindowBackend`](crate::WindowBackend).
+    #[inline]
+    pub fn new(window: impl WindowOps + 'static) -> Window {
+        Window(Arc::new(window))
+    }
+
+    pub(crate) fn from_sys(window: sys::Window) -> Window {
+        Window::new(window)
+    }
+
+    pub(crate) fn as_sys(&self) -> Option<&sys::Window> {
+        (self.0.as_ref() as &dyn Any).downcast_ref()
+    }
+
     // TODO: Is there a point to returning this struct? maybe we should have a platform-specific way to return a raw handle instead. The handle could be useful for hashing (e.g. key in hash map), but maybe it'd be better to just hash the window struct itself (need to impl Hash)?
     /// A handle associated with the window.
     #[inline]
     pub fn handle(&self) -> WindowHandle {
-        WindowHandle(self.0.handle())
+        self.0.handle()
     }
 
     /// The title of the window.
@@ -21,18 +41,49 @@ impl Window {
         self.0.title()
     }
 
-    /// The logical size of the window.
+    /// The size of the window in logical pixels, including its decorations.
     #[inline]
-    pub fn size(&self) -> Result<Size, WindowError> {
+    pub fn size(&self) -> Result<LogicalSize, WindowError> {
         self.0.size()
     }
 
-    /// The logical position of the window relative to the current display.
+    /// The size of the window in physical pixels, see [`Window::scale_factor`].
     #[inline]
-    pub fn position(&self) -> Result<Position, WindowError> {
+    pub fn physical_size(&self) -> Result<PhysicalSize, WindowError> {
+        Ok(self.size()?.to_physical(self.scale_factor()?))
+    }
+
+    /// The position of the top-left corner of the window's decorations in logical pixels relative
+    /// to the current display.
+    #[inline]
+    pub fn position(&self) -> Result<LogicalPosition, WindowError> {
         self.0.position()
     }
 
+    /// The position of the window in physical pixels relative to the current display, see
+    /// [`Window::scale_factor`].
+    #[inline]
+    pub fn physical_position(&self) -> Result<PhysicalPosition, WindowError> {
+        Ok(self.position()?.to_physical(self.scale_factor()?))
+    }
+
+    /// The ratio of physical pixels to logical pixels on the display the window is on.
+    ///
+    /// On macOS, this is the ratio of pixels to points of the display containing the center of
+    /// the window, usually `2.0` for Retina displays.
+    ///
+    /// On Windows, this is the DPI of the window divided by 96.
+    ///
+    /// On Linux (X11), this is `Xft.dpi` from the X resources divided by 96, or `1.0` if it isn't
+    /// set. X11 doesn't report scaling per window, so it's the same for every window and read once.
+    ///
+    /// On Linux (Wayland), this is the scale of the window's output on sway and Hyprland. The
+    /// wlr, ext-toplevel, and Plasma backends don't know which output a window is on.
+    #[inline]
+    pub fn scale_factor(&self) -> Result<f64, WindowError> {
+        self.0.scale_factor()
+    }
+
     /// Whether or not the window is focused.
     #[inline]
     pub fn is_focused(&self) -> Result<bool, WindowError> {
@@ -57,23 +108,117 @@ impl Window {
     ///
     /// On Windows, this function checks if the window is hidden or cloaked.
     /// "Cloaking" a window is a fancy way of hiding it, [read more here](https://devblogs.microsoft.com/oldnewthing/20200302-00/?p=103507).
+    ///
+    /// On Linux (X11), this function checks if the window is unmapped, which includes minimized windows.
+    ///
+    /// On Linux (Wayland), this function checks if the window is minimized.
     #[inline]
     pub fn is_hidden(&self) -> Result<bool, WindowError> {
         self.0.is_hidden()
     }
 
-    /// Change the size of the window.
+    /// The properties in the mask, fetched together.
+    ///
+    /// Properties the window doesn't support are left empty, rather than failing the whole call.
+    ///
+    /// On macOS, the attributes of the window are copied in a single message to its application.
+    ///
+    /// On Linux (X11), every request is sent before waiting on any of the replies.
+    ///
+    /// On Linux (Wayland), the i3 (sway) and Hyprland backends read every property from a single
+    /// request. The remaining backends fetch them one at a time.
     #[inline]
-    pub fn resize(&self, size: Size) -> Result<(), WindowError> {
+    pub fn properties(&self, mask: PropertyMask) -> Result<WindowProperties, WindowError> {
+        self.0.properties(mask)
+    }
+
+    /// Change the size of the window including its decorations, in either logical or physical
+    /// pixels.
+    #[inline]
+    pub fn resize(&self, size: impl Into<Size>) -> Result<(), WindowError> {
+        let size = match size.into() {
+            Size::Logical(size) => size,
+            Size::Physical(size) => size.to_logical(self.scale_factor()?),
+        };
         self.0.resize(size)
     }
 
-    /// Change the position of the window.
+    /// Change the position of the top-left corner of the window's decorations, in either logical
+    /// or physical pixels.
     #[inline]
-    pub fn reposition(&self, position: Position) -> Result<(), WindowError> {
+    pub fn reposition(&self, position: impl Into<Position>) -> Result<(), WindowError> {
+        let position = match position.into() {
+            Position::Logical(position) => position,
+            Position::Physical(position) => position.to_logical(self.scale_factor()?),
+        };
         self.0.reposition(position)
     }
 
+    /// The position and size of the window in logical pixels including its decorations, read at
+    /// once.
+    ///
+    /// This is the same frame as [`Window::position`] and [`Window::size`].
+    #[inline]
+    pub fn outer_frame(&self) -> Result<Rect, WindowError> {
+        self.0.frame()
+    }
+
+    /// The position and size of the window's content in logical pixels, excluding its
+    /// decorations.
+    ///
+    /// This is the outer frame shrunk by the [`Window::decoration_insets`].
+    pub fn content_frame(&self) -> Result<Rect, WindowError> {
+        Ok(self.0.frame()?.inset(self.decoration_insets()?))
+    }
+
+    /// The space taken by the decorations on each side of the window's content in logical pixels.
+    ///
+    /// Decorations drawn by the application itself, such as a GTK header bar, are part of the
+    /// content.
+    ///
+    /// On macOS, the accessibility API doesn't expose the title bar, so the insets are those of a
+    /// standard title bar. Windows with a toolbar or a custom title bar may differ.
+    ///
+    /// On Windows, the decorations include the invisible borders used for resizing.
+    ///
+    /// On Linux (X11), the insets are reported by the window manager through
+    /// `_NET_FRAME_EXTENTS`.
+    ///
+    /// On Linux (Wayland), only i3 (sway) reports the insets.
+    #[inline]
+    pub fn decoration_insets(&self) -> Result<Insets, WindowError> {
+        self.0.decoration_insets()
+    }
+
+    /// Change the position and size of the window in a single operation.
+    ///
+    /// The `kind` decides whether the frame refers to the [outer frame](Window::outer_frame) or
+    /// the [content frame](Window::content_frame). Reading a frame and passing it back with the
+    /// same kind leaves the window as it is.
+    ///
+    /// Unlike calling [`Window::reposition`] and [`Window::resize`] separately, the window never
+    /// shows up at an intermediate frame and isn't clamped to the display at its old position
+    /// before being resized. The window may not accept the requested frame as is (e.g. due to a
+    /// minimum size), so the frame it ended up with is returned, of the same kind.
+    ///
+    /// On macOS, there is no single accessibility operation for the frame, so the size is set
+    /// around the position to avoid clamping.
+    ///
+    /// On Linux (X11), the window manager applies the request asynchronously, so the frame is
+    /// read back once it matches the request, or after a short while if the window manager
+    /// adjusted it. The same applies to i3 (sway) and Hyprland.
+    ///
+    /// On Linux (Wayland), the wlr, ext-toplevel, and Plasma backends can't move or resize windows.
+    pub fn set_frame(&self, frame: Rect, kind: FrameKind) -> Result<Rect, WindowError> {
+        match kind {
+            FrameKind::Outer => self.0.set_frame(frame),
+            FrameKind::Content => {
+                let insets = self.decoration_insets()?;
+                Ok(self.0.set_frame(frame.outset(insets))?.inset(insets))
+            }
+        }
+    }
+
     /// Focus the window.
     #[inline]
     pub fn focus(&self) -> Result<(), WindowError> {
@@ -121,6 +266,8 @@ impl Window {
     ///
     /// On Windows, this function will cloak the window rather than hide it. Read [`Window::is_hidden`](Window::is_hidden)
     /// for more information.
+    ///
+    /// On Linux (X11 and Wayland), this function will unminimize the window.
     #[inline]
     pub fn show(&self) -> Result<(), WindowError> {
         self.0.show()
@@ -132,6 +279,8 @@ impl Window {
     ///
     /// On Windows, this function will cloak the window rather than hide it. Read [`Window::is_hidden`](Window::is_hidden)
     /// for more information.
+    ///
+    /// On Linux (X11 and Wayland), the default behavior is to minimize the window.
     #[inline]
     pub fn hide(&self) -> Result<(), WindowError> {
         self.0.hide()
@@ -144,8 +293,58 @@ impl Window {
     pub fn bring_to_front(&self) -> Result<(), WindowError> {
         self.0.bring_to_front()
     }
+
+    /// Ask the window to close.
+    ///
+    /// This is the same as pressing the window's close button, so the application may prompt the
+    /// user, e.g. to save their work, or ignore the request entirely. Use [`Window::kill`] to
+    /// forcefully close it.
+    ///
+    /// On macOS, this function presses the window's close button through the accessibility API.
+    ///
+    /// On Windows, this function posts `WM_CLOSE` to the window.
+    ///
+    /// On Linux (X11), this function sends `_NET_CLOSE_WINDOW` to the window manager, or
+    /// `WM_DELETE_WINDOW` to the window if the window manager doesn't support it.
+    ///
+    /// On Linux (Wayland), the ext-toplevel backend can't close windows.
+    #[inline]
+    pub fn close(&self) -> Result<(), WindowError> {
+        self.0.close()
+    }
+
+    /// Forcefully terminate the process that owns the window.
+    ///
+    /// Every other window of the application closes along with it and unsaved work is lost.
+    ///
+    /// On Linux, this function sends `SIGKILL` to the process, so it is only supported by
+    /// backends that know which process owns a window (see [`Window::application`]).
+    #[inline]
+    pub fn kill(&self) -> Result<(), WindowError> {
+        self.0.kill()
+    }
+
+    /// The application that owns the window.
+    ///
+    /// On Linux (X11), this function relies on the window setting `_NET_WM_PID`, which most clients do.
+    /// Clients running on another machine, such as ones forwarded over SSH, are unsupported.
+    ///
+    /// On Linux (Wayland), only the i3 (sway), Hyprland, and Plasma backends know which process owns a window.
+    #[inline]
+    pub fn application(&self) -> Result<Application, WindowError> {
+        self.0.application()
+    }
 }
 
+// The handle uniquely identifies a window within its backend.
+impl PartialEq for Window {
+    fn eq(&self, other: &Self) -> bool {
+        self.handle() == other.handle()
+    }
+}
+
+impl Eq for Window {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
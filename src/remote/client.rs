@@ -0,0 +1,586 @@
+use std::{
+    io::{self, BufRead, BufReader},
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+#[cfg(unix)]
+use std::{os::unix::net::UnixStream, path::Path};
+
+use crate::{
+    Application, ApplicationOps, Backend, EventSource, Insets, LogicalPosition, LogicalSize,
+    PropertyMask, Rect, Window, WindowBackend, WindowError, WindowEvent, WindowHandle, WindowOps,
+    WindowProperties,
+};
+
+use super::{
+    read_message, write_message, ApplicationOperation, EventKind, Operation, Reply, Request,
+    Response, Stream, Value,
+};
+
+/// A backend that forwards every operation to a [`Server`](super::Server).
+#[derive(Debug, Clone)]
+pub struct RemoteBackend {
+    client: Arc<Client>,
+}
+
+impl RemoteBackend {
+    /// Connects to a server listening on a TCP socket.
+    pub fn connect_tcp(address: impl ToSocketAddrs) -> Result<RemoteBackend, WindowError> {
+        let stream = TcpStream::connect(address).map_err(WindowError::OsError)?;
+        // Remember the resolved address, so that watchers connect to the same server.
+        let address = stream.peer_addr().map_err(WindowError::OsError)?;
+        RemoteBackend::new(Address::Tcp(address), Connection::tcp(stream)?)
+    }
+
+    /// Connects to a server listening on a Unix socket.
+    #[cfg(unix)]
+    pub fn connect_unix(path: impl AsRef<Path>) -> Result<RemoteBackend, WindowError> {
+        let address = Address::Unix(path.as_ref().to_owned());
+        let connection = address.connect()?;
+        RemoteBackend::new(address, connection)
+    }
+
+    fn new(address: Address, connection: Connection) -> Result<RemoteBackend, WindowError> {
+        Ok(RemoteBackend {
+            client: Arc::new(Client {
+                address,
+                connection: Mutex::new(connection),
+            }),
+        })
+    }
+}
+
+impl WindowBackend for RemoteBackend {
+    fn kind(&self) -> Backend {
+        Backend::Custom("remote")
+    }
+
+    // Events are streamed over a dedicated connection, so that requests aren't interleaved with them.
+    fn watch(&self) -> Result<Box<dyn EventSource>, WindowError> {
+        let mut connection = self.client.address.connect()?;
+        match connection.request(&Request::Watch)? {
+            Reply::Done => {}
+            reply => return Err(unexpected(reply)),
+        }
+
+        Ok(Box::new(RemoteEvents {
+            connection,
+            client: self.client.clone(),
+        }))
+    }
+
+    fn iter_windows(&self) -> Box<dyn Iterator<Item = Result<Window, WindowError>>> {
+        self.client.windows(&Request::IterWindows)
+    }
+
+    fn focused_window(&self) -> Result<Option<Window>, WindowError> {
+        match self.client.request(&Request::FocusedWindow)? {
+            Reply::Window(id) => Ok(id.map(|id| self.client.window(id))),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    fn iter_applications(&self) -> Box<dyn Iterator<Item = Result<Application, WindowError>>> {
+        let applications = match self.client.request(&Request::IterApplications) {
+            Ok(Reply::Applications(pids)) => pids
+                .into_iter()
+                .map(|result| {
+                    result
+                        .map(|pid| self.client.application(pid))
+                        .map_err(WindowError::from)
+                })
+                .collect(),
+            Ok(reply) => vec![Err(unexpected(reply))],
+            Err(err) => vec![Err(err)],
+        };
+        Box::new(applications.into_iter())
+    }
+}
+
+#[derive(Debug)]
+enum Address {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl Address {
+    fn connect(&self) -> Result<Connection, WindowError> {
+        match self {
+            Address::Tcp(address) => {
+                Connection::tcp(TcpStream::connect(address).map_err(WindowError::OsError)?)
+            }
+            #[cfg(unix)]
+            Address::Unix(path) => Connection::new(Stream::Unix(
+                UnixStream::connect(path).map_err(WindowError::OsError)?,
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Connection {
+    reader: BufReader<Stream>,
+    writer: Stream,
+}
+
+impl Connection {
+    fn tcp(stream: TcpStream) -> Result<Connection, WindowError> {
+        // Every request waits on its reply, so send it right away.
+        stream.set_nodelay(true).map_err(WindowError::OsError)?;
+        Connection::new(Stream::Tcp(stream))
+    }
+
+    fn new(stream: Stream) -> Result<Connection, WindowError> {
+        Ok(Connection {
+            reader: BufReader::new(stream.try_clone().map_err(WindowError::OsError)?),
+            writer: stream,
+        })
+    }
+
+    fn request(&mut self, request: &Request) -> Result<Reply, WindowError> {
+        write_message(&mut self.writer, request).map_err(WindowError::OsError)?;
+        self.receive()
+    }
+
+    // Waits until a message starts arriving, returning `false` if the timeout elapses first.
+    fn wait(&mut self, timeout: Duration) -> Result<bool, WindowError> {
+        if !self.reader.buffer().is_empty() {
+            return Ok(true);
+        }
+        // A timeout of zero isn't allowed, since it would block indefinitely.
+        if timeout.is_zero() {
+            return Ok(false);
+        }
+
+        self.reader
+            .get_ref()
+            .set_read_timeout(Some(timeout))
+            .map_err(WindowError::OsError)?;
+        // Only fills the buffer, so a timeout doesn't lose part of a message.
+        let result = self.reader.fill_buf().map(|_| ());
+        self.reader
+            .get_ref()
+            .set_read_timeout(None)
+            .map_err(WindowError::OsError)?;
+
+        match result {
+            Ok(()) => Ok(true),
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                Ok(false)
+            }
+            Err(err) => Err(WindowError::OsError(err)),
+        }
+    }
+
+    fn receive(&mut self) -> Result<Reply, WindowError> {
+        match read_message::<Response>(&mut self.reader).map_err(WindowError::OsError)? {
+            Some(response) => response.map_err(WindowError::from),
+            None => Err(WindowError::OsError(io::Error::new(
+                io::ErrorKind::ConnectionAborted,
+                "the server closed the connection",
+            ))),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Client {
+    address: Address,
+    // Replies are matched to requests by order, so only one request can be in flight.
+    connection: Mutex<Connection>,
+}
+
+impl Client {
+    fn request(&self, request: &Request) -> Result<Reply, WindowError> {
+        self.connection.lock().unwrap().request(request)
+    }
+
+    fn window(self: &Arc<Self>, id: u64) -> Window {
+        Window::new(RemoteWindow {
+            id,
+            client: self.clone(),
+        })
+    }
+
+    fn application(self: &Arc<Self>, pid: u32) -> Application {
+        Application::new(RemoteApplication {
+            pid,
+            client: self.clone(),
+        })
+    }
+
+    // Sends a request that replies with a list of windows.
+    fn windows(
+        self: &Arc<Self>,
+        request: &Request,
+    ) -> Box<dyn Iterator<Item = Result<Window, WindowError>>> {
+        let windows = match self.request(request) {
+            Ok(Reply::Windows(ids)) => ids
+                .into_iter()
+                .map(|result| result.map(|id| self.window(id)).map_err(WindowError::from))
+                .collect(),
+            Ok(reply) => vec![Err(unexpected(reply))],
+            Err(err) => vec![Err(err)],
+        };
+        Box::new(windows.into_iter())
+    }
+}
+
+fn unexpected(reply: Reply) -> WindowError {
+    WindowError::OsError(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("unexpected reply from the server: {reply:?}"),
+    ))
+}
+
+#[derive(Debug)]
+struct RemoteWindow {
+    id: u64,
+    client: Arc<Client>,
+}
+
+impl RemoteWindow {
+    fn request(&self, operation: Operation) -> Result<Reply, WindowError> {
+        self.client.request(&Request::Window {
+            id: self.id,
+            operation,
+        })
+    }
+
+    fn query(&self, operation: Operation) -> Result<bool, WindowError> {
+        match self.request(operation)? {
+            Reply::Bool(value) => Ok(value),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    fn perform(&self, operation: Operation) -> Result<(), WindowError> {
+        match self.request(operation)? {
+            Reply::Done => Ok(()),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    fn request_frame(&self, operation: Operation) -> Result<Rect, WindowError> {
+        match self.request(operation)? {
+            Reply::Frame {
+                x,
+                y,
+                width,
+                height,
+            } => Ok(Rect {
+                position: LogicalPosition { x, y },
+                size: LogicalSize { width, height },
+            }),
+            reply => Err(unexpected(reply)),
+        }
+    }
+}
+
+impl WindowOps for RemoteWindow {
+    fn handle(&self) -> WindowHandle {
+        WindowHandle::custom(self.id)
+    }
+
+    fn title(&self) -> Result<String, WindowError> {
+        match self.request(Operation::Title)? {
+            Reply::Title(title) => Ok(title),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    fn size(&self) -> Result<LogicalSize, WindowError> {
+        match self.request(Operation::Size)? {
+            Reply::Size { width, height } => Ok(LogicalSize { width, height }),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    fn position(&self) -> Result<LogicalPosition, WindowError> {
+        match self.request(Operation::Position)? {
+            Reply::Position { x, y } => Ok(LogicalPosition { x, y }),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    fn is_focused(&self) -> Result<bool, WindowError> {
+        self.query(Operation::IsFocused)
+    }
+
+    fn is_fullscreen(&self) -> Result<bool, WindowError> {
+        self.query(Operation::IsFullscreen)
+    }
+
+    fn is_minimized(&self) -> Result<bool, WindowError> {
+        self.query(Operation::IsMinimized)
+    }
+
+    fn is_hidden(&self) -> Result<bool, WindowError> {
+        self.query(Operation::IsHidden)
+    }
+
+    fn scale_factor(&self) -> Result<f64, WindowError> {
+        match self.request(Operation::ScaleFactor)? {
+            Reply::ScaleFactor(scale_factor) => Ok(scale_factor),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    // Every property is fetched in a single round trip.
+    fn properties(&self, mask: PropertyMask) -> Result<WindowProperties, WindowError> {
+        match self.request(Operation::Properties { mask: mask.bits() })? {
+            Reply::Properties {
+                title,
+                width,
+                height,
+                x,
+                y,
+                is_focused,
+                is_fullscreen,
+                is_minimized,
+                is_hidden,
+            } => Ok(WindowProperties {
+                title,
+                size: width
+                    .zip(height)
+                    .map(|(width, height)| LogicalSize { width, height }),
+                position: x.zip(y).map(|(x, y)| LogicalPosition { x, y }),
+                is_focused,
+                is_fullscreen,
+                is_minimized,
+                is_hidden,
+            }),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    fn resize(&self, size: LogicalSize) -> Result<(), WindowError> {
+        self.perform(Operation::Resize {
+            width: size.width,
+            height: size.height,
+        })
+    }
+
+    fn reposition(&self, position: LogicalPosition) -> Result<(), WindowError> {
+        self.perform(Operation::Reposition {
+            x: position.x,
+            y: position.y,
+        })
+    }
+
+    fn frame(&self) -> Result<Rect, WindowError> {
+        self.request_frame(Operation::Frame)
+    }
+
+    fn set_frame(&self, frame: Rect) -> Result<Rect, WindowError> {
+        self.request_frame(Operation::SetFrame {
+            x: frame.position.x,
+            y: frame.position.y,
+            width: frame.size.width,
+            height: frame.size.height,
+        })
+    }
+
+    fn decoration_insets(&self) -> Result<Insets, WindowError> {
+        match self.request(Operation::DecorationInsets)? {
+            Reply::Insets {
+                top,
+                right,
+                bottom,
+                left,
+            } => Ok(Insets {
+                top,
+                right,
+                bottom,
+                left,
+            }),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    fn focus(&self) -> Result<(), WindowError> {
+        self.perform(Operation::Focus)
+    }
+
+    fn fullscreen(&self) -> Result<(), WindowError> {
+        self.perform(Operation::Fullscreen)
+    }
+
+    fn unfullscreen(&self) -> Result<(), WindowError> {
+        self.perform(Operation::Unfullscreen)
+    }
+
+    fn maximize(&self) -> Result<(), WindowError> {
+        self.perform(Operation::Maximize)
+    }
+
+    fn minimize(&self) -> Result<(), WindowError> {
+        self.perform(Operation::Minimize)
+    }
+
+    fn unminimize(&self) -> Result<(), WindowError> {
+        self.perform(Operation::Unminimize)
+    }
+
+    fn show(&self) -> Result<(), WindowError> {
+        self.perform(Operation::Show)
+    }
+
+    fn hide(&self) -> Result<(), WindowError> {
+        self.perform(Operation::Hide)
+    }
+
+    fn bring_to_front(&self) -> Result<(), WindowError> {
+        self.perform(Operation::BringToFront)
+    }
+
+    fn close(&self) -> Result<(), WindowError> {
+        self.perform(Operation::Close)
+    }
+
+    fn kill(&self) -> Result<(), WindowError> {
+        self.perform(Operation::Kill)
+    }
+
+    fn application(&self) -> Result<Application, WindowError> {
+        match self.request(Operation::Application)? {
+            Reply::Application(pid) => Ok(self.client.application(pid)),
+            reply => Err(unexpected(reply)),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct RemoteApplication {
+    pid: u32,
+    client: Arc<Client>,
+}
+
+impl RemoteApplication {
+    fn request(&self, operation: ApplicationOperation) -> Result<Reply, WindowError> {
+        self.client.request(&self.message(operation))
+    }
+
+    fn message(&self, operation: ApplicationOperation) -> Request {
+        Request::Application {
+            pid: self.pid,
+            operation,
+        }
+    }
+}
+
+impl ApplicationOps for RemoteApplication {
+    fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    fn name(&self) -> Result<String, WindowError> {
+        match self.request(ApplicationOperation::Name)? {
+            Reply::Name(name) => Ok(name),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    fn executable_path(&self) -> Result<PathBuf, WindowError> {
+        match self.request(ApplicationOperation::ExecutablePath)? {
+            Reply::Path(path) => Ok(path),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    fn iter_windows(&self) -> Box<dyn Iterator<Item = Result<Window, WindowError>>> {
+        self.client
+            .windows(&self.message(ApplicationOperation::IterWindows))
+    }
+
+    fn is_frontmost(&self) -> Result<bool, WindowError> {
+        match self.request(ApplicationOperation::IsFrontmost)? {
+            Reply::Bool(value) => Ok(value),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    fn activate(&self) -> Result<(), WindowError> {
+        match self.request(ApplicationOperation::Activate)? {
+            Reply::Done => Ok(()),
+            reply => Err(unexpected(reply)),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct RemoteEvents {
+    connection: Connection,
+    // Windows in events send their requests over the client's connection.
+    client: Arc<Client>,
+}
+
+impl EventSource for RemoteEvents {
+    fn next_request(&mut self) -> Result<WindowEvent, WindowError> {
+        let (kind, id, old, new) = match self.connection.receive()? {
+            Reply::Event { kind, id, old, new } => (kind, id, old, new),
+            reply => return Err(unexpected(reply)),
+        };
+
+        // Application events carry a pid instead of a window id.
+        let pid = id as u32;
+        let window = self.client.window(id);
+        let application = self.client.application(pid);
+        Ok(match kind {
+            EventKind::Opened => WindowEvent::Opened(window),
+            EventKind::Closed => WindowEvent::Closed(window.handle()),
+            EventKind::Hidden => WindowEvent::Hidden(window),
+            EventKind::Shown => WindowEvent::Shown(window),
+            EventKind::Minimized => WindowEvent::Minimized(window),
+            EventKind::Unminimized => WindowEvent::Unminimized(window),
+            EventKind::Maximized => WindowEvent::Maximized(window),
+            EventKind::Unmaximized => WindowEvent::Unmaximized(window),
+            EventKind::Fullscreened => WindowEvent::Fullscreened(window),
+            EventKind::Unfullscreened => WindowEvent::Unfullscreened(window),
+            EventKind::Focused => WindowEvent::Focused(window),
+            EventKind::Unfocused => WindowEvent::Unfocused(window),
+            EventKind::Moved => WindowEvent::Moved {
+                window,
+                old: old.and_then(Value::position),
+                new: new.and_then(Value::position),
+            },
+            EventKind::Resized => WindowEvent::Resized {
+                window,
+                old: old.and_then(Value::size),
+                new: new.and_then(Value::size),
+            },
+            EventKind::MoveResizeStarted => WindowEvent::MoveResizeStarted(window),
+            EventKind::MoveResizeEnded => WindowEvent::MoveResizeEnded(window),
+            EventKind::Renamed => WindowEvent::Renamed {
+                window,
+                old_title: old.and_then(Value::title),
+                new_title: new.and_then(Value::title),
+            },
+            EventKind::ApplicationLaunched => WindowEvent::ApplicationLaunched(application),
+            EventKind::ApplicationTerminated => WindowEvent::ApplicationTerminated(pid),
+            EventKind::ApplicationActivated => WindowEvent::ApplicationActivated(application),
+            EventKind::ApplicationHidden => WindowEvent::ApplicationHidden(application),
+            EventKind::ApplicationShown => WindowEvent::ApplicationShown(application),
+        })
+    }
+
+    fn next_request_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<WindowEvent>, WindowError> {
+        if !self.connection.wait(timeout)? {
+            return Ok(None);
+        }
+
+        self.next_request().map(Some)
+    }
+}
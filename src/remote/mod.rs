@@ -0,0 +1,607 @@
+//! Controls windows on another machine over a TCP or Unix socket.
+//!
+//! The target machine runs a [`Server`], which serves the windows of a local [`Session`](crate::Session).
+//! Elsewhere, a [`RemoteBackend`] forwards every operation to that server, so it can be passed to
+//! [`Session::from_backend`](crate::Session::from_backend) and used like any other backend.
+//!
+//! Requests and responses are JSON objects separated by newlines. Windows are identified on the
+//! wire by ids the server assigns, applications by their pid on the server, and errors are sent back as the same [`WindowError`] variant,
+//! including the kind and OS error code of I/O errors.
+//!
+//! The server does not authenticate clients, so only expose it on a trusted network, or bind it
+//! to a Unix socket and tunnel the connection.
+
+use std::{
+    env,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    path::PathBuf,
+    time::Duration,
+};
+
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{LogicalPosition, LogicalSize, WindowError, WindowEvent};
+
+pub use client::RemoteBackend;
+pub use server::Server;
+
+mod client;
+mod server;
+
+#[derive(Debug, Serialize, Deserialize)]
+enum Request {
+    IterWindows,
+    IterApplications,
+    FocusedWindow,
+    // Switches the connection to streaming events.
+    Watch,
+    Window {
+        id: u64,
+        operation: Operation,
+    },
+    Application {
+        pid: u32,
+        operation: ApplicationOperation,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum Operation {
+    Title,
+    Size,
+    Position,
+    IsFocused,
+    IsFullscreen,
+    IsMinimized,
+    IsHidden,
+    ScaleFactor,
+    Properties {
+        mask: u8,
+    },
+    Resize {
+        width: f64,
+        height: f64,
+    },
+    Reposition {
+        x: f64,
+        y: f64,
+    },
+    Frame,
+    SetFrame {
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+    },
+    DecorationInsets,
+    Focus,
+    Fullscreen,
+    Unfullscreen,
+    Maximize,
+    Minimize,
+    Unminimize,
+    Show,
+    Hide,
+    BringToFront,
+    Close,
+    Kill,
+    Application,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum ApplicationOperation {
+    Name,
+    ExecutablePath,
+    IterWindows,
+    IsFrontmost,
+    Activate,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum Reply {
+    Done,
+    Windows(Vec<Result<u64, Error>>),
+    Window(Option<u64>),
+    Applications(Vec<Result<u32, Error>>),
+    Application(u32),
+    Title(String),
+    Name(String),
+    Path(PathBuf),
+    Size {
+        width: f64,
+        height: f64,
+    },
+    Position {
+        x: f64,
+        y: f64,
+    },
+    Frame {
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+    },
+    Insets {
+        top: f64,
+        right: f64,
+        bottom: f64,
+        left: f64,
+    },
+    Bool(bool),
+    ScaleFactor(f64),
+    Properties {
+        title: Option<String>,
+        width: Option<f64>,
+        height: Option<f64>,
+        x: Option<f64>,
+        y: Option<f64>,
+        is_focused: Option<bool>,
+        is_fullscreen: Option<bool>,
+        is_minimized: Option<bool>,
+        is_hidden: Option<bool>,
+    },
+    // The id is the pid for application events, and only moved, resized and renamed events
+    // carry values.
+    Event {
+        kind: EventKind,
+        id: u64,
+        old: Option<Value>,
+        new: Option<Value>,
+    },
+}
+
+// The previous or new value of a moved, resized or renamed event.
+#[derive(Debug, Serialize, Deserialize)]
+enum Value {
+    Position { x: f64, y: f64 },
+    Size { width: f64, height: f64 },
+    Title(String),
+}
+
+impl Value {
+    fn position(self) -> Option<LogicalPosition> {
+        match self {
+            Value::Position { x, y } => Some(LogicalPosition { x, y }),
+            _ => None,
+        }
+    }
+
+    fn size(self) -> Option<LogicalSize> {
+        match self {
+            Value::Size { width, height } => Some(LogicalSize { width, height }),
+            _ => None,
+        }
+    }
+
+    fn title(self) -> Option<String> {
+        match self {
+            Value::Title(title) => Some(title),
+            _ => None,
+        }
+    }
+}
+
+impl From<LogicalPosition> for Value {
+    fn from(position: LogicalPosition) -> Value {
+        Value::Position {
+            x: position.x,
+            y: position.y,
+        }
+    }
+}
+
+impl From<LogicalSize> for Value {
+    fn from(size: LogicalSize) -> Value {
+        Value::Size {
+            width: size.width,
+            height: size.height,
+        }
+    }
+}
+
+type Response = Result<Reply, Error>;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+enum EventKind {
+    Opened,
+    Closed,
+    Hidden,
+    Shown,
+    Minimized,
+    Unminimized,
+    Maximized,
+    Unmaximized,
+    Fullscreened,
+    Unfullscreened,
+    Focused,
+    Unfocused,
+    Moved,
+    Resized,
+    MoveResizeStarted,
+    MoveResizeEnded,
+    Renamed,
+    ApplicationLaunched,
+    ApplicationTerminated,
+    ApplicationActivated,
+    ApplicationHidden,
+    ApplicationShown,
+}
+
+impl EventKind {
+    fn of(event: &WindowEvent) -> EventKind {
+        match event {
+            WindowEvent::Opened(_) => EventKind::Opened,
+            WindowEvent::Closed(_) => EventKind::Closed,
+            WindowEvent::Hidden(_) => EventKind::Hidden,
+            WindowEvent::Shown(_) => EventKind::Shown,
+            WindowEvent::Minimized(_) => EventKind::Minimized,
+            WindowEvent::Unminimized(_) => EventKind::Unminimized,
+            WindowEvent::Maximized(_) => EventKind::Maximized,
+            WindowEvent::Unmaximized(_) => EventKind::Unmaximized,
+            WindowEvent::Fullscreened(_) => EventKind::Fullscreened,
+            WindowEvent::Unfullscreened(_) => EventKind::Unfullscreened,
+            WindowEvent::Focused(_) => EventKind::Focused,
+            WindowEvent::Unfocused(_) => EventKind::Unfocused,
+            WindowEvent::Moved { .. } => EventKind::Moved,
+            WindowEvent::Resized { .. } => EventKind::Resized,
+            WindowEvent::MoveResizeStarted(_) => EventKind::MoveResizeStarted,
+            WindowEvent::MoveResizeEnded(_) => EventKind::MoveResizeEnded,
+            WindowEvent::Renamed { .. } => EventKind::Renamed,
+            WindowEvent::ApplicationLaunched(_) => EventKind::ApplicationLaunched,
+            WindowEvent::ApplicationTerminated(_) => EventKind::ApplicationTerminated,
+            WindowEvent::ApplicationActivated(_) => EventKind::ApplicationActivated,
+            WindowEvent::ApplicationHidden(_) => EventKind::ApplicationHidden,
+            WindowEvent::ApplicationShown(_) => EventKind::ApplicationShown,
+        }
+    }
+}
+
+/// A [`WindowError`] as sent over the wire.
+#[derive(Debug, Serialize, Deserialize)]
+enum Error {
+    NotTrusted,
+    InvalidInternalArgument,
+    InvalidHandle,
+    Unsupported,
+    Os {
+        // The OS the code came from, it's meaningless anywhere else.
+        os: String,
+        code: Option<i32>,
+        kind: String,
+        message: String,
+    },
+}
+
+impl From<WindowError> for Error {
+    fn from(err: WindowError) -> Self {
+        match err {
+            WindowError::NotTrusted => Error::NotTrusted,
+            WindowError::InvalidInternalArgument => Error::InvalidInternalArgument,
+            WindowError::InvalidHandle => Error::InvalidHandle,
+            WindowError::Unsupported => Error::Unsupported,
+            WindowError::OsError(err) => Error::Os {
+                os: env::consts::OS.to_owned(),
+                code: err.raw_os_error(),
+                kind: format!("{:?}", err.kind()),
+                message: err.to_string(),
+            },
+        }
+    }
+}
+
+impl From<Error> for WindowError {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::NotTrusted => WindowError::NotTrusted,
+            Error::InvalidInternalArgument => WindowError::InvalidInternalArgument,
+            Error::InvalidHandle => WindowError::InvalidHandle,
+            Error::Unsupported => WindowError::Unsupported,
+            Error::Os {
+                os,
+                code: Some(code),
+                ..
+            } if os == env::consts::OS => WindowError::OsError(io::Error::from_raw_os_error(code)),
+            Error::Os { kind, message, .. } => {
+                WindowError::OsError(io::Error::new(error_kind(&kind), message))
+            }
+        }
+    }
+}
+
+// Parses the `Debug` representation of an `io::ErrorKind`, anything unknown becomes `Other`.
+fn error_kind(kind: &str) -> io::ErrorKind {
+    use io::ErrorKind::*;
+
+    [
+        NotFound,
+        PermissionDenied,
+        ConnectionRefused,
+        ConnectionReset,
+        ConnectionAborted,
+        NotConnected,
+        AddrInUse,
+        AddrNotAvailable,
+        BrokenPipe,
+        AlreadyExists,
+        WouldBlock,
+        InvalidInput,
+        InvalidData,
+        TimedOut,
+        WriteZero,
+        Interrupted,
+        Unsupported,
+        UnexpectedEof,
+        OutOfMemory,
+    ]
+    .into_iter()
+    .find(|candidate| format!("{candidate:?}") == kind)
+    .unwrap_or(Other)
+}
+
+#[derive(Debug)]
+enum Stream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Stream {
+    fn try_clone(&self) -> io::Result<Stream> {
+        match self {
+            Stream::Tcp(stream) => stream.try_clone().map(Stream::Tcp),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.try_clone().map(Stream::Unix),
+        }
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.set_read_timeout(timeout),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.set_read_timeout(timeout),
+        }
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+fn write_message(stream: &mut Stream, message: &impl Serialize) -> io::Result<()> {
+    let mut bytes = serde_json::to_vec(message)?;
+    bytes.push(b'\n');
+    stream.write_all(&bytes)
+}
+
+// Returns `None` once the other end closes the connection.
+fn read_message<T: DeserializeOwned>(stream: &mut BufReader<Stream>) -> io::Result<Option<T>> {
+    let mut line = String::new();
+    if stream.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+
+    Ok(Some(serde_json::from_str(&line)?))
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use std::net::TcpListener;
+
+    use crate::{
+        mock::{MockDesktop, Operation as MockOperation},
+        Backend, FrameKind, Insets, LogicalPosition, LogicalSize, PropertyMask, Rect, Session,
+    };
+
+    use super::*;
+
+    // Serves the desktop on an ephemeral port and connects to it.
+    fn connect(desktop: &MockDesktop) -> Session {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = Server::new(desktop.session());
+        std::thread::spawn(move || server.serve_tcp(&listener));
+
+        Session::from_backend(RemoteBackend::connect_tcp(address).unwrap())
+    }
+
+    const ORIGIN: LogicalPosition = LogicalPosition { x: 0.0, y: 0.0 };
+    const SIZE: LogicalSize = LogicalSize {
+        width: 800.0,
+        height: 600.0,
+    };
+
+    #[test]
+    fn window_operations() {
+        let desktop = MockDesktop::new();
+        desktop.spawn("first", ORIGIN, SIZE);
+        desktop.spawn("second", ORIGIN, SIZE);
+        let session = connect(&desktop);
+
+        let windows = session
+            .iter_windows()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(windows[1].title().unwrap(), "second");
+        assert_eq!(windows[0].size().unwrap(), SIZE);
+
+        windows[0].focus().unwrap();
+        assert!(windows[0].is_focused().unwrap());
+        assert_eq!(session.focused_window().unwrap(), Some(windows[0].clone()));
+
+        let position = LogicalPosition { x: 10.0, y: 20.0 };
+        windows[1].reposition(position).unwrap();
+        assert_eq!(windows[1].position().unwrap(), position);
+
+        let frame = Rect {
+            position: ORIGIN,
+            size: LogicalSize {
+                width: 400.0,
+                height: 300.0,
+            },
+        };
+        assert_eq!(
+            windows[1].set_frame(frame, FrameKind::Outer).unwrap(),
+            frame
+        );
+        assert_eq!(windows[1].outer_frame().unwrap(), frame);
+
+        let properties = windows[1]
+            .properties(PropertyMask::TITLE | PropertyMask::FRAME | PropertyMask::FOCUSED)
+            .unwrap();
+        assert_eq!(properties.title.as_deref(), Some("second"));
+        assert_eq!(properties.size, Some(frame.size));
+        assert_eq!(properties.position, Some(frame.position));
+        assert_eq!(properties.is_focused, Some(false));
+        assert_eq!(properties.is_hidden, None);
+
+        desktop.set_scale_factor(2.0);
+        assert_eq!(windows[1].scale_factor().unwrap(), 2.0);
+
+        let insets = Insets {
+            top: 28.0,
+            ..Insets::default()
+        };
+        desktop.set_decoration_insets(insets);
+        assert_eq!(windows[1].decoration_insets().unwrap(), insets);
+        assert_eq!(windows[1].content_frame().unwrap(), frame.inset(insets));
+    }
+
+    #[test]
+    fn applications() {
+        let desktop = MockDesktop::new();
+        let editor = desktop.launch("editor", "/usr/bin/editor");
+        desktop.spawn_for(&editor, "first", ORIGIN, SIZE).unwrap();
+        let second = desktop.spawn_for(&editor, "second", ORIGIN, SIZE).unwrap();
+        let session = connect(&desktop);
+
+        let window = session.iter_windows().last().unwrap().unwrap();
+        let application = window.application().unwrap();
+        assert_eq!(application.pid(), editor.pid());
+        assert_eq!(application.name().unwrap(), "editor");
+        assert_eq!(
+            application.executable_path().unwrap(),
+            PathBuf::from("/usr/bin/editor")
+        );
+        assert_eq!(application.iter_windows().count(), 2);
+        assert_eq!(
+            session
+                .iter_applications()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap(),
+            [editor]
+        );
+
+        application.activate().unwrap();
+        assert!(application.is_frontmost().unwrap());
+        assert!(second.is_focused().unwrap());
+
+        window.close().unwrap();
+        assert_eq!(application.iter_windows().count(), 1);
+        session
+            .iter_windows()
+            .next()
+            .unwrap()
+            .unwrap()
+            .kill()
+            .unwrap();
+        assert!(matches!(
+            application.name(),
+            Err(WindowError::InvalidHandle)
+        ));
+    }
+
+    #[test]
+    fn errors_preserved() {
+        let desktop = MockDesktop::new();
+        let window = desktop.spawn("window", ORIGIN, SIZE);
+        let session = connect(&desktop);
+        let remote = session.iter_windows().next().unwrap().unwrap();
+
+        desktop.fail(MockOperation::Title, || {
+            WindowError::OsError(io::Error::new(io::ErrorKind::TimedOut, "too slow"))
+        });
+        match remote.title() {
+            Err(WindowError::OsError(err)) => {
+                assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+                assert_eq!(err.to_string(), "too slow");
+            }
+            result => panic!("unexpected result {result:?}"),
+        }
+
+        desktop.close(&window.handle()).unwrap();
+        assert!(matches!(remote.focus(), Err(WindowError::InvalidHandle)));
+    }
+
+    #[test]
+    fn watcher_events() {
+        let desktop = MockDesktop::new();
+        let session = connect(&desktop);
+        let mut watcher = session.watch().unwrap();
+
+        let window = desktop.spawn("window", ORIGIN, SIZE);
+        let opened = match watcher.next_request().unwrap() {
+            WindowEvent::Opened(opened) => opened,
+            event => panic!("unexpected event {event:?}"),
+        };
+        assert_eq!(opened.title().unwrap(), "window");
+
+        desktop.close(&window.handle()).unwrap();
+        assert!(matches!(
+            watcher.next_request(),
+            Ok(WindowEvent::Closed(handle)) if handle == opened.handle()
+        ));
+
+        let editor = desktop.launch("editor", "/usr/bin/editor");
+        let launched = match watcher.next_request().unwrap() {
+            WindowEvent::ApplicationLaunched(launched) => launched,
+            event => panic!("unexpected event {event:?}"),
+        };
+        assert_eq!(launched.name().unwrap(), "editor");
+
+        desktop.quit(&editor).unwrap();
+        assert!(matches!(
+            watcher.next_request(),
+            Ok(WindowEvent::ApplicationTerminated(pid)) if pid == editor.pid()
+        ));
+
+        let window = desktop.spawn("window", ORIGIN, SIZE);
+        window.focus().unwrap();
+        let batch = watcher.next_batch(Duration::from_millis(200)).unwrap();
+        assert!(matches!(
+            batch
+                .iter()
+                .map(|envelope| &envelope.event)
+                .collect::<Vec<_>>()[..],
+            [WindowEvent::Opened(_), WindowEvent::Focused(_)]
+        ));
+        assert_eq!(batch[0].source, Backend::Custom("remote"));
+    }
+}
@@ -0,0 +1,384 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufReader},
+    net::TcpListener,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+
+use crate::{
+    Application, FrameKind, Insets, LogicalPosition, LogicalSize, PropertyMask, Rect, Session,
+    Window, WindowError, WindowEvent, WindowHandle, WindowProperties,
+};
+
+use super::{
+    read_message, write_message, ApplicationOperation, Error, EventKind, Operation, Reply, Request,
+    Response, Stream, Value,
+};
+
+/// Serves the windows of a [`Session`] to [`RemoteBackend`](super::RemoteBackend)s.
+///
+/// Each connection is handled on its own thread.
+#[derive(Debug, Clone)]
+pub struct Server {
+    session: Session,
+    registry: Arc<Mutex<Registry>>,
+}
+
+impl Server {
+    /// Creates a server for the windows of the session.
+    pub fn new(session: Session) -> Server {
+        Server {
+            session,
+            registry: Arc::default(),
+        }
+    }
+
+    /// Accepts connections from the listener until it fails.
+    pub fn serve_tcp(&self, listener: &TcpListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            // Requests are small and wait on their reply, so don't let them sit in a buffer.
+            stream.set_nodelay(true)?;
+            self.spawn(Stream::Tcp(stream));
+        }
+
+        Ok(())
+    }
+
+    /// Accepts connections from the listener until it fails.
+    #[cfg(unix)]
+    pub fn serve_unix(&self, listener: &UnixListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            self.spawn(Stream::Unix(stream?));
+        }
+
+        Ok(())
+    }
+
+    fn spawn(&self, stream: Stream) {
+        let server = self.clone();
+        // A failed connection only affects its own client, which will notice it was closed.
+        thread::spawn(move || server.handle(stream));
+    }
+
+    fn handle(&self, stream: Stream) -> io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = stream;
+        while let Some(request) = read_message(&mut reader)? {
+            match request {
+                Request::Watch => return self.watch(writer),
+                request => write_message(&mut writer, &self.respond(request))?,
+            }
+        }
+
+        Ok(())
+    }
+
+    // The connection only streams events from now on, until the client disconnects.
+    fn watch(&self, mut writer: Stream) -> io::Result<()> {
+        let mut watcher = match self.session.watch() {
+            Ok(watcher) => {
+                write_message(&mut writer, &Response::Ok(Reply::Done))?;
+                watcher
+            }
+            Err(err) => return write_message(&mut writer, &Response::Err(err.into())),
+        };
+
+        loop {
+            let response = watcher
+                .next_request()
+                .map(|event| self.event(event))
+                .map_err(Error::from);
+            write_message(&mut writer, &response)?;
+        }
+    }
+
+    fn event(&self, event: WindowEvent) -> Reply {
+        let kind = EventKind::of(&event);
+        let (old, new) = match &event {
+            WindowEvent::Moved { old, new, .. } => (old.map(Value::from), new.map(Value::from)),
+            WindowEvent::Resized { old, new, .. } => (old.map(Value::from), new.map(Value::from)),
+            WindowEvent::Renamed {
+                old_title,
+                new_title,
+                ..
+            } => (
+                old_title.clone().map(Value::Title),
+                new_title.clone().map(Value::Title),
+            ),
+            _ => (None, None),
+        };
+        let mut registry = self.registry.lock().unwrap();
+        let id = match event {
+            WindowEvent::Closed(handle) => registry.unregister(&handle),
+            WindowEvent::Opened(window)
+            | WindowEvent::Hidden(window)
+            | WindowEvent::Shown(window)
+            | WindowEvent::Minimized(window)
+            | WindowEvent::Unminimized(window)
+            | WindowEvent::Maximized(window)
+            | WindowEvent::Unmaximized(window)
+            | WindowEvent::Fullscreened(window)
+            | WindowEvent::Unfullscreened(window)
+            | WindowEvent::Focused(window)
+            | WindowEvent::Unfocused(window)
+            | WindowEvent::Moved { window, .. }
+            | WindowEvent::Resized { window, .. }
+            | WindowEvent::MoveResizeStarted(window)
+            | WindowEvent::MoveResizeEnded(window)
+            | WindowEvent::Renamed { window, .. } => registry.register(window),
+            WindowEvent::ApplicationTerminated(pid) => pid.into(),
+            WindowEvent::ApplicationLaunched(application)
+            | WindowEvent::ApplicationActivated(application)
+            | WindowEvent::ApplicationHidden(application)
+            | WindowEvent::ApplicationShown(application) => {
+                registry.register_application(application).into()
+            }
+        };
+
+        Reply::Event { kind, id, old, new }
+    }
+
+    fn respond(&self, request: Request) -> Response {
+        match request {
+            Request::IterWindows => {
+                let mut registry = self.registry.lock().unwrap();
+                let windows = self
+                    .session
+                    .iter_windows()
+                    .map(|result| result.map(|window| registry.register(window)))
+                    .map(|result| result.map_err(Error::from))
+                    .collect();
+                Ok(Reply::Windows(windows))
+            }
+            Request::IterApplications => {
+                let mut registry = self.registry.lock().unwrap();
+                let applications = self
+                    .session
+                    .iter_applications()
+                    .map(|result| {
+                        result.map(|application| registry.register_application(application))
+                    })
+                    .map(|result| result.map_err(Error::from))
+                    .collect();
+                Ok(Reply::Applications(applications))
+            }
+            Request::FocusedWindow => {
+                let focused = self.session.focused_window()?;
+                let mut registry = self.registry.lock().unwrap();
+                Ok(Reply::Window(
+                    focused.map(|window| registry.register(window)),
+                ))
+            }
+            Request::Watch => unreachable!("watch requests switch the connection to events"),
+            Request::Window { id, operation } => {
+                let window = self.registry.lock().unwrap().window(id)?;
+                Ok(operate(&window, operation, &self.registry)?)
+            }
+            Request::Application { pid, operation } => {
+                let application = self.registry.lock().unwrap().application(pid)?;
+                Ok(operate_application(
+                    &application,
+                    operation,
+                    &self.registry,
+                )?)
+            }
+        }
+    }
+}
+
+fn operate(
+    window: &Window,
+    operation: Operation,
+    registry: &Mutex<Registry>,
+) -> Result<Reply, WindowError> {
+    Ok(match operation {
+        Operation::Title => Reply::Title(window.title()?),
+        Operation::Size => {
+            let LogicalSize { width, height } = window.size()?;
+            Reply::Size { width, height }
+        }
+        Operation::Position => {
+            let LogicalPosition { x, y } = window.position()?;
+            Reply::Position { x, y }
+        }
+        Operation::IsFocused => Reply::Bool(window.is_focused()?),
+        Operation::IsFullscreen => Reply::Bool(window.is_fullscreen()?),
+        Operation::IsMinimized => Reply::Bool(window.is_minimized()?),
+        Operation::IsHidden => Reply::Bool(window.is_hidden()?),
+        Operation::ScaleFactor => Reply::ScaleFactor(window.scale_factor()?),
+        Operation::Properties { mask } => {
+            properties(window.properties(PropertyMask::from_bits_truncate(mask))?)
+        }
+        Operation::Resize { width, height } => {
+            window.resize(LogicalSize { width, height })?;
+            Reply::Done
+        }
+        Operation::Reposition { x, y } => {
+            window.reposition(LogicalPosition { x, y })?;
+            Reply::Done
+        }
+        Operation::Frame => frame(window.outer_frame()?),
+        Operation::SetFrame {
+            x,
+            y,
+            width,
+            height,
+        } => frame(window.set_frame(
+            Rect {
+                position: LogicalPosition { x, y },
+                size: LogicalSize { width, height },
+            },
+            FrameKind::Outer,
+        )?),
+        Operation::DecorationInsets => {
+            let Insets {
+                top,
+                right,
+                bottom,
+                left,
+            } = window.decoration_insets()?;
+            Reply::Insets {
+                top,
+                right,
+                bottom,
+                left,
+            }
+        }
+        Operation::Focus => done(window.focus())?,
+        Operation::Fullscreen => done(window.fullscreen())?,
+        Operation::Unfullscreen => done(window.unfullscreen())?,
+        Operation::Maximize => done(window.maximize())?,
+        Operation::Minimize => done(window.minimize())?,
+        Operation::Unminimize => done(window.unminimize())?,
+        Operation::Show => done(window.show())?,
+        Operation::Hide => done(window.hide())?,
+        Operation::BringToFront => done(window.bring_to_front())?,
+        Operation::Close => done(window.close())?,
+        Operation::Kill => done(window.kill())?,
+        Operation::Application => {
+            let application = window.application()?;
+            Reply::Application(registry.lock().unwrap().register_application(application))
+        }
+    })
+}
+
+fn operate_application(
+    application: &Application,
+    operation: ApplicationOperation,
+    registry: &Mutex<Registry>,
+) -> Result<Reply, WindowError> {
+    Ok(match operation {
+        ApplicationOperation::Name => Reply::Name(application.name()?),
+        ApplicationOperation::ExecutablePath => Reply::Path(application.executable_path()?),
+        ApplicationOperation::IterWindows => {
+            // Don't hold the registry while the backend is queried.
+            let windows = application.iter_windows().collect::<Vec<_>>();
+            let mut registry = registry.lock().unwrap();
+            Reply::Windows(
+                windows
+                    .into_iter()
+                    .map(|result| result.map(|window| registry.register(window)))
+                    .map(|result| result.map_err(Error::from))
+                    .collect(),
+            )
+        }
+        ApplicationOperation::IsFrontmost => Reply::Bool(application.is_frontmost()?),
+        ApplicationOperation::Activate => done(application.activate())?,
+    })
+}
+
+fn done(result: Result<(), WindowError>) -> Result<Reply, WindowError> {
+    result.map(|_| Reply::Done)
+}
+
+fn frame(frame: Rect) -> Reply {
+    Reply::Frame {
+        x: frame.position.x,
+        y: frame.position.y,
+        width: frame.size.width,
+        height: frame.size.height,
+    }
+}
+
+fn properties(properties: WindowProperties) -> Reply {
+    Reply::Properties {
+        title: properties.title,
+        width: properties.size.map(|size| size.width),
+        height: properties.size.map(|size| size.height),
+        x: properties.position.map(|position| position.x),
+        y: properties.position.map(|position| position.y),
+        is_focused: properties.is_focused,
+        is_fullscreen: properties.is_fullscreen,
+        is_minimized: properties.is_minimized,
+        is_hidden: properties.is_hidden,
+    }
+}
+
+/// Assigns ids to the windows handed out to clients, since handles can't be sent over the wire.
+#[derive(Debug, Default)]
+struct Registry {
+    next_id: u64,
+    ids: HashMap<WindowHandle, u64>,
+    windows: HashMap<u64, Window>,
+    // Applications are already identified by their pid, but the server has to hold on to them.
+    applications: HashMap<u32, Application>,
+}
+
+impl Registry {
+    fn register(&mut self, window: Window) -> u64 {
+        let handle = window.handle();
+        if let Some(&id) = self.ids.get(&handle) {
+            // The id is stale if the handle was reused by a new window.
+            if self.windows.contains_key(&id) {
+                return id;
+            }
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.ids.insert(handle, id);
+        self.windows.insert(id, window);
+        id
+    }
+
+    // The id is kept around, since every watcher receives the same closed event.
+    fn unregister(&mut self, handle: &WindowHandle) -> u64 {
+        match self.ids.get(handle) {
+            Some(&id) => {
+                self.windows.remove(&id);
+                id
+            }
+            // The client never saw this window, so any unused id will do.
+            None => {
+                self.next_id += 1;
+                self.next_id - 1
+            }
+        }
+    }
+
+    fn window(&self, id: u64) -> Result<Window, WindowError> {
+        self.windows
+            .get(&id)
+            .cloned()
+            .ok_or(WindowError::InvalidHandle)
+    }
+
+    // A reused pid replaces the application that exited.
+    fn register_application(&mut self, application: Application) -> u32 {
+        let pid = application.pid();
+        self.applications.insert(pid, application);
+        pid
+    }
+
+    fn application(&self, pid: u32) -> Result<Application, WindowError> {
+        self.applications
+            .get(&pid)
+            .cloned()
+            .ok_or(WindowError::InvalidHandle)
+    }
+}
@@ -0,0 +1,340 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use wayland_client::{
+    event_created_child,
+    globals::{registry_queue_init, GlobalListContents},
+    protocol::{wl_callback::WlCallback, wl_registry::WlRegistry},
+    Connection, Dispatch, Proxy, QueueHandle,
+};
+use wayland_protocols::ext::foreign_toplevel_list::v1::client::{
+    ext_foreign_toplevel_handle_v1::{self, ExtForeignToplevelHandleV1},
+    ext_foreign_toplevel_list_v1::{self, ExtForeignToplevelListV1},
+};
+
+use crate::{protocol, WindowError, WindowEvent};
+
+use super::wayland::{self, disconnected, SyncDone};
+
+pub use window::Window;
+
+mod window;
+
+// The identifier is stable and unique for the lifetime of the compositor, unlike the proxy which is
+// specific to our connection.
+pub type WindowHandle = String;
+
+type Event = Result<WindowEvent, WindowError>;
+
+// Same as the wlr backend, properties are sent as events and cached here by a background thread.
+static SESSION: Mutex<Option<Arc<Session>>> = Mutex::new(None);
+
+pub(crate) fn session() -> Result<Arc<Session>, WindowError> {
+    let mut session = SESSION.lock().unwrap();
+    match &*session {
+        Some(session) => Ok(session.clone()),
+        None => {
+            let new_session = Arc::new(Session::new()?);
+            *session = Some(new_session.clone());
+            Ok(new_session)
+        }
+    }
+}
+
+/// The properties of a toplevel, as last reported by the compositor.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Properties {
+    pub identifier: String,
+    pub title: String,
+    pub app_id: String,
+}
+
+#[derive(Debug, Default)]
+struct Toplevel {
+    current: Properties,
+    // Properties are double-buffered, they only apply once the `done` event is received.
+    pending: Properties,
+    // Whether or not the initial `done` event was received.
+    initialized: bool,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    toplevels: HashMap<ExtForeignToplevelHandleV1, Toplevel>,
+    subscribers: Vec<Sender<Event>>,
+    disconnected: bool,
+}
+
+impl Shared {
+    fn emit(&mut self, event: impl Fn() -> WindowEvent) {
+        // If the receiver is gone, then the watcher was dropped and we can remove it.
+        self.subscribers
+            .retain(|subscriber| subscriber.send(Ok(event())).is_ok());
+    }
+
+    fn initialized(&self) -> impl Iterator<Item = &Properties> {
+        self.toplevels
+            .values()
+            .filter(|toplevel| toplevel.initialized)
+            .map(|toplevel| &toplevel.current)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Session {
+    connection: Connection,
+    queue_handle: QueueHandle<State>,
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl Session {
+    fn new() -> Result<Session, WindowError> {
+        let connection = Connection::connect_to_env()?;
+        let (globals, mut queue) = registry_queue_init::<State>(&connection)?;
+        let queue_handle = queue.handle();
+
+        // The list immediately advertises every existing toplevel once bound.
+        let _list: ExtForeignToplevelListV1 = globals.bind(&queue_handle, 1..=1, ())?;
+
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let mut state = State {
+            shared: shared.clone(),
+        };
+        queue.roundtrip(&mut state)?;
+
+        thread::spawn(move || loop {
+            if let Err(err) = queue.blocking_dispatch(&mut state) {
+                let mut shared = state.shared.lock().unwrap();
+                shared.disconnected = true;
+                for subscriber in shared.subscribers.drain(..) {
+                    let _ = subscriber.send(Err(disconnected(&err.to_string())));
+                }
+                break;
+            }
+        });
+
+        Ok(Session {
+            connection,
+            queue_handle,
+            shared,
+        })
+    }
+
+    pub fn sync(&self) -> Result<(), WindowError> {
+        wayland::sync(&self.connection, &self.queue_handle)
+    }
+
+    pub fn properties(&self, identifier: &str) -> Result<Properties, WindowError> {
+        self.sync()?;
+        self.shared
+            .lock()
+            .unwrap()
+            .initialized()
+            .find(|properties| properties.identifier == identifier)
+            .cloned()
+            .ok_or(WindowError::InvalidHandle)
+    }
+
+    pub fn identifiers(&self) -> Result<Vec<String>, WindowError> {
+        self.sync()?;
+        Ok(self
+            .shared
+            .lock()
+            .unwrap()
+            .initialized()
+            .map(|properties| properties.identifier.clone())
+            .collect())
+    }
+
+    fn subscribe(&self) -> Result<Receiver<Event>, WindowError> {
+        let mut shared = self.shared.lock().unwrap();
+        if shared.disconnected {
+            return Err(disconnected("the connection was closed"));
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        shared.subscribers.push(sender);
+        Ok(receiver)
+    }
+}
+
+#[derive(Debug)]
+pub struct Watcher {
+    receiver: Receiver<Event>,
+    // Keep the session alive for as long as we're watching.
+    _session: Arc<Session>,
+}
+
+impl Watcher {
+    pub fn new() -> Result<Watcher, WindowError> {
+        let session = session()?;
+        Ok(Watcher {
+            receiver: session.subscribe()?,
+            _session: session,
+        })
+    }
+
+    pub fn next_request(&mut self) -> Result<WindowEvent, WindowError> {
+        self.receiver
+            .recv()
+            .unwrap_or_else(|_| Err(disconnected("the connection was closed")))
+    }
+
+    pub fn next_request_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<WindowEvent>, WindowError> {
+        match self.receiver.recv_timeout(timeout) {
+            Ok(event) => event.map(Some),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err(disconnected("the connection was closed")),
+        }
+    }
+}
+
+pub fn iter_windows() -> impl Iterator<Item = Result<Window, WindowError>> {
+    let windows = match session().and_then(|session| session.identifiers()) {
+        Ok(identifiers) => identifiers
+            .into_iter()
+            .map(|identifier| Ok(Window::new(identifier)))
+            .collect(),
+        Err(err) => vec![Err(err)],
+    };
+    windows.into_iter()
+}
+
+// The protocol doesn't report which toplevel is activated.
+pub fn focused_window() -> Result<Option<Window>, WindowError> {
+    Err(WindowError::Unsupported)
+}
+
+#[derive(Debug)]
+struct State {
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for State {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WlRegistry,
+        _event: <WlRegistry as Proxy>::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<WlCallback, SyncDone> for State {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WlCallback,
+        _event: <WlCallback as Proxy>::Event,
+        data: &SyncDone,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        wayland::sync_done(data);
+    }
+}
+
+impl Dispatch<ExtForeignToplevelListV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        _proxy: &ExtForeignToplevelListV1,
+        event: ext_foreign_toplevel_list_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        if let ext_foreign_toplevel_list_v1::Event::Toplevel { toplevel } = event {
+            state
+                .shared
+                .lock()
+                .unwrap()
+                .toplevels
+                .insert(toplevel, Toplevel::default());
+        }
+    }
+
+    event_created_child!(State, ExtForeignToplevelListV1, [
+        ext_foreign_toplevel_list_v1::EVT_TOPLEVEL_OPCODE => (ExtForeignToplevelHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ExtForeignToplevelHandleV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        proxy: &ExtForeignToplevelHandleV1,
+        event: ext_foreign_toplevel_handle_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        let mut shared = state.shared.lock().unwrap();
+
+        match event {
+            ext_foreign_toplevel_handle_v1::Event::Closed => {
+                if let Some(toplevel) = shared.toplevels.remove(proxy) {
+                    if toplevel.initialized {
+                        let identifier = toplevel.current.identifier;
+                        shared.emit(|| {
+                            WindowEvent::Closed(protocol::WindowHandle::from_sys(
+                                identifier.clone().into(),
+                            ))
+                        });
+                    }
+                }
+                proxy.destroy();
+                return;
+            }
+            ext_foreign_toplevel_handle_v1::Event::Done => {}
+            event => {
+                if let Some(toplevel) = shared.toplevels.get_mut(proxy) {
+                    let pending = &mut toplevel.pending;
+                    match event {
+                        ext_foreign_toplevel_handle_v1::Event::Identifier { identifier } => {
+                            pending.identifier = identifier
+                        }
+                        ext_foreign_toplevel_handle_v1::Event::Title { title } => {
+                            pending.title = title
+                        }
+                        ext_foreign_toplevel_handle_v1::Event::AppId { app_id } => {
+                            pending.app_id = app_id
+                        }
+                        _ => {}
+                    }
+                }
+                return;
+            }
+        }
+
+        // Apply the pending properties now that we received the `done` event.
+        let Some(toplevel) = shared.toplevels.get_mut(proxy) else {
+            return;
+        };
+        let previous = toplevel.current.clone();
+        let current = toplevel.pending.clone();
+        let initialized = toplevel.initialized;
+        toplevel.current = current.clone();
+        toplevel.initialized = true;
+
+        let window = || protocol::Window::from_sys(Window::new(current.identifier.clone()).into());
+        if !initialized {
+            shared.emit(|| WindowEvent::Opened(window()));
+        } else if previous.title != current.title {
+            shared.emit(|| WindowEvent::Renamed {
+                window: window(),
+                old_title: Some(previous.title.clone()),
+                new_title: Some(current.title.clone()),
+            });
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use crate::{Insets, LogicalPosition, LogicalSize, Rect, WindowError};
+
+use super::{session, WindowHandle};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Window {
+    identifier: String,
+}
+
+// NOTE: the protocol only lists toplevels, it can't change them or report anything beyond the title
+//       and app id.
+impl Window {
+    pub(crate) fn new(identifier: String) -> Window {
+        Window { identifier }
+    }
+
+    pub fn handle(&self) -> WindowHandle {
+        self.identifier.clone()
+    }
+
+    pub fn title(&self) -> Result<String, WindowError> {
+        Ok(session()?.properties(&self.identifier)?.title)
+    }
+
+    pub fn size(&self) -> Result<LogicalSize, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn position(&self) -> Result<LogicalPosition, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn scale_factor(&self) -> Result<f64, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn is_focused(&self) -> Result<bool, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn is_fullscreen(&self) -> Result<bool, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn is_minimized(&self) -> Result<bool, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn is_hidden(&self) -> Result<bool, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn resize(&self, _size: LogicalSize) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn reposition(&self, _position: LogicalPosition) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn frame(&self) -> Result<Rect, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn set_frame(&self, _frame: Rect) -> Result<Rect, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn decoration_insets(&self) -> Result<Insets, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn focus(&self) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn fullscreen(&self) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn unfullscreen(&self) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn maximize(&self) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn minimize(&self) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn unminimize(&self) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn show(&self) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn hide(&self) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn bring_to_front(&self) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn pid(&self) -> Result<u32, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    // The protocol is read-only.
+    pub fn close(&self) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+}
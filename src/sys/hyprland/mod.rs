@@ -0,0 +1,649 @@
+use std::{
+    env,
+    io::{self, BufRead, BufReader, Read, Write},
+    os::{fd::AsFd, unix::net::UnixStream},
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Deserializer};
+
+use crate::{
+    protocol::{self, transaction::Change},
+    LogicalPosition, LogicalSize, PropertyMask, WindowError, WindowEvent, WindowProperties,
+};
+
+pub use window::Window;
+
+mod window;
+
+/// The address of a window, which is unique for the lifetime of the window.
+pub type WindowHandle = u64;
+
+// Hyprland has no concept of minimizing, so minimized windows are moved to this special workspace,
+// similar to how most minimize plugins and scripts do it.
+const MINIMIZED_WORKSPACE: &str = "special:minimized";
+
+static INSTANCE: OnceLock<Instance> = OnceLock::new();
+
+pub(crate) fn instance() -> Result<&'static Instance, WindowError> {
+    if let Some(instance) = INSTANCE.get() {
+        return Ok(instance);
+    }
+
+    let instance = Instance::from_env()?;
+    // Requests open a new connection each time, so check that the socket is reachable up front.
+    UnixStream::connect(instance.request_socket()).map_err(WindowError::OsError)?;
+    Ok(INSTANCE.get_or_init(|| instance))
+}
+
+/// A window as reported by `j/clients`, only the fields we use are deserialized.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct Client {
+    #[serde(deserialize_with = "deserialize_address")]
+    pub address: WindowHandle,
+    pub mapped: bool,
+    pub hidden: bool,
+    pub at: [i32; 2],
+    pub size: [i32; 2],
+    pub workspace: Workspace,
+    pub fullscreen: Fullscreen,
+    pub title: String,
+    // -1 if the process is unknown, older versions don't report it at all.
+    #[serde(default)]
+    pub pid: Option<i32>,
+    // -1 if the window isn't on a monitor.
+    #[serde(default)]
+    pub monitor: Option<i64>,
+}
+
+impl Client {
+    pub fn is_minimized(&self) -> bool {
+        self.workspace.name == MINIMIZED_WORKSPACE
+    }
+
+    // Windows in an inactive tab of a group are also hidden.
+    pub fn is_hidden(&self) -> bool {
+        self.hidden || !self.mapped || self.is_minimized()
+    }
+
+    pub fn properties(&self, active: Option<WindowHandle>, mask: PropertyMask) -> WindowProperties {
+        WindowProperties {
+            title: mask.select(PropertyMask::TITLE, || self.title.clone()),
+            size: mask.select(PropertyMask::SIZE, || LogicalSize {
+                width: self.size[0] as f64,
+                height: self.size[1] as f64,
+            }),
+            position: mask.select(PropertyMask::POSITION, || LogicalPosition {
+                x: self.at[0] as f64,
+                y: self.at[1] as f64,
+            }),
+            is_focused: mask.select(PropertyMask::FOCUSED, || active == Some(self.address)),
+            is_fullscreen: mask
+                .select(PropertyMask::FULLSCREEN, || self.fullscreen.is_fullscreen()),
+            is_minimized: mask.select(PropertyMask::MINIMIZED, || self.is_minimized()),
+            is_hidden: mask.select(PropertyMask::HIDDEN, || self.is_hidden()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct Workspace {
+    pub id: i64,
+    pub name: String,
+}
+
+/// Older versions report whether the window is fullscreen, newer versions report the mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum Fullscreen {
+    Enabled(bool),
+    // 0 is none, 1 is maximized, 2 is fullscreen, and 3 is both.
+    Mode(u8),
+}
+
+impl Fullscreen {
+    pub fn is_fullscreen(self) -> bool {
+        match self {
+            Fullscreen::Enabled(enabled) => enabled,
+            Fullscreen::Mode(mode) => mode & 2 != 0,
+        }
+    }
+
+    pub fn is_maximized(self) -> bool {
+        match self {
+            // Older versions only report the mode through a separate field.
+            Fullscreen::Enabled(_) => false,
+            Fullscreen::Mode(mode) => mode & 1 != 0,
+        }
+    }
+}
+
+/// A monitor as reported by `j/monitors`, only the fields we use are deserialized.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct Monitor {
+    pub id: i64,
+    pub scale: f64,
+}
+
+// `j/activewindow` returns an empty object if there is no active window.
+#[derive(Debug, Deserialize)]
+struct ActiveWindow {
+    #[serde(default, deserialize_with = "deserialize_optional_address")]
+    address: Option<WindowHandle>,
+}
+
+/// The sockets of a running Hyprland instance.
+#[derive(Debug, Clone)]
+pub(crate) struct Instance {
+    dir: PathBuf,
+}
+
+impl Instance {
+    fn from_env() -> Result<Instance, WindowError> {
+        let signature =
+            env::var_os("HYPRLAND_INSTANCE_SIGNATURE").ok_or(WindowError::Unsupported)?;
+        // Hyprland moved its sockets from /tmp to the runtime directory in v0.40.
+        let dir = env::var_os("XDG_RUNTIME_DIR")
+            .map(|dir| PathBuf::from(dir).join("hypr").join(&signature))
+            .filter(|dir| dir.exists())
+            .unwrap_or_else(|| Path::new("/tmp/hypr").join(&signature));
+        Ok(Instance { dir })
+    }
+
+    fn request_socket(&self) -> PathBuf {
+        self.dir.join(".socket.sock")
+    }
+
+    fn event_socket(&self) -> PathBuf {
+        self.dir.join(".socket2.sock")
+    }
+
+    // Hyprland handles a single request per connection, and closes it after replying.
+    fn request(&self, request: &str) -> Result<String, WindowError> {
+        let mut stream =
+            UnixStream::connect(self.request_socket()).map_err(WindowError::OsError)?;
+        stream
+            .write_all(request.as_bytes())
+            .map_err(WindowError::OsError)?;
+
+        let mut reply = String::new();
+        stream
+            .read_to_string(&mut reply)
+            .map_err(WindowError::OsError)?;
+        Ok(reply)
+    }
+
+    fn request_json<T: DeserializeOwned>(&self, request: &str) -> Result<T, WindowError> {
+        let reply = self.request(&format!("j/{request}"))?;
+        serde_json::from_str(&reply).map_err(invalid_reply)
+    }
+
+    pub fn clients(&self) -> Result<Vec<Client>, WindowError> {
+        self.request_json("clients")
+    }
+
+    pub fn client(&self, address: WindowHandle) -> Result<Client, WindowError> {
+        self.clients()?
+            .into_iter()
+            .find(|client| client.address == address)
+            .ok_or(WindowError::InvalidHandle)
+    }
+
+    pub fn monitors(&self) -> Result<Vec<Monitor>, WindowError> {
+        self.request_json("monitors")
+    }
+
+    pub fn active_window(&self) -> Result<Option<WindowHandle>, WindowError> {
+        Ok(self.request_json::<ActiveWindow>("activewindow")?.address)
+    }
+
+    pub fn active_workspace(&self) -> Result<Workspace, WindowError> {
+        self.request_json("activeworkspace")
+    }
+
+    pub fn dispatch(&self, dispatcher: &str) -> Result<(), WindowError> {
+        let reply = self.request(&format!("dispatch {dispatcher}"))?;
+        dispatch_result(dispatcher, Some(reply.trim()))
+    }
+
+    // Batched dispatchers are handled within the same request, so they're applied together. The
+    // result of each dispatcher is returned in order.
+    pub fn dispatch_batch(
+        &self,
+        dispatchers: &[String],
+    ) -> Result<Vec<Result<(), WindowError>>, WindowError> {
+        let batch = dispatchers
+            .iter()
+            .map(|dispatcher| format!("dispatch {dispatcher}"))
+            .collect::<Vec<_>>()
+            .join(";");
+        let reply = self.request(&format!("[[BATCH]]{batch}"))?;
+        // Each command replies on its own line.
+        let mut replies = reply.lines().filter(|line| !line.is_empty());
+        Ok(dispatchers
+            .iter()
+            .map(|dispatcher| dispatch_result(dispatcher, replies.next()))
+            .collect())
+    }
+}
+
+fn dispatch_result(dispatcher: &str, reply: Option<&str>) -> Result<(), WindowError> {
+    match reply {
+        Some("ok") => Ok(()),
+        Some(reply) => Err(WindowError::OsError(io::Error::other(format!(
+            "Hyprland failed to dispatch `{dispatcher}`: {reply}"
+        )))),
+        None => Err(WindowError::OsError(io::Error::other(format!(
+            "Hyprland didn't reply to `{dispatcher}`"
+        )))),
+    }
+}
+
+#[derive(Debug)]
+pub struct Watcher {
+    events: BufReader<UnixStream>,
+    // Fullscreen events only report the new state, they apply to the active window.
+    active: Option<WindowHandle>,
+}
+
+impl Watcher {
+    pub fn new() -> Result<Watcher, WindowError> {
+        Watcher::connect(instance()?)
+    }
+
+    fn connect(instance: &Instance) -> Result<Watcher, WindowError> {
+        let events = UnixStream::connect(instance.event_socket()).map_err(WindowError::OsError)?;
+        Ok(Watcher {
+            events: BufReader::new(events),
+            active: instance.active_window()?,
+        })
+    }
+
+    pub fn next_request(&mut self) -> Result<WindowEvent, WindowError> {
+        loop {
+            if let Some(event) = self.read_event()? {
+                return Ok(event);
+            }
+        }
+    }
+
+    pub fn next_request_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<WindowEvent>, WindowError> {
+        let deadline = Instant::now() + timeout;
+        // Lines are written whole, so once one starts arriving it can be read without waiting.
+        while !self.events.buffer().is_empty()
+            || super::wait_readable(self.events.get_ref().as_fd(), deadline)?
+        {
+            if let Some(event) = self.read_event()? {
+                return Ok(Some(event));
+            }
+        }
+
+        Ok(None)
+    }
+
+    // Reads the next line, which may not translate to any event.
+    fn read_event(&mut self) -> Result<Option<WindowEvent>, WindowError> {
+        let mut line = String::new();
+        if self
+            .events
+            .read_line(&mut line)
+            .map_err(WindowError::OsError)?
+            == 0
+        {
+            return Err(WindowError::OsError(io::Error::new(
+                io::ErrorKind::ConnectionAborted,
+                "Hyprland closed the event socket",
+            )));
+        }
+
+        // Each event is a line in the format `EVENT>>DATA`, where DATA is comma separated.
+        let Some((event, data)) = line.trim_end().split_once(">>") else {
+            return Ok(None);
+        };
+        let mut args = data.split(',');
+        let address = args.next().and_then(parse_address);
+        let window =
+            |address: WindowHandle| protocol::Window::from_sys(Window::new(address).into());
+
+        Ok(match (event, address) {
+            ("openwindow", Some(address)) => Some(WindowEvent::Opened(window(address))),
+            ("closewindow", Some(address)) => Some(WindowEvent::Closed(
+                protocol::WindowHandle::from_sys(address.into()),
+            )),
+            ("activewindowv2", address) => {
+                self.active = address;
+                address.map(|address| WindowEvent::Focused(window(address)))
+            }
+            // `windowtitle` is sent alongside this without the title, so only handle this one. The
+            // title itself may contain commas.
+            ("windowtitlev2", Some(address)) => Some(WindowEvent::Renamed {
+                window: window(address),
+                old_title: None,
+                new_title: data.split_once(',').map(|(_, title)| title.to_owned()),
+            }),
+            // Sent when a window is moved to another workspace, including the minimized one.
+            ("movewindow", Some(address)) => Some(WindowEvent::Moved {
+                window: window(address),
+                old: None,
+                new: None,
+            }),
+            // Only applies to the active window, and doesn't tell maximizing and fullscreening
+            // apart.
+            ("fullscreen", _) => self.active.map(|active| match data {
+                "1" => WindowEvent::Fullscreened(window(active)),
+                _ => WindowEvent::Unfullscreened(window(active)),
+            }),
+            _ => None,
+        })
+    }
+}
+
+// Every dispatcher is sent in a single batch, which Hyprland applies before rendering again.
+pub fn commit(changes: &[(&Window, Change)]) -> Result<Vec<Result<(), WindowError>>, WindowError> {
+    let dispatchers: Vec<_> = changes
+        .iter()
+        .map(|(window, change)| window.dispatchers(*change))
+        .collect();
+    let batch: Vec<String> = dispatchers.iter().flatten().cloned().collect();
+    let mut results = instance()?.dispatch_batch(&batch)?.into_iter();
+
+    Ok(dispatchers
+        .iter()
+        .map(|dispatchers| {
+            // Every result is taken before failing, so they stay aligned with the next change.
+            let results: Vec<_> = results.by_ref().take(dispatchers.len()).collect();
+            results.into_iter().collect()
+        })
+        .collect())
+}
+
+// Every window is read from a single client list, rather than requesting it for each property.
+pub fn properties(
+    windows: &[&Window],
+    mask: PropertyMask,
+) -> Result<Vec<Result<WindowProperties, WindowError>>, WindowError> {
+    let instance = instance()?;
+    let clients = instance.clients()?;
+    let active = if mask.contains(PropertyMask::FOCUSED) {
+        instance.active_window()?
+    } else {
+        None
+    };
+
+    Ok(windows
+        .iter()
+        .map(|window| {
+            clients
+                .iter()
+                .find(|client| client.address == window.handle())
+                .map(|client| client.properties(active, mask))
+                .ok_or(WindowError::InvalidHandle)
+        })
+        .collect())
+}
+
+pub fn iter_windows() -> impl Iterator<Item = Result<Window, WindowError>> {
+    let windows = match instance().and_then(|instance| instance.clients()) {
+        Ok(clients) => clients
+            .into_iter()
+            // Unmapped clients haven't been shown yet (or are being destroyed).
+            .filter(|client| client.mapped)
+            .map(|client| Ok(Window::new(client.address)))
+            .collect(),
+        Err(err) => vec![Err(err)],
+    };
+    windows.into_iter()
+}
+
+pub fn focused_window() -> Result<Option<Window>, WindowError> {
+    Ok(instance()?.active_window()?.map(Window::new))
+}
+
+// Addresses are prefixed with `0x` in replies, but not in events.
+fn parse_address(address: &str) -> Option<WindowHandle> {
+    let address = address.trim();
+    WindowHandle::from_str_radix(address.strip_prefix("0x").unwrap_or(address), 16).ok()
+}
+
+fn deserialize_address<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<WindowHandle, D::Error> {
+    let address = String::deserialize(deserializer)?;
+    parse_address(&address)
+        .ok_or_else(|| serde::de::Error::custom(format!("invalid address `{address}`")))
+}
+
+fn deserialize_optional_address<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<WindowHandle>, D::Error> {
+    deserialize_address(deserializer).map(Some)
+}
+
+fn invalid_reply(err: serde_json::Error) -> WindowError {
+    WindowError::OsError(io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        os::unix::net::UnixListener,
+        process,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            mpsc::{self, Receiver},
+        },
+        thread,
+    };
+
+    use super::*;
+
+    // Recorded from Hyprland v0.45, trimmed down.
+    const CLIENTS: &str = r#"[
+        {
+            "address": "0x55d0f8a3c1e0", "mapped": true, "hidden": false,
+            "at": [10, 50], "size": [1260, 700],
+            "workspace": {"id": 1, "name": "1"}, "floating": false, "pseudo": false,
+            "monitor": 0, "class": "kitty", "title": "~", "initialClass": "kitty", "initialTitle": "kitty",
+            "pid": 1412, "xwayland": false, "pinned": false, "fullscreen": 2, "fullscreenClient": 0,
+            "grouped": [], "tags": [], "swallowing": "0x0", "focusHistoryID": 0
+        },
+        {
+            "address": "0x55d0f8b71a20", "mapped": true, "hidden": false,
+            "at": [1290, 50], "size": [620, 700],
+            "workspace": {"id": -98, "name": "special:minimized"}, "floating": true, "pseudo": false,
+            "monitor": 0, "class": "org.gnome.Nautilus", "title": "Home", "initialClass": "org.gnome.Nautilus",
+            "initialTitle": "Loading…", "pid": 1630, "xwayland": false, "pinned": false, "fullscreen": 0,
+            "fullscreenClient": 0, "grouped": [], "tags": [], "swallowing": "0x0", "focusHistoryID": 1
+        }
+    ]"#;
+
+    // A stand-in for a Hyprland instance directory. Each request socket connection is answered with
+    // the next reply, and the event socket sends all of the given lines to its first client.
+    fn serve(replies: Vec<&'static str>, events: &'static str) -> (Instance, Receiver<String>) {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        let dir = env::temp_dir().join(format!(
+            "fowin-hypr-{}-{}",
+            process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let instance = Instance { dir };
+
+        let requests = UnixListener::bind(instance.request_socket()).unwrap();
+        let dir = instance.dir.clone();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for reply in replies {
+                let (mut stream, _) = requests.accept().unwrap();
+                let mut request = [0; 1024];
+                let len = stream.read(&mut request).unwrap();
+                sender
+                    .send(String::from_utf8_lossy(&request[..len]).into_owned())
+                    .unwrap();
+                stream.write_all(reply.as_bytes()).unwrap();
+            }
+            // Connections are opened in order, so every other socket is connected by now.
+            let _ = fs::remove_dir_all(dir);
+        });
+
+        let listener = UnixListener::bind(instance.event_socket()).unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(events.as_bytes()).unwrap();
+        });
+
+        (instance, receiver)
+    }
+
+    #[test]
+    fn clients() {
+        let (instance, requests) = serve(vec![CLIENTS], "");
+        let clients = instance.clients().unwrap();
+        assert_eq!(requests.recv().unwrap(), "j/clients");
+
+        assert_eq!(clients.len(), 2);
+        let kitty = &clients[0];
+        assert_eq!(kitty.address, 0x55d0f8a3c1e0);
+        assert_eq!(kitty.at, [10, 50]);
+        assert_eq!(kitty.size, [1260, 700]);
+        assert_eq!(kitty.pid, Some(1412));
+        assert_eq!(kitty.monitor, Some(0));
+        assert!(kitty.fullscreen.is_fullscreen());
+        assert!(!kitty.is_minimized());
+        assert!(clients[1].is_minimized());
+        assert!(!clients[1].fullscreen.is_fullscreen());
+        assert_eq!(
+            kitty.properties(
+                Some(kitty.address),
+                PropertyMask::FOCUSED | PropertyMask::POSITION
+            ),
+            WindowProperties {
+                position: Some(LogicalPosition { x: 10.0, y: 50.0 }),
+                is_focused: Some(true),
+                ..WindowProperties::default()
+            }
+        );
+        assert_eq!(
+            clients[1].properties(None, PropertyMask::HIDDEN).is_hidden,
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn monitors() {
+        let (instance, requests) = serve(
+            vec![
+                r#"[
+                    {"id": 0, "name": "eDP-1", "width": 2880, "height": 1800, "x": 0, "y": 0, "scale": 1.50},
+                    {"id": 1, "name": "DP-1", "width": 1920, "height": 1080, "x": 1920, "y": 0, "scale": 1.00}
+                ]"#,
+            ],
+            "",
+        );
+        let monitors = instance.monitors().unwrap();
+        assert_eq!(requests.recv().unwrap(), "j/monitors");
+        assert_eq!(monitors.len(), 2);
+        assert_eq!((monitors[0].id, monitors[0].scale), (0, 1.5));
+        assert_eq!((monitors[1].id, monitors[1].scale), (1, 1.0));
+    }
+
+    #[test]
+    fn legacy_fullscreen() {
+        let client: Client = serde_json::from_str(
+            r#"{
+                "address": "0x1", "mapped": true, "hidden": false, "at": [0, 0], "size": [1, 1],
+                "workspace": {"id": 1, "name": "1"}, "fullscreen": true, "title": ""
+            }"#,
+        )
+        .unwrap();
+        assert!(client.fullscreen.is_fullscreen());
+    }
+
+    #[test]
+    fn dispatch() {
+        let (instance, requests) = serve(vec!["ok", "Window not found"], "");
+        instance
+            .dispatch("resizewindowpixel exact 800 600,address:0x55d0f8a3c1e0")
+            .unwrap();
+        assert_eq!(
+            requests.recv().unwrap(),
+            "dispatch resizewindowpixel exact 800 600,address:0x55d0f8a3c1e0"
+        );
+        assert!(matches!(
+            instance.dispatch("focuswindow address:0x1"),
+            Err(WindowError::OsError(_))
+        ));
+    }
+
+    #[test]
+    fn dispatch_batch() {
+        let (instance, requests) = serve(vec!["ok\n\nWindow not found\n\n"], "");
+        let results = instance
+            .dispatch_batch(&[
+                "focuswindow address:0x1".to_owned(),
+                "alterzorder top,address:0x2".to_owned(),
+                "alterzorder top,address:0x3".to_owned(),
+            ])
+            .unwrap();
+        assert_eq!(
+            requests.recv().unwrap(),
+            "[[BATCH]]dispatch focuswindow address:0x1;\
+             dispatch alterzorder top,address:0x2;\
+             dispatch alterzorder top,address:0x3"
+        );
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1], Err(WindowError::OsError(_))));
+        // Dispatchers without a reply are reported as failed.
+        assert!(matches!(results[2], Err(WindowError::OsError(_))));
+    }
+
+    #[test]
+    fn watcher_events() {
+        let (instance, requests) = serve(
+            vec![r#"{"address": "0x55d0f8a3c1e0", "title": "~"}"#],
+            "workspace>>2\n\
+             openwindow>>55d0f8b71a20,2,org.gnome.Nautilus,Loading…\n\
+             windowtitle>>55d0f8b71a20\n\
+             windowtitlev2>>55d0f8b71a20,Home\n\
+             fullscreen>>1\n\
+             activewindow>>org.gnome.Nautilus,Home\n\
+             activewindowv2>>55d0f8b71a20\n\
+             movewindow>>55d0f8b71a20,special:minimized\n\
+             activewindowv2>>\n\
+             fullscreen>>0\n\
+             closewindow>>55d0f8b71a20\n",
+        );
+        let mut watcher = Watcher::connect(&instance).unwrap();
+        assert_eq!(requests.recv().unwrap(), "j/activewindow");
+
+        let kitty = protocol::WindowHandle::from_sys(0x55d0f8a3c1e0_u64.into());
+        let nautilus = protocol::WindowHandle::from_sys(0x55d0f8b71a20_u64.into());
+        let mut next = || watcher.next_request().unwrap();
+
+        assert!(matches!(next(), WindowEvent::Opened(window) if window.handle() == nautilus));
+        assert!(matches!(
+            next(),
+            WindowEvent::Renamed { window, new_title, .. }
+                if window.handle() == nautilus && new_title.as_deref() == Some("Home")
+        ));
+        // The initially active window is fullscreened.
+        assert!(matches!(next(), WindowEvent::Fullscreened(window) if window.handle() == kitty));
+        // Lines that were already buffered are read without waiting on the socket.
+        assert!(matches!(
+            watcher.next_request_timeout(Duration::ZERO),
+            Ok(Some(WindowEvent::Focused(window))) if window.handle() == nautilus
+        ));
+        let mut next = || watcher.next_request().unwrap();
+        assert!(matches!(next(), WindowEvent::Moved { window, .. } if window.handle() == nautilus));
+        // No window is active, so the fullscreen event is skipped.
+        assert!(matches!(next(), WindowEvent::Closed(handle) if handle == nautilus));
+    }
+}
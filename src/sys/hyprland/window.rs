@@ -0,0 +1,214 @@
+use crate::{
+    protocol::transaction::Change, Insets, LogicalPosition, LogicalSize, PropertyMask, Rect,
+    WindowError, WindowProperties,
+};
+
+use super::{instance, Client, WindowHandle, MINIMIZED_WORKSPACE};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Window {
+    address: WindowHandle,
+}
+
+impl Window {
+    pub(crate) fn new(address: WindowHandle) -> Window {
+        Window { address }
+    }
+
+    pub fn handle(&self) -> WindowHandle {
+        self.address
+    }
+
+    fn client(&self) -> Result<Client, WindowError> {
+        instance()?.client(self.address)
+    }
+
+    // Most dispatchers accept a window as their last argument.
+    fn dispatch(&self, dispatcher: &str) -> Result<(), WindowError> {
+        instance()?.dispatch(&format!("{dispatcher},address:0x{:x}", self.address))
+    }
+
+    // The fullscreen dispatcher toggles the state of the active window, so the window has to be
+    // focused first.
+    fn toggle_fullscreen(&self, mode: u8) -> Result<(), WindowError> {
+        self.focus()?;
+        instance()?.dispatch(&format!("fullscreen {mode}"))
+    }
+
+    pub fn title(&self) -> Result<String, WindowError> {
+        Ok(self.client()?.title)
+    }
+
+    pub fn size(&self) -> Result<LogicalSize, WindowError> {
+        let client = self.client()?;
+        Ok(LogicalSize {
+            width: client.size[0] as f64,
+            height: client.size[1] as f64,
+        })
+    }
+
+    pub fn position(&self) -> Result<LogicalPosition, WindowError> {
+        let client = self.client()?;
+        Ok(LogicalPosition {
+            x: client.at[0] as f64,
+            y: client.at[1] as f64,
+        })
+    }
+
+    pub fn scale_factor(&self) -> Result<f64, WindowError> {
+        let monitor = self.client()?.monitor.ok_or(WindowError::Unsupported)?;
+        instance()?
+            .monitors()?
+            .into_iter()
+            .find(|other| other.id == monitor)
+            .map(|monitor| monitor.scale)
+            // The window isn't on any monitor.
+            .ok_or(WindowError::Unsupported)
+    }
+
+    pub fn is_focused(&self) -> Result<bool, WindowError> {
+        Ok(instance()?.active_window()? == Some(self.address))
+    }
+
+    pub fn is_fullscreen(&self) -> Result<bool, WindowError> {
+        Ok(self.client()?.fullscreen.is_fullscreen())
+    }
+
+    pub fn is_minimized(&self) -> Result<bool, WindowError> {
+        Ok(self.client()?.is_minimized())
+    }
+
+    pub fn is_hidden(&self) -> Result<bool, WindowError> {
+        Ok(self.client()?.is_hidden())
+    }
+
+    pub fn properties(&self, mask: PropertyMask) -> Result<WindowProperties, WindowError> {
+        super::properties(&[self], mask)?.remove(0)
+    }
+
+    pub fn resize(&self, size: LogicalSize) -> Result<(), WindowError> {
+        self.dispatch(&format!(
+            "resizewindowpixel exact {} {}",
+            size.width as i32, size.height as i32
+        ))
+    }
+
+    pub fn reposition(&self, position: LogicalPosition) -> Result<(), WindowError> {
+        self.dispatch(&format!(
+            "movewindowpixel exact {} {}",
+            position.x as i32, position.y as i32
+        ))
+    }
+
+    pub fn frame(&self) -> Result<Rect, WindowError> {
+        let client = self.client()?;
+        Ok(Rect {
+            position: LogicalPosition {
+                x: client.at[0] as f64,
+                y: client.at[1] as f64,
+            },
+            size: LogicalSize {
+                width: client.size[0] as f64,
+                height: client.size[1] as f64,
+            },
+        })
+    }
+
+    pub fn set_frame(&self, frame: Rect) -> Result<Rect, WindowError> {
+        instance()?
+            .dispatch_batch(&self.dispatchers(Change::SetFrame(frame)))?
+            .into_iter()
+            .collect::<Result<(), _>>()?;
+        super::super::settled_frame(frame, || self.frame())
+    }
+
+    // Borders are drawn outside of the client geometry and their size is a compositor option
+    // rather than a property of the window.
+    pub fn decoration_insets(&self) -> Result<Insets, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    // The dispatchers that make up each change, for dispatching many at once.
+    pub(super) fn dispatchers(&self, change: Change) -> Vec<String> {
+        let address = format!("address:0x{:x}", self.address);
+        match change {
+            Change::SetFrame(frame) => vec![
+                format!(
+                    "resizewindowpixel exact {} {},{address}",
+                    frame.size.width as i32, frame.size.height as i32
+                ),
+                format!(
+                    "movewindowpixel exact {} {},{address}",
+                    frame.position.x as i32, frame.position.y as i32
+                ),
+            ],
+            Change::Minimize => vec![format!(
+                "movetoworkspacesilent {MINIMIZED_WORKSPACE},{address}"
+            )],
+            Change::Focus => vec![format!("focuswindow {address}")],
+            Change::Raise => vec![format!("alterzorder top,{address}")],
+        }
+    }
+
+    pub fn focus(&self) -> Result<(), WindowError> {
+        instance()?.dispatch(&format!("focuswindow address:0x{:x}", self.address))
+    }
+
+    pub fn fullscreen(&self) -> Result<(), WindowError> {
+        if self.is_fullscreen()? {
+            return Ok(());
+        }
+
+        self.toggle_fullscreen(0)
+    }
+
+    pub fn unfullscreen(&self) -> Result<(), WindowError> {
+        if !self.is_fullscreen()? {
+            return Ok(());
+        }
+
+        self.toggle_fullscreen(0)
+    }
+
+    // Mode 1 keeps the gaps and bars, which is Hyprland's equivalent of maximizing.
+    pub fn maximize(&self) -> Result<(), WindowError> {
+        if self.client()?.fullscreen.is_maximized() {
+            return Ok(());
+        }
+
+        self.toggle_fullscreen(1)
+    }
+
+    pub fn minimize(&self) -> Result<(), WindowError> {
+        self.dispatch(&format!("movetoworkspacesilent {MINIMIZED_WORKSPACE}"))
+    }
+
+    pub fn unminimize(&self) -> Result<(), WindowError> {
+        let workspace = instance()?.active_workspace()?;
+        self.dispatch(&format!("movetoworkspacesilent {}", workspace.id))
+    }
+
+    pub fn show(&self) -> Result<(), WindowError> {
+        self.unminimize()
+    }
+
+    pub fn hide(&self) -> Result<(), WindowError> {
+        self.minimize()
+    }
+
+    // Only affects floating windows, tiled windows are never stacked.
+    pub fn bring_to_front(&self) -> Result<(), WindowError> {
+        self.dispatch("alterzorder top")
+    }
+
+    pub fn pid(&self) -> Result<u32, WindowError> {
+        self.client()?
+            .pid
+            .and_then(|pid| u32::try_from(pid).ok())
+            .ok_or(WindowError::Unsupported)
+    }
+
+    pub fn close(&self) -> Result<(), WindowError> {
+        instance()?.dispatch(&format!("closewindow address:0x{:x}", self.address))
+    }
+}
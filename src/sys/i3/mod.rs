@@ -0,0 +1,629 @@
+use std::{
+    env,
+    io::{self, Read, Write},
+    os::{fd::AsFd, unix::net::UnixStream},
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::{
+    protocol::{self, transaction::Change},
+    Insets, LogicalPosition, LogicalSize, PropertyMask, WindowError, WindowEvent, WindowProperties,
+};
+
+pub use window::Window;
+
+mod window;
+
+/// The id of a container, which is unique for the lifetime of the window manager.
+pub type WindowHandle = i64;
+
+// Values defined by the i3 IPC protocol, sway implements the same protocol.
+// https://i3wm.org/docs/ipc.html
+const MAGIC: &[u8; 6] = b"i3-ipc";
+const RUN_COMMAND: u32 = 0;
+const SUBSCRIBE: u32 = 2;
+const GET_TREE: u32 = 4;
+const EVENT_WINDOW: u32 = 0x8000_0003;
+
+// The scratchpad is a hidden workspace, windows moved there are the closest thing to minimized.
+const SCRATCHPAD: &str = "__i3_scratch";
+
+static CONNECTION: OnceLock<Connection> = OnceLock::new();
+
+pub(crate) fn connection() -> Result<&'static Connection, WindowError> {
+    if let Some(connection) = CONNECTION.get() {
+        return Ok(connection);
+    }
+
+    let connection = Connection::connect(&socket_path()?)?;
+    Ok(CONNECTION.get_or_init(|| connection))
+}
+
+// Both window managers export the path of their IPC socket to child processes.
+pub(crate) fn socket_path() -> Result<PathBuf, WindowError> {
+    env::var_os("I3SOCK")
+        .or_else(|| env::var_os("SWAYSOCK"))
+        .map(PathBuf::from)
+        .ok_or(WindowError::Unsupported)
+}
+
+/// The position and size of a container in root coordinates.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub(crate) struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A node in the layout tree, only the fields we use are deserialized.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct Node {
+    pub id: WindowHandle,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub rect: Rect,
+    // The client window relative to `rect`, the rest of the container is its border and title bar.
+    #[serde(default)]
+    pub window_rect: Rect,
+    #[serde(default)]
+    pub focused: bool,
+    // 0 means not fullscreen, 1 fullscreen on its output, 2 fullscreen globally.
+    #[serde(default)]
+    pub fullscreen_mode: u8,
+    // The X11 window id, set for X11 clients (including XWayland clients on sway).
+    #[serde(default)]
+    pub window: Option<u32>,
+    // Only set by sway for native Wayland clients.
+    #[serde(default)]
+    pub app_id: Option<String>,
+    // Only set by sway.
+    #[serde(default)]
+    pub pid: Option<u32>,
+    // Only set by sway on outputs, i3 doesn't scale outputs.
+    #[serde(default)]
+    pub scale: Option<f64>,
+    #[serde(default)]
+    pub nodes: Vec<Node>,
+    #[serde(default)]
+    pub floating_nodes: Vec<Node>,
+}
+
+/// A container holding a client window.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Leaf<'a> {
+    pub node: &'a Node,
+    pub scratchpad: bool,
+    // The scale of the output containing the window.
+    pub scale_factor: f64,
+}
+
+impl Leaf<'_> {
+    pub fn insets(&self) -> Insets {
+        let (rect, window) = (self.node.rect, self.node.window_rect);
+        Insets {
+            top: window.y as f64,
+            right: rect.width as f64 - window.x as f64 - window.width as f64,
+            bottom: rect.height as f64 - window.y as f64 - window.height as f64,
+            left: window.x as f64,
+        }
+    }
+
+    pub fn properties(&self, mask: PropertyMask) -> WindowProperties {
+        let rect = self.node.rect;
+        WindowProperties {
+            title: mask.select(PropertyMask::TITLE, || {
+                self.node.name.clone().unwrap_or_default()
+            }),
+            size: mask.select(PropertyMask::SIZE, || LogicalSize {
+                width: rect.width as f64,
+                height: rect.height as f64,
+            }),
+            position: mask.select(PropertyMask::POSITION, || LogicalPosition {
+                x: rect.x as f64,
+                y: rect.y as f64,
+            }),
+            is_focused: mask.select(PropertyMask::FOCUSED, || self.node.focused),
+            is_fullscreen: mask.select(PropertyMask::FULLSCREEN, || self.node.fullscreen_mode != 0),
+            is_minimized: mask.select(PropertyMask::MINIMIZED, || self.scratchpad),
+            // Windows are hidden by minimizing them, see `Window::is_hidden`.
+            is_hidden: mask.select(PropertyMask::HIDDEN, || self.scratchpad),
+        }
+    }
+}
+
+impl Node {
+    fn is_window(&self) -> bool {
+        self.window.is_some() || self.app_id.is_some()
+    }
+
+    /// Every window in the tree, depth first.
+    pub fn leaves(&self) -> Vec<Leaf<'_>> {
+        let mut leaves = Vec::new();
+        self.collect_leaves(false, 1.0, &mut leaves);
+        leaves
+    }
+
+    pub fn find(&self, id: WindowHandle) -> Option<Leaf<'_>> {
+        self.leaves().into_iter().find(|leaf| leaf.node.id == id)
+    }
+
+    fn collect_leaves<'a>(
+        &'a self,
+        scratchpad: bool,
+        scale_factor: f64,
+        leaves: &mut Vec<Leaf<'a>>,
+    ) {
+        let scratchpad =
+            scratchpad || (self.kind == "workspace" && self.name.as_deref() == Some(SCRATCHPAD));
+        let scale_factor = self.scale.unwrap_or(scale_factor);
+        if self.is_window() {
+            leaves.push(Leaf {
+                node: self,
+                scratchpad,
+                scale_factor,
+            });
+        }
+
+        for node in self.nodes.iter().chain(&self.floating_nodes) {
+            node.collect_leaves(scratchpad, scale_factor, leaves);
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CommandOutcome {
+    success: bool,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+impl CommandOutcome {
+    fn into_result(self, command: &str) -> Result<(), WindowError> {
+        if self.success {
+            Ok(())
+        } else {
+            Err(WindowError::OsError(io::Error::other(format!(
+                "window manager failed to run `{command}`: {}",
+                self.error.unwrap_or_default()
+            ))))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct WindowChange {
+    change: String,
+    container: Node,
+}
+
+#[derive(Debug)]
+pub(crate) struct Connection {
+    // Replies are matched to requests by order, so only one request can be in flight.
+    stream: Mutex<UnixStream>,
+}
+
+impl Connection {
+    pub fn connect(path: &Path) -> Result<Connection, WindowError> {
+        Ok(Connection {
+            stream: Mutex::new(UnixStream::connect(path).map_err(WindowError::OsError)?),
+        })
+    }
+
+    fn request<T: DeserializeOwned>(&self, kind: u32, payload: &str) -> Result<T, WindowError> {
+        let mut stream = self.stream.lock().unwrap();
+        write_message(&mut stream, kind, payload).map_err(WindowError::OsError)?;
+        loop {
+            let (reply_kind, reply) = read_message(&mut stream).map_err(WindowError::OsError)?;
+            // Events aren't sent on this connection since it never subscribes, but just in case.
+            if reply_kind == kind {
+                return serde_json::from_slice(&reply).map_err(invalid_reply);
+            }
+        }
+    }
+
+    pub fn tree(&self) -> Result<Node, WindowError> {
+        self.request(GET_TREE, "")
+    }
+
+    /// Runs a command on the container with the specified id.
+    pub fn command(&self, id: WindowHandle, command: &str) -> Result<(), WindowError> {
+        self.run(&[format!("[con_id={id}] {command}")])?
+            .into_iter()
+            .try_for_each(|outcome| outcome.into_result(command))
+    }
+
+    /// Runs every command in a single message, the window manager replies with an outcome for
+    /// each.
+    fn run(&self, commands: &[String]) -> Result<Vec<CommandOutcome>, WindowError> {
+        self.request(RUN_COMMAND, &commands.join("; "))
+    }
+}
+
+#[derive(Debug)]
+pub struct Watcher {
+    stream: UnixStream,
+}
+
+impl Watcher {
+    pub fn new() -> Result<Watcher, WindowError> {
+        Watcher::connect(&socket_path()?)
+    }
+
+    // Subscriptions apply to the whole connection, so the watcher needs its own.
+    fn connect(path: &Path) -> Result<Watcher, WindowError> {
+        let mut stream = UnixStream::connect(path).map_err(WindowError::OsError)?;
+        write_message(&mut stream, SUBSCRIBE, r#"["window"]"#).map_err(WindowError::OsError)?;
+        let (_, reply) = read_message(&mut stream).map_err(WindowError::OsError)?;
+        let outcome: CommandOutcome = serde_json::from_slice(&reply).map_err(invalid_reply)?;
+        if !outcome.success {
+            return Err(WindowError::OsError(io::Error::other(
+                "window manager refused to subscribe to window events",
+            )));
+        }
+
+        Ok(Watcher { stream })
+    }
+
+    pub fn next_request(&mut self) -> Result<WindowEvent, WindowError> {
+        loop {
+            if let Some(event) = self.read_event()? {
+                return Ok(event);
+            }
+        }
+    }
+
+    pub fn next_request_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<WindowEvent>, WindowError> {
+        let deadline = Instant::now() + timeout;
+        // Messages are written whole, so once one starts arriving it can be read without waiting.
+        while super::wait_readable(self.stream.as_fd(), deadline)? {
+            if let Some(event) = self.read_event()? {
+                return Ok(Some(event));
+            }
+        }
+
+        Ok(None)
+    }
+
+    // Reads the next message, which may not translate to any event.
+    fn read_event(&mut self) -> Result<Option<WindowEvent>, WindowError> {
+        let (kind, payload) = read_message(&mut self.stream).map_err(WindowError::OsError)?;
+        if kind != EVENT_WINDOW {
+            return Ok(None);
+        }
+
+        let change: WindowChange = serde_json::from_slice(&payload).map_err(invalid_reply)?;
+        let id = change.container.id;
+        let window = || protocol::Window::from_sys(Window::new(id).into());
+        // "floating", "urgent" and "mark" changes have no equivalent event.
+        Ok(match change.change.as_str() {
+            "new" => Some(WindowEvent::Opened(window())),
+            "close" => Some(WindowEvent::Closed(protocol::WindowHandle::from_sys(
+                id.into(),
+            ))),
+            "focus" => Some(WindowEvent::Focused(window())),
+            "title" => Some(WindowEvent::Renamed {
+                window: window(),
+                old_title: None,
+                new_title: change.container.name,
+            }),
+            // The container is sent before the layout is updated, so its rect is outdated.
+            "move" => Some(WindowEvent::Moved {
+                window: window(),
+                old: None,
+                new: None,
+            }),
+            "fullscreen_mode" if change.container.fullscreen_mode != 0 => {
+                Some(WindowEvent::Fullscreened(window()))
+            }
+            "fullscreen_mode" => Some(WindowEvent::Unfullscreened(window())),
+            _ => None,
+        })
+    }
+}
+
+// Every command is sent in a single message, which the window manager runs before rendering again.
+pub fn commit(changes: &[(&Window, Change)]) -> Result<Vec<Result<(), WindowError>>, WindowError> {
+    let commands: Vec<_> = changes
+        .iter()
+        .map(|(window, change)| window.commands(*change))
+        .collect();
+    let batch: Vec<String> = commands.iter().flatten().flatten().cloned().collect();
+    let mut outcomes = connection()?.run(&batch)?.into_iter();
+
+    Ok(commands
+        .into_iter()
+        .map(|commands| {
+            // Every outcome is taken before failing, so they stay aligned with the next change.
+            let results: Vec<_> = commands?
+                .iter()
+                .map(|command| match outcomes.next() {
+                    Some(outcome) => outcome.into_result(command),
+                    None => Err(WindowError::OsError(io::Error::other(format!(
+                        "window manager didn't reply to `{command}`"
+                    )))),
+                })
+                .collect();
+            results.into_iter().collect()
+        })
+        .collect())
+}
+
+// Every property of every window is read from a single tree.
+pub fn properties(
+    windows: &[&Window],
+    mask: PropertyMask,
+) -> Result<Vec<Result<WindowProperties, WindowError>>, WindowError> {
+    let tree = connection()?.tree()?;
+    Ok(windows
+        .iter()
+        .map(|window| {
+            tree.find(window.handle())
+                .map(|leaf| leaf.properties(mask))
+                .ok_or(WindowError::InvalidHandle)
+        })
+        .collect())
+}
+
+pub fn iter_windows() -> impl Iterator<Item = Result<Window, WindowError>> {
+    let windows = match connection().and_then(|connection| connection.tree()) {
+        Ok(tree) => tree
+            .leaves()
+            .into_iter()
+            .map(|leaf| Ok(Window::new(leaf.node.id)))
+            .collect(),
+        Err(err) => vec![Err(err)],
+    };
+    windows.into_iter()
+}
+
+pub fn focused_window() -> Result<Option<Window>, WindowError> {
+    Ok(connection()?
+        .tree()?
+        .leaves()
+        .into_iter()
+        .find(|leaf| leaf.node.focused)
+        .map(|leaf| Window::new(leaf.node.id)))
+}
+
+// Each message is the magic string followed by the length and type of the payload in native
+// byte order.
+fn write_message(stream: &mut UnixStream, kind: u32, payload: &str) -> io::Result<()> {
+    let mut message = Vec::with_capacity(MAGIC.len() + 8 + payload.len());
+    message.extend_from_slice(MAGIC);
+    message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+    message.extend_from_slice(&kind.to_ne_bytes());
+    message.extend_from_slice(payload.as_bytes());
+    stream.write_all(&message)
+}
+
+fn read_message(stream: &mut UnixStream) -> io::Result<(u32, Vec<u8>)> {
+    let mut header = [0; MAGIC.len() + 8];
+    stream.read_exact(&mut header)?;
+    if &header[..MAGIC.len()] != MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "invalid magic string in IPC message",
+        ));
+    }
+
+    let len = u32::from_ne_bytes(header[6..10].try_into().unwrap());
+    let kind = u32::from_ne_bytes(header[10..14].try_into().unwrap());
+    let mut payload = vec![0; len as usize];
+    stream.read_exact(&mut payload)?;
+    Ok((kind, payload))
+}
+
+fn invalid_reply(err: serde_json::Error) -> WindowError {
+    WindowError::OsError(io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        os::unix::net::UnixListener,
+        process,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            mpsc::{self, Receiver},
+        },
+        thread,
+    };
+
+    use super::*;
+
+    // Recorded from sway, trimmed down to the fields we deserialize.
+    const TREE: &str = r#"{
+        "id": 1, "name": "root", "type": "root", "rect": {"x": 0, "y": 0, "width": 1920, "height": 1080},
+        "nodes": [
+            {
+                "id": 2, "name": "__i3", "type": "output",
+                "nodes": [{
+                    "id": 3, "name": "__i3_scratch", "type": "workspace",
+                    "floating_nodes": [{
+                        "id": 9, "name": "notes", "type": "floating_con", "app_id": "org.gnome.TextEditor",
+                        "rect": {"x": 560, "y": 290, "width": 800, "height": 500}
+                    }]
+                }]
+            },
+            {
+                "id": 4, "name": "eDP-1", "type": "output", "scale": 1.5,
+                "nodes": [{
+                    "id": 5, "name": "1", "type": "workspace",
+                    "nodes": [
+                        {
+                            "id": 6, "name": "Alacritty", "type": "con", "app_id": "Alacritty", "focused": true,
+                            "rect": {"x": 0, "y": 0, "width": 960, "height": 1080},
+                            "window_rect": {"x": 2, "y": 24, "width": 956, "height": 1054}
+                        },
+                        {
+                            "id": 7, "name": "Mozilla Firefox", "type": "con", "window": 12582915,
+                            "fullscreen_mode": 1, "rect": {"x": 960, "y": 0, "width": 960, "height": 1080}
+                        }
+                    ],
+                    "floating_nodes": []
+                }]
+            }
+        ]
+    }"#;
+
+    // Serves a single client, reading one message then replying with all of the given messages.
+    fn serve(messages: Vec<(u32, String)>) -> (PathBuf, Receiver<(u32, String)>) {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        let path = env::temp_dir().join(format!(
+            "fowin-i3-{}-{}.sock",
+            process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+
+        let (sender, receiver) = mpsc::channel();
+        let socket = path.clone();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = fs::remove_file(socket);
+            let (kind, payload) = read_message(&mut stream).unwrap();
+            sender
+                .send((kind, String::from_utf8(payload).unwrap()))
+                .unwrap();
+            for (kind, payload) in messages {
+                write_message(&mut stream, kind, &payload).unwrap();
+            }
+        });
+
+        (path, receiver)
+    }
+
+    #[test]
+    fn tree_leaves() {
+        let (path, requests) = serve(vec![(GET_TREE, TREE.to_owned())]);
+        let tree = Connection::connect(&path).unwrap().tree().unwrap();
+        assert_eq!(requests.recv().unwrap(), (GET_TREE, String::new()));
+
+        let leaves = tree.leaves();
+        let ids: Vec<_> = leaves.iter().map(|leaf| leaf.node.id).collect();
+        assert_eq!(ids, [9, 6, 7]);
+
+        let notes = tree.find(9).unwrap();
+        assert!(notes.scratchpad);
+        assert_eq!(notes.scale_factor, 1.0);
+        let firefox = tree.find(7).unwrap();
+        assert!(!firefox.scratchpad);
+        assert_eq!(firefox.scale_factor, 1.5);
+        assert_eq!(firefox.node.fullscreen_mode, 1);
+        assert_eq!(
+            firefox.node.rect,
+            Rect {
+                x: 960,
+                y: 0,
+                width: 960,
+                height: 1080
+            }
+        );
+        let alacritty = tree.find(6).unwrap();
+        assert!(alacritty.node.focused);
+        assert_eq!(
+            alacritty.insets(),
+            Insets {
+                top: 24.0,
+                right: 2.0,
+                bottom: 2.0,
+                left: 2.0
+            }
+        );
+        assert_eq!(
+            firefox
+                .properties(PropertyMask::FULLSCREEN | PropertyMask::SIZE | PropertyMask::HIDDEN),
+            WindowProperties {
+                size: Some(LogicalSize {
+                    width: 960.0,
+                    height: 1080.0
+                }),
+                is_fullscreen: Some(true),
+                is_hidden: Some(false),
+                ..WindowProperties::default()
+            }
+        );
+        // Workspaces and outputs aren't windows.
+        assert!(tree.find(5).is_none());
+    }
+
+    #[test]
+    fn command_criteria() {
+        let (path, requests) = serve(vec![(RUN_COMMAND, r#"[{"success":true}]"#.to_owned())]);
+        Connection::connect(&path)
+            .unwrap()
+            .command(7, "fullscreen enable")
+            .unwrap();
+        assert_eq!(
+            requests.recv().unwrap(),
+            (RUN_COMMAND, "[con_id=7] fullscreen enable".to_owned())
+        );
+    }
+
+    #[test]
+    fn command_failure() {
+        let (path, _requests) = serve(vec![(
+            RUN_COMMAND,
+            r#"[{"success":false,"parse_error":false,"error":"No matching node"}]"#.to_owned(),
+        )]);
+        let result = Connection::connect(&path).unwrap().command(42, "focus");
+        assert!(matches!(result, Err(WindowError::OsError(_))));
+    }
+
+    #[test]
+    fn watcher_events() {
+        let event = |change: &str, id: i64| {
+            (
+                EVENT_WINDOW,
+                format!(r#"{{"change":"{change}","container":{{"id":{id},"type":"con"}}}}"#),
+            )
+        };
+        let (path, requests) = serve(vec![
+            (SUBSCRIBE, r#"{"success":true}"#.to_owned()),
+            // A workspace event, which we never subscribe to but should be skipped regardless.
+            (0x8000_0000, r#"{"change":"focus"}"#.to_owned()),
+            event("new", 6),
+            event("mark", 6),
+            event("title", 6),
+            event("close", 6),
+        ]);
+
+        let mut watcher = Watcher::connect(&path).unwrap();
+        assert_eq!(
+            requests.recv().unwrap(),
+            (SUBSCRIBE, r#"["window"]"#.to_owned())
+        );
+
+        let handle = protocol::WindowHandle::from_sys(6i64.into());
+        match watcher.next_request().unwrap() {
+            WindowEvent::Opened(window) => assert_eq!(window.handle(), handle),
+            event => panic!("expected opened, got {event:?}"),
+        }
+        // Skipped messages don't end the wait early.
+        match watcher
+            .next_request_timeout(Duration::from_secs(5))
+            .unwrap()
+        {
+            Some(WindowEvent::Renamed { window, .. }) => assert_eq!(window.handle(), handle),
+            event => panic!("expected renamed, got {event:?}"),
+        }
+        match watcher.next_request().unwrap() {
+            WindowEvent::Closed(closed) => assert_eq!(closed, handle),
+            event => panic!("expected closed, got {event:?}"),
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use crate::{
+    protocol::transaction::Change, Insets, LogicalPosition, LogicalSize, PropertyMask, Rect,
+    WindowError, WindowProperties,
+};
+
+use super::{connection, Leaf, WindowHandle};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Window {
+    id: WindowHandle,
+}
+
+impl Window {
+    pub(crate) fn new(id: WindowHandle) -> Window {
+        Window { id }
+    }
+
+    pub fn handle(&self) -> WindowHandle {
+        self.id
+    }
+
+    // The tree is the only way to query a container, so every getter requests it.
+    fn with_leaf<T>(&self, f: impl FnOnce(Leaf) -> T) -> Result<T, WindowError> {
+        let tree = connection()?.tree()?;
+        tree.find(self.id).map(f).ok_or(WindowError::InvalidHandle)
+    }
+
+    fn command(&self, command: &str) -> Result<(), WindowError> {
+        connection()?.command(self.id, command)
+    }
+
+    // The commands that make up each change, for running many at once.
+    pub(super) fn commands(&self, change: Change) -> Result<Vec<String>, WindowError> {
+        let commands = match change {
+            Change::SetFrame(frame) => vec![
+                format!(
+                    "resize set {} px {} px",
+                    frame.size.width as u32, frame.size.height as u32
+                ),
+                format!(
+                    "move position {} px {} px",
+                    frame.position.x as i32, frame.position.y as i32
+                ),
+            ],
+            Change::Minimize => vec!["move scratchpad".to_owned()],
+            Change::Focus => vec!["focus".to_owned()],
+            Change::Raise => return Err(WindowError::Unsupported),
+        };
+        Ok(commands
+            .into_iter()
+            .map(|command| format!("[con_id={}] {command}", self.id))
+            .collect())
+    }
+
+    pub fn title(&self) -> Result<String, WindowError> {
+        self.with_leaf(|leaf| leaf.node.name.clone().unwrap_or_default())
+    }
+
+    pub fn size(&self) -> Result<LogicalSize, WindowError> {
+        self.with_leaf(|leaf| LogicalSize {
+            width: leaf.node.rect.width as f64,
+            height: leaf.node.rect.height as f64,
+        })
+    }
+
+    pub fn position(&self) -> Result<LogicalPosition, WindowError> {
+        self.with_leaf(|leaf| LogicalPosition {
+            x: leaf.node.rect.x as f64,
+            y: leaf.node.rect.y as f64,
+        })
+    }
+
+    pub fn scale_factor(&self) -> Result<f64, WindowError> {
+        self.with_leaf(|leaf| leaf.scale_factor)
+    }
+
+    pub fn is_focused(&self) -> Result<bool, WindowError> {
+        self.with_leaf(|leaf| leaf.node.focused)
+    }
+
+    pub fn is_fullscreen(&self) -> Result<bool, WindowError> {
+        self.with_leaf(|leaf| leaf.node.fullscreen_mode != 0)
+    }
+
+    pub fn is_minimized(&self) -> Result<bool, WindowError> {
+        self.with_leaf(|leaf| leaf.scratchpad)
+    }
+
+    #[inline]
+    pub fn is_hidden(&self) -> Result<bool, WindowError> {
+        // Default behavior of Window::hide is to minimize, so check if minimized.
+        self.is_minimized()
+    }
+
+    pub fn properties(&self, mask: PropertyMask) -> Result<WindowProperties, WindowError> {
+        self.with_leaf(|leaf| leaf.properties(mask))
+    }
+
+    // Tiled containers are resized relative to their siblings, the exact size only applies to
+    // floating containers.
+    pub fn resize(&self, size: LogicalSize) -> Result<(), WindowError> {
+        self.command(&format!(
+            "resize set {} px {} px",
+            size.width as u32, size.height as u32
+        ))
+    }
+
+    // Tiled containers can't be positioned, the window manager returns an error.
+    pub fn reposition(&self, position: LogicalPosition) -> Result<(), WindowError> {
+        self.command(&format!(
+            "move position {} px {} px",
+            position.x as i32, position.y as i32
+        ))
+    }
+
+    pub fn frame(&self) -> Result<Rect, WindowError> {
+        self.with_leaf(|leaf| Rect {
+            position: LogicalPosition {
+                x: leaf.node.rect.x as f64,
+                y: leaf.node.rect.y as f64,
+            },
+            size: LogicalSize {
+                width: leaf.node.rect.width as f64,
+                height: leaf.node.rect.height as f64,
+            },
+        })
+    }
+
+    // Both commands share the criteria and are applied together by the window manager.
+    pub fn set_frame(&self, frame: Rect) -> Result<Rect, WindowError> {
+        self.command(&format!(
+            "resize set {} px {} px, move position {} px {} px",
+            frame.size.width as u32,
+            frame.size.height as u32,
+            frame.position.x as i32,
+            frame.position.y as i32
+        ))?;
+        super::super::settled_frame(frame, || self.frame())
+    }
+
+    pub fn decoration_insets(&self) -> Result<Insets, WindowError> {
+        self.with_leaf(|leaf| leaf.insets())
+    }
+
+    pub fn focus(&self) -> Result<(), WindowError> {
+        self.command("focus")
+    }
+
+    pub fn fullscreen(&self) -> Result<(), WindowError> {
+        self.command("fullscreen enable")
+    }
+
+    pub fn unfullscreen(&self) -> Result<(), WindowError> {
+        self.command("fullscreen disable")
+    }
+
+    // Tiling window managers have no concept of maximizing.
+    pub fn maximize(&self) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn minimize(&self) -> Result<(), WindowError> {
+        self.command("move scratchpad")
+    }
+
+    // NOTE: windows shown from the scratchpad are always floating.
+    pub fn unminimize(&self) -> Result<(), WindowError> {
+        self.command("scratchpad show")
+    }
+
+    pub fn show(&self) -> Result<(), WindowError> {
+        self.unminimize()
+    }
+
+    pub fn hide(&self) -> Result<(), WindowError> {
+        self.minimize()
+    }
+
+    // Containers can only be raised by focusing them.
+    pub fn bring_to_front(&self) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    // i3 doesn't report which process owns a container.
+    pub fn pid(&self) -> Result<u32, WindowError> {
+        self.with_leaf(|leaf| leaf.node.pid)?
+            .ok_or(WindowError::Unsupported)
+    }
+
+    // Despite the name, `kill` asks the window to close rather than killing the client.
+    pub fn close(&self) -> Result<(), WindowError> {
+        self.command("kill")
+    }
+}
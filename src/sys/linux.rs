@@ -0,0 +1,676 @@
+use std::{
+    env, fs, io,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+#[cfg(any(feature = "x11", feature = "i3", feature = "hyprland"))]
+use std::{
+    os::fd::{AsRawFd, BorrowedFd},
+    thread,
+};
+
+use crate::{
+    protocol::transaction::Change, Backend, EventFilter, Insets, LogicalPosition, LogicalSize,
+    PropertyMask, Rect, WindowError, WindowEvent, WindowProperties,
+};
+
+#[cfg(feature = "ext-toplevel")]
+use super::ext_toplevel;
+#[cfg(feature = "hyprland")]
+use super::hyprland;
+#[cfg(feature = "i3")]
+use super::i3;
+#[cfg(feature = "plasma")]
+use super::plasma;
+#[cfg(feature = "wlr")]
+use super::wlr;
+#[cfg(feature = "x11")]
+use super::x11;
+
+#[cfg(not(any(
+    feature = "x11",
+    feature = "wlr",
+    feature = "ext-toplevel",
+    feature = "i3",
+    feature = "hyprland",
+    feature = "plasma"
+)))]
+compile_error!(
+    "at least one of the `x11`, `wlr`, `ext-toplevel`, `i3`, `hyprland`, or `plasma` features must be enabled on Linux"
+);
+
+// None of the backends know about applications, so their events are derived from window events.
+pub const APPLICATION_EVENTS: bool = false;
+
+/// Every backend compiled in, in order of preference.
+pub const BACKENDS: &[Backend] = &[
+    // The window manager's own IPC exposes more than the generic protocols.
+    #[cfg(feature = "i3")]
+    Backend::I3,
+    #[cfg(feature = "hyprland")]
+    Backend::Hyprland,
+    #[cfg(feature = "plasma")]
+    Backend::Plasma,
+    #[cfg(feature = "wlr")]
+    Backend::Wlr,
+    #[cfg(feature = "ext-toplevel")]
+    Backend::ExtToplevel,
+    // Under XWayland, X11 can only see X11 clients, so prefer the Wayland backends when possible.
+    #[cfg(feature = "x11")]
+    Backend::X11,
+];
+
+// Calls the same method on whichever backend the value belongs to.
+macro_rules! dispatch {
+    ($value:expr, $inner:ident => $body:expr) => {
+        match $value {
+            #[cfg(feature = "x11")]
+            Self::X11($inner) => $body,
+            #[cfg(feature = "wlr")]
+            Self::Wlr($inner) => $body,
+            #[cfg(feature = "ext-toplevel")]
+            Self::ExtToplevel($inner) => $body,
+            #[cfg(feature = "i3")]
+            Self::I3($inner) => $body,
+            #[cfg(feature = "hyprland")]
+            Self::Hyprland($inner) => $body,
+            #[cfg(feature = "plasma")]
+            Self::Plasma($inner) => $body,
+        }
+    };
+}
+
+// Implements `From` for the inner type of each variant so that backends can `.into()` their own types.
+macro_rules! impl_from {
+    ($ty:ident, $($feature:literal => $variant:ident($inner:ty)),* $(,)?) => {
+        $(
+            #[cfg(feature = $feature)]
+            impl From<$inner> for $ty {
+                fn from(inner: $inner) -> Self {
+                    $ty::$variant(inner)
+                }
+            }
+        )*
+    };
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum WindowHandle {
+    #[cfg(feature = "x11")]
+    X11(x11::WindowHandle),
+    #[cfg(feature = "wlr")]
+    Wlr(wlr::WindowHandle),
+    #[cfg(feature = "ext-toplevel")]
+    ExtToplevel(ext_toplevel::WindowHandle),
+    #[cfg(feature = "i3")]
+    I3(i3::WindowHandle),
+    #[cfg(feature = "hyprland")]
+    Hyprland(hyprland::WindowHandle),
+    #[cfg(feature = "plasma")]
+    Plasma(plasma::WindowHandle),
+}
+
+impl_from!(
+    WindowHandle,
+    "x11" => X11(x11::WindowHandle),
+    "wlr" => Wlr(wlr::WindowHandle),
+    "ext-toplevel" => ExtToplevel(ext_toplevel::WindowHandle),
+    "i3" => I3(i3::WindowHandle),
+    "hyprland" => Hyprland(hyprland::WindowHandle),
+    "plasma" => Plasma(plasma::WindowHandle),
+);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Window {
+    #[cfg(feature = "x11")]
+    X11(x11::Window),
+    #[cfg(feature = "wlr")]
+    Wlr(wlr::Window),
+    #[cfg(feature = "ext-toplevel")]
+    ExtToplevel(ext_toplevel::Window),
+    #[cfg(feature = "i3")]
+    I3(i3::Window),
+    #[cfg(feature = "hyprland")]
+    Hyprland(hyprland::Window),
+    #[cfg(feature = "plasma")]
+    Plasma(plasma::Window),
+}
+
+impl_from!(
+    Window,
+    "x11" => X11(x11::Window),
+    "wlr" => Wlr(wlr::Window),
+    "ext-toplevel" => ExtToplevel(ext_toplevel::Window),
+    "i3" => I3(i3::Window),
+    "hyprland" => Hyprland(hyprland::Window),
+    "plasma" => Plasma(plasma::Window),
+);
+
+impl Window {
+    pub fn handle(&self) -> WindowHandle {
+        dispatch!(self, window => window.handle().into())
+    }
+
+    pub fn title(&self) -> Result<String, WindowError> {
+        dispatch!(self, window => window.title())
+    }
+
+    pub fn size(&self) -> Result<LogicalSize, WindowError> {
+        dispatch!(self, window => window.size())
+    }
+
+    pub fn position(&self) -> Result<LogicalPosition, WindowError> {
+        dispatch!(self, window => window.position())
+    }
+
+    pub fn scale_factor(&self) -> Result<f64, WindowError> {
+        dispatch!(self, window => window.scale_factor())
+    }
+
+    pub fn is_focused(&self) -> Result<bool, WindowError> {
+        dispatch!(self, window => window.is_focused())
+    }
+
+    pub fn is_fullscreen(&self) -> Result<bool, WindowError> {
+        dispatch!(self, window => window.is_fullscreen())
+    }
+
+    pub fn is_minimized(&self) -> Result<bool, WindowError> {
+        dispatch!(self, window => window.is_minimized())
+    }
+
+    pub fn is_hidden(&self) -> Result<bool, WindowError> {
+        dispatch!(self, window => window.is_hidden())
+    }
+
+    pub fn properties(&self, mask: PropertyMask) -> Result<WindowProperties, WindowError> {
+        match self {
+            #[cfg(feature = "x11")]
+            Self::X11(window) => window.properties(mask),
+            #[cfg(feature = "i3")]
+            Self::I3(window) => window.properties(mask),
+            #[cfg(feature = "hyprland")]
+            Self::Hyprland(window) => window.properties(mask),
+            // The Wayland protocols push every property to us, so they are already cached locally.
+            #[allow(unreachable_patterns)]
+            _ => WindowProperties::fetch(self, mask),
+        }
+    }
+
+    pub fn resize(&self, size: LogicalSize) -> Result<(), WindowError> {
+        dispatch!(self, window => window.resize(size))
+    }
+
+    pub fn reposition(&self, position: LogicalPosition) -> Result<(), WindowError> {
+        dispatch!(self, window => window.reposition(position))
+    }
+
+    pub fn frame(&self) -> Result<Rect, WindowError> {
+        dispatch!(self, window => window.frame())
+    }
+
+    pub fn set_frame(&self, frame: Rect) -> Result<Rect, WindowError> {
+        dispatch!(self, window => window.set_frame(frame))
+    }
+
+    pub fn decoration_insets(&self) -> Result<Insets, WindowError> {
+        dispatch!(self, window => window.decoration_insets())
+    }
+
+    pub fn focus(&self) -> Result<(), WindowError> {
+        dispatch!(self, window => window.focus())
+    }
+
+    pub fn fullscreen(&self) -> Result<(), WindowError> {
+        dispatch!(self, window => window.fullscreen())
+    }
+
+    pub fn unfullscreen(&self) -> Result<(), WindowError> {
+        dispatch!(self, window => window.unfullscreen())
+    }
+
+    pub fn maximize(&self) -> Result<(), WindowError> {
+        dispatch!(self, window => window.maximize())
+    }
+
+    pub fn minimize(&self) -> Result<(), WindowError> {
+        dispatch!(self, window => window.minimize())
+    }
+
+    pub fn unminimize(&self) -> Result<(), WindowError> {
+        dispatch!(self, window => window.unminimize())
+    }
+
+    pub fn show(&self) -> Result<(), WindowError> {
+        dispatch!(self, window => window.show())
+    }
+
+    pub fn hide(&self) -> Result<(), WindowError> {
+        dispatch!(self, window => window.hide())
+    }
+
+    pub fn bring_to_front(&self) -> Result<(), WindowError> {
+        dispatch!(self, window => window.bring_to_front())
+    }
+
+    pub fn close(&self) -> Result<(), WindowError> {
+        dispatch!(self, window => window.close())
+    }
+
+    pub fn pid(&self) -> Result<u32, WindowError> {
+        dispatch!(self, window => window.pid())
+    }
+
+    #[cfg(feature = "x11")]
+    fn as_x11(&self) -> Option<&x11::Window> {
+        #[allow(irrefutable_let_patterns)]
+        if let Window::X11(window) = self {
+            Some(window)
+        } else {
+            None
+        }
+    }
+
+    #[cfg(feature = "i3")]
+    fn as_i3(&self) -> Option<&i3::Window> {
+        #[allow(irrefutable_let_patterns)]
+        if let Window::I3(window) = self {
+            Some(window)
+        } else {
+            None
+        }
+    }
+
+    #[cfg(feature = "hyprland")]
+    fn as_hyprland(&self) -> Option<&hyprland::Window> {
+        #[allow(irrefutable_let_patterns)]
+        if let Window::Hyprland(window) = self {
+            Some(window)
+        } else {
+            None
+        }
+    }
+
+    pub fn backend(&self) -> Backend {
+        match self {
+            #[cfg(feature = "x11")]
+            Window::X11(_) => Backend::X11,
+            #[cfg(feature = "wlr")]
+            Window::Wlr(_) => Backend::Wlr,
+            #[cfg(feature = "ext-toplevel")]
+            Window::ExtToplevel(_) => Backend::ExtToplevel,
+            #[cfg(feature = "i3")]
+            Window::I3(_) => Backend::I3,
+            #[cfg(feature = "hyprland")]
+            Window::Hyprland(_) => Backend::Hyprland,
+            #[cfg(feature = "plasma")]
+            Window::Plasma(_) => Backend::Plasma,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Watcher {
+    // The X11 watcher caches the state of every window, so keep it off the stack.
+    #[cfg(feature = "x11")]
+    X11(Box<x11::Watcher>),
+    #[cfg(feature = "wlr")]
+    Wlr(wlr::Watcher),
+    #[cfg(feature = "ext-toplevel")]
+    ExtToplevel(ext_toplevel::Watcher),
+    #[cfg(feature = "i3")]
+    I3(i3::Watcher),
+    #[cfg(feature = "hyprland")]
+    Hyprland(hyprland::Watcher),
+    #[cfg(feature = "plasma")]
+    Plasma(plasma::Watcher),
+}
+
+impl Watcher {
+    pub fn next_request(&mut self) -> Result<WindowEvent, WindowError> {
+        dispatch!(self, watcher => watcher.next_request())
+    }
+
+    pub fn next_request_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<WindowEvent>, WindowError> {
+        dispatch!(self, watcher => watcher.next_request_timeout(timeout))
+    }
+
+    pub fn event_time(&self) -> Option<Instant> {
+        match self {
+            #[cfg(feature = "x11")]
+            Watcher::X11(watcher) => watcher.event_time(),
+            // Neither Wayland nor the i3 and Hyprland IPC report when events happened.
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+}
+
+/// Returns the compiled backends that match the current session, in order of preference.
+pub fn detect() -> Vec<Backend> {
+    let session_type = env::var("XDG_SESSION_TYPE").unwrap_or_default();
+    let wayland = env::var_os("WAYLAND_DISPLAY").is_some() || session_type == "wayland";
+    // XWayland sets `DISPLAY` in Wayland sessions too.
+    let x11 = env::var_os("DISPLAY").is_some() || session_type == "x11";
+
+    BACKENDS
+        .iter()
+        .copied()
+        .filter(|backend| match backend {
+            Backend::X11 => x11,
+            Backend::Wlr | Backend::ExtToplevel | Backend::Plasma => wayland,
+            Backend::I3 => env::var_os("I3SOCK").is_some() || env::var_os("SWAYSOCK").is_some(),
+            Backend::Hyprland => env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some(),
+            _ => false,
+        })
+        .collect()
+}
+
+pub fn connect(backend: Backend) -> Result<(), WindowError> {
+    match backend {
+        #[cfg(feature = "x11")]
+        Backend::X11 => x11::connection().map(|_| ()),
+        #[cfg(feature = "wlr")]
+        Backend::Wlr => wlr::session().map(|_| ()),
+        #[cfg(feature = "ext-toplevel")]
+        Backend::ExtToplevel => ext_toplevel::session().map(|_| ()),
+        #[cfg(feature = "i3")]
+        Backend::I3 => i3::connection().map(|_| ()),
+        #[cfg(feature = "hyprland")]
+        Backend::Hyprland => hyprland::instance().map(|_| ()),
+        #[cfg(feature = "plasma")]
+        Backend::Plasma => plasma::session().map(|_| ()),
+        _ => Err(WindowError::Unsupported),
+    }
+}
+
+// The Linux backends receive every event over a single connection, so there is nothing to gain
+// from the filter.
+pub fn watch(backend: Backend, _filter: &EventFilter) -> Result<Watcher, WindowError> {
+    match backend {
+        #[cfg(feature = "x11")]
+        Backend::X11 => x11::Watcher::new().map(|watcher| Watcher::X11(Box::new(watcher))),
+        #[cfg(feature = "wlr")]
+        Backend::Wlr => wlr::Watcher::new().map(Watcher::Wlr),
+        #[cfg(feature = "ext-toplevel")]
+        Backend::ExtToplevel => ext_toplevel::Watcher::new().map(Watcher::ExtToplevel),
+        #[cfg(feature = "i3")]
+        Backend::I3 => i3::Watcher::new().map(Watcher::I3),
+        #[cfg(feature = "hyprland")]
+        Backend::Hyprland => hyprland::Watcher::new().map(Watcher::Hyprland),
+        #[cfg(feature = "plasma")]
+        Backend::Plasma => plasma::Watcher::new().map(Watcher::Plasma),
+        _ => Err(WindowError::Unsupported),
+    }
+}
+
+pub fn iter_windows(backend: Backend) -> Box<dyn Iterator<Item = Result<Window, WindowError>>> {
+    match backend {
+        #[cfg(feature = "x11")]
+        Backend::X11 => Box::new(x11::iter_windows().map(|result| result.map(Window::X11))),
+        #[cfg(feature = "wlr")]
+        Backend::Wlr => Box::new(wlr::iter_windows().map(|result| result.map(Window::Wlr))),
+        #[cfg(feature = "ext-toplevel")]
+        Backend::ExtToplevel => {
+            Box::new(ext_toplevel::iter_windows().map(|result| result.map(Window::ExtToplevel)))
+        }
+        #[cfg(feature = "i3")]
+        Backend::I3 => Box::new(i3::iter_windows().map(|result| result.map(Window::I3))),
+        #[cfg(feature = "hyprland")]
+        Backend::Hyprland => {
+            Box::new(hyprland::iter_windows().map(|result| result.map(Window::Hyprland)))
+        }
+        #[cfg(feature = "plasma")]
+        Backend::Plasma => {
+            Box::new(plasma::iter_windows().map(|result| result.map(Window::Plasma)))
+        }
+        _ => Box::new(std::iter::once(Err(WindowError::Unsupported))),
+    }
+}
+
+pub fn focused_window(backend: Backend) -> Result<Option<Window>, WindowError> {
+    match backend {
+        #[cfg(feature = "x11")]
+        Backend::X11 => x11::focused_window().map(|option| option.map(Window::X11)),
+        #[cfg(feature = "wlr")]
+        Backend::Wlr => wlr::focused_window().map(|option| option.map(Window::Wlr)),
+        #[cfg(feature = "ext-toplevel")]
+        Backend::ExtToplevel => {
+            ext_toplevel::focused_window().map(|option| option.map(Window::ExtToplevel))
+        }
+        #[cfg(feature = "i3")]
+        Backend::I3 => i3::focused_window().map(|option| option.map(Window::I3)),
+        #[cfg(feature = "hyprland")]
+        Backend::Hyprland => hyprland::focused_window().map(|option| option.map(Window::Hyprland)),
+        #[cfg(feature = "plasma")]
+        Backend::Plasma => plasma::focused_window().map(|option| option.map(Window::Plasma)),
+        _ => Err(WindowError::Unsupported),
+    }
+}
+
+pub fn stacking_order(backend: Backend) -> Result<Vec<Window>, WindowError> {
+    match backend {
+        #[cfg(feature = "x11")]
+        Backend::X11 => Ok(x11::stacking_order()?
+            .into_iter()
+            .map(Window::X11)
+            .collect()),
+        // The Wayland protocols and window manager IPCs don't expose the stacking order.
+        _ => Err(WindowError::Unsupported),
+    }
+}
+
+// Every backend reports the pid of the local process, so `/proc` describes it regardless of the backend.
+pub fn application_name(pid: u32) -> Result<String, WindowError> {
+    let comm = fs::read_to_string(format!("/proc/{pid}/comm")).map_err(process_error)?;
+    Ok(comm.trim_end_matches('\n').to_owned())
+}
+
+pub fn executable_path(pid: u32) -> Result<PathBuf, WindowError> {
+    fs::read_link(format!("/proc/{pid}/exe")).map_err(process_error)
+}
+
+// None of the backends can activate an application as a whole, so focus one of its windows instead.
+pub fn activate_application(backend: Backend, pid: u32) -> Result<(), WindowError> {
+    for window in iter_windows(backend) {
+        let window = window?;
+        if window.pid().ok() == Some(pid) {
+            return window.focus();
+        }
+    }
+
+    Err(WindowError::InvalidHandle)
+}
+
+// Changes are grouped by backend so that each can batch its own, the backends without a batch
+// primitive apply them one at a time.
+pub fn commit(changes: &[(&Window, Change)]) -> Vec<Result<(), WindowError>> {
+    // Only mutated by the backends that can batch changes.
+    #[allow(unused_mut)]
+    let mut results: Vec<Option<Result<(), WindowError>>> = changes.iter().map(|_| None).collect();
+    #[cfg(feature = "x11")]
+    batch(changes, &mut results, Window::as_x11, x11::commit);
+    #[cfg(feature = "i3")]
+    batch(changes, &mut results, Window::as_i3, i3::commit);
+    #[cfg(feature = "hyprland")]
+    batch(changes, &mut results, Window::as_hyprland, hyprland::commit);
+
+    changes
+        .iter()
+        .zip(results)
+        .map(|((window, change), result)| result.unwrap_or_else(|| change.apply(*window)))
+        .collect()
+}
+
+#[cfg(any(feature = "x11", feature = "i3", feature = "hyprland"))]
+fn batch<'a, T: 'a>(
+    changes: &[(&'a Window, Change)],
+    results: &mut [Option<Result<(), WindowError>>],
+    select: impl Fn(&'a Window) -> Option<&'a T>,
+    commit: impl FnOnce(&[(&'a T, Change)]) -> Result<Vec<Result<(), WindowError>>, WindowError>,
+) {
+    let (indices, batch): (Vec<_>, Vec<_>) = changes
+        .iter()
+        .enumerate()
+        .filter_map(|(index, (window, change))| Some((index, (select(window)?, *change))))
+        .unzip();
+    if batch.is_empty() {
+        return;
+    }
+
+    // If the batch couldn't be sent at all, the changes are applied one at a time instead.
+    let Ok(batched) = commit(&batch) else {
+        return;
+    };
+    for (index, result) in indices.into_iter().zip(batched) {
+        results[index] = Some(result);
+    }
+}
+
+pub fn properties(
+    windows: &[&Window],
+    mask: PropertyMask,
+) -> Vec<Result<WindowProperties, WindowError>> {
+    #[cfg(feature = "x11")]
+    if let Some(properties) = batch_properties(windows, mask, Window::as_x11, x11::properties) {
+        return properties;
+    }
+    #[cfg(feature = "i3")]
+    if let Some(properties) = batch_properties(windows, mask, Window::as_i3, i3::properties) {
+        return properties;
+    }
+    #[cfg(feature = "hyprland")]
+    if let Some(properties) =
+        batch_properties(windows, mask, Window::as_hyprland, hyprland::properties)
+    {
+        return properties;
+    }
+
+    windows
+        .iter()
+        .map(|window| window.properties(mask))
+        .collect()
+}
+
+// Windows from the same `iter_windows` always share a backend, otherwise they're fetched one at a
+// time.
+#[cfg(any(feature = "x11", feature = "i3", feature = "hyprland"))]
+fn batch_properties<'a, T: 'a>(
+    windows: &[&'a Window],
+    mask: PropertyMask,
+    select: impl Fn(&'a Window) -> Option<&'a T>,
+    properties: impl FnOnce(
+        &[&'a T],
+        PropertyMask,
+    ) -> Result<Vec<Result<WindowProperties, WindowError>>, WindowError>,
+) -> Option<Vec<Result<WindowProperties, WindowError>>> {
+    let windows = windows
+        .iter()
+        .map(|window| select(window))
+        .collect::<Option<Vec<_>>>()?;
+    if windows.is_empty() {
+        return None;
+    }
+
+    properties(&windows, mask).ok()
+}
+
+pub fn kill_process(pid: u32) -> Result<(), WindowError> {
+    let pid = libc::pid_t::try_from(pid).map_err(|_| WindowError::InvalidHandle)?;
+    if unsafe { libc::kill(pid, libc::SIGKILL) } == 0 {
+        Ok(())
+    } else {
+        Err(process_error(io::Error::last_os_error()))
+    }
+}
+
+// The name of this machine, as X11 clients report it in `WM_CLIENT_MACHINE`.
+#[cfg(feature = "x11")]
+pub(crate) fn hostname() -> Result<Vec<u8>, WindowError> {
+    let mut name = [0u8; 256];
+    if unsafe { libc::gethostname(name.as_mut_ptr().cast(), name.len()) } != 0 {
+        return Err(WindowError::OsError(io::Error::last_os_error()));
+    }
+    let len = name
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(name.len());
+    Ok(name[..len].to_vec())
+}
+
+fn process_error(err: io::Error) -> WindowError {
+    match err.kind() {
+        // The process exited.
+        io::ErrorKind::NotFound => WindowError::InvalidHandle,
+        _ if err.raw_os_error() == Some(libc::ESRCH) => WindowError::InvalidHandle,
+        _ => WindowError::OsError(err),
+    }
+}
+
+// How long the window manager is given to apply a frame that it adjusted or refused.
+#[cfg(any(feature = "x11", feature = "i3", feature = "hyprland"))]
+const SETTLE_TIMEOUT: Duration = Duration::from_millis(200);
+
+// Window managers apply frame requests asynchronously, so the frame is read until it matches the
+// request, or until the timeout if the window manager adjusted it (e.g. to a minimum size).
+#[cfg(any(feature = "x11", feature = "i3", feature = "hyprland"))]
+pub(crate) fn settled_frame(
+    requested: Rect,
+    frame: impl Fn() -> Result<Rect, WindowError>,
+) -> Result<Rect, WindowError> {
+    let deadline = Instant::now() + SETTLE_TIMEOUT;
+    loop {
+        let current = frame()?;
+        // Requests are sent in whole pixels.
+        let settled = [
+            (current.position.x, requested.position.x),
+            (current.position.y, requested.position.y),
+            (current.size.width, requested.size.width),
+            (current.size.height, requested.size.height),
+        ]
+        .iter()
+        .all(|(current, requested)| (current - requested).abs() < 1.0);
+        if settled || Instant::now() >= deadline {
+            return Ok(current);
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+// Waits until the socket has data to read, returning `false` if the deadline passes first.
+#[cfg(any(feature = "x11", feature = "i3", feature = "hyprland"))]
+pub(crate) fn wait_readable(fd: BorrowedFd, deadline: Instant) -> Result<bool, WindowError> {
+    loop {
+        let mut poll_fd = libc::pollfd {
+            fd: fd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // Round up, otherwise the last millisecond would be spent polling without waiting.
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let timeout = remaining.as_micros().div_ceil(1000).min(i32::MAX as u128) as i32;
+        match unsafe { libc::poll(&mut poll_fd, 1, timeout) } {
+            0 => return Ok(false),
+            // A closed socket is readable too, reading it reports the error.
+            count if count > 0 => return Ok(true),
+            _ => {
+                let err = io::Error::last_os_error();
+                if err.kind() != io::ErrorKind::Interrupted {
+                    return Err(WindowError::OsError(err));
+                }
+            }
+        }
+    }
+}
+
+// None of the Linux backends have a concept of permissions.
+pub fn trusted() -> bool {
+    true
+}
+
+pub fn request_trust() -> Result<bool, WindowError> {
+    Ok(true)
+}
@@ -10,8 +10,9 @@ use objc2_application_services::{AXError, AXObserver, AXUIElement};
 use objc2_core_foundation::{kCFRunLoopDefaultMode, CFArray, CFRetained, CFRunLoop, CFString};
 
 use crate::{
-    protocol::{self, WindowError, WindowEvent},
+    protocol::{self, EventMask, WindowError, WindowEvent},
     sys::platform::ffi::{self, kAXFocusedWindowAttribute, CFRetainedSafe},
+    Backend,
 };
 
 use super::{
@@ -83,8 +84,9 @@ impl Application {
     pub fn watch(
         &self,
         sender: Sender<Result<WindowEvent, WindowError>>,
+        events: EventMask,
     ) -> Result<Watcher, WindowError> {
-        Watcher::new(self, sender)
+        Watcher::new(self, sender, events)
     }
 
     // Some processes aren't immediately accessible by the AX API and default to erroring with kAXErrorCannotComplete.
@@ -173,9 +175,11 @@ impl Watcher {
         // kAXUIElementDestroyedNotification, // NOTE: commented because it's manually handled
     ];
 
+    // Only the notifications that are reported as one of the events are registered.
     pub fn new(
         app: &Application,
         sender: Sender<Result<WindowEvent, WindowError>>,
+        events: EventMask,
     ) -> Result<Watcher, WindowError> {
         let mut observer = ptr::null_mut();
         let result = unsafe {
@@ -189,13 +193,21 @@ impl Watcher {
             AXError::Success => {
                 let observer = unsafe { CFRetained::from_raw(NonNull::new_unchecked(observer)) };
 
-                let raw_windows = raw_windows(&app.inner)?;
-                let mut resources =
-                    Vec::with_capacity(raw_windows.len() + Watcher::NOTIFICATIONS.len());
+                let notifications = Watcher::NOTIFICATIONS
+                    .into_iter()
+                    .filter(|&notification| events.intersects(Watcher::events(notification)));
+                let raw_windows = events
+                    .contains(EventMask::CLOSED)
+                    .then(|| raw_windows(&app.inner))
+                    .transpose()?;
+                let mut resources = Vec::with_capacity(
+                    raw_windows.as_ref().map_or(0, |windows| windows.len())
+                        + Watcher::NOTIFICATIONS.len(),
+                );
 
                 // Since the destroyed notification doesn't include any information on the window, we must register
                 // for each window with opaque data specifying the window being destroyed.
-                for window_handle in raw_windows.iter() {
+                for window_handle in raw_windows.iter().flat_map(|windows| windows.iter()) {
                     unsafe {
                         // TODO: handle error?
                         window_handle.set_messaging_timeout(app.timeout.as_secs_f32());
@@ -217,7 +229,7 @@ impl Watcher {
                     resources.push(info)
                 }
 
-                for notification in Watcher::NOTIFICATIONS {
+                for notification in notifications {
                     let info = Box::new(CallbackInfo {
                         sender: sender.clone(),
                         notification: match notification {
@@ -270,6 +282,28 @@ impl Watcher {
         }
     }
 
+    // The events a notification is reported as.
+    fn events(notification: &str) -> EventMask {
+        match notification {
+            ffi::kAXWindowCreatedNotification => EventMask::OPENED,
+            ffi::kAXFocusedWindowChangedNotification => EventMask::FOCUSED,
+            ffi::kAXApplicationActivatedNotification => {
+                EventMask::FOCUSED | EventMask::APPLICATION_ACTIVATED
+            }
+            ffi::kAXMovedNotification => EventMask::MOVED,
+            ffi::kAXResizedNotification => EventMask::RESIZED,
+            ffi::kAXTitleChangedNotification => EventMask::RENAMED,
+            // Every window of the application is reported as well.
+            ffi::kAXApplicationShownNotification => EventMask::SHOWN | EventMask::APPLICATION_SHOWN,
+            ffi::kAXApplicationHiddenNotification => {
+                EventMask::HIDDEN | EventMask::APPLICATION_HIDDEN
+            }
+            ffi::kAXWindowMiniaturizedNotification => EventMask::MINIMIZED,
+            ffi::kAXWindowDeminiaturizedNotification => EventMask::UNMINIMIZED,
+            _ => unreachable!(),
+        }
+    }
+
     pub(crate) fn run_on_thread(&self, thread_loop: &CFRunLoop) {
         unsafe {
             thread_loop.add_source(
@@ -323,17 +357,29 @@ pub enum Notification {
 impl Notification {
     // TODO: kind of meh function that relies on the caller guaranteeing some constraints
     pub fn info(&self, window: Option<Window>) -> WindowEvent {
-        let window = window.map(protocol::Window);
+        let window = window.map(protocol::Window::from_sys);
         match self {
             Notification::Created(_) => WindowEvent::Opened(window.unwrap()),
             Notification::Destroyed(window_handle) => {
-                WindowEvent::Closed(protocol::WindowHandle(window_handle.clone()))
+                WindowEvent::Closed(protocol::WindowHandle::from_sys(window_handle.clone()))
             }
             Notification::Focused(_) => WindowEvent::Focused(window.unwrap()),
             Notification::Activated(_) => WindowEvent::Focused(window.unwrap()),
-            Notification::Moved(_) => WindowEvent::Moved(window.unwrap()),
-            Notification::Resized(_) => WindowEvent::Resized(window.unwrap()),
-            Notification::Renamed(_) => WindowEvent::Renamed(window.unwrap()),
+            Notification::Moved(_) => WindowEvent::Moved {
+                window: window.unwrap(),
+                old: None,
+                new: None,
+            },
+            Notification::Resized(_) => WindowEvent::Resized {
+                window: window.unwrap(),
+                old: None,
+                new: None,
+            },
+            Notification::Renamed(_) => WindowEvent::Renamed {
+                window: window.unwrap(),
+                old_title: None,
+                new_title: None,
+            },
             Notification::Shown(_) => WindowEvent::Shown(window.unwrap()),
             Notification::Hidden(_) => WindowEvent::Hidden(window.unwrap()),
             Notification::Miniaturized(_) => WindowEvent::Minimized(window.unwrap()),
@@ -378,6 +424,12 @@ unsafe extern "C-unwind" fn app_notification(
             (*callback_info).notification.info(Some(window))
         }
         Notification::Activated(app_handle) => {
+            if let Some(application) = application(app_handle) {
+                let _ = (*callback_info)
+                    .sender
+                    .send(Ok(WindowEvent::ApplicationActivated(application)));
+            }
+
             // TODO: lots of code dupe between above and from window module
             let mut window_handle = ptr::null();
             let result = unsafe {
@@ -401,15 +453,21 @@ unsafe extern "C-unwind" fn app_notification(
         }
         Notification::Shown(app_handle) | Notification::Hidden(app_handle) => {
             // TODO: we do a lot of error skipping here, reevaluate
-            let mut pid = 0;
-            let result = unsafe { app_handle.pid(NonNull::new_unchecked(&mut pid)) };
-            if result == AXError::Success {
-                if let Ok(window_iter) = Application::new(pid).iter_windows() {
-                    for window in window_iter.into_iter().flatten() {
-                        let _ = (*callback_info)
-                            .sender
-                            .send(Ok((*callback_info).notification.info(Some(window))));
-                    }
+            let Some(application) = application(app_handle) else {
+                return;
+            };
+            let pid = application.pid() as pid_t;
+            let event = match (*callback_info).notification {
+                Notification::Shown(_) => WindowEvent::ApplicationShown(application),
+                _ => WindowEvent::ApplicationHidden(application),
+            };
+            let _ = (*callback_info).sender.send(Ok(event));
+
+            if let Ok(window_iter) = Application::new(pid).iter_windows() {
+                for window in window_iter.into_iter().flatten() {
+                    let _ = (*callback_info)
+                        .sender
+                        .send(Ok((*callback_info).notification.info(Some(window))));
                 }
             }
             return;
@@ -421,6 +479,13 @@ unsafe extern "C-unwind" fn app_notification(
     let _ = (*callback_info).sender.send(Ok(event));
 }
 
+fn application(app_handle: &AXUIElement) -> Option<protocol::Application> {
+    let mut pid = 0;
+    let result = unsafe { app_handle.pid(NonNull::new_unchecked(&mut pid)) };
+    (result == AXError::Success)
+        .then(|| protocol::Application::from_sys(Backend::MacOS, pid as u32))
+}
+
 pub(super) fn raw_windows(
     inner: &AXUIElement,
 ) -> Result<CFRetained<CFArray<AXUIElement>>, WindowError> {
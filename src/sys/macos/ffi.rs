@@ -3,7 +3,7 @@
 use std::ops::Deref;
 
 use objc2_application_services::AXUIElement;
-use objc2_core_foundation::{CFRetained, Type};
+use objc2_core_foundation::{CFRetained, CGPoint, Type};
 
 pub const kAXFrontmostAttribute: &str = "AXFrontmost";
 pub const kAXWindowsAttribute: &str = "AXWindows";
@@ -13,7 +13,9 @@ pub const kAXPositionAttribute: &str = "AXPosition";
 pub const kAXTitleAttribute: &str = "AXTitle";
 pub const kAXFocusedWindowAttribute: &str = "AXFocusedWindow";
 pub const kAXFullScreenAttribute: &str = "AXFullScreen";
+pub const kAXCloseButtonAttribute: &str = "AXCloseButton";
 pub const kAXRaiseAction: &str = "AXRaise";
+pub const kAXPressAction: &str = "AXPress";
 
 pub const kAXApplicationActivatedNotification: &str = "AXApplicationActivated";
 pub const kAXResizedNotification: &str = "AXResized";
@@ -27,7 +29,12 @@ pub const kAXFocusedWindowChangedNotification: &str = "AXFocusedWindowChanged";
 pub const kAXMovedNotification: &str = "AXMoved";
 pub const kAXTitleChangedNotification: &str = "AXTitleChanged";
 
+// https://developer.apple.com/documentation/appkit/nswindow/stylemask-swift.struct/titled
+pub const NSWindowStyleMaskTitled: usize = 1 << 0;
+
 pub type CGWindowID = u32;
+pub type CGDirectDisplayID = u32;
+pub type CGDisplayModeRef = *mut std::ffi::c_void;
 
 // TODO: AXUIElementRefs can be compared for equality using CFEqual, impl Eq for Window as well
 //       https://lists.apple.com/archives/accessibility-dev/2006/Jun/msg00010.html
@@ -64,3 +71,18 @@ extern "C" {
     pub fn _AXUIElementGetWindow(element: &AXUIElement, identifier: *mut CGWindowID) -> i32;
 
 }
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    pub fn CGGetDisplaysWithPoint(
+        point: CGPoint,
+        max_displays: u32,
+        displays: *mut CGDirectDisplayID,
+        matching_display_count: *mut u32,
+    ) -> i32;
+    pub fn CGMainDisplayID() -> CGDirectDisplayID;
+    pub fn CGDisplayCopyDisplayMode(display: CGDirectDisplayID) -> CGDisplayModeRef;
+    pub fn CGDisplayModeGetWidth(mode: CGDisplayModeRef) -> usize;
+    pub fn CGDisplayModeGetPixelWidth(mode: CGDisplayModeRef) -> usize;
+    pub fn CGDisplayModeRelease(mode: CGDisplayModeRef);
+}
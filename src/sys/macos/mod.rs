@@ -5,15 +5,19 @@ use std::{
     io,
     iter::{self, Once},
     marker::PhantomData,
+    path::PathBuf,
     ptr,
     sync::mpsc::{self, Receiver, Sender},
     thread::{self, ThreadId},
-    time::Instant,
+    time::{Duration, Instant},
 };
 
 use libc::pid_t;
 use objc2::{define_class, msg_send, rc::Retained, runtime::AnyObject, AnyThread};
-use objc2_app_kit::{NSApplicationActivationPolicy, NSRunningApplication, NSWorkspace};
+use objc2_app_kit::{
+    NSApplicationActivationOptions, NSApplicationActivationPolicy, NSRunningApplication,
+    NSWorkspace,
+};
 use objc2_application_services::{
     kAXTrustedCheckOptionPrompt, AXError, AXIsProcessTrusted, AXIsProcessTrustedWithOptions,
     AXUIElement,
@@ -28,8 +32,13 @@ use objc2_foundation::{
 };
 
 use crate::{
-    protocol::{WindowError, WindowEvent},
+    protocol::{
+        self,
+        transaction::{self, Change},
+        EventFilter, WindowError, WindowEvent,
+    },
     sys::platform::ffi::CFRetainedSafe,
+    Backend,
 };
 
 use self::application::WindowIterator;
@@ -43,6 +52,9 @@ const TIMEOUT_STEPS: u32 = 10;
 
 pub type WindowHandle = CFRetainedSafe<AXUIElement>;
 
+// The watcher observes running applications, which is how it finds their windows to begin with.
+pub const APPLICATION_EVENTS: bool = true;
+
 // TODO: various properties of windows
 // https://github.com/nikitabobko/AeroSpace/blob/0569bb0d663ebf732c2ea12cc168d4ff60378394/src/util/accessibility.swift#L24
 // interesting: https://github.com/nikitabobko/AeroSpace/blob/0569bb0d663ebf732c2ea12cc168d4ff60378394/src/util/accessibility.swift#L296
@@ -72,6 +84,8 @@ pub struct Watcher {
     sender: Sender<Result<WindowEvent, WindowError>>,
     receiver: Receiver<Result<WindowEvent, WindowError>>,
     watchers: HashMap<pid_t, WatcherState>,
+    // Decides which applications are registered, and for which notifications.
+    filter: EventFilter,
     thread_id: ThreadId,
     // NOTE: replace with negative_impls when stabilized
     //       https://github.com/rust-lang/rust/issues/68318
@@ -80,7 +94,7 @@ pub struct Watcher {
 }
 
 impl Watcher {
-    pub fn new() -> Result<Watcher, WindowError> {
+    pub fn new(filter: &EventFilter) -> Result<Watcher, WindowError> {
         // Start the app watcher so we never miss any new apps while registering existing apps.
         let app_watcher = AppWatcher::new();
 
@@ -89,7 +103,7 @@ impl Watcher {
                 .context
                 .sender
                 .send(AppEvent {
-                    kind: AppEventKind::Launched,
+                    kind: AppEventKind::Running,
                     pid: app.pid(),
                 })
                 // Sender + Receiver always exist.
@@ -102,29 +116,38 @@ impl Watcher {
             sender,
             receiver,
             watchers: HashMap::new(),
+            filter: filter.clone(),
             thread_id: thread::current().id(),
             _not_send_sync: PhantomData,
         })
     }
 
-    // TODO: same as below, but orders the output
-    // pub fn next_request_buffered_ordered(
-    //     &self,
-    //     interval: Duration,
-    // ) -> Result<WindowEvent, WindowError> {
-    //     todo!()
-    // }
-
-    // TODO: this function will call CFRunLoopInMode w/ interval seconds, it returns a list of events >= interval age
-    // pub fn next_request_buffered(&self, interval: Duration) -> Result<WindowEvent, WindowError> {
-    //     todo!()
-    // }
-
     // TODO: This function MUST be called on the thread its watchers were created (preferably main thread for perf/responsiveness?)
     //
     // TODO: the user may run their own run loop somewhere else, this code would interfere with that
     //       it may be wise to separate the run loop logic from the receiver logic
     pub fn next_request(&mut self) -> Result<WindowEvent, WindowError> {
+        loop {
+            if let Some(event) = self.run(None)? {
+                return Ok(event);
+            }
+        }
+    }
+
+    pub fn next_request_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<WindowEvent>, WindowError> {
+        self.run(Some(Instant::now() + timeout))
+    }
+
+    // The accessibility API doesn't report when events happened.
+    pub fn event_time(&self) -> Option<Instant> {
+        None
+    }
+
+    // Runs the run loop until an event is received, or the deadline passes.
+    fn run(&mut self, deadline: Option<Instant>) -> Result<Option<WindowEvent>, WindowError> {
         assert!(
             thread::current().id() == self.thread_id,
             "can only get next request on the same thread the `Watcher` was created"
@@ -138,42 +161,60 @@ impl Watcher {
         // * CFRunLoopInMode caches events internally when they happen. Calling the function will execute the callback for one event.
         // * The if statement below is to handle outstanding events (e.g. failing to register, new app added, etc.).
         if let Ok(event) = self.receiver.try_recv() {
-            return event;
+            return event.map(Some);
         }
 
         loop {
-            // TODO: it is impossible to get a timestamp for when an event occurs
-            //       this function should run the loop to completion each call and return a vector of events
-            //       this way, the next time this function is called, you know those events are guaranteed to happen after the last
-            //       vector of events. It provides some sense of ordering and the vector will only occasionally have >1 element
+            // It is impossible to get a timestamp for when an event occurs, they're stamped by the
+            // `Watcher` as they're returned from here instead.
+            let seconds = match deadline {
+                Some(deadline) => deadline
+                    .saturating_duration_since(Instant::now())
+                    .as_secs_f64(),
+                None => f64::MAX,
+            };
             unsafe {
                 // Possible errors:
                 // * kCFRunLoopRunFinished: Impossible to occur, there will always be the app watcher.
                 // * kCFRunLoopRunStopped: Can only occur if the user calls it, but who cares about them.
-                // * kCFRunLoopRunTimedOut: It would take millions of years for the interval to timeout.
+                // * kCFRunLoopRunTimedOut: Only once the deadline passes, without a deadline it would take millions of years.
                 // * kCFRunLoopRunHandledSource: AKA success.
-                CFRunLoop::run_in_mode(kCFRunLoopDefaultMode, f64::MAX, true);
+                CFRunLoop::run_in_mode(kCFRunLoopDefaultMode, seconds, true);
             }
 
-            // Handle registering/deregistering launched/terminated apps.
+            // Handle registering/deregistering launched/terminated apps, which also queues their events.
             if let Some(event) = self.app_watcher.next_request() {
                 self.handle_app_event(event)?;
-                // Since CFRunLoopInMode only processes one event at a time, skip checking for window events.
-                continue;
             }
 
             // It can only error w/ disconnected if the sender is disconnected, but that's not possible because we
             // always have a reference to the sender within this struct. If it errors with empty then we skip to the
             // next iteration.
             if let Ok(event) = self.receiver.try_recv() {
-                return event;
+                return event.map(Some);
+            }
+
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Ok(None);
             }
         }
     }
 
     fn handle_app_event(&mut self, event: AppEvent) -> Result<(), WindowError> {
         match event.kind {
-            AppEventKind::Launched => {
+            AppEventKind::Launched | AppEventKind::Running => {
+                let application = protocol::Application::from_sys(Backend::MacOS, event.pid as u32);
+                let included = self.filter.includes_application(&application);
+                if matches!(event.kind, AppEventKind::Launched) {
+                    let _ = self
+                        .sender
+                        .send(Ok(WindowEvent::ApplicationLaunched(application)));
+                }
+                // Registering takes a thread per application, so skip the ones that are filtered out.
+                if !included {
+                    return Ok(());
+                }
+
                 self.watchers
                     .insert(event.pid, WatcherState::Registering(event.pid));
 
@@ -184,6 +225,7 @@ impl Watcher {
                 let source = CFRetainedSafe(self.app_watcher.context.source.clone());
                 // TODO: safe to unwrap?
                 let thread_loop = CFRetainedSafe(CFRunLoop::current().unwrap());
+                let events = self.filter.events();
 
                 thread::spawn(move || {
                     let app = Application::new(event.pid);
@@ -200,7 +242,7 @@ impl Watcher {
                         return;
                     }
 
-                    match app.watch(sender.clone()) {
+                    match app.watch(sender.clone(), events) {
                         Ok(watcher) => {
                             let thread_loop = thread_loop;
                             watcher.run_on_thread(&thread_loop);
@@ -221,6 +263,9 @@ impl Watcher {
             }
             AppEventKind::Terminated => {
                 self.watchers.remove(&event.pid);
+                let _ = self
+                    .sender
+                    .send(Ok(WindowEvent::ApplicationTerminated(event.pid as u32)));
             }
             AppEventKind::Registered(watcher) => {
                 // If it already exists in the hash map, then it MUST be WatcherState::Registering, which is the only acceptable case.
@@ -283,6 +328,59 @@ pub fn iter_windows() -> impl Iterator<Item = Result<Window, WindowError>> {
     iter_windows_with_app_iter(iter_apps())
 }
 
+// The accessibility API only orders windows within an application, and the window server's order
+// can't be matched to accessibility elements without private API.
+pub fn stacking_order() -> Result<Vec<Window>, WindowError> {
+    Err(WindowError::Unsupported)
+}
+
+pub fn application_name(pid: u32) -> Result<String, WindowError> {
+    running_application(pid)?
+        .localizedName()
+        .map(|name| name.to_string())
+        .ok_or(WindowError::Unsupported)
+}
+
+pub fn executable_path(pid: u32) -> Result<PathBuf, WindowError> {
+    running_application(pid)?
+        .executableURL()
+        .and_then(|url| url.path())
+        .map(|path| PathBuf::from(path.to_string()))
+        .ok_or(WindowError::Unsupported)
+}
+
+pub fn activate_application(pid: u32) -> Result<(), WindowError> {
+    // Same as `Window::focus`, the deprecated option is the only way to steal focus.
+    #[allow(deprecated)]
+    let activated = running_application(pid)?
+        .activateWithOptions(NSApplicationActivationOptions::ActivateIgnoringOtherApps);
+    if activated {
+        Ok(())
+    } else {
+        // The application quit in the meantime.
+        Err(WindowError::InvalidHandle)
+    }
+}
+
+// The accessibility API has no batch primitive, each change is a separate message to the application.
+pub fn commit(changes: &[(&Window, Change)]) -> Vec<Result<(), WindowError>> {
+    transaction::apply_all(changes)
+}
+
+pub fn kill_process(pid: u32) -> Result<(), WindowError> {
+    if running_application(pid)?.forceTerminate() {
+        Ok(())
+    } else {
+        // The application already quit.
+        Err(WindowError::InvalidHandle)
+    }
+}
+
+fn running_application(pid: u32) -> Result<Retained<NSRunningApplication>, WindowError> {
+    NSRunningApplication::runningApplicationWithProcessIdentifier(pid as pid_t)
+        .ok_or(WindowError::InvalidHandle)
+}
+
 #[inline]
 fn iter_windows_with_app_iter(
     app_iter: impl Iterator<Item = impl Borrow<Application>>,
@@ -327,6 +425,8 @@ fn filter_apps(
 #[derive(Debug)]
 pub enum AppEventKind {
     Launched,
+    // The app was already running when the watcher was created.
+    Running,
     Terminated,
     Registered(application::Watcher),
 }
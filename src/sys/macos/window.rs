@@ -5,23 +5,30 @@ use std::{
 };
 
 use libc::pid_t;
-use objc2::{msg_send, rc::Retained, ClassType};
+use objc2::{class, msg_send, rc::Retained, ClassType};
 use objc2_app_kit::{NSApplicationActivationOptions, NSRunningApplication};
-use objc2_application_services::{AXError, AXUIElement, AXValue, AXValueType};
+use objc2_application_services::{
+    AXCopyMultipleAttributeOptions, AXError, AXUIElement, AXValue, AXValueType,
+};
 use objc2_core_foundation::{
-    kCFBooleanFalse, kCFBooleanTrue, CFBoolean, CFRetained, CFString, CFType, CGPoint, CGSize, Type,
+    kCFBooleanFalse, kCFBooleanTrue, CFArray, CFBoolean, CFRetained, CFString, CFType, CGPoint,
+    CGRect, CGSize, Type,
 };
 
 use crate::{
-    protocol::{Position, Size, WindowError},
+    protocol::{
+        Insets, LogicalPosition, LogicalSize, PropertyMask, Rect, WindowError, WindowProperties,
+    },
     sys::platform::ffi::CFRetainedSafe,
 };
 
 use super::{
     ffi::{
-        kAXFocusedWindowAttribute, kAXFrontmostAttribute, kAXFullScreenAttribute,
-        kAXMinimizedAttribute, kAXPositionAttribute, kAXRaiseAction, kAXSizeAttribute,
-        kAXTitleAttribute,
+        kAXCloseButtonAttribute, kAXFocusedWindowAttribute, kAXFrontmostAttribute,
+        kAXFullScreenAttribute, kAXMinimizedAttribute, kAXPositionAttribute, kAXPressAction,
+        kAXRaiseAction, kAXSizeAttribute, kAXTitleAttribute, CGDisplayCopyDisplayMode,
+        CGDisplayModeGetPixelWidth, CGDisplayModeGetWidth, CGDisplayModeRelease,
+        CGGetDisplaysWithPoint, CGMainDisplayID, NSWindowStyleMaskTitled,
     },
     WindowHandle,
 };
@@ -63,34 +70,58 @@ impl Window {
         Ok(title.to_string())
     }
 
-    // TODO: this returns the window size + title bar size.
-    //       should we return this? return only the content size?
-    //       make resize() include the title bar size? or just
-    //       document it?
-    pub fn size(&self) -> Result<Size, WindowError> {
+    // Both reading and writing the size include the title bar, so this is the outer frame.
+    pub fn size(&self) -> Result<LogicalSize, WindowError> {
         let frame: CGSize = Self::value_for_ax_value(
             &self.inner,
             &CFString::from_static_str(kAXSizeAttribute),
             AXValueType::CGSize,
         )?;
-        Ok(Size {
+        Ok(LogicalSize {
             width: frame.width,
             height: frame.height,
         })
     }
 
-    pub fn position(&self) -> Result<Position, WindowError> {
+    pub fn position(&self) -> Result<LogicalPosition, WindowError> {
         let frame: CGPoint = Self::value_for_ax_value(
             &self.inner,
             &CFString::from_static_str(kAXPositionAttribute),
             AXValueType::CGPoint,
         )?;
-        Ok(Position {
+        Ok(LogicalPosition {
             x: frame.x,
             y: frame.y,
         })
     }
 
+    // Like AppKit, the display containing the center of the window decides its scale. Points
+    // are logical pixels, so the scale is the ratio of the display mode's pixels to its points.
+    pub fn scale_factor(&self) -> Result<f64, WindowError> {
+        let frame = self.frame()?;
+        let center = CGPoint::new(
+            frame.position.x + frame.size.width / 2.0,
+            frame.position.y + frame.size.height / 2.0,
+        );
+
+        let mut display = 0;
+        let mut count = 0;
+        let result = unsafe { CGGetDisplaysWithPoint(center, 1, &mut display, &mut count) };
+        if result != 0 || count == 0 {
+            // The window is entirely off screen.
+            display = unsafe { CGMainDisplayID() };
+        }
+
+        let mode = unsafe { CGDisplayCopyDisplayMode(display) };
+        if mode.is_null() {
+            return Err(WindowError::Unsupported);
+        }
+        let scale =
+            unsafe { CGDisplayModeGetPixelWidth(mode) as f64 / CGDisplayModeGetWidth(mode) as f64 };
+        unsafe { CGDisplayModeRelease(mode) };
+        Ok(scale)
+    }
+
     pub fn is_focused(&self) -> Result<bool, WindowError> {
         // First check if the application is frontmost (AKA activated AKA application is focused).
         let frontmost = Self::bool_for_attribute(
@@ -130,8 +161,97 @@ impl Window {
         self.is_minimized()
     }
 
-    // TODO: this sets the inner window wsize (excluding title bar)
-    pub fn resize(&self, size: Size) -> Result<(), WindowError> {
+    // The attributes of the window are copied in one message and those of its application in
+    // another, rather than a message for each attribute.
+    pub fn properties(&self, mask: PropertyMask) -> Result<WindowProperties, WindowError> {
+        let attributes = [
+            (PropertyMask::TITLE, kAXTitleAttribute),
+            (PropertyMask::POSITION, kAXPositionAttribute),
+            (PropertyMask::SIZE, kAXSizeAttribute),
+            (PropertyMask::FULLSCREEN, kAXFullScreenAttribute),
+            // Hidden windows are minimized, see `Window::is_hidden`.
+            (
+                PropertyMask::MINIMIZED | PropertyMask::HIDDEN,
+                kAXMinimizedAttribute,
+            ),
+        ];
+        let requested: Vec<_> = attributes
+            .iter()
+            .filter(|(property, _)| mask.intersects(*property))
+            .map(|(_, attribute)| *attribute)
+            .collect();
+        let mut values = if requested.is_empty() {
+            Vec::new()
+        } else {
+            Self::values_for_attributes(&self.inner, &requested)?
+        }
+        .into_iter();
+        // The values are in the same order as the requested attributes.
+        let mut next = |property| {
+            if mask.intersects(property) {
+                values.next()
+            } else {
+                None
+            }
+        };
+
+        let title = next(PropertyMask::TITLE)
+            .and_then(|value| value.downcast::<CFString>().ok())
+            .map(|title| title.to_string());
+        let position = next(PropertyMask::POSITION)
+            .and_then(|value| value.downcast::<AXValue>().ok())
+            .and_then(|value| Self::decode_ax_value::<CGPoint>(&value, AXValueType::CGPoint))
+            .map(|point| LogicalPosition {
+                x: point.x,
+                y: point.y,
+            });
+        let size = next(PropertyMask::SIZE)
+            .and_then(|value| value.downcast::<AXValue>().ok())
+            .and_then(|value| Self::decode_ax_value::<CGSize>(&value, AXValueType::CGSize))
+            .map(|size| LogicalSize {
+                width: size.width,
+                height: size.height,
+            });
+        let fullscreen = next(PropertyMask::FULLSCREEN)
+            .and_then(|value| value.downcast::<CFBoolean>().ok())
+            .map(|value| value.value());
+        let minimized = next(PropertyMask::MINIMIZED | PropertyMask::HIDDEN)
+            .and_then(|value| value.downcast::<CFBoolean>().ok())
+            .map(|value| value.value());
+
+        Ok(WindowProperties {
+            title,
+            size,
+            position,
+            is_focused: mask
+                .select(PropertyMask::FOCUSED, || self.focused_in_application())
+                .transpose()?
+                .flatten(),
+            is_fullscreen: fullscreen,
+            is_minimized: minimized.filter(|_| mask.contains(PropertyMask::MINIMIZED)),
+            is_hidden: minimized.filter(|_| mask.contains(PropertyMask::HIDDEN)),
+        })
+    }
+
+    // Same as `Window::is_focused`, but with both attributes copied in a single message.
+    fn focused_in_application(&self) -> Result<Option<bool>, WindowError> {
+        let mut values = Self::values_for_attributes(
+            &self.app_handle,
+            &[kAXFrontmostAttribute, kAXFocusedWindowAttribute],
+        )?
+        .into_iter();
+        let frontmost = values
+            .next()
+            .and_then(|value| value.downcast::<CFBoolean>().ok())
+            .map(|value| value.value());
+        let window = values
+            .next()
+            .and_then(|value| value.downcast::<AXUIElement>().ok());
+        Ok(frontmost
+            .map(|frontmost| frontmost && window.is_some_and(|window| *window == *self.inner.0)))
+    }
+
+    pub fn resize(&self, size: LogicalSize) -> Result<(), WindowError> {
         let size = unsafe {
             AXValue::new(
                 AXValueType::CGSize,
@@ -149,7 +269,7 @@ impl Window {
         )
     }
 
-    pub fn reposition(&self, position: Position) -> Result<(), WindowError> {
+    pub fn reposition(&self, position: LogicalPosition) -> Result<(), WindowError> {
         let position = unsafe {
             AXValue::new(
                 AXValueType::CGPoint,
@@ -167,6 +287,43 @@ impl Window {
         )
     }
 
+    pub fn frame(&self) -> Result<Rect, WindowError> {
+        Ok(Rect {
+            position: self.position()?,
+            size: self.size()?,
+        })
+    }
+
+    // The accessibility API clamps the size to the display the window is on, so resize before
+    // moving it and again once it's on the new display.
+    pub fn set_frame(&self, frame: Rect) -> Result<Rect, WindowError> {
+        self.resize(frame.size)?;
+        self.reposition(frame.position)?;
+        self.resize(frame.size)?;
+        self.frame()
+    }
+
+    // The accessibility API doesn't expose the title bar, so AppKit is asked for the title bar of a
+    // standard titled window instead. Fullscreen windows hide their title bar.
+    pub fn decoration_insets(&self) -> Result<Insets, WindowError> {
+        if self.is_fullscreen()? {
+            return Ok(Insets::default());
+        }
+
+        let frame = CGRect::new(CGPoint::new(0.0, 0.0), CGSize::new(100.0, 100.0));
+        let content: CGRect = unsafe {
+            msg_send![
+                class!(NSWindow),
+                contentRectForFrameRect: frame,
+                styleMask: NSWindowStyleMaskTitled
+            ]
+        };
+        Ok(Insets {
+            top: frame.size.height - content.size.height,
+            ..Insets::default()
+        })
+    }
+
     pub fn focus(&self) -> Result<(), WindowError> {
         self.bring_to_front()?;
 
@@ -174,7 +331,7 @@ impl Window {
         unsafe {
             let app: Retained<NSRunningApplication> = msg_send![
                 NSRunningApplication::class(),
-                runningApplicationWithProcessIdentifier: self.pid()?
+                runningApplicationWithProcessIdentifier: self.pid()? as pid_t
             ];
             // TODO: supposedly this option is deprecated, but it does provide the behavior we want, TEST IT
             //       this method also returns a bool signifying if the app has quit or if it can be activated
@@ -244,11 +401,25 @@ impl Window {
         }
     }
 
-    fn pid(&self) -> Result<pid_t, WindowError> {
-        let mut pid = 0;
+    pub fn pid(&self) -> Result<u32, WindowError> {
+        let mut pid: pid_t = 0;
         let result = unsafe { self.app_handle.pid(NonNull::new_unchecked(&mut pid)) };
         if result == AXError::Success {
-            Ok(pid)
+            Ok(pid as u32)
+        } else {
+            Err(result.into())
+        }
+    }
+
+    // Windows without a close button (e.g. panels) can't be closed this way.
+    pub fn close(&self) -> Result<(), WindowError> {
+        let button = Self::value_for_attribute::<AXUIElement>(
+            &self.inner,
+            &CFString::from_static_str(kAXCloseButtonAttribute),
+        )?;
+        let result = unsafe { button.perform_action(&CFString::from_static_str(kAXPressAction)) };
+        if result == AXError::Success {
+            Ok(())
         } else {
             Err(result.into())
         }
@@ -282,26 +453,55 @@ impl Window {
         }
     }
 
+    // Copies every attribute in a single message. Attributes that fail, such as those the element
+    // doesn't support, hold an error value in place of their own.
+    fn values_for_attributes(
+        handle: &AXUIElement,
+        attributes: &[&'static str],
+    ) -> Result<Vec<CFRetained<CFType>>, WindowError> {
+        let attributes: Vec<_> = attributes
+            .iter()
+            .map(|attribute| CFString::from_static_str(attribute))
+            .collect();
+        let attributes = CFArray::from_retained_objects(&attributes);
+
+        let mut values = ptr::null();
+        let result = unsafe {
+            handle.copy_multiple_attribute_values(
+                attributes.as_opaque(),
+                AXCopyMultipleAttributeOptions(0),
+                NonNull::from_mut(&mut values),
+            )
+        };
+        if result == AXError::Success {
+            let values = unsafe {
+                CFRetained::from_raw(NonNull::new_unchecked(values as *mut CFArray<CFType>))
+            };
+            Ok(values.iter().collect())
+        } else {
+            Err(result.into())
+        }
+    }
+
     fn value_for_ax_value<T>(
         handle: &AXUIElement,
         attribute: &CFString,
         value_type: AXValueType,
     ) -> Result<T, WindowError> {
         let ax_value = Self::value_for_attribute::<AXValue>(handle, attribute)?;
+        Self::decode_ax_value(&ax_value, value_type).ok_or(WindowError::InvalidInternalArgument)
+    }
 
-        let mut frame = MaybeUninit::uninit();
+    // Fails if the value holds another type, such as an error.
+    fn decode_ax_value<T>(ax_value: &AXValue, value_type: AXValueType) -> Option<T> {
+        let mut value = MaybeUninit::uninit();
         let result = unsafe {
             ax_value.value(
                 value_type,
-                NonNull::new_unchecked(frame.as_mut_ptr()).cast(),
+                NonNull::new_unchecked(value.as_mut_ptr()).cast(),
             )
         };
-
-        if result {
-            Ok(unsafe { frame.assume_init() })
-        } else {
-            Err(WindowError::InvalidInternalArgument)
-        }
+        result.then(|| unsafe { value.assume_init() })
     }
 
     fn bool_for_attribute(handle: &AXUIElement, attribute: &CFString) -> Result<bool, WindowError> {
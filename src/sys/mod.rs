@@ -6,4 +6,40 @@ mod platform;
 #[path = "windows/mod.rs"]
 mod platform;
 
-pub use platform::*;
+// macOS and Windows only have a single backend, so they are adapted to the same interface as Linux.
+#[cfg(not(target_os = "linux"))]
+mod native;
+
+#[cfg(not(target_os = "linux"))]
+pub use native::*;
+
+// Linux can have several backends compiled at once, the one to use is chosen at runtime.
+#[cfg(target_os = "linux")]
+mod linux;
+
+#[cfg(target_os = "linux")]
+pub use linux::*;
+
+#[cfg(all(target_os = "linux", feature = "x11"))]
+mod x11;
+
+#[cfg(all(target_os = "linux", feature = "wlr"))]
+mod wlr;
+
+#[cfg(all(target_os = "linux", feature = "ext-toplevel"))]
+mod ext_toplevel;
+
+#[cfg(all(target_os = "linux", feature = "i3"))]
+mod i3;
+
+#[cfg(all(target_os = "linux", feature = "hyprland"))]
+mod hyprland;
+
+#[cfg(all(target_os = "linux", feature = "plasma"))]
+mod plasma;
+
+#[cfg(all(
+    target_os = "linux",
+    any(feature = "wlr", feature = "ext-toplevel", feature = "plasma")
+))]
+mod wayland;
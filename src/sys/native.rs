@@ -0,0 +1,95 @@
+use std::{iter, path::PathBuf};
+
+use crate::{
+    protocol::transaction::Change, Backend, EventFilter, PropertyMask, WindowError,
+    WindowProperties,
+};
+
+use super::platform;
+
+pub use platform::{request_trust, trusted, Watcher, Window, WindowHandle, APPLICATION_EVENTS};
+
+#[cfg(target_os = "macos")]
+const NATIVE: Backend = Backend::MacOS;
+
+#[cfg(target_os = "windows")]
+const NATIVE: Backend = Backend::Windows;
+
+pub const BACKENDS: &[Backend] = &[NATIVE];
+
+fn check(backend: Backend) -> Result<(), WindowError> {
+    if backend == NATIVE {
+        Ok(())
+    } else {
+        Err(WindowError::Unsupported)
+    }
+}
+
+pub fn detect() -> Vec<Backend> {
+    vec![NATIVE]
+}
+
+// The native APIs are always available, there is nothing to connect to.
+pub fn connect(backend: Backend) -> Result<(), WindowError> {
+    check(backend)
+}
+
+pub fn watch(backend: Backend, filter: &EventFilter) -> Result<Watcher, WindowError> {
+    check(backend)?;
+    Watcher::new(filter)
+}
+
+pub fn iter_windows(backend: Backend) -> Box<dyn Iterator<Item = Result<Window, WindowError>>> {
+    match check(backend) {
+        Ok(()) => Box::new(platform::iter_windows()),
+        Err(err) => Box::new(iter::once(Err(err))),
+    }
+}
+
+pub fn focused_window(backend: Backend) -> Result<Option<Window>, WindowError> {
+    check(backend)?;
+    platform::focused_window()
+}
+
+pub fn stacking_order(backend: Backend) -> Result<Vec<Window>, WindowError> {
+    check(backend)?;
+    platform::stacking_order()
+}
+
+pub fn application_name(pid: u32) -> Result<String, WindowError> {
+    platform::application_name(pid)
+}
+
+pub fn executable_path(pid: u32) -> Result<PathBuf, WindowError> {
+    platform::executable_path(pid)
+}
+
+pub fn activate_application(backend: Backend, pid: u32) -> Result<(), WindowError> {
+    check(backend)?;
+    platform::activate_application(pid)
+}
+
+pub fn commit(changes: &[(&Window, Change)]) -> Vec<Result<(), WindowError>> {
+    platform::commit(changes)
+}
+
+// The native APIs can't query several windows at once, so each window is fetched on its own.
+pub fn properties(
+    windows: &[&Window],
+    mask: PropertyMask,
+) -> Vec<Result<WindowProperties, WindowError>> {
+    windows
+        .iter()
+        .map(|window| window.properties(mask))
+        .collect()
+}
+
+pub fn kill_process(pid: u32) -> Result<(), WindowError> {
+    platform::kill_process(pid)
+}
+
+impl Window {
+    pub fn backend(&self) -> Backend {
+        NATIVE
+    }
+}
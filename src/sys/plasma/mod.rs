@@ -0,0 +1,478 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use wayland_client::{
+    globals::{registry_queue_init, GlobalListContents},
+    protocol::{wl_callback::WlCallback, wl_registry::WlRegistry},
+    Connection, Dispatch, Proxy, QueueHandle,
+};
+use wayland_protocols_plasma::plasma_window_management::client::{
+    org_kde_plasma_window::{self, OrgKdePlasmaWindow},
+    org_kde_plasma_window_management::{self, OrgKdePlasmaWindowManagement},
+};
+
+use crate::{protocol, LogicalPosition, LogicalSize, WindowError, WindowEvent};
+
+use super::wayland::{self, disconnected, SyncDone};
+
+pub use window::Window;
+
+mod window;
+
+/// The uuid of a window, which is unique for the lifetime of the compositor.
+// Wrapped so that it's distinguishable from the identifier used by ext-toplevel.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct Uuid(pub(crate) String);
+
+pub type WindowHandle = Uuid;
+
+type Event = Result<WindowEvent, WindowError>;
+
+// Values of `org_kde_plasma_window_management::state`, the state is sent as a plain bitfield.
+pub(crate) const STATE_ACTIVE: u32 = 0x1;
+pub(crate) const STATE_MINIMIZED: u32 = 0x2;
+pub(crate) const STATE_MAXIMIZED: u32 = 0x4;
+pub(crate) const STATE_FULLSCREEN: u32 = 0x8;
+
+// Windows are announced by uuid since version 13, and the geometry event was added in version 6.
+const MANAGER_VERSION: u32 = 16;
+
+// Same as the wlr backend, properties are sent as events and cached here by a background thread.
+static SESSION: Mutex<Option<Arc<Session>>> = Mutex::new(None);
+
+pub(crate) fn session() -> Result<Arc<Session>, WindowError> {
+    let mut session = SESSION.lock().unwrap();
+    match &*session {
+        Some(session) => Ok(session.clone()),
+        None => {
+            let new_session = Arc::new(Session::new()?);
+            *session = Some(new_session.clone());
+            Ok(new_session)
+        }
+    }
+}
+
+/// The outer frame of a window in global compositor coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Frame {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Frame {
+    pub fn position(&self) -> LogicalPosition {
+        LogicalPosition {
+            x: self.x as f64,
+            y: self.y as f64,
+        }
+    }
+
+    pub fn size(&self) -> LogicalSize {
+        LogicalSize {
+            width: self.width as f64,
+            height: self.height as f64,
+        }
+    }
+}
+
+/// The properties of a window, as last reported by the compositor.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Properties {
+    pub title: String,
+    pub app_id: String,
+    pub pid: Option<u32>,
+    // Only sent by compositors implementing version 6 or later.
+    pub frame: Option<Frame>,
+    pub virtual_desktops: Vec<String>,
+    // Also includes flags that don't map onto `Window`, such as keep-above and on-all-desktops.
+    pub state: u32,
+}
+
+impl Properties {
+    pub fn has(&self, state: u32) -> bool {
+        self.state & state != 0
+    }
+}
+
+#[derive(Debug)]
+struct Toplevel {
+    proxy: OrgKdePlasmaWindow,
+    properties: Properties,
+    // Whether or not the `initial_state` event was received.
+    initialized: bool,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    toplevels: HashMap<String, Toplevel>,
+    subscribers: Vec<Sender<Event>>,
+    disconnected: bool,
+}
+
+impl Shared {
+    fn emit(&mut self, event: impl Fn() -> WindowEvent) {
+        // If the receiver is gone, then the watcher was dropped and we can remove it.
+        self.subscribers
+            .retain(|subscriber| subscriber.send(Ok(event())).is_ok());
+    }
+
+    fn initialized(&self) -> impl Iterator<Item = (&String, &Toplevel)> {
+        self.toplevels
+            .iter()
+            .filter(|(_, toplevel)| toplevel.initialized)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Session {
+    connection: Connection,
+    queue_handle: QueueHandle<State>,
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl Session {
+    fn new() -> Result<Session, WindowError> {
+        let connection = Connection::connect_to_env()?;
+        let (globals, mut queue) = registry_queue_init::<State>(&connection)?;
+        let queue_handle = queue.handle();
+
+        // The manager immediately advertises every existing window once bound.
+        let _manager: OrgKdePlasmaWindowManagement =
+            globals.bind(&queue_handle, 4..=MANAGER_VERSION, ())?;
+
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let mut state = State {
+            shared: shared.clone(),
+        };
+        // The first roundtrip receives the windows, the second receives their initial state.
+        queue.roundtrip(&mut state)?;
+        queue.roundtrip(&mut state)?;
+
+        thread::spawn(move || loop {
+            if let Err(err) = queue.blocking_dispatch(&mut state) {
+                let mut shared = state.shared.lock().unwrap();
+                shared.disconnected = true;
+                for subscriber in shared.subscribers.drain(..) {
+                    let _ = subscriber.send(Err(disconnected(&err.to_string())));
+                }
+                break;
+            }
+        });
+
+        Ok(Session {
+            connection,
+            queue_handle,
+            shared,
+        })
+    }
+
+    pub fn sync(&self) -> Result<(), WindowError> {
+        wayland::sync(&self.connection, &self.queue_handle)
+    }
+
+    pub fn properties(&self, uuid: &str) -> Result<Properties, WindowError> {
+        self.sync()?;
+        self.shared
+            .lock()
+            .unwrap()
+            .initialized()
+            .find(|(handle, _)| *handle == uuid)
+            .map(|(_, toplevel)| toplevel.properties.clone())
+            .ok_or(WindowError::InvalidHandle)
+    }
+
+    pub fn uuids(&self) -> Result<Vec<String>, WindowError> {
+        self.sync()?;
+        Ok(self
+            .shared
+            .lock()
+            .unwrap()
+            .initialized()
+            .map(|(uuid, _)| uuid.clone())
+            .collect())
+    }
+
+    /// Sends a request for the specified window.
+    pub fn request(
+        &self,
+        uuid: &str,
+        request: impl FnOnce(&OrgKdePlasmaWindow),
+    ) -> Result<(), WindowError> {
+        {
+            let shared = self.shared.lock().unwrap();
+            let toplevel = shared
+                .toplevels
+                .get(uuid)
+                .ok_or(WindowError::InvalidHandle)?;
+            request(&toplevel.proxy);
+        }
+
+        self.connection.flush()?;
+        Ok(())
+    }
+
+    fn subscribe(&self) -> Result<Receiver<Event>, WindowError> {
+        let mut shared = self.shared.lock().unwrap();
+        if shared.disconnected {
+            return Err(disconnected("the connection was closed"));
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        shared.subscribers.push(sender);
+        Ok(receiver)
+    }
+}
+
+#[derive(Debug)]
+pub struct Watcher {
+    receiver: Receiver<Event>,
+    // Keep the session alive for as long as we're watching.
+    _session: Arc<Session>,
+}
+
+impl Watcher {
+    pub fn new() -> Result<Watcher, WindowError> {
+        let session = session()?;
+        Ok(Watcher {
+            receiver: session.subscribe()?,
+            _session: session,
+        })
+    }
+
+    pub fn next_request(&mut self) -> Result<WindowEvent, WindowError> {
+        self.receiver
+            .recv()
+            .unwrap_or_else(|_| Err(disconnected("the connection was closed")))
+    }
+
+    pub fn next_request_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<WindowEvent>, WindowError> {
+        match self.receiver.recv_timeout(timeout) {
+            Ok(event) => event.map(Some),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err(disconnected("the connection was closed")),
+        }
+    }
+}
+
+pub fn iter_windows() -> impl Iterator<Item = Result<Window, WindowError>> {
+    let windows = match session().and_then(|session| session.uuids()) {
+        Ok(uuids) => uuids
+            .into_iter()
+            .map(|uuid| Ok(Window::new(uuid)))
+            .collect(),
+        Err(err) => vec![Err(err)],
+    };
+    windows.into_iter()
+}
+
+pub fn focused_window() -> Result<Option<Window>, WindowError> {
+    let session = session()?;
+    session.sync()?;
+    let shared = session.shared.lock().unwrap();
+    let focused = shared
+        .initialized()
+        .find(|(_, toplevel)| toplevel.properties.has(STATE_ACTIVE))
+        .map(|(uuid, _)| Window::new(uuid.clone()));
+    Ok(focused)
+}
+
+#[derive(Debug)]
+struct State {
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for State {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WlRegistry,
+        _event: <WlRegistry as Proxy>::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<WlCallback, SyncDone> for State {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WlCallback,
+        _event: <WlCallback as Proxy>::Event,
+        data: &SyncDone,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        wayland::sync_done(data);
+    }
+}
+
+impl Dispatch<OrgKdePlasmaWindowManagement, ()> for State {
+    fn event(
+        state: &mut Self,
+        proxy: &OrgKdePlasmaWindowManagement,
+        event: org_kde_plasma_window_management::Event,
+        _data: &(),
+        _conn: &Connection,
+        qhandle: &QueueHandle<Self>,
+    ) {
+        let uuid = match event {
+            org_kde_plasma_window_management::Event::WindowWithUuid { uuid, .. } => uuid,
+            // Older compositors only announce windows by their (deprecated) internal id, newer ones
+            // send both events.
+            org_kde_plasma_window_management::Event::Window { id } if proxy.version() < 13 => {
+                id.to_string()
+            }
+            _ => return,
+        };
+
+        let window = if proxy.version() >= 12 {
+            proxy.get_window_by_uuid(uuid.clone(), qhandle, uuid.clone())
+        } else {
+            // Before version 13 the uuid is the internal id, see above.
+            proxy.get_window(uuid.parse().unwrap(), qhandle, uuid.clone())
+        };
+        state.shared.lock().unwrap().toplevels.insert(
+            uuid,
+            Toplevel {
+                proxy: window,
+                properties: Properties::default(),
+                initialized: false,
+            },
+        );
+    }
+}
+
+impl Dispatch<OrgKdePlasmaWindow, String> for State {
+    fn event(
+        state: &mut Self,
+        proxy: &OrgKdePlasmaWindow,
+        event: org_kde_plasma_window::Event,
+        uuid: &String,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        let mut shared = state.shared.lock().unwrap();
+        let window = || protocol::Window::from_sys(Window::new(uuid.clone()).into());
+
+        if let org_kde_plasma_window::Event::Unmapped = event {
+            if let Some(toplevel) = shared.toplevels.remove(uuid) {
+                if toplevel.initialized {
+                    shared.emit(|| {
+                        WindowEvent::Closed(protocol::WindowHandle::from_sys(
+                            Uuid(uuid.clone()).into(),
+                        ))
+                    });
+                }
+            }
+            proxy.destroy();
+            return;
+        }
+
+        let Some(toplevel) = shared.toplevels.get_mut(uuid) else {
+            return;
+        };
+        let previous = toplevel.properties.clone();
+        let properties = &mut toplevel.properties;
+        match event {
+            org_kde_plasma_window::Event::TitleChanged { title } => properties.title = title,
+            org_kde_plasma_window::Event::AppIdChanged { app_id } => properties.app_id = app_id,
+            org_kde_plasma_window::Event::PidChanged { pid } => properties.pid = Some(pid),
+            org_kde_plasma_window::Event::StateChanged { flags } => properties.state = flags,
+            org_kde_plasma_window::Event::Geometry {
+                x,
+                y,
+                width,
+                height,
+            } => {
+                properties.frame = Some(Frame {
+                    x,
+                    y,
+                    width,
+                    height,
+                })
+            }
+            org_kde_plasma_window::Event::VirtualDesktopEntered { id } => {
+                properties.virtual_desktops.push(id)
+            }
+            org_kde_plasma_window::Event::VirtualDesktopLeft { is } => {
+                properties.virtual_desktops.retain(|id| *id != is)
+            }
+            org_kde_plasma_window::Event::InitialState => {
+                toplevel.initialized = true;
+                shared.emit(|| WindowEvent::Opened(window()));
+                return;
+            }
+            _ => return,
+        }
+
+        // Changes before the initial state are part of the window being opened.
+        if !toplevel.initialized {
+            return;
+        }
+        let current = toplevel.properties.clone();
+
+        if previous.title != current.title {
+            shared.emit(|| WindowEvent::Renamed {
+                window: window(),
+                old_title: Some(previous.title.clone()),
+                new_title: Some(current.title.clone()),
+            });
+        }
+        if previous.has(STATE_MINIMIZED) != current.has(STATE_MINIMIZED) {
+            if current.has(STATE_MINIMIZED) {
+                shared.emit(|| WindowEvent::Minimized(window()));
+            } else {
+                shared.emit(|| WindowEvent::Unminimized(window()));
+            }
+        }
+        if previous.has(STATE_MAXIMIZED) != current.has(STATE_MAXIMIZED) {
+            if current.has(STATE_MAXIMIZED) {
+                shared.emit(|| WindowEvent::Maximized(window()));
+            } else {
+                shared.emit(|| WindowEvent::Unmaximized(window()));
+            }
+        }
+        if previous.has(STATE_FULLSCREEN) != current.has(STATE_FULLSCREEN) {
+            if current.has(STATE_FULLSCREEN) {
+                shared.emit(|| WindowEvent::Fullscreened(window()));
+            } else {
+                shared.emit(|| WindowEvent::Unfullscreened(window()));
+            }
+        }
+        if previous.has(STATE_ACTIVE) != current.has(STATE_ACTIVE) {
+            if current.has(STATE_ACTIVE) {
+                shared.emit(|| WindowEvent::Focused(window()));
+            } else {
+                shared.emit(|| WindowEvent::Unfocused(window()));
+            }
+        }
+        if let (Some(previous), Some(current)) = (previous.frame, current.frame) {
+            if (previous.x, previous.y) != (current.x, current.y) {
+                shared.emit(|| WindowEvent::Moved {
+                    window: window(),
+                    old: Some(previous.position()),
+                    new: Some(current.position()),
+                });
+            }
+            if (previous.width, previous.height) != (current.width, current.height) {
+                shared.emit(|| WindowEvent::Resized {
+                    window: window(),
+                    old: Some(previous.size()),
+                    new: Some(current.size()),
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{Insets, LogicalPosition, LogicalSize, Rect, WindowError};
+
+use super::{
+    session, Frame, Uuid, WindowHandle, STATE_ACTIVE, STATE_FULLSCREEN, STATE_MAXIMIZED,
+    STATE_MINIMIZED,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Window {
+    uuid: String,
+}
+
+impl Window {
+    pub(crate) fn new(uuid: String) -> Window {
+        Window { uuid }
+    }
+
+    pub fn handle(&self) -> WindowHandle {
+        Uuid(self.uuid.clone())
+    }
+
+    fn has(&self, state: u32) -> Result<bool, WindowError> {
+        Ok(session()?.properties(&self.uuid)?.has(state))
+    }
+
+    fn geometry(&self) -> Result<Frame, WindowError> {
+        session()?
+            .properties(&self.uuid)?
+            .frame
+            .ok_or(WindowError::Unsupported)
+    }
+
+    // Every state change is requested by masking the flags that should change.
+    fn set_state(&self, flags: u32, enabled: bool) -> Result<(), WindowError> {
+        let state = if enabled { flags } else { 0 };
+        session()?.request(&self.uuid, |window| window.set_state(flags, state))
+    }
+
+    pub fn title(&self) -> Result<String, WindowError> {
+        Ok(session()?.properties(&self.uuid)?.title)
+    }
+
+    pub fn size(&self) -> Result<LogicalSize, WindowError> {
+        Ok(self.geometry()?.size())
+    }
+
+    pub fn position(&self) -> Result<LogicalPosition, WindowError> {
+        Ok(self.geometry()?.position())
+    }
+
+    // The geometry is in the compositor's logical coordinates, but the protocol doesn't say which
+    // output the window is on.
+    pub fn scale_factor(&self) -> Result<f64, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn is_focused(&self) -> Result<bool, WindowError> {
+        self.has(STATE_ACTIVE)
+    }
+
+    pub fn is_fullscreen(&self) -> Result<bool, WindowError> {
+        self.has(STATE_FULLSCREEN)
+    }
+
+    pub fn is_minimized(&self) -> Result<bool, WindowError> {
+        self.has(STATE_MINIMIZED)
+    }
+
+    #[inline]
+    pub fn is_hidden(&self) -> Result<bool, WindowError> {
+        // Default behavior of Window::hide is to minimize, so check if minimized.
+        self.is_minimized()
+    }
+
+    // The protocol only offers interactive moves and resizes driven by the pointer.
+    pub fn resize(&self, _size: LogicalSize) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn reposition(&self, _position: LogicalPosition) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn frame(&self) -> Result<Rect, WindowError> {
+        let frame = self.geometry()?;
+        Ok(Rect {
+            position: frame.position(),
+            size: frame.size(),
+        })
+    }
+
+    pub fn set_frame(&self, _frame: Rect) -> Result<Rect, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    // KWin draws the decorations, but the protocol doesn't report their size.
+    pub fn decoration_insets(&self) -> Result<Insets, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn focus(&self) -> Result<(), WindowError> {
+        self.set_state(STATE_ACTIVE, true)
+    }
+
+    pub fn fullscreen(&self) -> Result<(), WindowError> {
+        self.set_state(STATE_FULLSCREEN, true)
+    }
+
+    pub fn unfullscreen(&self) -> Result<(), WindowError> {
+        self.set_state(STATE_FULLSCREEN, false)
+    }
+
+    pub fn maximize(&self) -> Result<(), WindowError> {
+        self.set_state(STATE_MAXIMIZED, true)
+    }
+
+    pub fn minimize(&self) -> Result<(), WindowError> {
+        self.set_state(STATE_MINIMIZED, true)
+    }
+
+    pub fn unminimize(&self) -> Result<(), WindowError> {
+        self.set_state(STATE_MINIMIZED, false)
+    }
+
+    pub fn show(&self) -> Result<(), WindowError> {
+        self.unminimize()
+    }
+
+    pub fn hide(&self) -> Result<(), WindowError> {
+        self.minimize()
+    }
+
+    // KWin raises windows when they are activated, but there's no way to only raise them.
+    pub fn bring_to_front(&self) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn pid(&self) -> Result<u32, WindowError> {
+        session()?
+            .properties(&self.uuid)?
+            .pid
+            .ok_or(WindowError::Unsupported)
+    }
+
+    pub fn close(&self) -> Result<(), WindowError> {
+        session()?.request(&self.uuid, |window| window.close())
+    }
+}
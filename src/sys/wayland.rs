@@ -0,0 +1,109 @@
+//! Utilities shared between the Wayland backends.
+
+use std::{
+    io,
+    sync::{Arc, Condvar, Mutex},
+    time::Duration,
+};
+
+use wayland_client::{
+    backend::WaylandError,
+    globals::{BindError, GlobalError},
+    protocol::wl_callback::WlCallback,
+    ConnectError, Connection, Dispatch, DispatchError, QueueHandle,
+};
+
+use crate::WindowError;
+
+/// Signaled once the compositor responds to a sync request.
+pub(crate) type SyncDone = Arc<(Mutex<bool>, Condvar)>;
+
+// How long to wait for the compositor to respond to a sync request before giving up.
+const SYNC_TIMEOUT: Duration = Duration::from_secs(1);
+
+// Waits until all events sent prior to this call are dispatched, so that the cached properties are
+// up to date (e.g. after requesting a change).
+pub(crate) fn sync<State>(
+    connection: &Connection,
+    queue_handle: &QueueHandle<State>,
+) -> Result<(), WindowError>
+where
+    State: Dispatch<WlCallback, SyncDone> + 'static,
+{
+    let done = SyncDone::default();
+    connection.display().sync(queue_handle, done.clone());
+    connection.flush()?;
+
+    let (lock, condvar) = &*done;
+    let (_guard, result) = condvar
+        .wait_timeout_while(lock.lock().unwrap(), SYNC_TIMEOUT, |done| !*done)
+        .unwrap();
+    if result.timed_out() {
+        Err(WindowError::OsError(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "Wayland compositor did not respond in time",
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+// Called from the `WlCallback` dispatch of each backend.
+pub(crate) fn sync_done(done: &SyncDone) {
+    let (lock, condvar) = &**done;
+    *lock.lock().unwrap() = true;
+    condvar.notify_all();
+}
+
+pub(crate) fn disconnected(reason: &str) -> WindowError {
+    WindowError::OsError(io::Error::new(
+        io::ErrorKind::ConnectionAborted,
+        format!("lost connection to the Wayland compositor: {reason}"),
+    ))
+}
+
+impl From<ConnectError> for WindowError {
+    fn from(err: ConnectError) -> Self {
+        WindowError::OsError(io::Error::other(format!(
+            "failed to connect to the Wayland compositor: {err}"
+        )))
+    }
+}
+
+impl From<GlobalError> for WindowError {
+    fn from(err: GlobalError) -> Self {
+        match err {
+            GlobalError::Backend(err) => err.into(),
+            GlobalError::InvalidId(_) => WindowError::InvalidInternalArgument,
+        }
+    }
+}
+
+impl From<BindError> for WindowError {
+    fn from(err: BindError) -> Self {
+        match err {
+            // The compositor doesn't implement the protocol.
+            BindError::NotPresent | BindError::UnsupportedVersion => WindowError::Unsupported,
+        }
+    }
+}
+
+impl From<WaylandError> for WindowError {
+    fn from(err: WaylandError) -> Self {
+        match err {
+            WaylandError::Io(err) => WindowError::OsError(err),
+            err => WindowError::OsError(io::Error::other(format!("Wayland protocol error: {err}"))),
+        }
+    }
+}
+
+impl From<DispatchError> for WindowError {
+    fn from(err: DispatchError) -> Self {
+        match err {
+            DispatchError::Backend(err) => err.into(),
+            err => WindowError::OsError(io::Error::other(format!(
+                "failed to dispatch Wayland event: {err}"
+            ))),
+        }
+    }
+}
@@ -1,13 +1,30 @@
-use std::{io, marker::PhantomData, ptr, sync::LazyLock};
+use std::{
+    ffi::OsString,
+    io,
+    marker::PhantomData,
+    os::windows::ffi::OsStringExt,
+    path::PathBuf,
+    ptr,
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
 
 use flume::{Receiver, Sender};
 use windows_sys::Win32::{
-    Foundation::{BOOL, HANDLE, HWND, LPARAM, TRUE},
+    Foundation::{CloseHandle, BOOL, FALSE, HANDLE, HWND, LPARAM, TRUE},
+    System::{
+        SystemInformation::GetTickCount,
+        Threading::{
+            OpenProcess, QueryFullProcessImageNameW, TerminateProcess, PROCESS_NAME_WIN32,
+            PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_TERMINATE,
+        },
+    },
     UI::{
         Accessibility::{SetWinEventHook, UnhookWinEvent, HWINEVENTHOOK},
         WindowsAndMessaging::{
-            EnumWindows, GetForegroundWindow, EVENT_MAX, EVENT_MIN, EVENT_OBJECT_CLOAKED,
-            EVENT_OBJECT_CREATE, EVENT_OBJECT_DESTROY, EVENT_OBJECT_FOCUS, EVENT_OBJECT_HIDE,
+            BeginDeferWindowPos, EndDeferWindowPos, EnumWindows, GetForegroundWindow, EVENT_MAX,
+            EVENT_MIN, EVENT_OBJECT_CLOAKED, EVENT_OBJECT_CREATE, EVENT_OBJECT_DESTROY,
+            EVENT_OBJECT_FOCUS, EVENT_OBJECT_HIDE, EVENT_OBJECT_LOCATIONCHANGE,
             EVENT_OBJECT_NAMECHANGE, EVENT_OBJECT_SHOW, EVENT_OBJECT_UNCLOAKED,
             EVENT_SYSTEM_FOREGROUND, EVENT_SYSTEM_MINIMIZEEND, EVENT_SYSTEM_MINIMIZESTART,
             EVENT_SYSTEM_MOVESIZEEND, EVENT_SYSTEM_MOVESIZESTART, OBJID_WINDOW,
@@ -16,7 +33,13 @@ use windows_sys::Win32::{
     },
 };
 
-use crate::{protocol, WindowError, WindowEvent};
+use crate::{
+    protocol::{
+        self,
+        transaction::{self, Change},
+    },
+    EventFilter, WindowError, WindowEvent,
+};
 
 pub use window::Window;
 
@@ -24,7 +47,11 @@ mod window;
 
 pub type WindowHandle = HWND;
 
-type Event = Result<WindowEvent, WindowError>;
+// Processes are only observed through their windows.
+pub const APPLICATION_EVENTS: bool = false;
+
+// Events are sent along with when they happened.
+type Event = (Result<WindowEvent, WindowError>, Instant);
 
 // We need a multi-producer, multi-consumer channel to support the "local" nature of a Watcher, where
 // multiple watchers can be created and manage their own state. Thus the reason we use flume, for
@@ -35,6 +62,8 @@ static EVENT_SENDER: LazyLock<(Sender<Event>, Receiver<Event>)> = LazyLock::new(
 pub struct Watcher {
     handle: HWINEVENTHOOK,
     receiver: Receiver<Event>,
+    // When the event that was returned last happened.
+    time: Option<Instant>,
     // NOTE: replace with negative_impls when stabilized
     //       https://github.com/rust-lang/rust/issues/68318
     // NOTE: UnhookWinEvent must be executed on the same thread the hook was created
@@ -43,7 +72,13 @@ pub struct Watcher {
 }
 
 impl Watcher {
-    pub fn new() -> Result<Watcher, WindowError> {
+    pub fn new(filter: &EventFilter) -> Result<Watcher, WindowError> {
+        // The hook can be limited to a single process, otherwise it observes every process.
+        let pid = match filter.pids() {
+            Some(&[pid]) => pid,
+            _ => 0,
+        };
+
         Ok(Watcher {
             handle: unsafe {
                 // TODO: can also register multiple hooks with specific event ids
@@ -52,20 +87,41 @@ impl Watcher {
                     EVENT_MAX,
                     ptr::null::<HANDLE>() as HANDLE,
                     Some(window_event),
-                    0,
+                    pid,
                     0,
                     WINEVENT_OUTOFCONTEXT, // TODO: also can try WINEVENT_INCONTEXT
                 )
             },
             receiver: EVENT_SENDER.1.clone(),
+            time: None,
             _not_send_sync: PhantomData,
         })
     }
 
     // What a beautiful sight in comparison to the macOS backend.
-    pub fn next_request(&self) -> Result<WindowEvent, WindowError> {
+    pub fn next_request(&mut self) -> Result<WindowEvent, WindowError> {
         // Impossible to error, the sender lives as long as the program.
-        self.receiver.recv().unwrap()
+        let (event, time) = self.receiver.recv().unwrap();
+        self.time = Some(time);
+        event
+    }
+
+    pub fn next_request_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<WindowEvent>, WindowError> {
+        // The channel can only time out, for the same reason as above.
+        match self.receiver.recv_timeout(timeout) {
+            Ok((event, time)) => {
+                self.time = Some(time);
+                event.map(Some)
+            }
+            Err(_) => Ok(None),
+        }
+    }
+
+    pub fn event_time(&self) -> Option<Instant> {
+        self.time
     }
 }
 
@@ -89,14 +145,19 @@ pub fn request_trust() -> Result<bool, WindowError> {
 pub fn iter_windows() -> impl Iterator<Item = Result<Window, WindowError>> {
     // TODO: I can also do something w/ a sender here to get a more on-demand iterator
     let mut windows: Vec<HWND> = Vec::new();
-    let result = unsafe { EnumWindows(Some(enum_windows), windows.as_mut_ptr() as LPARAM) };
-    if result == TRUE {
-        windows.into_iter().map(|window| Ok(Window::new(window)))
-    } else {
-        // TODO: need to either return iterator surrounded by error or return enum that impls Iterator
-        // iter::once(WindowError::last_os_error())
-        todo!()
-    }
+    let result =
+        unsafe { EnumWindows(Some(enum_windows), &mut windows as *mut Vec<HWND> as LPARAM) };
+    // The windows listed before the failure are still returned.
+    let err = (result != TRUE).then(|| Err(WindowError::last_os_error()));
+    windows
+        .into_iter()
+        .map(|window| Ok(Window::new(window)))
+        .chain(err)
+}
+
+// `EnumWindows` already lists top-level windows from the top of the z-order to the bottom.
+pub fn stacking_order() -> Result<Vec<Window>, WindowError> {
+    iter_windows().collect()
 }
 
 pub fn focused_window() -> Result<Option<Window>, WindowError> {
@@ -108,6 +169,123 @@ pub fn focused_window() -> Result<Option<Window>, WindowError> {
     }
 }
 
+pub fn application_name(pid: u32) -> Result<String, WindowError> {
+    // There's no display name for a process, so use the name of its executable like Task Manager.
+    let path = executable_path(pid)?;
+    Ok(path
+        .file_stem()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default())
+}
+
+pub fn executable_path(pid: u32) -> Result<PathBuf, WindowError> {
+    let process = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, FALSE, pid) };
+    if process == 0 {
+        return Err(WindowError::last_os_error());
+    }
+
+    // The longest possible path, including the extended-length prefix.
+    let mut buffer = vec![0; 32768];
+    let mut len = buffer.len() as u32;
+    let path = if unsafe {
+        QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, buffer.as_mut_ptr(), &mut len)
+    } != 0
+    {
+        Ok(PathBuf::from(OsString::from_wide(&buffer[..len as usize])))
+    } else {
+        Err(WindowError::last_os_error())
+    };
+
+    unsafe {
+        CloseHandle(process);
+    }
+    path
+}
+
+// Processes can't be activated as a whole, so focus one of its visible windows instead.
+pub fn activate_application(pid: u32) -> Result<(), WindowError> {
+    for window in iter_windows() {
+        let window = window?;
+        if window.pid()? == pid && !window.is_hidden()? {
+            return window.focus();
+        }
+    }
+
+    Err(WindowError::InvalidHandle)
+}
+
+pub fn kill_process(pid: u32) -> Result<(), WindowError> {
+    let process = unsafe { OpenProcess(PROCESS_TERMINATE, FALSE, pid) };
+    if process == 0 {
+        return Err(WindowError::last_os_error());
+    }
+
+    // Same exit code as Task Manager.
+    let result = if unsafe { TerminateProcess(process, 1) } != 0 {
+        Ok(())
+    } else {
+        Err(WindowError::last_os_error())
+    };
+
+    unsafe {
+        CloseHandle(process);
+    }
+    result
+}
+
+// Frames and stacking are deferred so that every window is repositioned at once, wherever they
+// are in the list, then the rest are applied in order since they have no batch primitive.
+pub fn commit(changes: &[(&Window, Change)]) -> Vec<Result<(), WindowError>> {
+    let (deferred, rest): (Vec<_>, Vec<_>) = changes
+        .iter()
+        .enumerate()
+        .partition(|(_, (_, change))| matches!(change, Change::SetFrame(_) | Change::Raise));
+
+    let mut results: Vec<_> = changes.iter().map(|_| Ok(())).collect();
+    let deferred_changes: Vec<_> = deferred.iter().map(|(_, change)| **change).collect();
+    // The whole batch is discarded if a single window fails, so retry them one at a time to find
+    // out which.
+    if !deferred_changes.is_empty() && defer(&deferred_changes).is_err() {
+        for ((i, _), result) in deferred
+            .iter()
+            .zip(transaction::apply_all(&deferred_changes))
+        {
+            results[*i] = result;
+        }
+    }
+
+    let rest_changes: Vec<_> = rest.iter().map(|(_, change)| **change).collect();
+    for ((i, _), result) in rest.iter().zip(transaction::apply_all(&rest_changes)) {
+        results[*i] = result;
+    }
+    results
+}
+
+fn defer(changes: &[(&Window, Change)]) -> Result<(), WindowError> {
+    window::dpi_aware(|| {
+        // Read before the batch begins, so that a failure doesn't leave it open.
+        let scales = changes
+            .iter()
+            .map(|(window, _)| window.monitor_scale())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut hdwp = unsafe { BeginDeferWindowPos(changes.len() as i32) };
+        for ((window, change), scale) in changes.iter().zip(scales) {
+            if hdwp == 0 {
+                return Err(WindowError::last_os_error());
+            }
+            // On failure, the structure has already been freed.
+            hdwp = window.defer(hdwp, *change, scale);
+        }
+
+        if hdwp != 0 && unsafe { EndDeferWindowPos(hdwp) } != 0 {
+            Ok(())
+        } else {
+            Err(WindowError::last_os_error())
+        }
+    })
+}
+
 unsafe extern "system" fn enum_windows(hwnd: HWND, l_param: LPARAM) -> BOOL {
     let windows = l_param as *mut Vec<HWND>;
     (*windows).push(hwnd);
@@ -121,36 +299,63 @@ unsafe extern "system" fn window_event(
     idobject: i32,
     _idchild: i32,
     _ideventthread: u32,
-    _dwmseventtime: u32,
+    dwmseventtime: u32,
 ) {
     if idobject == OBJID_WINDOW {
         // TODO: https://github.com/LGUG2Z/komorebi/issues/151
         //       supposedly events are "guaranteed to be in sequential order" as described
-        //       by SetWinEventHook docs, but komorebi says different? I wonder if the timestamp would
-        //       notice the discrepancy?
-        // let timestamp = Duration::from_millis(dwmseventtime as u64);
+        //       by SetWinEventHook docs, but komorebi says different? The watcher orders
+        //       batches by when events happened to make up for it.
 
-        let window = protocol::Window(Window::new(hwnd));
+        let window = protocol::Window::from_sys(Window::new(hwnd));
         let event = match event {
             EVENT_OBJECT_CREATE => WindowEvent::Opened(window),
-            EVENT_OBJECT_DESTROY => WindowEvent::Closed(protocol::WindowHandle(hwnd)),
+            EVENT_OBJECT_DESTROY => WindowEvent::Closed(protocol::WindowHandle::from_sys(hwnd)),
             EVENT_OBJECT_HIDE | EVENT_OBJECT_CLOAKED => WindowEvent::Hidden(window),
             EVENT_OBJECT_SHOW | EVENT_OBJECT_UNCLOAKED => WindowEvent::Shown(window),
             EVENT_SYSTEM_MINIMIZESTART => WindowEvent::Minimized(window),
             EVENT_SYSTEM_MINIMIZEEND => WindowEvent::Unminimized(window),
             EVENT_OBJECT_FOCUS | EVENT_SYSTEM_FOREGROUND => WindowEvent::Focused(window),
-            EVENT_SYSTEM_MOVESIZESTART | EVENT_SYSTEM_MOVESIZEEND => {
-                // TODO: is there really no way to differentiate resizing/positioning events without caching?
-                todo!()
+            EVENT_SYSTEM_MOVESIZESTART => WindowEvent::MoveResizeStarted(window),
+            EVENT_SYSTEM_MOVESIZEEND => WindowEvent::MoveResizeEnded(window),
+            // Windows doesn't report whether the window was moved or resized, so both are sent
+            // without values. The watcher reads the frame, drops what didn't change and derives
+            // fullscreen from the new size.
+            EVENT_OBJECT_LOCATIONCHANGE => {
+                let time = event_time(dwmseventtime);
+                let moved = WindowEvent::Moved {
+                    window: window.clone(),
+                    old: None,
+                    new: None,
+                };
+                let _ = EVENT_SENDER.0.send((Ok(moved), time));
+                WindowEvent::Resized {
+                    window,
+                    old: None,
+                    new: None,
+                }
             }
-            EVENT_OBJECT_NAMECHANGE => WindowEvent::Renamed(window),
+            EVENT_OBJECT_NAMECHANGE => WindowEvent::Renamed {
+                window,
+                old_title: None,
+                new_title: None,
+            },
             _ => return,
         };
 
-        let _ = EVENT_SENDER.0.send(Ok(event));
+        let _ = EVENT_SENDER.0.send((Ok(event), event_time(dwmseventtime)));
     }
 }
 
+// Event times are on the system tick count, which wraps around every 49.7 days, so they're converted
+// by how long ago they were.
+fn event_time(tick_count: u32) -> Instant {
+    let elapsed = unsafe { GetTickCount() }.wrapping_sub(tick_count);
+    let now = Instant::now();
+    now.checked_sub(Duration::from_millis(elapsed as u64))
+        .unwrap_or(now)
+}
+
 impl WindowError {
     pub(self) fn last_os_error() -> WindowError {
         WindowError::OsError(io::Error::last_os_error())
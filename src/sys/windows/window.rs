@@ -5,28 +5,55 @@ use std::{
 };
 
 use windows_sys::Win32::{
-    Foundation::{SetLastError, BOOL, FALSE, HWND, RECT, S_OK, TRUE},
+    Foundation::{SetLastError, BOOL, FALSE, HWND, POINT, RECT, S_OK, TRUE},
     Graphics::{
         Dwm::{DwmGetWindowAttribute, DWMWA_CLOAKED},
-        Gdi::{GetMonitorInfoW, MonitorFromWindow, MONITORINFO, MONITOR_DEFAULTTONEAREST},
+        Gdi::{
+            ClientToScreen, GetMonitorInfoW, MonitorFromWindow, MONITORINFO,
+            MONITOR_DEFAULTTONEAREST,
+        },
     },
     System::Threading::{AttachThreadInput, GetCurrentThreadId},
     UI::{
+        HiDpi::{
+            GetDpiForMonitor, SetThreadDpiAwarenessContext,
+            DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2, MDT_EFFECTIVE_DPI,
+        },
         Input::KeyboardAndMouse::SetFocus,
         WindowsAndMessaging::{
-            GetForegroundWindow, GetWindowLongPtrW, GetWindowRect, GetWindowTextLengthW,
-            GetWindowTextW, GetWindowThreadProcessId, IsIconic, IsWindowVisible, SetWindowLongPtrW,
-            SetWindowPos, ShowWindow, GWL_STYLE, HWND_TOP, HWND_TOPMOST, SWP_FRAMECHANGED,
-            SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOOWNERZORDER, SWP_NOSIZE, SWP_NOZORDER, SW_HIDE,
-            SW_MAXIMIZE, SW_MINIMIZE, SW_RESTORE, SW_SHOW, WS_OVERLAPPEDWINDOW,
+            DeferWindowPos, GetClientRect, GetForegroundWindow, GetWindowLongPtrW, GetWindowRect,
+            GetWindowTextLengthW, GetWindowTextW, GetWindowThreadProcessId, IsIconic,
+            IsWindowVisible, PostMessageW, SetWindowLongPtrW, SetWindowPos, ShowWindow, GWL_STYLE,
+            HDWP, HWND_TOP, HWND_TOPMOST, SWP_FRAMECHANGED, SWP_NOACTIVATE, SWP_NOMOVE,
+            SWP_NOOWNERZORDER, SWP_NOSIZE, SWP_NOZORDER, SW_HIDE, SW_MAXIMIZE, SW_MINIMIZE,
+            SW_RESTORE, SW_SHOW, WM_CLOSE, WS_OVERLAPPEDWINDOW,
         },
     },
 };
 
-use crate::{Position, Size, WindowError};
+use crate::{
+    protocol::transaction::Change, Insets, LogicalPosition, LogicalSize, PhysicalPosition,
+    PhysicalSize, PropertyMask, Rect, WindowError, WindowProperties,
+};
 
 use super::WindowHandle;
 
+// The DPI that corresponds to a scale factor of 1.
+const DEFAULT_DPI: f64 = 96.0;
+
+// The geometry APIs scale their coordinates to the DPI awareness of the calling thread, so the
+// thread is made per-monitor aware for their duration to always work in physical pixels.
+pub(super) fn dpi_aware<T>(f: impl FnOnce() -> T) -> T {
+    let previous =
+        unsafe { SetThreadDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2) };
+    let result = f();
+    // Older versions of Windows don't support per-monitor awareness, nothing was changed.
+    if previous != 0 {
+        unsafe { SetThreadDpiAwarenessContext(previous) };
+    }
+    result
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Window {
     inner: HWND,
@@ -66,30 +93,28 @@ impl Window {
         }
     }
 
-    pub fn size(&self) -> Result<Size, WindowError> {
-        let mut rect: MaybeUninit<RECT> = MaybeUninit::uninit();
-        if unsafe { GetWindowRect(self.inner, rect.as_mut_ptr()) } == TRUE {
-            let rect = unsafe { rect.assume_init() };
-            Ok(Size {
-                width: (rect.right - rect.left) as f64,
-                height: (rect.bottom - rect.top) as f64,
-            })
-        } else {
-            Err(WindowError::last_os_error())
-        }
+    pub fn size(&self) -> Result<LogicalSize, WindowError> {
+        Ok(self.frame()?.size)
     }
 
-    // TODO: dedup with above
-    pub fn position(&self) -> Result<Position, WindowError> {
-        let mut rect: MaybeUninit<RECT> = MaybeUninit::uninit();
-        if unsafe { GetWindowRect(self.inner, rect.as_mut_ptr()) } == TRUE {
-            let rect = unsafe { rect.assume_init() };
-            Ok(Position {
-                x: rect.left as f64,
-                y: rect.top as f64,
-            })
+    pub fn position(&self) -> Result<LogicalPosition, WindowError> {
+        Ok(self.frame()?.position)
+    }
+
+    pub fn scale_factor(&self) -> Result<f64, WindowError> {
+        dpi_aware(|| self.monitor_scale())
+    }
+
+    // The scale of the monitor the window is mostly on, the thread must be DPI aware.
+    pub(super) fn monitor_scale(&self) -> Result<f64, WindowError> {
+        let monitor = unsafe { MonitorFromWindow(self.inner, MONITOR_DEFAULTTONEAREST) };
+        let (mut dpi_x, mut dpi_y) = (0, 0);
+        let result =
+            unsafe { GetDpiForMonitor(monitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y) };
+        if result == S_OK {
+            Ok(dpi_x as f64 / DEFAULT_DPI)
         } else {
-            Err(WindowError::last_os_error())
+            Err(WindowError::OsError(io::Error::from_raw_os_error(result)))
         }
     }
 
@@ -138,47 +163,203 @@ impl Window {
         }
     }
 
-    pub fn resize(&self, size: Size) -> Result<(), WindowError> {
-        let result = unsafe {
-            SetWindowPos(
-                self.inner,
-                HWND_TOP,
-                0,
-                0,
-                size.width as i32,
-                size.height as i32,
-                SWP_NOMOVE | SWP_NOACTIVATE | SWP_NOZORDER,
-            )
-        };
-        if result != 0 {
-            Ok(())
-        } else {
-            Err(WindowError::last_os_error())
-        }
+    // Only the title is read from the owning process, the rest is kept by the system and cheap to
+    // read on its own. The frame is read once for both the size and position.
+    pub fn properties(&self, mask: PropertyMask) -> Result<WindowProperties, WindowError> {
+        let frame = mask
+            .intersects(PropertyMask::FRAME)
+            .then(|| self.frame())
+            .transpose()?;
+        Ok(WindowProperties {
+            title: mask
+                .select(PropertyMask::TITLE, || self.title())
+                .transpose()?,
+            size: frame
+                .filter(|_| mask.contains(PropertyMask::SIZE))
+                .map(|frame| frame.size),
+            position: frame
+                .filter(|_| mask.contains(PropertyMask::POSITION))
+                .map(|frame| frame.position),
+            is_focused: mask
+                .select(PropertyMask::FOCUSED, || self.is_focused())
+                .transpose()?,
+            is_fullscreen: mask
+                .select(PropertyMask::FULLSCREEN, || self.is_fullscreen())
+                .transpose()?,
+            is_minimized: mask
+                .select(PropertyMask::MINIMIZED, || self.is_minimized())
+                .transpose()?,
+            is_hidden: mask
+                .select(PropertyMask::HIDDEN, || self.is_hidden())
+                .transpose()?,
+        })
+    }
+
+    pub fn resize(&self, size: LogicalSize) -> Result<(), WindowError> {
+        dpi_aware(|| {
+            let size = size.to_physical(self.monitor_scale()?);
+            let result = unsafe {
+                SetWindowPos(
+                    self.inner,
+                    HWND_TOP,
+                    0,
+                    0,
+                    size.width as i32,
+                    size.height as i32,
+                    SWP_NOMOVE | SWP_NOACTIVATE | SWP_NOZORDER,
+                )
+            };
+            if result != 0 {
+                Ok(())
+            } else {
+                Err(WindowError::last_os_error())
+            }
+        })
     }
 
     // TODO: dedup above
     // TODO: DeferWindowPos for bulk manipulations
-    pub fn reposition(&self, position: Position) -> Result<(), WindowError> {
-        if unsafe {
-            SetWindowPos(
-                self.inner,
-                HWND_TOP,
-                position.x as i32,
-                position.y as i32,
-                0,
-                0,
-                // TODO: SWP_NOSENDCHANGING | SWP_NOCOPYBITS | SWP_FRAMECHANGED
-                SWP_NOSIZE | SWP_NOACTIVATE | SWP_NOZORDER,
-            )
-        } != 0
-        {
-            Ok(())
+    pub fn reposition(&self, position: LogicalPosition) -> Result<(), WindowError> {
+        dpi_aware(|| {
+            let position = position.to_physical(self.monitor_scale()?);
+            if unsafe {
+                SetWindowPos(
+                    self.inner,
+                    HWND_TOP,
+                    position.x as i32,
+                    position.y as i32,
+                    0,
+                    0,
+                    // TODO: SWP_NOSENDCHANGING | SWP_NOCOPYBITS | SWP_FRAMECHANGED
+                    SWP_NOSIZE | SWP_NOACTIVATE | SWP_NOZORDER,
+                )
+            } != 0
+            {
+                Ok(())
+            } else {
+                Err(WindowError::last_os_error())
+            }
+        })
+    }
+
+    // The outer frame in physical screen coordinates, the thread must be DPI aware.
+    fn window_rect(&self) -> Result<RECT, WindowError> {
+        let mut rect: MaybeUninit<RECT> = MaybeUninit::uninit();
+        if unsafe { GetWindowRect(self.inner, rect.as_mut_ptr()) } == TRUE {
+            Ok(unsafe { rect.assume_init() })
         } else {
             Err(WindowError::last_os_error())
         }
     }
 
+    pub fn frame(&self) -> Result<Rect, WindowError> {
+        dpi_aware(|| {
+            let rect = self.window_rect()?;
+            let scale = self.monitor_scale()?;
+            Ok(Rect {
+                position: PhysicalPosition {
+                    x: rect.left as f64,
+                    y: rect.top as f64,
+                }
+                .to_logical(scale),
+                size: PhysicalSize {
+                    width: (rect.right - rect.left) as f64,
+                    height: (rect.bottom - rect.top) as f64,
+                }
+                .to_logical(scale),
+            })
+        })
+    }
+
+    // The client area is relative to itself, so its origin is mapped to the screen to compare it
+    // with the outer frame.
+    pub fn decoration_insets(&self) -> Result<Insets, WindowError> {
+        dpi_aware(|| {
+            let window = self.window_rect()?;
+            let mut client: MaybeUninit<RECT> = MaybeUninit::uninit();
+            if unsafe { GetClientRect(self.inner, client.as_mut_ptr()) } == FALSE {
+                return Err(WindowError::last_os_error());
+            }
+            let client = unsafe { client.assume_init() };
+            let mut origin = POINT { x: 0, y: 0 };
+            if unsafe { ClientToScreen(self.inner, &mut origin) } == FALSE {
+                return Err(WindowError::last_os_error());
+            }
+
+            let scale = self.monitor_scale()?;
+            Ok(Insets {
+                top: (origin.y - window.top) as f64 / scale,
+                right: (window.right - origin.x - client.right) as f64 / scale,
+                bottom: (window.bottom - origin.y - client.bottom) as f64 / scale,
+                left: (origin.x - window.left) as f64 / scale,
+            })
+        })
+    }
+
+    pub fn set_frame(&self, frame: Rect) -> Result<Rect, WindowError> {
+        dpi_aware(|| {
+            let scale = self.monitor_scale()?;
+            let (position, size) = (
+                frame.position.to_physical(scale),
+                frame.size.to_physical(scale),
+            );
+            if unsafe {
+                SetWindowPos(
+                    self.inner,
+                    HWND_TOP,
+                    position.x as i32,
+                    position.y as i32,
+                    size.width as i32,
+                    size.height as i32,
+                    SWP_NOACTIVATE | SWP_NOZORDER,
+                )
+            } != 0
+            {
+                self.frame()
+            } else {
+                Err(WindowError::last_os_error())
+            }
+        })
+    }
+
+    // Same as `set_frame` and `bring_to_front`, but applied once the batch ends. Frames are
+    // scaled by the scale of the window's monitor, the thread must be DPI aware.
+    pub(super) fn defer(&self, hdwp: HDWP, change: Change, scale: f64) -> HDWP {
+        match change {
+            Change::SetFrame(frame) => unsafe {
+                let (position, size) = (
+                    frame.position.to_physical(scale),
+                    frame.size.to_physical(scale),
+                );
+                DeferWindowPos(
+                    hdwp,
+                    self.inner,
+                    HWND_TOP,
+                    position.x as i32,
+                    position.y as i32,
+                    size.width as i32,
+                    size.height as i32,
+                    SWP_NOACTIVATE | SWP_NOZORDER,
+                )
+            },
+            Change::Raise => unsafe {
+                DeferWindowPos(
+                    hdwp,
+                    self.inner,
+                    HWND_TOPMOST,
+                    0,
+                    0,
+                    0,
+                    0,
+                    SWP_NOSIZE | SWP_NOMOVE | SWP_NOACTIVATE,
+                )
+            },
+            Change::Minimize | Change::Focus => {
+                unreachable!("only frames and stacking can be deferred")
+            }
+        }
+    }
+
     pub fn focus(&self) -> Result<(), WindowError> {
         // Start by spawning a new thread so that attaching the window thread's
         // input doesn't affect the caller's code.
@@ -378,4 +559,22 @@ impl Window {
             Err(WindowError::last_os_error())
         }
     }
+
+    pub fn pid(&self) -> Result<u32, WindowError> {
+        let mut pid = 0;
+        if unsafe { GetWindowThreadProcessId(self.inner, &mut pid) } != 0 {
+            Ok(pid)
+        } else {
+            Err(WindowError::last_os_error())
+        }
+    }
+
+    // Posted rather than sent so that a window prompting the user doesn't block us.
+    pub fn close(&self) -> Result<(), WindowError> {
+        if unsafe { PostMessageW(self.inner, WM_CLOSE, 0, 0) } != 0 {
+            Ok(())
+        } else {
+            Err(WindowError::last_os_error())
+        }
+    }
 }
@@ -0,0 +1,419 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use wayland_client::{
+    event_created_child,
+    globals::{registry_queue_init, GlobalListContents},
+    protocol::{wl_callback::WlCallback, wl_registry::WlRegistry, wl_seat::WlSeat},
+    Connection, Dispatch, Proxy, QueueHandle,
+};
+use wayland_protocols_wlr::foreign_toplevel::v1::client::{
+    zwlr_foreign_toplevel_handle_v1::{self, ZwlrForeignToplevelHandleV1},
+    zwlr_foreign_toplevel_manager_v1::{self, ZwlrForeignToplevelManagerV1},
+};
+
+use crate::{protocol, WindowError, WindowEvent};
+
+use super::wayland::{self, disconnected, SyncDone};
+
+pub use window::Window;
+
+mod window;
+
+pub type WindowHandle = ZwlrForeignToplevelHandleV1;
+
+type Event = Result<WindowEvent, WindowError>;
+
+// Unlike the other backends, Wayland doesn't let us query properties on demand. Instead, the compositor
+// sends them as events, which are dispatched on a background thread and cached here.
+static SESSION: Mutex<Option<Arc<Session>>> = Mutex::new(None);
+
+pub(crate) fn session() -> Result<Arc<Session>, WindowError> {
+    let mut session = SESSION.lock().unwrap();
+    match &*session {
+        Some(session) => Ok(session.clone()),
+        None => {
+            let new_session = Arc::new(Session::new()?);
+            *session = Some(new_session.clone());
+            Ok(new_session)
+        }
+    }
+}
+
+/// The properties of a toplevel, as last reported by the compositor.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Properties {
+    pub title: String,
+    pub app_id: String,
+    pub maximized: bool,
+    pub minimized: bool,
+    pub activated: bool,
+    pub fullscreen: bool,
+}
+
+#[derive(Debug, Default)]
+struct Toplevel {
+    current: Properties,
+    // Properties are double-buffered, they only apply once the `done` event is received.
+    pending: Properties,
+    // Whether or not the initial `done` event was received.
+    initialized: bool,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    toplevels: HashMap<ZwlrForeignToplevelHandleV1, Toplevel>,
+    subscribers: Vec<Sender<Event>>,
+    disconnected: bool,
+}
+
+impl Shared {
+    fn emit(&mut self, event: impl Fn() -> WindowEvent) {
+        // If the receiver is gone, then the watcher was dropped and we can remove it.
+        self.subscribers
+            .retain(|subscriber| subscriber.send(Ok(event())).is_ok());
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Session {
+    connection: Connection,
+    queue_handle: QueueHandle<State>,
+    seat: Option<WlSeat>,
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl Session {
+    fn new() -> Result<Session, WindowError> {
+        let connection = Connection::connect_to_env()?;
+        let (globals, mut queue) = registry_queue_init::<State>(&connection)?;
+        let queue_handle = queue.handle();
+
+        // The manager immediately advertises every existing toplevel once bound.
+        let _manager: ZwlrForeignToplevelManagerV1 = globals.bind(&queue_handle, 1..=3, ())?;
+        // A seat is only necessary for activating toplevels.
+        let seat = globals.bind(&queue_handle, 1..=1, ()).ok();
+
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let mut state = State {
+            shared: shared.clone(),
+        };
+        queue.roundtrip(&mut state)?;
+
+        thread::spawn(move || loop {
+            if let Err(err) = queue.blocking_dispatch(&mut state) {
+                let mut shared = state.shared.lock().unwrap();
+                shared.disconnected = true;
+                for subscriber in shared.subscribers.drain(..) {
+                    let _ = subscriber.send(Err(disconnected(&err.to_string())));
+                }
+                break;
+            }
+        });
+
+        Ok(Session {
+            connection,
+            queue_handle,
+            seat,
+            shared,
+        })
+    }
+
+    pub fn sync(&self) -> Result<(), WindowError> {
+        wayland::sync(&self.connection, &self.queue_handle)
+    }
+
+    pub fn properties(
+        &self,
+        handle: &ZwlrForeignToplevelHandleV1,
+    ) -> Result<Properties, WindowError> {
+        self.sync()?;
+        self.shared
+            .lock()
+            .unwrap()
+            .toplevels
+            .get(handle)
+            .filter(|toplevel| toplevel.initialized)
+            .map(|toplevel| toplevel.current.clone())
+            .ok_or(WindowError::InvalidHandle)
+    }
+
+    pub fn handles(&self) -> Result<Vec<ZwlrForeignToplevelHandleV1>, WindowError> {
+        self.sync()?;
+        Ok(self
+            .shared
+            .lock()
+            .unwrap()
+            .toplevels
+            .iter()
+            .filter(|(_, toplevel)| toplevel.initialized)
+            .map(|(handle, _)| handle.clone())
+            .collect())
+    }
+
+    pub fn seat(&self) -> Option<&WlSeat> {
+        self.seat.as_ref()
+    }
+
+    pub fn flush(&self) -> Result<(), WindowError> {
+        self.connection.flush()?;
+        Ok(())
+    }
+
+    fn subscribe(&self) -> Result<Receiver<Event>, WindowError> {
+        let mut shared = self.shared.lock().unwrap();
+        if shared.disconnected {
+            return Err(disconnected("the connection was closed"));
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        shared.subscribers.push(sender);
+        Ok(receiver)
+    }
+}
+
+#[derive(Debug)]
+pub struct Watcher {
+    receiver: Receiver<Event>,
+    // Keep the session alive for as long as we're watching.
+    _session: Arc<Session>,
+}
+
+impl Watcher {
+    pub fn new() -> Result<Watcher, WindowError> {
+        let session = session()?;
+        Ok(Watcher {
+            receiver: session.subscribe()?,
+            _session: session,
+        })
+    }
+
+    pub fn next_request(&mut self) -> Result<WindowEvent, WindowError> {
+        self.receiver
+            .recv()
+            .unwrap_or_else(|_| Err(disconnected("the connection was closed")))
+    }
+
+    pub fn next_request_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<WindowEvent>, WindowError> {
+        match self.receiver.recv_timeout(timeout) {
+            Ok(event) => event.map(Some),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err(disconnected("the connection was closed")),
+        }
+    }
+}
+
+pub fn iter_windows() -> impl Iterator<Item = Result<Window, WindowError>> {
+    let windows = match session().and_then(|session| session.handles()) {
+        Ok(handles) => handles
+            .into_iter()
+            .map(|handle| Ok(Window::new(handle)))
+            .collect(),
+        Err(err) => vec![Err(err)],
+    };
+    windows.into_iter()
+}
+
+pub fn focused_window() -> Result<Option<Window>, WindowError> {
+    let session = session()?;
+    session.sync()?;
+    let shared = session.shared.lock().unwrap();
+    Ok(shared
+        .toplevels
+        .iter()
+        .find(|(_, toplevel)| toplevel.initialized && toplevel.current.activated)
+        .map(|(handle, _)| Window::new(handle.clone())))
+}
+
+#[derive(Debug)]
+struct State {
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for State {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WlRegistry,
+        _event: <WlRegistry as Proxy>::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<WlSeat, ()> for State {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WlSeat,
+        _event: <WlSeat as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<WlCallback, SyncDone> for State {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WlCallback,
+        _event: <WlCallback as Proxy>::Event,
+        data: &SyncDone,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        wayland::sync_done(data);
+    }
+}
+
+impl Dispatch<ZwlrForeignToplevelManagerV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        _proxy: &ZwlrForeignToplevelManagerV1,
+        event: zwlr_foreign_toplevel_manager_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        // The `finished` event means the compositor will no longer send new toplevels, there's nothing
+        // we can do about that.
+        if let zwlr_foreign_toplevel_manager_v1::Event::Toplevel { toplevel } = event {
+            state
+                .shared
+                .lock()
+                .unwrap()
+                .toplevels
+                .insert(toplevel, Toplevel::default());
+        }
+    }
+
+    event_created_child!(State, ZwlrForeignToplevelManagerV1, [
+        zwlr_foreign_toplevel_manager_v1::EVT_TOPLEVEL_OPCODE => (ZwlrForeignToplevelHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrForeignToplevelHandleV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        proxy: &ZwlrForeignToplevelHandleV1,
+        event: zwlr_foreign_toplevel_handle_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        let mut shared = state.shared.lock().unwrap();
+        let window = || protocol::Window::from_sys(Window::new(proxy.clone()).into());
+
+        match event {
+            zwlr_foreign_toplevel_handle_v1::Event::Closed => {
+                if let Some(toplevel) = shared.toplevels.remove(proxy) {
+                    if toplevel.initialized {
+                        shared.emit(|| {
+                            WindowEvent::Closed(protocol::WindowHandle::from_sys(
+                                proxy.clone().into(),
+                            ))
+                        });
+                    }
+                }
+                proxy.destroy();
+                return;
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::Done => {}
+            event => {
+                if let Some(toplevel) = shared.toplevels.get_mut(proxy) {
+                    let pending = &mut toplevel.pending;
+                    match event {
+                        zwlr_foreign_toplevel_handle_v1::Event::Title { title } => {
+                            pending.title = title
+                        }
+                        zwlr_foreign_toplevel_handle_v1::Event::AppId { app_id } => {
+                            pending.app_id = app_id
+                        }
+                        zwlr_foreign_toplevel_handle_v1::Event::State { state } => {
+                            let states: Vec<u32> = state
+                                .chunks_exact(4)
+                                .map(|chunk| u32::from_ne_bytes(chunk.try_into().unwrap()))
+                                .collect();
+                            let has = |flag: zwlr_foreign_toplevel_handle_v1::State| {
+                                states.contains(&(flag as u32))
+                            };
+
+                            pending.maximized =
+                                has(zwlr_foreign_toplevel_handle_v1::State::Maximized);
+                            pending.minimized =
+                                has(zwlr_foreign_toplevel_handle_v1::State::Minimized);
+                            pending.activated =
+                                has(zwlr_foreign_toplevel_handle_v1::State::Activated);
+                            pending.fullscreen =
+                                has(zwlr_foreign_toplevel_handle_v1::State::Fullscreen);
+                        }
+                        _ => {}
+                    }
+                }
+                return;
+            }
+        }
+
+        // Apply the pending properties now that we received the `done` event.
+        let Some(toplevel) = shared.toplevels.get_mut(proxy) else {
+            return;
+        };
+        let previous = toplevel.current.clone();
+        let current = toplevel.pending.clone();
+        let initialized = toplevel.initialized;
+        toplevel.current = current.clone();
+        toplevel.initialized = true;
+
+        if !initialized {
+            shared.emit(|| WindowEvent::Opened(window()));
+            return;
+        }
+
+        if previous.title != current.title {
+            shared.emit(|| WindowEvent::Renamed {
+                window: window(),
+                old_title: Some(previous.title.clone()),
+                new_title: Some(current.title.clone()),
+            });
+        }
+        if previous.minimized != current.minimized {
+            if current.minimized {
+                shared.emit(|| WindowEvent::Minimized(window()));
+            } else {
+                shared.emit(|| WindowEvent::Unminimized(window()));
+            }
+        }
+        if previous.maximized != current.maximized {
+            if current.maximized {
+                shared.emit(|| WindowEvent::Maximized(window()));
+            } else {
+                shared.emit(|| WindowEvent::Unmaximized(window()));
+            }
+        }
+        if previous.fullscreen != current.fullscreen {
+            if current.fullscreen {
+                shared.emit(|| WindowEvent::Fullscreened(window()));
+            } else {
+                shared.emit(|| WindowEvent::Unfullscreened(window()));
+            }
+        }
+        if previous.activated != current.activated {
+            if current.activated {
+                shared.emit(|| WindowEvent::Focused(window()));
+            } else {
+                shared.emit(|| WindowEvent::Unfocused(window()));
+            }
+        }
+    }
+}
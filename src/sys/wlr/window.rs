@@ -0,0 +1,139 @@
+use wayland_client::Proxy;
+
+use crate::{Insets, LogicalPosition, LogicalSize, Rect, WindowError};
+
+use super::{session, WindowHandle};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Window {
+    inner: WindowHandle,
+}
+
+// NOTE: the protocol was designed for taskbars and docks, so it doesn't expose any geometry.
+impl Window {
+    pub(crate) fn new(inner: WindowHandle) -> Window {
+        Window { inner }
+    }
+
+    pub fn handle(&self) -> WindowHandle {
+        self.inner.clone()
+    }
+
+    pub fn title(&self) -> Result<String, WindowError> {
+        Ok(session()?.properties(&self.inner)?.title)
+    }
+
+    pub fn size(&self) -> Result<LogicalSize, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn position(&self) -> Result<LogicalPosition, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn scale_factor(&self) -> Result<f64, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn is_focused(&self) -> Result<bool, WindowError> {
+        Ok(session()?.properties(&self.inner)?.activated)
+    }
+
+    pub fn is_fullscreen(&self) -> Result<bool, WindowError> {
+        Ok(session()?.properties(&self.inner)?.fullscreen)
+    }
+
+    pub fn is_minimized(&self) -> Result<bool, WindowError> {
+        Ok(session()?.properties(&self.inner)?.minimized)
+    }
+
+    #[inline]
+    pub fn is_hidden(&self) -> Result<bool, WindowError> {
+        // Default behavior of Window::hide is to minimize, so check if minimized.
+        self.is_minimized()
+    }
+
+    pub fn resize(&self, _size: LogicalSize) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn reposition(&self, _position: LogicalPosition) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn frame(&self) -> Result<Rect, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn set_frame(&self, _frame: Rect) -> Result<Rect, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn decoration_insets(&self) -> Result<Insets, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn focus(&self) -> Result<(), WindowError> {
+        let session = session()?;
+        let seat = session.seat().ok_or(WindowError::Unsupported)?;
+        self.inner.activate(seat);
+        session.flush()
+    }
+
+    pub fn fullscreen(&self) -> Result<(), WindowError> {
+        // Fullscreening was added in the second version of the protocol.
+        if self.inner.version() < 2 {
+            return Err(WindowError::Unsupported);
+        }
+
+        self.inner.set_fullscreen(None);
+        session()?.flush()
+    }
+
+    pub fn unfullscreen(&self) -> Result<(), WindowError> {
+        if self.inner.version() < 2 {
+            return Err(WindowError::Unsupported);
+        }
+
+        self.inner.unset_fullscreen();
+        session()?.flush()
+    }
+
+    pub fn maximize(&self) -> Result<(), WindowError> {
+        self.inner.set_maximized();
+        session()?.flush()
+    }
+
+    pub fn minimize(&self) -> Result<(), WindowError> {
+        self.inner.set_minimized();
+        session()?.flush()
+    }
+
+    pub fn unminimize(&self) -> Result<(), WindowError> {
+        self.inner.unset_minimized();
+        session()?.flush()
+    }
+
+    pub fn show(&self) -> Result<(), WindowError> {
+        self.unminimize()
+    }
+
+    pub fn hide(&self) -> Result<(), WindowError> {
+        self.minimize()
+    }
+
+    // There's no way to raise a toplevel without activating it.
+    pub fn bring_to_front(&self) -> Result<(), WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    // The protocol only exposes the app id, not the process.
+    pub fn pid(&self) -> Result<u32, WindowError> {
+        Err(WindowError::Unsupported)
+    }
+
+    pub fn close(&self) -> Result<(), WindowError> {
+        self.inner.close();
+        session()?.flush()
+    }
+}
@@ -0,0 +1,1219 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io, mem,
+    os::fd::AsFd,
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+use x11rb::{
+    connection::Connection as _,
+    cookie,
+    errors::{ConnectError, ConnectionError, ReplyError},
+    protocol::{
+        xproto::{
+            self, AtomEnum, ChangeWindowAttributesAux, ClientMessageEvent, ConfigureWindowAux,
+            ConnectionExt, EventMask, GetGeometryReply, GetPropertyReply, GetWindowAttributesReply,
+            MapState, StackMode, TranslateCoordinatesReply,
+        },
+        ErrorKind, Event,
+    },
+    rust_connection::RustConnection,
+};
+
+use crate::{
+    protocol::{self, transaction::Change},
+    LogicalPosition, LogicalSize, PropertyMask, WindowError, WindowEvent, WindowProperties,
+};
+
+pub use window::Window;
+
+mod window;
+
+pub type WindowHandle = xproto::Window;
+
+type Cookie<'a, R> = cookie::Cookie<'a, RustConnection, R>;
+type VoidCookie<'a> = cookie::VoidCookie<'a, RustConnection>;
+
+x11rb::atom_manager! {
+    pub Atoms: AtomsCookie {
+        _NET_SUPPORTED,
+        _NET_CLIENT_LIST,
+        _NET_CLIENT_LIST_STACKING,
+        _NET_ACTIVE_WINDOW,
+        _NET_WM_NAME,
+        _NET_WM_PID,
+        _NET_WM_STATE,
+        _NET_WM_STATE_HIDDEN,
+        _NET_WM_STATE_FULLSCREEN,
+        _NET_WM_STATE_MAXIMIZED_VERT,
+        _NET_WM_STATE_MAXIMIZED_HORZ,
+        _NET_FRAME_EXTENTS,
+        _GTK_FRAME_EXTENTS,
+        _NET_MOVERESIZE_WINDOW,
+        _NET_RESTACK_WINDOW,
+        _NET_CLOSE_WINDOW,
+        WM_CHANGE_STATE,
+        WM_PROTOCOLS,
+        WM_DELETE_WINDOW,
+        UTF8_STRING,
+    }
+}
+
+// Values defined by the EWMH and ICCCM specifications.
+// https://specifications.freedesktop.org/wm-spec/latest/
+const SOURCE_INDICATION_PAGER: u32 = 2;
+const NET_WM_STATE_REMOVE: u32 = 0;
+const NET_WM_STATE_ADD: u32 = 1;
+const ICONIC_STATE: u32 = 3;
+const NORTH_WEST_GRAVITY: u32 = 1;
+
+// Window operations share a single connection, similar to how the other backends share a
+// single global API. The `Watcher` creates its own connection so that it is the only consumer
+// of the events it selects.
+static CONNECTION: OnceLock<Connection> = OnceLock::new();
+
+pub(crate) fn connection() -> Result<&'static Connection, WindowError> {
+    if let Some(connection) = CONNECTION.get() {
+        return Ok(connection);
+    }
+
+    // If another thread wins the race, our connection is dropped and theirs is used.
+    let connection = Connection::new()?;
+    Ok(CONNECTION.get_or_init(|| connection))
+}
+
+/// The position and size of a window's outer frame in root coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Frame {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Frame {
+    pub fn position(&self, scale_factor: f64) -> LogicalPosition {
+        LogicalPosition {
+            x: self.x as f64 / scale_factor,
+            y: self.y as f64 / scale_factor,
+        }
+    }
+
+    pub fn size(&self, scale_factor: f64) -> LogicalSize {
+        LogicalSize {
+            width: self.width as f64 / scale_factor,
+            height: self.height as f64 / scale_factor,
+        }
+    }
+}
+
+/// The size of each side of a border around a window.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Extents {
+    pub left: u32,
+    pub right: u32,
+    pub top: u32,
+    pub bottom: u32,
+}
+
+/// Everything between a client window and its outer frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct FrameExtents {
+    // The decorations drawn around the client by the window manager, from `_NET_FRAME_EXTENTS`.
+    pub decorations: Extents,
+    // The invisible margin drawn inside the client for its shadow by clients with client-side
+    // decorations, from `_GTK_FRAME_EXTENTS`. It isn't part of the outer frame.
+    pub shadow: Extents,
+}
+
+impl FrameExtents {
+    // The outer frame of a client with the specified geometry in root coordinates.
+    fn frame(&self, x: i32, y: i32, width: u32, height: u32) -> Frame {
+        let (decorations, shadow) = (self.decorations, self.shadow);
+        Frame {
+            x: x - decorations.left as i32 + shadow.left as i32,
+            y: y - decorations.top as i32 + shadow.top as i32,
+            width: (width + decorations.left + decorations.right)
+                .saturating_sub(shadow.left + shadow.right),
+            height: (height + decorations.top + decorations.bottom)
+                .saturating_sub(shadow.top + shadow.bottom),
+        }
+    }
+
+    // The requested size is the outer frame, but the window manager expects the client size.
+    pub fn client_size(&self, size: LogicalSize, scale_factor: f64) -> (u32, u32) {
+        let (decorations, shadow) = (self.decorations, self.shadow);
+        (
+            ((size.width * scale_factor).round() as u32 + shadow.left + shadow.right)
+                .saturating_sub(decorations.left + decorations.right),
+            ((size.height * scale_factor).round() as u32 + shadow.top + shadow.bottom)
+                .saturating_sub(decorations.top + decorations.bottom),
+        )
+    }
+
+    // The window manager places the top-left corner of its own frame, which includes the shadow.
+    pub fn client_position(&self, position: LogicalPosition, scale_factor: f64) -> (i32, i32) {
+        (
+            (position.x * scale_factor).round() as i32 - self.shadow.left as i32,
+            (position.y * scale_factor).round() as i32 - self.shadow.top as i32,
+        )
+    }
+}
+
+impl Extents {
+    fn from_reply(reply: GetPropertyReply) -> Extents {
+        let extents: Vec<u32> = reply
+            .value32()
+            .map(|values| values.collect())
+            .unwrap_or_default();
+        match extents[..] {
+            [left, right, top, bottom] => Extents {
+                left,
+                right,
+                top,
+                bottom,
+            },
+            // Either there's no window manager or it doesn't decorate this window.
+            _ => Extents::default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Connection {
+    inner: RustConnection,
+    root: xproto::Window,
+    atoms: Atoms,
+    scale_factor: f64,
+}
+
+impl Connection {
+    fn new() -> Result<Connection, WindowError> {
+        let (inner, screen) = RustConnection::connect(None)?;
+        let root = inner.setup().roots[screen].root;
+        let atoms = Atoms::new(&inner)?;
+        let resources = inner.get_property(
+            false,
+            root,
+            AtomEnum::RESOURCE_MANAGER,
+            AtomEnum::STRING,
+            0,
+            u32::MAX,
+        )?;
+        Ok(Connection {
+            atoms: atoms.reply()?,
+            scale_factor: scale_factor(&resources.reply()?.value),
+            inner,
+            root,
+        })
+    }
+
+    pub fn atoms(&self) -> &Atoms {
+        &self.atoms
+    }
+
+    // X11 doesn't scale windows, so the scale is the one that clients are asked to draw at through
+    // `Xft.dpi`, and the same for every window. It's read once when connecting.
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    pub fn client_list(&self) -> Result<Vec<xproto::Window>, WindowError> {
+        self.property32(
+            self.root,
+            self.atoms._NET_CLIENT_LIST,
+            AtomEnum::WINDOW.into(),
+        )
+    }
+
+    pub fn client_list_stacking(&self) -> Result<Vec<xproto::Window>, WindowError> {
+        self.property32(
+            self.root,
+            self.atoms._NET_CLIENT_LIST_STACKING,
+            AtomEnum::WINDOW.into(),
+        )
+    }
+
+    pub fn active_window(&self) -> Result<Option<xproto::Window>, WindowError> {
+        Ok(active_window(self.request_active_window()?.reply()?))
+    }
+
+    fn request_active_window(&self) -> Result<Cookie<'_, GetPropertyReply>, WindowError> {
+        self.request_property32(
+            self.root,
+            self.atoms._NET_ACTIVE_WINDOW,
+            AtomEnum::WINDOW.into(),
+        )
+    }
+
+    pub fn supports(&self, atom: xproto::Atom) -> Result<bool, WindowError> {
+        Ok(self.supported()?.contains(&atom))
+    }
+
+    fn supported(&self) -> Result<Vec<xproto::Atom>, WindowError> {
+        self.property32(self.root, self.atoms._NET_SUPPORTED, AtomEnum::ATOM.into())
+    }
+
+    pub fn title(&self, window: xproto::Window) -> Result<String, WindowError> {
+        self.request_title(window)?.reply()
+    }
+
+    // Both titles are requested up front, so that falling back doesn't cost another round trip.
+    fn request_title(&self, window: xproto::Window) -> Result<TitleCookies<'_>, WindowError> {
+        Ok(TitleCookies {
+            ewmh: self.inner.get_property(
+                false,
+                window,
+                self.atoms._NET_WM_NAME,
+                self.atoms.UTF8_STRING,
+                0,
+                u32::MAX,
+            )?,
+            icccm: self.inner.get_property(
+                false,
+                window,
+                AtomEnum::WM_NAME,
+                AtomEnum::ANY,
+                0,
+                u32::MAX,
+            )?,
+        })
+    }
+
+    // Clients aren't required to set it, and it's meaningless for clients on another machine, such
+    // as one forwarded over SSH. Another process with the same pid could be killed otherwise, so
+    // it's only used if the client says it runs on this machine, which EWMH requires alongside it.
+    pub fn pid(&self, window: xproto::Window) -> Result<u32, WindowError> {
+        let pid =
+            self.request_property32(window, self.atoms._NET_WM_PID, AtomEnum::CARDINAL.into())?;
+        let machine = self.inner.get_property(
+            false,
+            window,
+            AtomEnum::WM_CLIENT_MACHINE,
+            AtomEnum::STRING,
+            0,
+            u32::MAX,
+        )?;
+
+        let pid = values32(pid.reply()?)
+            .first()
+            .copied()
+            .ok_or(WindowError::Unsupported)?;
+        if machine.reply()?.value != super::hostname()? {
+            return Err(WindowError::Unsupported);
+        }
+        Ok(pid)
+    }
+
+    pub fn state(&self, window: xproto::Window) -> Result<Vec<xproto::Atom>, WindowError> {
+        Ok(values32(self.request_state(window)?.reply()?))
+    }
+
+    fn request_state(
+        &self,
+        window: xproto::Window,
+    ) -> Result<Cookie<'_, GetPropertyReply>, WindowError> {
+        self.request_property32(window, self.atoms._NET_WM_STATE, AtomEnum::ATOM.into())
+    }
+
+    pub fn extents(&self, window: xproto::Window) -> Result<FrameExtents, WindowError> {
+        self.request_extents(window)?.reply()
+    }
+
+    fn request_extents(&self, window: xproto::Window) -> Result<ExtentsCookies<'_>, WindowError> {
+        let request = |property| {
+            self.inner
+                .get_property(false, window, property, AtomEnum::CARDINAL, 0, 4)
+        };
+        Ok(ExtentsCookies {
+            decorations: request(self.atoms._NET_FRAME_EXTENTS)?,
+            shadow: request(self.atoms._GTK_FRAME_EXTENTS)?,
+        })
+    }
+
+    // The returned frame includes the decorations (e.g. title bar) drawn by the window manager, to stay
+    // consistent with the other backends.
+    pub fn frame(&self, window: xproto::Window) -> Result<Frame, WindowError> {
+        self.request_frame(window)?.reply()
+    }
+
+    // Send all requests before waiting on any of the replies to avoid unnecessary round trips.
+    fn request_frame(&self, window: xproto::Window) -> Result<FrameCookies<'_>, WindowError> {
+        Ok(FrameCookies {
+            geometry: self.inner.get_geometry(window)?,
+            origin: self.inner.translate_coordinates(window, self.root, 0, 0)?,
+            extents: self.request_extents(window)?,
+        })
+    }
+
+    pub fn is_viewable(&self, window: xproto::Window) -> Result<bool, WindowError> {
+        Ok(is_viewable(self.request_attributes(window)?.reply()?))
+    }
+
+    fn request_attributes(
+        &self,
+        window: xproto::Window,
+    ) -> Result<Cookie<'_, GetWindowAttributesReply>, WindowError> {
+        Ok(self.inner.get_window_attributes(window)?)
+    }
+
+    fn request_properties(
+        &self,
+        window: xproto::Window,
+        mask: PropertyMask,
+    ) -> Result<PropertyCookies<'_>, WindowError> {
+        Ok(PropertyCookies {
+            title: mask
+                .select(PropertyMask::TITLE, || self.request_title(window))
+                .transpose()?,
+            frame: mask
+                .intersects(PropertyMask::FRAME)
+                .then(|| self.request_frame(window))
+                .transpose()?,
+            state: mask
+                .intersects(PropertyMask::FULLSCREEN | PropertyMask::MINIMIZED)
+                .then(|| self.request_state(window))
+                .transpose()?,
+            attributes: mask
+                .select(PropertyMask::HIDDEN, || self.request_attributes(window))
+                .transpose()?,
+        })
+    }
+
+    pub fn move_resize(
+        &self,
+        window: xproto::Window,
+        x: Option<i32>,
+        y: Option<i32>,
+        width: Option<u32>,
+        height: Option<u32>,
+    ) -> Result<(), WindowError> {
+        let ewmh = self.supports(self.atoms._NET_MOVERESIZE_WINDOW)?;
+        self.request_move_resize(ewmh, window, x, y, width, height)?
+            .check()?;
+        Ok(())
+    }
+
+    // https://specifications.freedesktop.org/wm-spec/latest/ar01s04.html#id-1.5.6
+    fn request_move_resize(
+        &self,
+        ewmh: bool,
+        window: xproto::Window,
+        x: Option<i32>,
+        y: Option<i32>,
+        width: Option<u32>,
+        height: Option<u32>,
+    ) -> Result<VoidCookie<'_>, WindowError> {
+        if ewmh {
+            // With north west gravity, the position refers to the outer top-left corner of the frame.
+            let mut flags = NORTH_WEST_GRAVITY | (SOURCE_INDICATION_PAGER << 12);
+            for (bit, value) in [x.is_some(), y.is_some(), width.is_some(), height.is_some()]
+                .into_iter()
+                .enumerate()
+            {
+                if value {
+                    flags |= 1 << (8 + bit);
+                }
+            }
+
+            self.request_client_message(
+                window,
+                self.atoms._NET_MOVERESIZE_WINDOW,
+                [
+                    flags,
+                    x.unwrap_or_default() as u32,
+                    y.unwrap_or_default() as u32,
+                    width.unwrap_or_default(),
+                    height.unwrap_or_default(),
+                ],
+            )
+        } else {
+            // Without a window manager to handle the request, configure the window directly.
+            let mut aux = ConfigureWindowAux::new();
+            aux.x = x;
+            aux.y = y;
+            aux.width = width;
+            aux.height = height;
+            Ok(self.inner.configure_window(window, &aux)?)
+        }
+    }
+
+    pub fn change_state(
+        &self,
+        window: xproto::Window,
+        add: bool,
+        first: xproto::Atom,
+        second: xproto::Atom,
+    ) -> Result<(), WindowError> {
+        let action = if add {
+            NET_WM_STATE_ADD
+        } else {
+            NET_WM_STATE_REMOVE
+        };
+        self.send_client_message(
+            window,
+            self.atoms._NET_WM_STATE,
+            [action, first, second, SOURCE_INDICATION_PAGER, 0],
+        )
+    }
+
+    pub fn activate(&self, window: xproto::Window) -> Result<(), WindowError> {
+        self.request_activate(window)?.check()?;
+        Ok(())
+    }
+
+    fn request_activate(&self, window: xproto::Window) -> Result<VoidCookie<'_>, WindowError> {
+        self.request_client_message(
+            window,
+            self.atoms._NET_ACTIVE_WINDOW,
+            // The timestamp is left as `CurrentTime`, since we don't have access to the last user event.
+            [SOURCE_INDICATION_PAGER, x11rb::CURRENT_TIME, 0, 0, 0],
+        )
+    }
+
+    pub fn iconify(&self, window: xproto::Window) -> Result<(), WindowError> {
+        self.request_iconify(window)?.check()?;
+        Ok(())
+    }
+
+    // https://tronche.com/gui/x/icccm/sec-4.html#s-4.1.4
+    fn request_iconify(&self, window: xproto::Window) -> Result<VoidCookie<'_>, WindowError> {
+        self.request_client_message(
+            window,
+            self.atoms.WM_CHANGE_STATE,
+            [ICONIC_STATE, 0, 0, 0, 0],
+        )
+    }
+
+    pub fn map(&self, window: xproto::Window) -> Result<(), WindowError> {
+        // The window manager intercepts the map request and deiconifies the window.
+        self.inner.map_window(window)?.check()?;
+        Ok(())
+    }
+
+    pub fn raise(&self, window: xproto::Window) -> Result<(), WindowError> {
+        let ewmh = self.supports(self.atoms._NET_RESTACK_WINDOW)?;
+        self.request_raise(ewmh, window)?.check()?;
+        Ok(())
+    }
+
+    fn request_raise(
+        &self,
+        ewmh: bool,
+        window: xproto::Window,
+    ) -> Result<VoidCookie<'_>, WindowError> {
+        if ewmh {
+            self.request_client_message(
+                window,
+                self.atoms._NET_RESTACK_WINDOW,
+                [
+                    SOURCE_INDICATION_PAGER,
+                    x11rb::NONE,
+                    u32::from(StackMode::ABOVE),
+                    0,
+                    0,
+                ],
+            )
+        } else {
+            Ok(self.inner.configure_window(
+                window,
+                &ConfigureWindowAux::new().stack_mode(StackMode::ABOVE),
+            )?)
+        }
+    }
+
+    pub fn close(&self, window: xproto::Window) -> Result<(), WindowError> {
+        if self.supports(self.atoms._NET_CLOSE_WINDOW)? {
+            return self.send_client_message(
+                window,
+                self.atoms._NET_CLOSE_WINDOW,
+                [x11rb::CURRENT_TIME, SOURCE_INDICATION_PAGER, 0, 0, 0],
+            );
+        }
+
+        // Without a window manager to ask, the client has to opt in to being asked.
+        // https://tronche.com/gui/x/icccm/sec-4.html#s-4.2.8.1
+        let protocols = self.property32(window, self.atoms.WM_PROTOCOLS, AtomEnum::ATOM.into())?;
+        if !protocols.contains(&self.atoms.WM_DELETE_WINDOW) {
+            return Err(WindowError::Unsupported);
+        }
+
+        let event = ClientMessageEvent::new(
+            32,
+            window,
+            self.atoms.WM_PROTOCOLS,
+            [self.atoms.WM_DELETE_WINDOW, x11rb::CURRENT_TIME, 0, 0, 0],
+        );
+        self.inner
+            .send_event(false, window, EventMask::NO_EVENT, event)?
+            .check()?;
+        Ok(())
+    }
+
+    fn send_client_message(
+        &self,
+        window: xproto::Window,
+        message_type: xproto::Atom,
+        data: [u32; 5],
+    ) -> Result<(), WindowError> {
+        self.request_client_message(window, message_type, data)?
+            .check()?;
+        Ok(())
+    }
+
+    fn request_client_message(
+        &self,
+        window: xproto::Window,
+        message_type: xproto::Atom,
+        data: [u32; 5],
+    ) -> Result<VoidCookie<'_>, WindowError> {
+        let event = ClientMessageEvent::new(32, window, message_type, data);
+        Ok(self.inner.send_event(
+            false,
+            self.root,
+            EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+            event,
+        )?)
+    }
+
+    fn property32(
+        &self,
+        window: xproto::Window,
+        property: xproto::Atom,
+        type_: xproto::Atom,
+    ) -> Result<Vec<u32>, WindowError> {
+        Ok(values32(
+            self.request_property32(window, property, type_)?.reply()?,
+        ))
+    }
+
+    fn request_property32(
+        &self,
+        window: xproto::Window,
+        property: xproto::Atom,
+        type_: xproto::Atom,
+    ) -> Result<Cookie<'_, GetPropertyReply>, WindowError> {
+        Ok(self
+            .inner
+            .get_property(false, window, property, type_, 0, u32::MAX)?)
+    }
+}
+
+// Parses `Xft.dpi` out of the X resources, falling back to no scaling if it's missing.
+fn scale_factor(resources: &[u8]) -> f64 {
+    String::from_utf8_lossy(resources)
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim() == "Xft.dpi")
+        .and_then(|(_, dpi)| dpi.trim().parse::<f64>().ok())
+        .filter(|dpi| *dpi > 0.0)
+        .map_or(1.0, |dpi| dpi / 96.0)
+}
+
+fn values32(reply: GetPropertyReply) -> Vec<u32> {
+    reply
+        .value32()
+        .map(|values| values.collect())
+        .unwrap_or_default()
+}
+
+fn active_window(reply: GetPropertyReply) -> Option<xproto::Window> {
+    values32(reply)
+        .first()
+        .copied()
+        .filter(|&window| window != x11rb::NONE)
+}
+
+fn is_viewable(attributes: GetWindowAttributesReply) -> bool {
+    attributes.map_state == MapState::VIEWABLE
+}
+
+struct TitleCookies<'a> {
+    ewmh: Cookie<'a, GetPropertyReply>,
+    icccm: Cookie<'a, GetPropertyReply>,
+}
+
+impl TitleCookies<'_> {
+    fn reply(self) -> Result<String, WindowError> {
+        let reply = self.ewmh.reply()?;
+        if reply.type_ != x11rb::NONE {
+            return Ok(String::from_utf8_lossy(&reply.value).into_owned());
+        }
+
+        // Fallback to the ICCCM title for clients that don't set the EWMH title.
+        let reply = self.icccm.reply()?;
+        if reply.type_ == u32::from(AtomEnum::STRING) {
+            // `STRING` is encoded as Latin-1, which maps directly onto the first 256 code points.
+            Ok(reply.value.iter().map(|&byte| byte as char).collect())
+        } else {
+            // Typically `COMPOUND_TEXT`, which we coerce to UTF-8 for cross-platform sake.
+            Ok(String::from_utf8_lossy(&reply.value).into_owned())
+        }
+    }
+}
+
+struct ExtentsCookies<'a> {
+    decorations: Cookie<'a, GetPropertyReply>,
+    shadow: Cookie<'a, GetPropertyReply>,
+}
+
+impl ExtentsCookies<'_> {
+    fn reply(self) -> Result<FrameExtents, WindowError> {
+        Ok(FrameExtents {
+            decorations: Extents::from_reply(self.decorations.reply()?),
+            shadow: Extents::from_reply(self.shadow.reply()?),
+        })
+    }
+}
+
+struct FrameCookies<'a> {
+    geometry: Cookie<'a, GetGeometryReply>,
+    origin: Cookie<'a, TranslateCoordinatesReply>,
+    extents: ExtentsCookies<'a>,
+}
+
+impl FrameCookies<'_> {
+    fn reply(self) -> Result<Frame, WindowError> {
+        let geometry = self.geometry.reply()?;
+        let origin = self.origin.reply()?;
+        let extents = self.extents.reply()?;
+
+        Ok(extents.frame(
+            origin.dst_x.into(),
+            origin.dst_y.into(),
+            geometry.width.into(),
+            geometry.height.into(),
+        ))
+    }
+}
+
+/// The requests needed for the properties in a mask, only those in the mask are sent.
+struct PropertyCookies<'a> {
+    title: Option<TitleCookies<'a>>,
+    frame: Option<FrameCookies<'a>>,
+    state: Option<Cookie<'a, GetPropertyReply>>,
+    attributes: Option<Cookie<'a, GetWindowAttributesReply>>,
+}
+
+impl PropertyCookies<'_> {
+    fn reply(
+        self,
+        connection: &Connection,
+        window: xproto::Window,
+        active: Option<xproto::Window>,
+        mask: PropertyMask,
+    ) -> Result<WindowProperties, WindowError> {
+        let (atoms, scale_factor) = (&connection.atoms, connection.scale_factor);
+        let frame = self.frame.map(FrameCookies::reply).transpose()?;
+        let state = match self.state {
+            Some(state) => values32(state.reply()?),
+            None => Vec::new(),
+        };
+
+        Ok(WindowProperties {
+            title: self.title.map(TitleCookies::reply).transpose()?,
+            size: frame
+                .filter(|_| mask.contains(PropertyMask::SIZE))
+                .map(|frame| frame.size(scale_factor)),
+            position: frame
+                .filter(|_| mask.contains(PropertyMask::POSITION))
+                .map(|frame| frame.position(scale_factor)),
+            is_focused: mask.select(PropertyMask::FOCUSED, || active == Some(window)),
+            is_fullscreen: mask.select(PropertyMask::FULLSCREEN, || {
+                state.contains(&atoms._NET_WM_STATE_FULLSCREEN)
+            }),
+            is_minimized: mask.select(PropertyMask::MINIMIZED, || {
+                state.contains(&atoms._NET_WM_STATE_HIDDEN)
+            }),
+            is_hidden: self
+                .attributes
+                .map(|attributes| Ok::<_, WindowError>(!is_viewable(attributes.reply()?)))
+                .transpose()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct WindowState {
+    frame: Frame,
+    minimized: bool,
+    maximized: bool,
+    fullscreen: bool,
+}
+
+impl WindowState {
+    fn new(frame: Frame, state: &[xproto::Atom], atoms: &Atoms) -> WindowState {
+        WindowState {
+            frame,
+            minimized: state.contains(&atoms._NET_WM_STATE_HIDDEN),
+            // Windows that are only maximized in one direction aren't considered maximized.
+            maximized: state.contains(&atoms._NET_WM_STATE_MAXIMIZED_VERT)
+                && state.contains(&atoms._NET_WM_STATE_MAXIMIZED_HORZ),
+            fullscreen: state.contains(&atoms._NET_WM_STATE_FULLSCREEN),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Watcher {
+    connection: Connection,
+    windows: HashMap<xproto::Window, WindowState>,
+    active: Option<xproto::Window>,
+    // A single X11 event can translate to multiple window events (e.g. a change in the client list),
+    // along with when the X11 event happened.
+    pending: VecDeque<(WindowEvent, Option<Instant>)>,
+    // When the X11 event that is being handled happened.
+    time: Option<Instant>,
+    // When the event that was returned last happened.
+    last: Option<Instant>,
+}
+
+impl Watcher {
+    pub fn new() -> Result<Watcher, WindowError> {
+        let connection = Connection::new()?;
+
+        // Listen to changes in the client list and active window.
+        connection
+            .inner
+            .change_window_attributes(
+                connection.root,
+                &ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+            )?
+            .check()?;
+
+        let mut watcher = Watcher {
+            active: connection.active_window()?,
+            connection,
+            windows: HashMap::new(),
+            pending: VecDeque::new(),
+            time: None,
+            last: None,
+        };
+
+        for window in watcher.connection.client_list()? {
+            watcher.track(window)?;
+        }
+
+        Ok(watcher)
+    }
+
+    pub fn next_request(&mut self) -> Result<WindowEvent, WindowError> {
+        loop {
+            if let Some((event, time)) = self.pending.pop_front() {
+                self.last = time;
+                return Ok(event);
+            }
+
+            let event = self.connection.inner.wait_for_event()?;
+            self.handle_event(event)?;
+        }
+    }
+
+    pub fn next_request_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<WindowEvent>, WindowError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some((event, time)) = self.pending.pop_front() {
+                self.last = time;
+                return Ok(Some(event));
+            }
+
+            // Events that were already read from the socket are queued by x11rb, so only wait on
+            // the socket once they're handled.
+            match self.connection.inner.poll_for_event()? {
+                Some(event) => self.handle_event(event)?,
+                None => {
+                    self.connection.inner.flush()?;
+                    if !super::wait_readable(self.connection.inner.stream().as_fd(), deadline)? {
+                        return Ok(None);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn event_time(&self) -> Option<Instant> {
+        self.last
+    }
+
+    fn handle_event(&mut self, event: Event) -> Result<(), WindowError> {
+        // Only property changes are stamped by the X server.
+        self.time = match &event {
+            Event::PropertyNotify(event) => event_time(event.time),
+            _ => None,
+        };
+
+        let atoms = self.connection.atoms;
+        match event {
+            Event::PropertyNotify(event) if event.window == self.connection.root => {
+                if event.atom == atoms._NET_CLIENT_LIST {
+                    let clients = self.connection.client_list()?;
+
+                    let closed: Vec<xproto::Window> = self
+                        .windows
+                        .keys()
+                        .filter(|window| !clients.contains(window))
+                        .copied()
+                        .collect();
+                    for window in closed {
+                        self.untrack(window);
+                    }
+
+                    for window in clients {
+                        if !self.windows.contains_key(&window) && self.track(window)? {
+                            self.push(WindowEvent::Opened(Self::window(window)));
+                        }
+                    }
+                } else if event.atom == atoms._NET_ACTIVE_WINDOW {
+                    let active = self.connection.active_window()?;
+                    if active != self.active {
+                        let previous = mem::replace(&mut self.active, active);
+                        // The window may have been closed in the meantime.
+                        if let Some(window) =
+                            previous.filter(|window| self.windows.contains_key(window))
+                        {
+                            self.push(WindowEvent::Unfocused(Self::window(window)));
+                        }
+                        if let Some(window) = active {
+                            self.push(WindowEvent::Focused(Self::window(window)));
+                        }
+                    }
+                }
+            }
+            Event::PropertyNotify(event) if self.windows.contains_key(&event.window) => {
+                if event.atom == atoms._NET_WM_NAME || event.atom == u32::from(AtomEnum::WM_NAME) {
+                    self.push(WindowEvent::Renamed {
+                        window: Self::window(event.window),
+                        old_title: None,
+                        new_title: None,
+                    });
+                } else if event.atom == atoms._NET_WM_STATE {
+                    let state = match self.connection.state(event.window) {
+                        Ok(state) => state,
+                        // The window may have been destroyed in the meantime.
+                        Err(WindowError::InvalidHandle) => return Ok(()),
+                        Err(err) => return Err(err),
+                    };
+
+                    if let Some(cached) = self.windows.get_mut(&event.window) {
+                        let current = WindowState::new(cached.frame, &state, &atoms);
+                        let previous = mem::replace(cached, current);
+                        let window = || Self::window(event.window);
+                        if previous.minimized != current.minimized {
+                            self.push(if current.minimized {
+                                WindowEvent::Minimized(window())
+                            } else {
+                                WindowEvent::Unminimized(window())
+                            });
+                        }
+                        if previous.maximized != current.maximized {
+                            self.push(if current.maximized {
+                                WindowEvent::Maximized(window())
+                            } else {
+                                WindowEvent::Unmaximized(window())
+                            });
+                        }
+                        if previous.fullscreen != current.fullscreen {
+                            self.push(if current.fullscreen {
+                                WindowEvent::Fullscreened(window())
+                            } else {
+                                WindowEvent::Unfullscreened(window())
+                            });
+                        }
+                    }
+                } else if event.atom == atoms._NET_FRAME_EXTENTS
+                    || event.atom == atoms._GTK_FRAME_EXTENTS
+                {
+                    self.update_frame(event.window)?;
+                }
+            }
+            Event::ConfigureNotify(event) if self.windows.contains_key(&event.window) => {
+                // A single configure is often notified several times, such as by the X server and
+                // again by the window manager, or as a move followed by a resize. The frame is
+                // only compared once the notifies that were already received are merged, so that
+                // a half-updated frame isn't reported.
+                let mut next = None;
+                while let Some(queued) = self.connection.inner.poll_for_event()? {
+                    match queued {
+                        Event::ConfigureNotify(merged) if merged.window == event.window => {}
+                        queued => {
+                            next = Some(queued);
+                            break;
+                        }
+                    }
+                }
+
+                self.update_frame(event.window)?;
+                if let Some(event) = next {
+                    self.handle_event(event)?;
+                }
+            }
+            Event::MapNotify(event) if self.windows.contains_key(&event.window) => {
+                self.push(WindowEvent::Shown(Self::window(event.window)));
+            }
+            Event::UnmapNotify(event) if self.windows.contains_key(&event.window) => {
+                self.push(WindowEvent::Hidden(Self::window(event.window)));
+            }
+            Event::DestroyNotify(event) => {
+                self.untrack(event.window);
+            }
+            // Errors from requests that weren't checked, e.g. selecting input on a window that was
+            // destroyed before we got to it. There's nothing to handle.
+            Event::Error(_) => {}
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    // Returns false if the window no longer exists.
+    fn track(&mut self, window: xproto::Window) -> Result<bool, WindowError> {
+        // Selecting input before reading the state guarantees we won't miss a change in between.
+        let result = self
+            .connection
+            .inner
+            .change_window_attributes(
+                window,
+                &ChangeWindowAttributesAux::new()
+                    .event_mask(EventMask::PROPERTY_CHANGE | EventMask::STRUCTURE_NOTIFY),
+            )?
+            .check()
+            .map_err(WindowError::from)
+            .and_then(|_| {
+                Ok(WindowState::new(
+                    self.connection.frame(window)?,
+                    &self.connection.state(window)?,
+                    &self.connection.atoms,
+                ))
+            });
+
+        match result {
+            Ok(state) => {
+                self.windows.insert(window, state);
+                Ok(true)
+            }
+            Err(WindowError::InvalidHandle) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn untrack(&mut self, window: xproto::Window) {
+        if self.windows.remove(&window).is_some() {
+            self.push(WindowEvent::Closed(protocol::WindowHandle::from_sys(
+                window.into(),
+            )));
+        }
+    }
+
+    // X11 doesn't differentiate moving from resizing, so we compare against the last known frame.
+    fn update_frame(&mut self, window: xproto::Window) -> Result<(), WindowError> {
+        let frame = match self.connection.frame(window) {
+            Ok(frame) => frame,
+            Err(WindowError::InvalidHandle) => return Ok(()),
+            Err(err) => return Err(err),
+        };
+
+        if let Some(state) = self.windows.get_mut(&window) {
+            let previous = state.frame;
+            state.frame = frame;
+
+            if (previous.x, previous.y) != (frame.x, frame.y) {
+                self.push(WindowEvent::Moved {
+                    window: Self::window(window),
+                    old: Some(previous.position(self.connection.scale_factor)),
+                    new: Some(frame.position(self.connection.scale_factor)),
+                });
+            }
+            if (previous.width, previous.height) != (frame.width, frame.height) {
+                self.push(WindowEvent::Resized {
+                    window: Self::window(window),
+                    old: Some(previous.size(self.connection.scale_factor)),
+                    new: Some(frame.size(self.connection.scale_factor)),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn push(&mut self, event: WindowEvent) {
+        self.pending.push_back((event, self.time));
+    }
+
+    fn window(window: xproto::Window) -> protocol::Window {
+        protocol::Window::from_sys(Window::new(window).into())
+    }
+}
+
+// The X server stamps events with the milliseconds of its monotonic clock, wrapped to 32 bits,
+// which is the clock of `Instant` too when the server runs on this machine. Times that are more than
+// a minute off are from another clock, such as a remote server's, and are ignored. The server may
+// use a coarser clock, so its times can be slightly ahead.
+fn event_time(time: xproto::Timestamp) -> Option<Instant> {
+    let mut now = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    if unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut now) } != 0 {
+        return None;
+    }
+    let millis = (now.tv_sec as u64 * 1000 + now.tv_nsec as u64 / 1_000_000) as u32;
+    let elapsed = millis.wrapping_sub(time) as i32;
+    if !(-1000..60_000).contains(&elapsed) {
+        return None;
+    }
+    Instant::now().checked_sub(Duration::from_millis(elapsed.max(0) as u64))
+}
+
+// Every request is sent before checking any of them, so the window manager receives them back to
+// back rather than one per round trip.
+pub fn commit(changes: &[(&Window, Change)]) -> Result<Vec<Result<(), WindowError>>, WindowError> {
+    let connection = connection()?;
+    let supported = connection.supported()?;
+
+    // Frames are requested with the client size, so the extents are needed first. Unused replies
+    // are discarded once their cookie is dropped.
+    let extents: Vec<_> = changes
+        .iter()
+        .map(|(window, _)| connection.request_extents(window.handle()))
+        .collect();
+    let cookies: Vec<_> = changes
+        .iter()
+        .zip(extents)
+        .map(|((window, change), extents)| match change {
+            Change::SetFrame(frame) => {
+                let extents = extents?.reply()?;
+                let (x, y) = extents.client_position(frame.position, connection.scale_factor);
+                let (width, height) = extents.client_size(frame.size, connection.scale_factor);
+                connection.request_move_resize(
+                    supported.contains(&connection.atoms._NET_MOVERESIZE_WINDOW),
+                    window.handle(),
+                    Some(x),
+                    Some(y),
+                    Some(width),
+                    Some(height),
+                )
+            }
+            Change::Minimize => connection.request_iconify(window.handle()),
+            Change::Focus => connection.request_activate(window.handle()),
+            Change::Raise => connection.request_raise(
+                supported.contains(&connection.atoms._NET_RESTACK_WINDOW),
+                window.handle(),
+            ),
+        })
+        .collect();
+
+    Ok(cookies
+        .into_iter()
+        .map(|cookie| {
+            cookie?.check()?;
+            Ok(())
+        })
+        .collect())
+}
+
+// The requests for every window are sent before waiting on any of the replies, so the whole batch
+// takes a single round trip.
+pub fn properties(
+    windows: &[&Window],
+    mask: PropertyMask,
+) -> Result<Vec<Result<WindowProperties, WindowError>>, WindowError> {
+    let connection = connection()?;
+    let active = mask
+        .contains(PropertyMask::FOCUSED)
+        .then(|| connection.request_active_window())
+        .transpose()?;
+    let cookies: Vec<_> = windows
+        .iter()
+        .map(|window| connection.request_properties(window.handle(), mask))
+        .collect();
+    let active = match active {
+        Some(active) => active_window(active.reply()?),
+        None => None,
+    };
+
+    Ok(windows
+        .iter()
+        .zip(cookies)
+        .map(|(window, cookies)| cookies?.reply(connection, window.handle(), active, mask))
+        .collect())
+}
+
+pub fn iter_windows() -> impl Iterator<Item = Result<Window, WindowError>> {
+    let windows = match connection().and_then(|connection| connection.client_list()) {
+        Ok(windows) => windows
+            .into_iter()
+            .map(|window| Ok(Window::new(window)))
+            .collect(),
+        Err(err) => vec![Err(err)],
+    };
+    windows.into_iter()
+}
+
+pub fn stacking_order() -> Result<Vec<Window>, WindowError> {
+    let mut windows = connection()?.client_list_stacking()?;
+    // The window manager lists windows from the bottom to the top.
+    windows.reverse();
+    Ok(windows.into_iter().map(Window::new).collect())
+}
+
+pub fn focused_window() -> Result<Option<Window>, WindowError> {
+    Ok(connection()?.active_window()?.map(Window::new))
+}
+
+impl From<ConnectError> for WindowError {
+    fn from(err: ConnectError) -> Self {
+        match err {
+            ConnectError::IoError(err) => WindowError::OsError(err),
+            err => WindowError::OsError(io::Error::other(format!(
+                "failed to connect to the X server: {err}"
+            ))),
+        }
+    }
+}
+
+impl From<ConnectionError> for WindowError {
+    fn from(err: ConnectionError) -> Self {
+        match err {
+            ConnectionError::IoError(err) => WindowError::OsError(err),
+            err => WindowError::OsError(io::Error::other(format!(
+                "X server connection failed: {err}"
+            ))),
+        }
+    }
+}
+
+impl From<ReplyError> for WindowError {
+    fn from(err: ReplyError) -> Self {
+        match err {
+            ReplyError::ConnectionError(err) => err.into(),
+            ReplyError::X11Error(err) => match err.error_kind {
+                ErrorKind::Window | ErrorKind::Drawable => WindowError::InvalidHandle,
+                ErrorKind::Atom | ErrorKind::Value | ErrorKind::Match => {
+                    WindowError::InvalidInternalArgument
+                }
+                kind => {
+                    WindowError::OsError(io::Error::other(format!("X server returned {kind:?}")))
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xft_dpi() {
+        assert_eq!(scale_factor(b"Xft.antialias:\t1\nXft.dpi:\t192\n"), 2.0);
+        assert_eq!(scale_factor(b"Xft.dpi: 144"), 1.5);
+        // Not set, or set to something that isn't a dpi.
+        assert_eq!(scale_factor(b"Xcursor.size:\t24\n"), 1.0);
+        assert_eq!(scale_factor(b"Xft.dpi:\tlarge\n"), 1.0);
+        assert_eq!(scale_factor(b""), 1.0);
+    }
+}
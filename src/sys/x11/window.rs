@@ -0,0 +1,186 @@
+use x11rb::protocol::xproto;
+
+use crate::{
+    Insets, LogicalPosition, LogicalSize, PropertyMask, Rect, WindowError, WindowProperties,
+};
+
+use super::{connection, WindowHandle};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Window {
+    // NOTE: this is the client window, not the frame window created by a reparenting window manager.
+    inner: xproto::Window,
+}
+
+impl Window {
+    pub(crate) fn new(inner: xproto::Window) -> Window {
+        Window { inner }
+    }
+
+    pub fn handle(&self) -> WindowHandle {
+        self.inner
+    }
+
+    pub fn title(&self) -> Result<String, WindowError> {
+        connection()?.title(self.inner)
+    }
+
+    pub fn size(&self) -> Result<LogicalSize, WindowError> {
+        let connection = connection()?;
+        Ok(connection
+            .frame(self.inner)?
+            .size(connection.scale_factor()))
+    }
+
+    pub fn position(&self) -> Result<LogicalPosition, WindowError> {
+        let connection = connection()?;
+        Ok(connection
+            .frame(self.inner)?
+            .position(connection.scale_factor()))
+    }
+
+    pub fn scale_factor(&self) -> Result<f64, WindowError> {
+        Ok(connection()?.scale_factor())
+    }
+
+    pub fn is_focused(&self) -> Result<bool, WindowError> {
+        Ok(connection()?.active_window()? == Some(self.inner))
+    }
+
+    pub fn is_fullscreen(&self) -> Result<bool, WindowError> {
+        let connection = connection()?;
+        Ok(connection
+            .state(self.inner)?
+            .contains(&connection.atoms()._NET_WM_STATE_FULLSCREEN))
+    }
+
+    pub fn is_minimized(&self) -> Result<bool, WindowError> {
+        let connection = connection()?;
+        Ok(connection
+            .state(self.inner)?
+            .contains(&connection.atoms()._NET_WM_STATE_HIDDEN))
+    }
+
+    // Minimized windows are unmapped by the window manager, so they are also considered hidden.
+    pub fn is_hidden(&self) -> Result<bool, WindowError> {
+        Ok(!connection()?.is_viewable(self.inner)?)
+    }
+
+    pub fn properties(&self, mask: PropertyMask) -> Result<WindowProperties, WindowError> {
+        super::properties(&[self], mask)?.remove(0)
+    }
+
+    pub fn resize(&self, size: LogicalSize) -> Result<(), WindowError> {
+        let connection = connection()?;
+        let (width, height) = connection
+            .extents(self.inner)?
+            .client_size(size, connection.scale_factor());
+        connection.move_resize(self.inner, None, None, Some(width), Some(height))
+    }
+
+    pub fn reposition(&self, position: LogicalPosition) -> Result<(), WindowError> {
+        let connection = connection()?;
+        let (x, y) = connection
+            .extents(self.inner)?
+            .client_position(position, connection.scale_factor());
+        connection.move_resize(self.inner, Some(x), Some(y), None, None)
+    }
+
+    pub fn frame(&self) -> Result<Rect, WindowError> {
+        let connection = connection()?;
+        let frame = connection.frame(self.inner)?;
+        let scale_factor = connection.scale_factor();
+        Ok(Rect {
+            position: frame.position(scale_factor),
+            size: frame.size(scale_factor),
+        })
+    }
+
+    // Sent as a single request, so the window manager configures the window once.
+    pub fn set_frame(&self, frame: Rect) -> Result<Rect, WindowError> {
+        let connection = connection()?;
+        let extents = connection.extents(self.inner)?;
+        let (x, y) = extents.client_position(frame.position, connection.scale_factor());
+        let (width, height) = extents.client_size(frame.size, connection.scale_factor());
+        connection.move_resize(self.inner, Some(x), Some(y), Some(width), Some(height))?;
+        super::super::settled_frame(frame, || self.frame())
+    }
+
+    // The shadow of clients with client-side decorations is outside of both frames, so only the
+    // window manager's decorations count.
+    pub fn decoration_insets(&self) -> Result<Insets, WindowError> {
+        let connection = connection()?;
+        let decorations = connection.extents(self.inner)?.decorations;
+        let scale_factor = connection.scale_factor();
+        Ok(Insets {
+            top: decorations.top as f64 / scale_factor,
+            right: decorations.right as f64 / scale_factor,
+            bottom: decorations.bottom as f64 / scale_factor,
+            left: decorations.left as f64 / scale_factor,
+        })
+    }
+
+    pub fn focus(&self) -> Result<(), WindowError> {
+        connection()?.activate(self.inner)
+    }
+
+    pub fn fullscreen(&self) -> Result<(), WindowError> {
+        let connection = connection()?;
+        connection.change_state(
+            self.inner,
+            true,
+            connection.atoms()._NET_WM_STATE_FULLSCREEN,
+            x11rb::NONE,
+        )
+    }
+
+    pub fn unfullscreen(&self) -> Result<(), WindowError> {
+        let connection = connection()?;
+        connection.change_state(
+            self.inner,
+            false,
+            connection.atoms()._NET_WM_STATE_FULLSCREEN,
+            x11rb::NONE,
+        )
+    }
+
+    pub fn maximize(&self) -> Result<(), WindowError> {
+        let connection = connection()?;
+        connection.change_state(
+            self.inner,
+            true,
+            connection.atoms()._NET_WM_STATE_MAXIMIZED_VERT,
+            connection.atoms()._NET_WM_STATE_MAXIMIZED_HORZ,
+        )
+    }
+
+    pub fn minimize(&self) -> Result<(), WindowError> {
+        connection()?.iconify(self.inner)
+    }
+
+    pub fn unminimize(&self) -> Result<(), WindowError> {
+        connection()?.map(self.inner)
+    }
+
+    pub fn show(&self) -> Result<(), WindowError> {
+        self.unminimize()
+    }
+
+    // TODO: X11 has no concept of hiding a window without withdrawing it, which removes it from the
+    //       client list entirely, so we minimize it instead (same as macOS).
+    pub fn hide(&self) -> Result<(), WindowError> {
+        self.minimize()
+    }
+
+    pub fn bring_to_front(&self) -> Result<(), WindowError> {
+        connection()?.raise(self.inner)
+    }
+
+    pub fn pid(&self) -> Result<u32, WindowError> {
+        connection()?.pid(self.inner)
+    }
+
+    pub fn close(&self) -> Result<(), WindowError> {
+        connection()?.close(self.inner)
+    }
+}
@@ -15,6 +15,39 @@ macro_rules! init_windowing {
         fn main() {
             env_logger::init();
 
+            // Killing a window kills the process that owns it, so tests that kill a window run the test
+            // binary again as a child process that only hosts the window, see `ProcessExecutor`.
+            if let Some(title) = ::std::env::var_os(::fowin_test_lib::executor::HOST_WINDOW) {
+                use ::fowin_test_lib::executor::Executor;
+
+                let mut executor = ::fowin_test_lib::executor::WinitExecutor::new();
+                let mut state = ::fowin_test_lib::State::initial();
+                state.title = title.into_string().unwrap();
+                executor
+                    .execute(&::fowin_test_lib::Step::external(
+                        1,
+                        ::fowin_test_lib::Action::Spawn(state),
+                    ))
+                    .unwrap();
+                loop {
+                    executor.pump(::std::time::Duration::from_secs(1));
+                }
+            }
+
+            // On Linux, the tests need a display server to spawn windows on (e.g. `xvfb-run` or a headless
+            // sway). Rather than failing every test, mark them as ignored when there isn't one.
+            #[cfg(target_os = "linux")]
+            if ::std::env::var_os("DISPLAY").is_none()
+                && ::std::env::var_os("WAYLAND_DISPLAY").is_none()
+            {
+                let args = ::libtest_mimic_collect::libtest_mimic::Arguments::from_args();
+                let tests = ::libtest_mimic_collect::TestCollection::collect_tests()
+                    .into_iter()
+                    .map(|test| test.with_ignored_flag(true))
+                    .collect();
+                ::libtest_mimic_collect::libtest_mimic::run(&args, tests).exit();
+            }
+
             if !::fowin::request_trust().unwrap() {
                 panic!("{}", ::fowin::WindowError::NotTrusted);
             }
@@ -1,5 +1,6 @@
 use fowin_test_lib::{
-    executor::FowinExecutor, Action, Mutation, Position, Size, State, Step, Timeline,
+    executor::{encode_title, Executor, FowinExecutor},
+    Action, Mutation, Position, Size, State, Step, Timeline,
 };
 
 mod common;
@@ -118,3 +119,26 @@ fn read_minimize() -> Result<(), String> {
             .map_err(|err| err.to_string())
     })
 }
+
+#[test]
+fn read_listed() -> Result<(), String> {
+    WINIT_EXECUTOR.with_borrow_mut(|winit_executor| {
+        let state = State::initial();
+        let title = encode_title(&None, 1, &state.title);
+        winit_executor
+            .execute(&Step::external(1, Action::Spawn(state)))
+            .map_err(|err| err.to_string())?;
+
+        let listed = fowin::iter_windows()
+            .flatten()
+            .any(|window| window.title().is_ok_and(|other| other == title));
+
+        winit_executor
+            .execute(&Step::external(1, Action::Terminate))
+            .map_err(|err| err.to_string())?;
+        match listed {
+            true => Ok(()),
+            false => Err(format!("the window `{title}` wasn't listed")),
+        }
+    })
+}
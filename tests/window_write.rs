@@ -1,5 +1,8 @@
+use std::process;
+
 use fowin_test_lib::{
-    executor::FowinExecutor, Action, Mutation, Position, Size, State, Step, Timeline,
+    executor::{FowinExecutor, ProcessExecutor},
+    Action, Mutation, Position, Size, State, Step, Timeline,
 };
 
 mod common;
@@ -97,3 +100,36 @@ fn write_minimize() -> Result<(), String> {
             .map_err(|err| err.to_string())
     })
 }
+
+#[test]
+fn write_close() -> Result<(), String> {
+    WINIT_EXECUTOR.with_borrow_mut(|winit_executor| {
+        FowinExecutor::new()
+            .execute_all(
+                winit_executor,
+                Timeline::new(vec![
+                    Step::external(1, Action::Spawn(State::initial())),
+                    // Validates that the window is gone.
+                    Step::fowin(1, Action::Terminate),
+                ]),
+            )
+            .map_err(|err| err.to_string())
+    })
+}
+
+#[test]
+fn write_kill() -> Result<(), String> {
+    // Killing the window kills its process, so it's hosted by a child process. The namespace keeps
+    // the title apart from other test runs.
+    let namespace = format!("kill-{}", process::id());
+    FowinExecutor::with_namespace(&namespace)
+        .execute_all(
+            &mut ProcessExecutor::with_namespace(namespace),
+            Timeline::new(vec![
+                Step::external(1, Action::Spawn(State::initial())),
+                // Validates that the window is gone.
+                Step::fowin(1, Action::Kill),
+            ]),
+        )
+        .map_err(|err| err.to_string())
+}